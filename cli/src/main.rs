@@ -1,40 +1,50 @@
 #![allow(clippy::print_stderr, clippy::cast_precision_loss, clippy::use_debug)]
 use anyhow::{bail, ensure, Context};
-use clap::{Parser, Subcommand, ValueHint};
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueHint};
 use flate2::read::MultiGzDecoder;
 use oxhttp::model::{Body, HeaderName, HeaderValue, Method, Request, Response, Status};
-use oxhttp::Server;
+use oxhttp::{Client, Server};
 use oxigraph::io::{RdfFormat, RdfParser, RdfSerializer};
 use oxigraph::model::{
-    GraphName, GraphNameRef, IriParseError, NamedNode, NamedNodeRef, NamedOrBlankNode,
+    GraphName, GraphNameRef, IriParseError, NamedNode, NamedNodeRef, NamedOrBlankNode, Quad, Term,
 };
 use oxigraph::sparql::results::{QueryResultsFormat, QueryResultsSerializer};
-use oxigraph::sparql::{Query, QueryOptions, QueryResults, Update};
-use oxigraph::store::{BulkLoader, LoaderError, Store};
+use oxigraph::sparql::{EvaluationError, Query, QueryOptions, QueryResults, Update};
+use oxigraph::store::{
+    BulkLoader, ChangeOperation, LoaderError, StorageError, Store, StoreMigrator, StoreOptions,
+    StoreSnapshot, LATEST_STORAGE_VERSION,
+};
 use oxiri::Iri;
 use rand::random;
 use rayon_core::ThreadPoolBuilder;
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::cmp::{max, min};
+use std::collections::HashMap;
 #[cfg(target_os = "linux")]
 use std::env;
 use std::ffi::OsStr;
 use std::fs::File;
-use std::io::{self, stdin, stdout, BufWriter, Read, Write};
+use std::io::{self, stdin, stdout, BufRead, BufReader, BufWriter, Read, Write};
 use std::net::ToSocketAddrs;
 #[cfg(target_os = "linux")]
 use std::os::unix::net::UnixDatagram;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
 use std::thread::available_parallelism;
-use std::time::{Duration, Instant};
-use std::{fmt, fs, str};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use std::{fmt, fs, str, thread};
 use url::form_urlencoded;
 
 const MAX_SPARQL_BODY_SIZE: u64 = 0x0010_0000;
 const HTTP_TIMEOUT: Duration = Duration::from_secs(60);
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(600);
+const MAX_SNAPSHOTS: usize = 128;
+const SEQUENCE_NUMBER_HEADER: &str = "oxigraph-change-sequence-number";
+const REPLICATION_BATCH_SIZE: usize = 10_000;
+const REPLICATION_STATE_FILE: &str = "replicated_sequence_number";
 const HTML_ROOT_PAGE: &str = include_str!("../templates/query.html");
 const LOGO: &str = include_str!("../logo.svg");
 
@@ -61,6 +71,19 @@ enum Command {
         /// Allows cross-origin requests
         #[arg(long)]
         cors: bool,
+        /// Maximal number of seconds a SPARQL query evaluation might take.
+        ///
+        /// Queries running longer fail with a 503 Service Unavailable error.
+        /// It also bounds the evaluation of the WHERE clauses of SPARQL updates.
+        #[arg(long)]
+        query_timeout: Option<f64>,
+        /// Enables the store change log and exposes it to the replicas.
+        ///
+        /// Replicas started with the serve-replica command follow the changes through the /replication endpoints.
+        #[arg(long)]
+        replication: bool,
+        #[command(flatten)]
+        store_options: StoreOptionsArgs,
     },
     /// Start Oxigraph HTTP server in read-only mode.
     ///
@@ -68,8 +91,8 @@ enum Command {
     /// Opening as read-only while having an other process writing the database is undefined behavior.
     /// Please use the serve-secondary command in this case.
     ServeReadOnly {
-        /// Directory in which Oxigraph data are persisted.
-        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        /// Directory in which Oxigraph data are persisted or file written by the `pack` command.
+        #[arg(short, long, value_hint = ValueHint::AnyPath)]
         location: PathBuf,
         /// Host and port to listen to.
         #[arg(short, long, default_value = "localhost:7878")]
@@ -77,6 +100,12 @@ enum Command {
         /// Allows cross-origin requests
         #[arg(long)]
         cors: bool,
+        /// Maximal number of seconds a SPARQL query evaluation might take.
+        ///
+        /// Queries running longer fail with a 503 Service Unavailable error.
+        /// It also bounds the evaluation of the WHERE clauses of SPARQL updates.
+        #[arg(long)]
+        query_timeout: Option<f64>,
     },
     /// Start Oxigraph HTTP server in secondary mode.
     ///
@@ -100,6 +129,51 @@ enum Command {
         /// Allows cross-origin requests
         #[arg(long)]
         cors: bool,
+        /// Maximal number of seconds a SPARQL query evaluation might take.
+        ///
+        /// Queries running longer fail with a 503 Service Unavailable error.
+        /// It also bounds the evaluation of the WHERE clauses of SPARQL updates.
+        #[arg(long)]
+        query_timeout: Option<f64>,
+    },
+    /// Start Oxigraph HTTP server as a replica of an other Oxigraph server.
+    ///
+    /// The replica copies the content of the primary server and then follows its change log over HTTP.
+    /// The primary server must be started with the --replication option.
+    /// The replica serves read-only queries.
+    /// Its lag is reported by the /replication/status endpoint.
+    ///
+    /// Large sets of changes might be applied in multiple transactions
+    /// so queries might observe a primary transaction partially applied while the replica is catching up.
+    ServeReplica {
+        /// Base URL of the primary server like http://example.com:7878
+        #[arg(long, value_hint = ValueHint::Url)]
+        primary: String,
+        /// Directory in which the replicated data should be persisted.
+        ///
+        /// If not present. An in-memory storage will be used.
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        location: Option<PathBuf>,
+        /// Host and port to listen to.
+        #[arg(short, long, default_value = "localhost:7878", value_hint = ValueHint::Hostname)]
+        bind: String,
+        /// Allows cross-origin requests
+        #[arg(long)]
+        cors: bool,
+        /// Maximal number of seconds a SPARQL query evaluation might take.
+        ///
+        /// Queries running longer fail with a 503 Service Unavailable error.
+        /// It also bounds the evaluation of the WHERE clauses of SPARQL updates.
+        #[arg(long)]
+        query_timeout: Option<f64>,
+        /// Number of seconds to wait between two polls of the primary change log.
+        #[arg(long, default_value_t = 1.)]
+        poll_interval: f64,
+        /// Maximal number of seconds since the last synchronization with the primary.
+        ///
+        /// If the replica has not been synchronized for longer, queries fail with a 503 Service Unavailable error.
+        #[arg(long)]
+        max_lag: Option<f64>,
     },
     /// Creates database backup into a target directory.
     ///
@@ -111,6 +185,10 @@ enum Command {
     /// but hard links will be used to point to the original database immutable snapshots.
     /// This allows cheap regular backups.
     ///
+    /// With the --incremental option, the destination directory stores multiple backups
+    /// and only the files that are not already in a previous backup are copied.
+    /// Use the list-backups command to list them and the restore command to restore one of them.
+    ///
     /// If you want to move your data to another RDF storage system, you should use the dump operation instead.
     Backup {
         /// Directory in which Oxigraph data are persisted.
@@ -119,6 +197,52 @@ enum Command {
         /// Directory in which the backup will be written.
         #[arg(short, long, value_hint = ValueHint::DirPath)]
         destination: PathBuf,
+        /// Adds an incremental backup to the destination directory instead of writing a full copy.
+        #[arg(long)]
+        incremental: bool,
+        /// Number of incremental backups to keep, the oldest ones are removed.
+        #[arg(long, requires = "incremental")]
+        keep: Option<u32>,
+    },
+    /// Restores a backup created with `backup --incremental`.
+    ///
+    /// The backup is checked before replacing the content of the target directory.
+    /// No other process should use the target directory during the restoration.
+    Restore {
+        /// Directory in which Oxigraph data are persisted.
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        location: PathBuf,
+        /// Directory containing the incremental backups.
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        backup: PathBuf,
+        /// Identifier of the backup to restore.
+        ///
+        /// By default, the newest backup is restored.
+        #[arg(long)]
+        backup_id: Option<u32>,
+    },
+    /// Lists the backups created with `backup --incremental`.
+    ///
+    /// Writes one tab-separated line per backup with its identifier,
+    /// its creation Unix timestamp, its size in bytes and its number of files.
+    ListBackups {
+        /// Directory containing the incremental backups.
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        backup: PathBuf,
+    },
+    /// Writes the database content into a single read-only file.
+    ///
+    /// The written file is a compact sorted image of the database indexes.
+    /// It is memory-mapped and can be given to the `serve-read-only`, `query` and `dump` commands
+    /// in place of the database directory.
+    /// It is meant to publish datasets that are not modified anymore.
+    Pack {
+        /// Directory in which Oxigraph data are persisted.
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        location: PathBuf,
+        /// File to write the packed database to.
+        #[arg(short, long, value_hint = ValueHint::FilePath)]
+        file: PathBuf,
     },
     /// Load file(s) into the store.
     ///
@@ -156,11 +280,13 @@ enum Command {
         /// Only available when loading a graph file (N-Triples, Turtle...) and not a dataset file (N-Quads, TriG...).
         #[arg(long, value_hint = ValueHint::Url)]
         graph: Option<String>,
+        #[command(flatten)]
+        store_options: StoreOptionsArgs,
     },
     /// Dump the store content into a file.
     Dump {
-        /// Directory in which Oxigraph data are persisted.
-        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        /// Directory in which Oxigraph data are persisted or file written by the `pack` command.
+        #[arg(short, long, value_hint = ValueHint::AnyPath)]
         location: PathBuf,
         /// File to dump to.
         ///
@@ -182,8 +308,8 @@ enum Command {
     },
     /// Executes a SPARQL query against the store.
     Query {
-        /// Directory in which Oxigraph data are persisted.
-        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        /// Directory in which Oxigraph data are persisted or file written by the `pack` command.
+        #[arg(short, long, value_hint = ValueHint::AnyPath)]
         location: PathBuf,
         /// The SPARQL query to execute.
         ///
@@ -256,6 +382,22 @@ enum Command {
         #[arg(short, long, value_hint = ValueHint::DirPath)]
         location: PathBuf,
     },
+    /// Migrates the database storage format to an other version.
+    ///
+    /// Without the --target-version option, writes the current storage version and the latest supported one.
+    ///
+    /// It allows to downgrade a database to open it with an older Oxigraph version.
+    /// The database is rewritten in place: migrate a copy or make a backup first.
+    /// No other process should use the database during the migration.
+    /// The databases are automatically migrated to the latest version when opened in read-write mode.
+    Migrate {
+        /// Directory in which Oxigraph data are persisted.
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        location: PathBuf,
+        /// Version of the storage format to migrate to.
+        #[arg(long)]
+        target_version: Option<u64>,
+    },
     /// Converts a RDF serialization from one format to an other.
     Convert {
         /// File to convert from.
@@ -304,6 +446,62 @@ enum Command {
     },
 }
 
+/// Tuning options of the read-write storage.
+#[derive(ClapArgs)]
+struct StoreOptionsArgs {
+    /// Size in megabytes of the cache of uncompressed data blocks.
+    ///
+    /// By default, 32MB are used for the quads and 128MB for the IRIs and literals.
+    #[arg(long)]
+    block_cache_size: Option<usize>,
+    /// Size in megabytes of the in-memory buffers in which writes are accumulated before being written to disk.
+    ///
+    /// By default, 128MB are used.
+    #[arg(long)]
+    write_buffer_size: Option<usize>,
+    /// Maximal number of files to keep open at the same time.
+    ///
+    /// By default, all the file descriptors allowed by the operating system minus 48 are used.
+    #[arg(long)]
+    max_open_files: Option<u32>,
+    /// Disables the compression of the data written on disk.
+    #[arg(long)]
+    no_compression: bool,
+    /// Enables bloom filters on the quad indexes with the given number of bits per key.
+    #[arg(long)]
+    bloom_filter_bits_per_key: Option<f64>,
+}
+
+impl StoreOptionsArgs {
+    fn is_set(&self) -> bool {
+        self.block_cache_size.is_some()
+            || self.write_buffer_size.is_some()
+            || self.max_open_files.is_some()
+            || self.no_compression
+            || self.bloom_filter_bits_per_key.is_some()
+    }
+
+    fn to_store_options(&self) -> StoreOptions {
+        let mut options = StoreOptions::default();
+        if let Some(block_cache_size) = self.block_cache_size {
+            options = options.with_block_cache_size_in_megabytes(block_cache_size);
+        }
+        if let Some(write_buffer_size) = self.write_buffer_size {
+            options = options.with_write_buffer_size_in_megabytes(write_buffer_size);
+        }
+        if let Some(max_open_files) = self.max_open_files {
+            options = options.with_max_open_files(max_open_files);
+        }
+        if self.no_compression {
+            options = options.with_compression(false);
+        }
+        if let Some(bits_per_key) = self.bloom_filter_bits_per_key {
+            options = options.with_bloom_filter_bits_per_key(bits_per_key);
+        }
+        options
+    }
+}
+
 pub fn main() -> anyhow::Result<()> {
     let matches = Args::parse();
     match matches.command {
@@ -311,26 +509,50 @@ pub fn main() -> anyhow::Result<()> {
             location,
             bind,
             cors,
-        } => serve(
-            if let Some(location) = location {
-                Store::open(location)
+            query_timeout,
+            replication,
+            store_options,
+        } => {
+            let store = if let Some(location) = location {
+                Store::open_with_options(location, &store_options.to_store_options())
             } else {
+                ensure!(
+                    !store_options.is_set(),
+                    "The storage options are only supported with the --location option"
+                );
                 Store::new()
-            }?,
-            &bind,
-            false,
-            cors,
-        ),
+            }?;
+            if replication {
+                store.enable_change_log()?;
+            }
+            serve(
+                store,
+                &bind,
+                false,
+                cors,
+                parse_query_timeout(query_timeout)?,
+                None,
+            )
+        }
         Command::ServeReadOnly {
             location,
             bind,
             cors,
-        } => serve(Store::open_read_only(location)?, &bind, true, cors),
+            query_timeout,
+        } => serve(
+            open_read_only_store(location)?,
+            &bind,
+            true,
+            cors,
+            parse_query_timeout(query_timeout)?,
+            None,
+        ),
         Command::ServeSecondary {
             primary_location,
             secondary_location,
             bind,
             cors,
+            query_timeout,
         } => serve(
             if let Some(secondary_location) = secondary_location {
                 Store::open_persistent_secondary(primary_location, secondary_location)
@@ -340,13 +562,81 @@ pub fn main() -> anyhow::Result<()> {
             &bind,
             true,
             cors,
+            parse_query_timeout(query_timeout)?,
+            None,
         ),
+        Command::ServeReplica {
+            primary,
+            location,
+            bind,
+            cors,
+            query_timeout,
+            poll_interval,
+            max_lag,
+        } => {
+            let (store, replica) = Replica::open(
+                &primary,
+                location,
+                max_lag.map(Duration::try_from_secs_f64).transpose()?,
+            )?;
+            let replica = Arc::new(replica);
+            Arc::clone(&replica)
+                .spawn_follower(store.clone(), Duration::try_from_secs_f64(poll_interval)?)?;
+            serve(
+                store,
+                &bind,
+                true,
+                cors,
+                parse_query_timeout(query_timeout)?,
+                Some(replica),
+            )
+        }
         Command::Backup {
             location,
             destination,
+            incremental,
+            keep,
         } => {
             let store = Store::open_read_only(location)?;
-            store.backup(destination)?;
+            if incremental {
+                let backup = store.incremental_backup(&destination)?;
+                eprintln!("Backup {} created", backup.id);
+                if let Some(keep) = keep {
+                    Store::purge_old_backups(destination, keep)?;
+                }
+            } else {
+                store.backup(destination)?;
+            }
+            Ok(())
+        }
+        Command::Restore {
+            location,
+            backup,
+            backup_id,
+        } => {
+            Store::restore_backup(backup, backup_id, location)?;
+            Ok(())
+        }
+        Command::ListBackups { backup } => {
+            let mut output = stdout().lock();
+            for backup in Store::list_backups(backup)? {
+                writeln!(
+                    output,
+                    "{}\t{}\t{}\t{}",
+                    backup.id,
+                    backup
+                        .timestamp
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs(),
+                    backup.size,
+                    backup.number_of_files
+                )?;
+            }
+            Ok(())
+        }
+        Command::Pack { location, file } => {
+            Store::open_read_only(location)?.pack_to_file(file)?;
             Ok(())
         }
         Command::Load {
@@ -356,8 +646,9 @@ pub fn main() -> anyhow::Result<()> {
             format,
             base,
             graph,
+            store_options,
         } => {
-            let store = Store::open(location)?;
+            let store = Store::open_with_options(location, &store_options.to_store_options())?;
             let format = if let Some(format) = format {
                 Some(rdf_format_from_name(&format)?)
             } else {
@@ -484,7 +775,7 @@ pub fn main() -> anyhow::Result<()> {
             format,
             graph,
         } => {
-            let store = Store::open_read_only(location)?;
+            let store = open_read_only_store(location)?;
             let format = if let Some(format) = format {
                 rdf_format_from_name(&format)?
             } else if let Some(file) = &file {
@@ -536,7 +827,7 @@ pub fn main() -> anyhow::Result<()> {
                 io::read_to_string(stdin().lock())?
             };
             let query = Query::parse(&query, query_base.as_deref())?;
-            let store = Store::open_read_only(location)?;
+            let store = open_read_only_store(location)?;
             let (results, explanation) =
                 store.explain_query_opt(query, QueryOptions::default(), stats)?;
             let print_result = (|| {
@@ -687,6 +978,31 @@ pub fn main() -> anyhow::Result<()> {
             store.optimize()?;
             Ok(())
         }
+        Command::Migrate {
+            location,
+            target_version,
+        } => {
+            let migrator = StoreMigrator::open(location)?;
+            let version = migrator.storage_version()?;
+            let Some(target_version) = target_version else {
+                writeln!(
+                    stdout().lock(),
+                    "Storage version: {version}\nLatest storage version: {LATEST_STORAGE_VERSION}"
+                )?;
+                return Ok(());
+            };
+            let start = Instant::now();
+            migrator
+                .on_progress(move |version, size| {
+                    eprintln!(
+                        "{size} entries migrated to the storage version {version} in {}s",
+                        start.elapsed().as_secs()
+                    )
+                })
+                .migrate(target_version)?;
+            eprintln!("Storage migrated from the version {version} to {target_version}");
+            Ok(())
+        }
         Command::Convert {
             from_file,
             from_format,
@@ -902,16 +1218,63 @@ fn rdf_format_from_name(name: &str) -> anyhow::Result<RdfFormat> {
     bail!("The file format '{name}' is unknown")
 }
 
-fn serve(store: Store, bind: &str, read_only: bool, cors: bool) -> anyhow::Result<()> {
+/// Opens a database directory in read-only mode or a file written by the `pack` command.
+fn open_read_only_store(location: PathBuf) -> anyhow::Result<Store> {
+    Ok(if location.is_file() {
+        Store::open_packed(location)?
+    } else {
+        Store::open_read_only(location)?
+    })
+}
+
+fn parse_query_timeout(query_timeout: Option<f64>) -> anyhow::Result<Option<Duration>> {
+    query_timeout
+        .map(Duration::try_from_secs_f64)
+        .transpose()
+        .context("Invalid --query-timeout value")
+}
+
+fn query_options(query_timeout: Option<Duration>) -> QueryOptions {
+    if let Some(query_timeout) = query_timeout {
+        QueryOptions::default().with_timeout(query_timeout)
+    } else {
+        QueryOptions::default()
+    }
+}
+
+fn serve(
+    store: Store,
+    bind: &str,
+    read_only: bool,
+    cors: bool,
+    query_timeout: Option<Duration>,
+    replica: Option<Arc<Replica>>,
+) -> anyhow::Result<()> {
+    let snapshots = Arc::new(Snapshots::default());
+    Snapshots::spawn_sweeper(Arc::downgrade(&snapshots))?;
     let mut server = if cors {
         Server::new(cors_middleware(move |request| {
-            handle_request(request, store.clone(), read_only)
-                .unwrap_or_else(|(status, message)| error(status, message))
+            handle_request(
+                request,
+                store.clone(),
+                &snapshots,
+                read_only,
+                query_timeout,
+                replica.as_deref(),
+            )
+            .unwrap_or_else(|(status, message)| error(status, message))
         }))
     } else {
         Server::new(move |request| {
-            handle_request(request, store.clone(), read_only)
-                .unwrap_or_else(|(status, message)| error(status, message))
+            handle_request(
+                request,
+                store.clone(),
+                &snapshots,
+                read_only,
+                query_timeout,
+                replica.as_deref(),
+            )
+            .unwrap_or_else(|(status, message)| error(status, message))
         })
     }
     .with_global_timeout(HTTP_TIMEOUT)
@@ -974,11 +1337,500 @@ fn cors_middleware(
 
 type HttpError = (Status, String);
 
+/// The snapshots created with `POST /snapshot` indexed by their identifiers.
+///
+/// A snapshot is dropped if it has not been used for [`SNAPSHOT_TIMEOUT`].
+/// At most [`MAX_SNAPSHOTS`] snapshots are kept at the same time.
+#[derive(Default)]
+struct Snapshots(Mutex<HashMap<String, (StoreSnapshot, Instant)>>);
+
+impl Snapshots {
+    /// Drops regularly the expired snapshots until the [`Snapshots`] are dropped
+    fn spawn_sweeper(snapshots: Weak<Self>) -> io::Result<()> {
+        thread::Builder::new()
+            .name("snapshot-sweeper".into())
+            .spawn(move || loop {
+                thread::sleep(SNAPSHOT_TIMEOUT / 10);
+                let Some(snapshots) = snapshots.upgrade() else {
+                    return;
+                };
+                if snapshots.sweep().is_err() {
+                    return;
+                }
+            })?;
+        Ok(())
+    }
+
+    fn create(&self, store: &Store) -> Result<String, HttpError> {
+        let mut snapshots = self.0.lock().map_err(internal_server_error)?;
+        snapshots.retain(|_, (_, last_use)| last_use.elapsed() < SNAPSHOT_TIMEOUT);
+        if snapshots.len() >= MAX_SNAPSHOTS {
+            return Err((
+                Status::SERVICE_UNAVAILABLE,
+                format!("Too many snapshots are open, at most {MAX_SNAPSHOTS} are allowed"),
+            ));
+        }
+        let snapshot = store.snapshot().map_err(internal_server_error)?;
+        let id = format!("{:032x}", random::<u128>());
+        snapshots.insert(id.clone(), (snapshot, Instant::now()));
+        Ok(id)
+    }
+
+    fn get(&self, id: &str) -> Result<StoreSnapshot, HttpError> {
+        let mut snapshots = self.0.lock().map_err(internal_server_error)?;
+        let (snapshot, last_use) = snapshots
+            .get_mut(id)
+            .filter(|(_, last_use)| last_use.elapsed() < SNAPSHOT_TIMEOUT)
+            .ok_or_else(|| unknown_snapshot(id))?;
+        *last_use = Instant::now();
+        Ok(snapshot.clone())
+    }
+
+    fn sweep(&self) -> Result<(), HttpError> {
+        self.0
+            .lock()
+            .map_err(internal_server_error)?
+            .retain(|_, (_, last_use)| last_use.elapsed() < SNAPSHOT_TIMEOUT);
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), HttpError> {
+        self.0
+            .lock()
+            .map_err(internal_server_error)?
+            .remove(id)
+            .map(|_| ())
+            .ok_or_else(|| unknown_snapshot(id))
+    }
+}
+
+/// A replica following the change log of a primary server.
+struct Replica {
+    primary: String,
+    location: Option<PathBuf>,
+    max_lag: Option<Duration>,
+    client: Client,
+    status: Mutex<ReplicaStatus>,
+}
+
+#[derive(Default)]
+struct ReplicaStatus {
+    /// The sequence number of the last primary change applied to the replica, `None` if the primary content has not been copied yet
+    applied_sequence_number: Option<u64>,
+    /// The sequence number of the last primary change when the primary has been polled for the last time
+    primary_sequence_number: Option<u64>,
+    /// The last time the replica was known to contain all the primary changes
+    last_sync: Option<Instant>,
+}
+
+impl Replica {
+    /// Opens the replica store and the replication state persisted with it
+    fn open(
+        primary: &str,
+        location: Option<PathBuf>,
+        max_lag: Option<Duration>,
+    ) -> anyhow::Result<(Store, Self)> {
+        let mut applied_sequence_number = None;
+        let store = if let Some(location) = &location {
+            let store = Store::open(location)?;
+            match fs::read_to_string(location.join(REPLICATION_STATE_FILE)) {
+                Ok(state) => {
+                    if !state.is_empty() {
+                        applied_sequence_number =
+                            Some(u64::from_str(&state).with_context(|| {
+                                format!("Invalid replication state in {}", location.display())
+                            })?);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => ensure!(
+                    store.is_empty()?,
+                    "The store in {} is not empty and is not a replica",
+                    location.display()
+                ),
+                Err(e) => return Err(e.into()),
+            }
+            store
+        } else {
+            Store::new()?
+        };
+        let client = Client::new()
+            .with_global_timeout(HTTP_TIMEOUT)
+            .with_user_agent(concat!("Oxigraph/", env!("CARGO_PKG_VERSION")))?;
+        Ok((
+            store,
+            Self {
+                primary: primary.trim_end_matches('/').into(),
+                location,
+                max_lag,
+                client,
+                status: Mutex::new(ReplicaStatus {
+                    applied_sequence_number,
+                    ..ReplicaStatus::default()
+                }),
+            },
+        ))
+    }
+
+    /// Polls regularly the primary change log and applies the changes to the store
+    fn spawn_follower(self: Arc<Self>, store: Store, poll_interval: Duration) -> io::Result<()> {
+        thread::Builder::new()
+            .name("replication-follower".into())
+            .spawn(move || loop {
+                if let Err(e) = self.sync(&store) {
+                    eprintln!("Error while replicating {}: {e}", self.primary);
+                }
+                thread::sleep(poll_interval);
+            })?;
+        Ok(())
+    }
+
+    /// Applies all the changes currently in the primary change log
+    fn sync(&self, store: &Store) -> anyhow::Result<()> {
+        let start = Instant::now();
+        let applied_sequence_number = self.status()?.applied_sequence_number;
+        let mut applied_sequence_number =
+            if let Some(applied_sequence_number) = applied_sequence_number {
+                applied_sequence_number
+            } else {
+                self.copy_primary_content(store)?
+            };
+        let response = self.get(&format!(
+            "/replication/changes?from={}",
+            applied_sequence_number + 1
+        ))?;
+        if response.status() == Status::GONE {
+            eprintln!(
+                "The changes following {applied_sequence_number} are not in the primary change log anymore, the primary content is going to be copied again"
+            );
+            self.set_applied_sequence_number(None)?;
+            return Ok(());
+        }
+        let (primary_sequence_number, body) = self.check_response(response)?;
+        let mut batch = Vec::new();
+        for line in BufReader::new(body).lines() {
+            let (sequence_number, operation) = parse_change(&line?)?;
+            ensure!(
+                sequence_number == applied_sequence_number + batch.len() as u64 + 1,
+                "The change {sequence_number} has been returned by the primary instead of the change {}",
+                applied_sequence_number + batch.len() as u64 + 1
+            );
+            batch.push(operation);
+            if batch.len() >= REPLICATION_BATCH_SIZE {
+                applied_sequence_number =
+                    self.apply_changes(store, applied_sequence_number, &mut batch)?;
+            }
+        }
+        applied_sequence_number = self.apply_changes(store, applied_sequence_number, &mut batch)?;
+        let mut status = self.status()?;
+        status.primary_sequence_number = Some(primary_sequence_number);
+        if applied_sequence_number >= primary_sequence_number {
+            status.last_sync = Some(start);
+        }
+        Ok(())
+    }
+
+    /// Replaces the store content with the primary content and returns the matching change sequence number
+    fn copy_primary_content(&self, store: &Store) -> anyhow::Result<u64> {
+        // The replication state is emptied to restart the copy if it gets interrupted
+        // and to refuse the queries while the store content is incomplete
+        self.set_applied_sequence_number(None)?;
+        let (sequence_number, body) = self.check_response(self.get("/replication/snapshot")?)?;
+        eprintln!("Copying the content of {}", self.primary);
+        store.clear()?;
+        // The blank node identifiers must be kept to apply the following changes
+        let mut graph_names = Vec::new();
+        store.bulk_loader().load_ok_quads::<_, anyhow::Error>(
+            BufReader::new(body).lines().filter_map(|line| {
+                match line
+                    .map_err(anyhow::Error::from)
+                    .and_then(|line| parse_change_operation(&line))
+                {
+                    Ok(ChangeOperation::Insert(quad)) => Some(Ok(quad)),
+                    Ok(ChangeOperation::InsertNamedGraph(graph_name)) => {
+                        graph_names.push(graph_name);
+                        None
+                    }
+                    Ok(operation) => Some(Err(anyhow::anyhow!(
+                        "Unexpected operation in the primary content: {operation:?}"
+                    ))),
+                    Err(e) => Some(Err(e)),
+                }
+            }),
+        )?;
+        store.transaction(|mut transaction| {
+            for graph_name in &graph_names {
+                transaction.insert_named_graph(graph_name)?;
+            }
+            Ok::<_, StorageError>(())
+        })?;
+        self.set_applied_sequence_number(Some(sequence_number))?;
+        eprintln!(
+            "Content of {} copied up to the change {sequence_number}",
+            self.primary
+        );
+        Ok(sequence_number)
+    }
+
+    /// Applies a batch of changes in a single transaction and returns the new applied sequence number
+    fn apply_changes(
+        &self,
+        store: &Store,
+        applied_sequence_number: u64,
+        batch: &mut Vec<ChangeOperation>,
+    ) -> anyhow::Result<u64> {
+        if batch.is_empty() {
+            return Ok(applied_sequence_number);
+        }
+        store.transaction(|mut transaction| {
+            for operation in batch.iter() {
+                match operation {
+                    ChangeOperation::Insert(quad) => transaction.insert(quad)?,
+                    ChangeOperation::Remove(quad) => transaction.remove(quad)?,
+                    ChangeOperation::InsertNamedGraph(graph_name) => {
+                        transaction.insert_named_graph(graph_name)?
+                    }
+                    ChangeOperation::RemoveNamedGraph(graph_name) => {
+                        transaction.remove_named_graph(graph_name)?
+                    }
+                };
+            }
+            Ok::<_, StorageError>(())
+        })?;
+        let applied_sequence_number = applied_sequence_number + batch.len() as u64;
+        batch.clear();
+        self.set_applied_sequence_number(Some(applied_sequence_number))?;
+        Ok(applied_sequence_number)
+    }
+
+    fn get(&self, path: &str) -> anyhow::Result<Response> {
+        Ok(self.client.request(
+            Request::builder(
+                Method::GET,
+                format!("{}{path}", self.primary)
+                    .parse()
+                    .with_context(|| format!("Invalid primary URL {}", self.primary))?,
+            )
+            .build(),
+        )?)
+    }
+
+    /// Returns the change sequence number and the body of a successful response
+    fn check_response(&self, response: Response) -> anyhow::Result<(u64, FusedBody)> {
+        if !response.status().is_successful() {
+            bail!(
+                "Error {} returned by {} with payload:\n{}",
+                response.status(),
+                self.primary,
+                response.into_body().to_string()?
+            );
+        }
+        let sequence_number = response
+            .header(&HeaderName::try_from(SEQUENCE_NUMBER_HEADER)?)
+            .with_context(|| {
+                format!(
+                    "No change sequence number returned by {}, is replication enabled on the primary?",
+                    self.primary
+                )
+            })?
+            .to_str()?
+            .parse()?;
+        Ok((sequence_number, FusedBody(Some(response.into_body()))))
+    }
+
+    fn status(&self) -> anyhow::Result<MutexGuard<'_, ReplicaStatus>> {
+        self.status
+            .lock()
+            .map_err(|_| anyhow::anyhow!("The replication status lock is poisoned"))
+    }
+
+    /// Sets the applied sequence number, `None` meaning that the primary content has to be copied again
+    fn set_applied_sequence_number(&self, sequence_number: Option<u64>) -> anyhow::Result<()> {
+        self.write_state(sequence_number)?;
+        let mut status = self.status()?;
+        status.applied_sequence_number = sequence_number;
+        if sequence_number.is_none() {
+            status.last_sync = None;
+        }
+        Ok(())
+    }
+
+    /// Persists the applied sequence number next to the store.
+    ///
+    /// Changes applied again after a crash are harmless: replaying the change log from any earlier point leads to the same content.
+    fn write_state(&self, sequence_number: Option<u64>) -> io::Result<()> {
+        let Some(location) = &self.location else {
+            return Ok(());
+        };
+        let temporary_file = location.join(format!("{REPLICATION_STATE_FILE}.tmp"));
+        fs::write(
+            &temporary_file,
+            sequence_number.map(|s| s.to_string()).unwrap_or_default(),
+        )?;
+        fs::rename(temporary_file, location.join(REPLICATION_STATE_FILE))
+    }
+
+    /// Time since the replica was last known to contain all the primary changes
+    fn lag(&self) -> Result<Option<Duration>, HttpError> {
+        Ok(self
+            .status
+            .lock()
+            .map_err(internal_server_error)?
+            .last_sync
+            .map(|last_sync| last_sync.elapsed()))
+    }
+
+    fn check_lag(&self) -> Result<(), HttpError> {
+        if self
+            .status
+            .lock()
+            .map_err(internal_server_error)?
+            .applied_sequence_number
+            .is_none()
+        {
+            return Err((
+                Status::SERVICE_UNAVAILABLE,
+                "The replica is copying the primary content".into(),
+            ));
+        }
+        let Some(max_lag) = self.max_lag else {
+            return Ok(());
+        };
+        match self.lag()? {
+            Some(lag) if lag <= max_lag => Ok(()),
+            Some(lag) => Err((
+                Status::SERVICE_UNAVAILABLE,
+                format!(
+                    "The replica has not been synchronized with the primary for {}s",
+                    lag.as_secs_f64()
+                ),
+            )),
+            None => Err((
+                Status::SERVICE_UNAVAILABLE,
+                "The replica has not been synchronized with the primary yet".into(),
+            )),
+        }
+    }
+
+    /// Returns the replication status as JSON, with a 503 status if the lag is above the maximal lag
+    fn status_report(&self) -> Result<(Status, String), HttpError> {
+        let status = match self.check_lag() {
+            Ok(()) => Status::OK,
+            Err((status, _)) => status,
+        };
+        let lag = self.lag()?;
+        let replica_status = self.status.lock().map_err(internal_server_error)?;
+        Ok((
+            status,
+            format!(
+                "{{\"applied_sequence_number\":{},\"primary_sequence_number\":{},\"lag\":{}}}",
+                json_number(replica_status.applied_sequence_number),
+                json_number(replica_status.primary_sequence_number),
+                json_number(lag.map(|lag| lag.as_secs_f64()))
+            ),
+        ))
+    }
+}
+
+/// Stops reading a response body once its end has been reached.
+///
+/// Reading an HTTP client body again after its end blocks until the connection times out.
+struct FusedBody(Option<Body>);
+
+impl Read for FusedBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(body) = &mut self.0 else {
+            return Ok(0);
+        };
+        let len = body.read(buf)?;
+        if len == 0 && !buf.is_empty() {
+            self.0 = None;
+        }
+        Ok(len)
+    }
+}
+
+fn json_number(value: Option<impl fmt::Display>) -> String {
+    value.map_or_else(|| "null".into(), |value| value.to_string())
+}
+
+/// Parses a change line written by the `/replication/changes` endpoint like `12 A <s> <p> <o> <g> .`
+fn parse_change(line: &str) -> anyhow::Result<(u64, ChangeOperation)> {
+    let (sequence_number, operation) = line
+        .split_once(' ')
+        .with_context(|| format!("Invalid change line: {line}"))?;
+    Ok((
+        u64::from_str(sequence_number)?,
+        parse_change_operation(operation)?,
+    ))
+}
+
+/// Parses a change operation written by [`write_change_operation`]
+fn parse_change_operation(line: &str) -> anyhow::Result<ChangeOperation> {
+    let (operation, value) = line
+        .split_once(' ')
+        .with_context(|| format!("Invalid change line: {line}"))?;
+    let parse_quad = || -> anyhow::Result<Quad> {
+        RdfParser::from_format(RdfFormat::NQuads)
+            .parse_read(value.as_bytes())
+            .next()
+            .with_context(|| format!("No quad in the change line: {line}"))?
+            .map_err(Into::into)
+    };
+    let parse_graph_name = || -> anyhow::Result<NamedOrBlankNode> {
+        let graph_name = value
+            .strip_suffix(" .")
+            .with_context(|| format!("Invalid change line: {line}"))?;
+        match Term::from_str(graph_name)? {
+            Term::NamedNode(graph_name) => Ok(graph_name.into()),
+            Term::BlankNode(graph_name) => Ok(graph_name.into()),
+            _ => bail!("Invalid graph name in the change line: {line}"),
+        }
+    };
+    Ok(match operation {
+        "A" => ChangeOperation::Insert(parse_quad()?),
+        "D" => ChangeOperation::Remove(parse_quad()?),
+        "AG" => ChangeOperation::InsertNamedGraph(parse_graph_name()?),
+        "DG" => ChangeOperation::RemoveNamedGraph(parse_graph_name()?),
+        _ => bail!("Invalid change operation: {operation}"),
+    })
+}
+
+/// Writes a change operation line like `A <s> <p> <o> <g> .` for a quad or `AG <g> .` for a named graph
+fn write_change_operation(mut writer: impl Write, operation: &ChangeOperation) -> io::Result<()> {
+    match operation {
+        ChangeOperation::Insert(quad) => writeln!(writer, "A {quad} ."),
+        ChangeOperation::Remove(quad) => writeln!(writer, "D {quad} ."),
+        ChangeOperation::InsertNamedGraph(graph_name) => writeln!(writer, "AG {graph_name} ."),
+        ChangeOperation::RemoveNamedGraph(graph_name) => writeln!(writer, "DG {graph_name} ."),
+    }
+}
+
+fn last_change_sequence_number(snapshot: &StoreSnapshot) -> Result<u64, HttpError> {
+    snapshot
+        .last_change_sequence_number()
+        .map_err(internal_server_error)?
+        .ok_or_else(|| {
+            (
+                Status::NOT_FOUND,
+                "The change log is not enabled, use the --replication option to enable it".into(),
+            )
+        })
+}
+
 fn handle_request(
     request: &mut Request,
     store: Store,
+    snapshots: &Snapshots,
     read_only: bool,
+    query_timeout: Option<Duration>,
+    replica: Option<&Replica>,
 ) -> Result<Response, HttpError> {
+    if let Some(replica) = replica {
+        let path = request.url().path();
+        if path == "/query" || path.starts_with("/store") {
+            replica.check_lag()?;
+        }
+    }
     match (request.url().path(), request.method().as_ref()) {
         ("/", "HEAD") => Ok(Response::builder(Status::OK)
             .with_header(HeaderName::CONTENT_TYPE, "text_html")
@@ -996,9 +1848,14 @@ fn handle_request(
             .with_header(HeaderName::CONTENT_TYPE, "image/svg+xml")
             .unwrap()
             .with_body(LOGO)),
-        ("/query", "GET") => {
-            configure_and_evaluate_sparql_query(&store, &[url_query(request)], None, request)
-        }
+        ("/query", "GET") => configure_and_evaluate_sparql_query(
+            &store,
+            snapshots,
+            query_timeout,
+            &[url_query(request)],
+            None,
+            request,
+        ),
         ("/query", "POST") => {
             let content_type =
                 content_type(request).ok_or_else(|| bad_request("No Content-Type given"))?;
@@ -1007,6 +1864,8 @@ fn handle_request(
                     .map_err(bad_request)?;
                 configure_and_evaluate_sparql_query(
                     &store,
+                    snapshots,
+                    query_timeout,
                     &[url_query(request)],
                     Some(query),
                     request,
@@ -1020,6 +1879,8 @@ fn handle_request(
                     .map_err(bad_request)?;
                 configure_and_evaluate_sparql_query(
                     &store,
+                    snapshots,
+                    query_timeout,
                     &[url_query(request), &buffer],
                     None,
                     request,
@@ -1039,6 +1900,7 @@ fn handle_request(
                     .map_err(bad_request)?;
                 configure_and_evaluate_sparql_update(
                     &store,
+                    query_timeout,
                     &[url_query(request)],
                     Some(update),
                     request,
@@ -1052,6 +1914,7 @@ fn handle_request(
                     .map_err(bad_request)?;
                 configure_and_evaluate_sparql_update(
                     &store,
+                    query_timeout,
                     &[url_query(request), &buffer],
                     None,
                     request,
@@ -1060,17 +1923,110 @@ fn handle_request(
                 Err(unsupported_media_type(&content_type))
             }
         }
+        ("/snapshot", "POST") => {
+            if read_only {
+                return Err(the_server_is_read_only());
+            }
+            let id = snapshots.create(&store)?;
+            Ok(Response::builder(Status::CREATED)
+                .with_header(HeaderName::CONTENT_TYPE, "text/plain; charset=utf-8")
+                .unwrap()
+                .with_body(id))
+        }
+        (path, "DELETE") if path.starts_with("/snapshot/") => {
+            snapshots.remove(&path["/snapshot/".len()..])?;
+            Ok(Response::builder(Status::NO_CONTENT).build())
+        }
+        ("/replication/snapshot", "GET") => {
+            let snapshot = store.snapshot().map_err(internal_server_error)?;
+            let sequence_number = last_change_sequence_number(&snapshot)?;
+            // The named graphs are listed first so that the empty ones are also copied
+            let operations = snapshot
+                .named_graphs()
+                .map(|graph_name| graph_name.map(ChangeOperation::InsertNamedGraph))
+                .chain(
+                    snapshot
+                        .iter()
+                        .map(|quad| quad.map(ChangeOperation::Insert)),
+                );
+            let mut response = ReadForWrite::build_response(
+                move |w| Ok((w, operations)),
+                |(mut writer, mut operations)| {
+                    Ok(if let Some(operation) = operations.next() {
+                        write_change_operation(&mut writer, &operation?)?;
+                        Some((writer, operations))
+                    } else {
+                        None
+                    })
+                },
+                "text/plain; charset=utf-8",
+            )?;
+            response
+                .append_header(SEQUENCE_NUMBER_HEADER, sequence_number.to_string())
+                .map_err(internal_server_error)?;
+            Ok(response)
+        }
+        ("/replication/changes", "GET") => {
+            let from = url_query_parameter(request, "from")
+                .map(|from| u64::from_str(&from))
+                .transpose()
+                .map_err(bad_request)?
+                .unwrap_or(1)
+                .max(1);
+            let snapshot = store.snapshot().map_err(internal_server_error)?;
+            let sequence_number = last_change_sequence_number(&snapshot)?;
+            let mut changes = snapshot.changes(from);
+            let first = changes.next().transpose().map_err(internal_server_error)?;
+            if from <= sequence_number
+                && first.as_ref().map(|change| change.sequence_number) != Some(from)
+            {
+                return Err((
+                    Status::GONE,
+                    format!("The change {from} has been removed from the change log"),
+                ));
+            }
+            let mut response = ReadForWrite::build_response(
+                move |w| Ok((w, first.map(Ok).into_iter().chain(changes))),
+                |(mut writer, mut changes)| {
+                    Ok(if let Some(change) = changes.next() {
+                        let change = change?;
+                        write!(writer, "{} ", change.sequence_number)?;
+                        write_change_operation(&mut writer, &change.operation)?;
+                        Some((writer, changes))
+                    } else {
+                        None
+                    })
+                },
+                "text/plain; charset=utf-8",
+            )?;
+            response
+                .append_header(SEQUENCE_NUMBER_HEADER, sequence_number.to_string())
+                .map_err(internal_server_error)?;
+            Ok(response)
+        }
+        ("/replication/status", "GET") => {
+            let replica =
+                replica.ok_or_else(|| (Status::NOT_FOUND, "The server is not a replica".into()))?;
+            let (status, body) = replica.status_report()?;
+            Ok(Response::builder(status)
+                .with_header(HeaderName::CONTENT_TYPE, "application/json")
+                .map_err(internal_server_error)?
+                .with_body(body))
+        }
         (path, "GET") if path.starts_with("/store") => {
+            let snapshot = url_query_parameter(request, "snapshot")
+                .map(|id| snapshots.get(&id))
+                .transpose()?;
             if let Some(target) = store_target(request)? {
-                assert_that_graph_exists(&store, &target)?;
+                assert_that_graph_exists(&store, snapshot.as_ref(), &target)?;
                 let format = rdf_content_negotiation(request)?;
 
-                let quads = store.quads_for_pattern(
-                    None,
-                    None,
-                    None,
-                    Some(GraphName::from(target).as_ref()),
-                );
+                let graph_name = GraphName::from(target);
+                let quads = if let Some(snapshot) = snapshot {
+                    snapshot.quads_for_pattern(None, None, None, Some(graph_name.as_ref()))
+                } else {
+                    store.quads_for_pattern(None, None, None, Some(graph_name.as_ref()))
+                };
                 ReadForWrite::build_response(
                     move |w| {
                         Ok((
@@ -1100,7 +2056,7 @@ fn handle_request(
                     move |w| {
                         Ok((
                             RdfSerializer::from_format(format).serialize_to_write(w),
-                            store.iter(),
+                            snapshot.map_or_else(|| store.iter(), |s| s.iter()),
                         ))
                     },
                     |(mut writer, mut quads)| {
@@ -1201,7 +2157,7 @@ fn handle_request(
             if let Some(target) = store_target(request)? {
                 let format = RdfFormat::from_media_type(&content_type)
                     .ok_or_else(|| unsupported_media_type(&content_type))?;
-                let new = assert_that_graph_exists(&store, &target).is_ok();
+                let new = assert_that_graph_exists(&store, None, &target).is_ok();
                 web_load_graph(&store, request, format, &GraphName::from(target))?;
                 Ok(Response::builder(if new {
                     Status::CREATED
@@ -1228,7 +2184,7 @@ fn handle_request(
         }
         (path, "HEAD") if path.starts_with("/store") => {
             if let Some(target) = store_target(request)? {
-                assert_that_graph_exists(&store, &target)?;
+                assert_that_graph_exists(&store, None, &target)?;
             }
             Ok(Response::builder(Status::OK).build())
         }
@@ -1272,6 +2228,8 @@ fn url_query_parameter<'a>(request: &'a Request, param: &str) -> Option<Cow<'a,
 
 fn configure_and_evaluate_sparql_query(
     store: &Store,
+    snapshots: &Snapshots,
+    query_timeout: Option<Duration>,
     encoded: &[&[u8]],
     mut query: Option<String>,
     request: &Request,
@@ -1279,6 +2237,7 @@ fn configure_and_evaluate_sparql_query(
     let mut default_graph_uris = Vec::new();
     let mut named_graph_uris = Vec::new();
     let mut use_default_graph_as_union = false;
+    let mut snapshot = None;
     for encoded in encoded {
         for (k, v) in form_urlencoded::parse(encoded) {
             match k.as_ref() {
@@ -1291,6 +2250,12 @@ fn configure_and_evaluate_sparql_query(
                 "default-graph-uri" => default_graph_uris.push(v.into_owned()),
                 "union-default-graph" => use_default_graph_as_union = true,
                 "named-graph-uri" => named_graph_uris.push(v.into_owned()),
+                "snapshot" => {
+                    if snapshot.is_some() {
+                        return Err(bad_request("Multiple snapshot parameters provided"));
+                    }
+                    snapshot = Some(snapshots.get(&v)?)
+                }
                 _ => (),
             }
         }
@@ -1298,6 +2263,8 @@ fn configure_and_evaluate_sparql_query(
     let query = query.ok_or_else(|| bad_request("You should set the 'query' parameter"))?;
     evaluate_sparql_query(
         store,
+        snapshot,
+        query_timeout,
         &query,
         use_default_graph_as_union,
         default_graph_uris,
@@ -1308,6 +2275,8 @@ fn configure_and_evaluate_sparql_query(
 
 fn evaluate_sparql_query(
     store: &Store,
+    snapshot: Option<StoreSnapshot>,
+    query_timeout: Option<Duration>,
     query: &str,
     use_default_graph_as_union: bool,
     default_graph_uris: Vec<String>,
@@ -1340,15 +2309,29 @@ fn evaluate_sparql_query(
         );
     }
 
-    let results = store.query(query).map_err(internal_server_error)?;
+    let options = query_options(query_timeout);
+    let results = if let Some(snapshot) = snapshot {
+        snapshot.query_opt(query, options)
+    } else {
+        store.query_opt(query, options)
+    }
+    .map_err(evaluation_error)?;
     match results {
         QueryResults::Solutions(solutions) => {
             let format = query_results_content_negotiation(request)?;
+            // Errors before the first solution, like timeouts during sorting, are returned with a proper status
+            let variables = solutions.variables().to_vec();
+            let mut solutions = solutions.peekable();
+            if let Some(Err(_)) = solutions.peek() {
+                if let Some(Err(e)) = solutions.next() {
+                    return Err(evaluation_error(e));
+                }
+            }
             ReadForWrite::build_response(
                 move |w| {
                     Ok((
                         QueryResultsSerializer::from_format(format)
-                            .serialize_solutions_to_write(w, solutions.variables().to_vec())?,
+                            .serialize_solutions_to_write(w, variables)?,
                         solutions,
                     ))
                 },
@@ -1377,6 +2360,12 @@ fn evaluate_sparql_query(
         }
         QueryResults::Graph(triples) => {
             let format = rdf_content_negotiation(request)?;
+            let mut triples = triples.peekable();
+            if let Some(Err(_)) = triples.peek() {
+                if let Some(Err(e)) = triples.next() {
+                    return Err(evaluation_error(e));
+                }
+            }
             ReadForWrite::build_response(
                 move |w| {
                     Ok((
@@ -1401,6 +2390,7 @@ fn evaluate_sparql_query(
 
 fn configure_and_evaluate_sparql_update(
     store: &Store,
+    query_timeout: Option<Duration>,
     encoded: &[&[u8]],
     mut update: Option<String>,
     request: &Request,
@@ -1427,6 +2417,7 @@ fn configure_and_evaluate_sparql_update(
     let update = update.ok_or_else(|| bad_request("You should set the 'update' parameter"))?;
     evaluate_sparql_update(
         store,
+        query_timeout,
         &update,
         use_default_graph_as_union,
         default_graph_uris,
@@ -1437,6 +2428,7 @@ fn configure_and_evaluate_sparql_update(
 
 fn evaluate_sparql_update(
     store: &Store,
+    query_timeout: Option<Duration>,
     update: &str,
     use_default_graph_as_union: bool,
     default_graph_uris: Vec<String>,
@@ -1481,7 +2473,9 @@ fn evaluate_sparql_update(
             using.set_available_named_graphs(named_graph_uris.clone());
         }
     }
-    store.update(update).map_err(internal_server_error)?;
+    store
+        .update_opt(update, query_options(query_timeout))
+        .map_err(evaluation_error)?;
     Ok(Response::builder(Status::NO_CONTENT).build())
 }
 
@@ -1518,12 +2512,19 @@ fn store_target(request: &Request) -> Result<Option<NamedGraphName>, HttpError>
     }
 }
 
-fn assert_that_graph_exists(store: &Store, target: &NamedGraphName) -> Result<(), HttpError> {
+fn assert_that_graph_exists(
+    store: &Store,
+    snapshot: Option<&StoreSnapshot>,
+    target: &NamedGraphName,
+) -> Result<(), HttpError> {
     if match target {
         NamedGraphName::DefaultGraph => true,
-        NamedGraphName::NamedNode(target) => store
-            .contains_named_graph(target)
-            .map_err(internal_server_error)?,
+        NamedGraphName::NamedNode(target) => if let Some(snapshot) = snapshot {
+            snapshot.contains_named_graph(target)
+        } else {
+            store.contains_named_graph(target)
+        }
+        .map_err(internal_server_error)?,
     } {
         Ok(())
     } else {
@@ -1731,6 +2732,13 @@ fn the_server_is_read_only() -> HttpError {
     (Status::FORBIDDEN, "The server is read-only".into())
 }
 
+fn unknown_snapshot(id: &str) -> HttpError {
+    (
+        Status::NOT_FOUND,
+        format!("The snapshot {id} does not exist or has expired"),
+    )
+}
+
 fn unsupported_media_type(content_type: &str) -> HttpError {
     (
         Status::UNSUPPORTED_MEDIA_TYPE,
@@ -1743,6 +2751,15 @@ fn internal_server_error(message: impl fmt::Display) -> HttpError {
     (Status::INTERNAL_SERVER_ERROR, message.to_string())
 }
 
+fn evaluation_error(e: EvaluationError) -> HttpError {
+    match e {
+        EvaluationError::Timeout | EvaluationError::Cancelled => {
+            (Status::SERVICE_UNAVAILABLE, e.to_string())
+        }
+        _ => internal_server_error(e),
+    }
+}
+
 fn loader_to_http_error(e: LoaderError) -> HttpError {
     match e {
         LoaderError::Parsing(e) => bad_request(e),
@@ -1857,9 +2874,12 @@ mod tests {
     use flate2::write::GzEncoder;
     use flate2::Compression;
     use oxhttp::model::Method;
+    use oxigraph::model::Literal;
     use predicates::prelude::*;
     use std::fs::remove_dir_all;
     use std::io::read_to_string;
+    use std::net::TcpListener;
+    use std::process::{Child, Stdio};
 
     fn cli_command() -> Result<Command> {
         Ok(Command::from_std(
@@ -1970,9 +2990,58 @@ mod tests {
             .arg("--file")
             .arg(output_file.path())
             .assert()
-            .success();
-        output_file
-            .assert("<http://example.com/s> <http://example.com/p> <http://example.com/o> <http://example.com/g> .\n");
+            .success();
+        output_file
+            .assert("<http://example.com/s> <http://example.com/p> <http://example.com/o> <http://example.com/g> .\n");
+        Ok(())
+    }
+
+    #[test]
+    fn cli_load_with_store_options() -> Result<()> {
+        let store_dir = TempDir::new()?;
+        let input_file = NamedTempFile::new("input.nq")?;
+        input_file
+            .write_str("<http://example.com/s> <http://example.com/p> <http://example.com/o> <http://example.com/g> .")?;
+        cli_command()?
+            .arg("load")
+            .arg("--location")
+            .arg(store_dir.path())
+            .arg("--file")
+            .arg(input_file.path())
+            .arg("--block-cache-size")
+            .arg("1")
+            .arg("--write-buffer-size")
+            .arg("1")
+            .arg("--max-open-files")
+            .arg("128")
+            .arg("--no-compression")
+            .arg("--bloom-filter-bits-per-key")
+            .arg("10")
+            .assert()
+            .success();
+
+        let output_file = NamedTempFile::new("output.nq")?;
+        cli_command()?
+            .arg("dump")
+            .arg("--location")
+            .arg(store_dir.path())
+            .arg("--file")
+            .arg(output_file.path())
+            .assert()
+            .success();
+        output_file
+            .assert("<http://example.com/s> <http://example.com/p> <http://example.com/o> <http://example.com/g> .\n");
+        Ok(())
+    }
+
+    #[test]
+    fn cli_serve_store_options_without_location() -> Result<()> {
+        cli_command()?
+            .arg("serve")
+            .arg("--block-cache-size")
+            .arg("1")
+            .assert()
+            .failure();
         Ok(())
     }
 
@@ -2122,6 +3191,330 @@ mod tests {
         )
     }
 
+    #[test]
+    fn cli_pack() -> Result<()> {
+        let store_dir = initialized_cli_store(
+            "<http://example.com/s> <http://example.com/p> <http://example.com/o> .\n<http://example.com/g> { <http://example.com/s> <http://example.com/p> \"o\" }",
+        )?;
+        let pack_dir = TempDir::new()?;
+        let pack_file = pack_dir.path().join("store.oxpack");
+        cli_command()?
+            .arg("pack")
+            .arg("--location")
+            .arg(store_dir.path())
+            .arg("--file")
+            .arg(&pack_file)
+            .assert()
+            .success();
+
+        cli_command()?
+            .arg("dump")
+            .arg("--location")
+            .arg(&pack_file)
+            .arg("--format")
+            .arg("nq")
+            .assert()
+            .stdout("<http://example.com/s> <http://example.com/p> <http://example.com/o> .\n<http://example.com/s> <http://example.com/p> \"o\" <http://example.com/g> .\n")
+            .success();
+        cli_command()?
+            .arg("query")
+            .arg("--location")
+            .arg(&pack_file)
+            .arg("--query")
+            .arg("SELECT (COUNT(*) AS ?c) WHERE { GRAPH ?g { ?s ?p ?o } }")
+            .arg("--results-format")
+            .arg("csv")
+            .assert()
+            .stdout("c\r\n1\r\n")
+            .success();
+        Ok(())
+    }
+
+    #[test]
+    fn cli_incremental_backup_and_restore() -> Result<()> {
+        let store_dir = initialized_cli_store(
+            "<http://example.com/s> <http://example.com/p> <http://example.com/o> .",
+        )?;
+        let backup_dir = TempDir::new()?;
+        for _ in 0..3 {
+            cli_command()?
+                .arg("backup")
+                .arg("--location")
+                .arg(store_dir.path())
+                .arg("--destination")
+                .arg(backup_dir.path())
+                .arg("--incremental")
+                .arg("--keep")
+                .arg("2")
+                .assert()
+                .success();
+        }
+        cli_command()?
+            .arg("list-backups")
+            .arg("--backup")
+            .arg(backup_dir.path())
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with("2\t"))
+            .stdout(predicate::str::contains("\n3\t"))
+            .stdout(predicate::str::contains("\n1\t").not());
+
+        cli_command()?
+            .arg("update")
+            .arg("--location")
+            .arg(store_dir.path())
+            .arg("--update")
+            .arg("DELETE WHERE { ?s ?p ?o }")
+            .assert()
+            .success();
+        cli_command()?
+            .arg("restore")
+            .arg("--location")
+            .arg(store_dir.path())
+            .arg("--backup")
+            .arg(backup_dir.path())
+            .arg("--backup-id")
+            .arg("2")
+            .assert()
+            .success();
+        assert_cli_state(
+            &store_dir,
+            "<http://example.com/s> <http://example.com/p> <http://example.com/o> .\n",
+        )
+    }
+
+    /// A server process killed when dropped
+    struct ServerProcess(Child);
+
+    impl ServerProcess {
+        fn spawn(args: &[&OsStr]) -> Result<Self> {
+            Ok(Self(
+                escargot::CargoBuild::new()
+                    .bin("oxigraph")
+                    .manifest_path(format!("{}/Cargo.toml", env!("CARGO_MANIFEST_DIR")))
+                    .run()?
+                    .command()
+                    .args(args)
+                    .stderr(Stdio::null())
+                    .spawn()?,
+            ))
+        }
+    }
+
+    impl Drop for ServerProcess {
+        fn drop(&mut self) {
+            self.0.kill().ok();
+            self.0.wait().ok();
+        }
+    }
+
+    fn free_address() -> Result<String> {
+        Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.to_string())
+    }
+
+    /// Executes the request until it returns the expected body
+    fn wait_for_body(client: &Client, url: &str, accept: &str, expected: &str) -> Result<()> {
+        let start = Instant::now();
+        let mut last = String::new();
+        while start.elapsed() < Duration::from_secs(60) {
+            let request = Request::builder(Method::GET, url.parse()?)
+                .with_header(HeaderName::ACCEPT, accept.to_owned())?
+                .build();
+            if let Ok(response) = client.request(request) {
+                if response.status() == Status::OK {
+                    last = response.into_body().to_string()?;
+                    if last == expected {
+                        return Ok(());
+                    }
+                }
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        bail!("{url} returned {last} instead of {expected}")
+    }
+
+    #[test]
+    fn cli_serve_replica() -> Result<()> {
+        let primary_dir = TempDir::new()?;
+        let replica_dir = TempDir::new()?;
+        let primary_address = free_address()?;
+        let replica_address = free_address()?;
+        let primary = format!("http://{primary_address}");
+        let replica = format!("http://{replica_address}");
+        let client = Client::new();
+        let query = "query=SELECT%20?o%20WHERE%20{%20{%20?s%20?p%20?o%20}%20UNION%20{%20GRAPH%20?g%20{%20?s%20?p%20?o%20}%20}%20}%20ORDER%20BY%20?o";
+
+        let _primary_process = ServerProcess::spawn(&[
+            "serve".as_ref(),
+            "--location".as_ref(),
+            primary_dir.path().as_ref(),
+            "--bind".as_ref(),
+            primary_address.as_ref(),
+            "--replication".as_ref(),
+        ])?;
+        wait_for_body(
+            &client,
+            &format!("{primary}/query?{query}"),
+            "text/csv",
+            "o\r\n",
+        )?;
+        let update = |update: &str| -> Result<()> {
+            let response = client.request(
+                Request::builder(Method::POST, format!("{primary}/update").parse()?)
+                    .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")?
+                    .with_body(update.to_owned()),
+            )?;
+            assert_eq!(response.status(), Status::NO_CONTENT);
+            Ok(())
+        };
+        update("INSERT DATA { <http://example.com/s> <http://example.com/p> 1, 2 }; CREATE GRAPH <http://example.com/e>")?;
+
+        // The replica copies the primary content
+        let mut replica_process = ServerProcess::spawn(&[
+            "serve-replica".as_ref(),
+            "--primary".as_ref(),
+            primary.as_ref(),
+            "--location".as_ref(),
+            replica_dir.path().as_ref(),
+            "--bind".as_ref(),
+            replica_address.as_ref(),
+            "--poll-interval".as_ref(),
+            "0.1".as_ref(),
+            "--max-lag".as_ref(),
+            "60".as_ref(),
+        ])?;
+        wait_for_body(
+            &client,
+            &format!("{replica}/query?{query}"),
+            "text/csv",
+            "o\r\n1\r\n2\r\n",
+        )?;
+        // The empty named graphs are also copied
+        wait_for_body(
+            &client,
+            &format!("{replica}/store?graph=http%3A%2F%2Fexample.com%2Fe"),
+            "application/n-triples",
+            "",
+        )?;
+
+        // The replica follows the primary changes
+        update("DELETE DATA { <http://example.com/s> <http://example.com/p> 1 }; INSERT DATA { GRAPH <http://example.com/g> { <http://example.com/s> <http://example.com/p> 3 } }; DROP GRAPH <http://example.com/e>; CREATE GRAPH <http://example.com/f>")?;
+        wait_for_body(
+            &client,
+            &format!("{replica}/query?{query}"),
+            "text/csv",
+            "o\r\n2\r\n3\r\n",
+        )?;
+        wait_for_body(
+            &client,
+            &format!("{replica}/store?graph=http%3A%2F%2Fexample.com%2Ff"),
+            "application/n-triples",
+            "",
+        )?;
+        let response = client.request(
+            Request::builder(
+                Method::GET,
+                format!("{replica}/store?graph=http%3A%2F%2Fexample.com%2Fe").parse()?,
+            )
+            .build(),
+        )?;
+        assert_eq!(response.status(), Status::NOT_FOUND);
+        let response = client.request(
+            Request::builder(
+                Method::GET,
+                format!("{replica}/replication/status").parse()?,
+            )
+            .build(),
+        )?;
+        assert_eq!(response.status(), Status::OK);
+        assert!(response
+            .into_body()
+            .to_string()?
+            .starts_with(r#"{"applied_sequence_number":7,"primary_sequence_number":7,"lag":"#));
+        let response = client.request(
+            Request::builder(Method::POST, format!("{replica}/update").parse()?)
+                .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")?
+                .with_body("CLEAR ALL"),
+        )?;
+        assert_eq!(response.status(), Status::FORBIDDEN);
+
+        // The replica resumes from its persisted state after a restart
+        drop(replica_process);
+        update("INSERT DATA { <http://example.com/s> <http://example.com/p> 4 }")?;
+        replica_process = ServerProcess::spawn(&[
+            "serve-replica".as_ref(),
+            "--primary".as_ref(),
+            primary.as_ref(),
+            "--location".as_ref(),
+            replica_dir.path().as_ref(),
+            "--bind".as_ref(),
+            replica_address.as_ref(),
+            "--poll-interval".as_ref(),
+            "0.1".as_ref(),
+        ])?;
+        wait_for_body(
+            &client,
+            &format!("{replica}/query?{query}"),
+            "text/csv",
+            "o\r\n2\r\n3\r\n4\r\n",
+        )?;
+        drop(replica_process);
+        Ok(())
+    }
+
+    #[test]
+    fn cli_migrate() -> Result<()> {
+        let store_dir = initialized_cli_store("<http://example.com/s> <http://example.com/p> 1 .")?;
+        let latest = format!("Latest storage version: {LATEST_STORAGE_VERSION}\n");
+        cli_command()?
+            .arg("migrate")
+            .arg("--location")
+            .arg(store_dir.path())
+            .assert()
+            .success()
+            .stdout(format!(
+                "Storage version: {LATEST_STORAGE_VERSION}\n{latest}"
+            ));
+
+        cli_command()?
+            .arg("migrate")
+            .arg("--location")
+            .arg(store_dir.path())
+            .arg("--target-version")
+            .arg("1")
+            .assert()
+            .success();
+        cli_command()?
+            .arg("migrate")
+            .arg("--location")
+            .arg(store_dir.path())
+            .assert()
+            .success()
+            .stdout(format!("Storage version: 1\n{latest}"));
+        // The read-only mode does not migrate
+        cli_command()?
+            .arg("dump")
+            .arg("--location")
+            .arg(store_dir.path())
+            .arg("--format")
+            .arg("nq")
+            .assert()
+            .failure();
+
+        cli_command()?
+            .arg("migrate")
+            .arg("--location")
+            .arg(store_dir.path())
+            .arg("--target-version")
+            .arg(LATEST_STORAGE_VERSION.to_string())
+            .assert()
+            .success();
+        assert_cli_state(
+            &store_dir,
+            "<http://example.com/s> <http://example.com/p> \"1\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n",
+        )
+    }
+
     #[test]
     fn cli_ask_query_inline() -> Result<()> {
         let store_dir = initialized_cli_store(
@@ -2351,6 +3744,206 @@ mod tests {
         )
     }
 
+    #[test]
+    fn query_timeout() -> Result<()> {
+        let mut server = ServerTest::new()?;
+        server.query_timeout = Some(Duration::ZERO);
+
+        let request = Request::builder(Method::POST, "http://localhost/store".parse()?)
+            .with_header(HeaderName::CONTENT_TYPE, "application/trig")?
+            .with_body("<http://example.com> <http://example.com> <http://example.com> .");
+        server.test_status(request, Status::NO_CONTENT)?;
+
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/query?query=SELECT%20?s%20?p%20?o%20WHERE%20{%20?s%20?p%20?o%20}"
+                .parse()?,
+        )
+        .build();
+        server.test_status(request, Status::SERVICE_UNAVAILABLE)?;
+
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/query?query=ASK%20{%20?s%20?p%20?o%20}".parse()?,
+        )
+        .build();
+        server.test_status(request, Status::SERVICE_UNAVAILABLE)?;
+
+        let request = Request::builder(Method::POST, "http://localhost/update".parse()?)
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")?
+            .with_body("DELETE WHERE { ?s ?p ?o }");
+        server.test_status(request, Status::SERVICE_UNAVAILABLE)?;
+        assert_eq!(server.store.len()?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn query_snapshot() -> Result<()> {
+        let server = ServerTest::new()?;
+        let request = Request::builder(Method::POST, "http://localhost/store".parse()?)
+            .with_header(HeaderName::CONTENT_TYPE, "application/trig")?
+            .with_body("<http://example.com> <http://example.com> <http://example.com> .");
+        server.test_status(request, Status::NO_CONTENT)?;
+
+        let mut response = server
+            .exec(Request::builder(Method::POST, "http://localhost/snapshot".parse()?).build());
+        assert_eq!(response.status(), Status::CREATED);
+        let id = read_to_string(response.body_mut())?;
+
+        let request = Request::builder(Method::DELETE, "http://localhost/store".parse()?).build();
+        server.test_status(request, Status::NO_CONTENT)?;
+
+        let request = Request::builder(
+            Method::GET,
+            format!("http://localhost/query?query=SELECT%20?s%20WHERE%20{{%20?s%20?p%20?o%20}}&snapshot={id}")
+                .parse()?,
+        )
+        .with_header(HeaderName::ACCEPT, "text/csv")?
+        .build();
+        server.test_body(request, "s\r\nhttp://example.com\r\n")?;
+        let request = Request::builder(
+            Method::GET,
+            format!("http://localhost/store?default&snapshot={id}").parse()?,
+        )
+        .with_header(HeaderName::ACCEPT, "application/n-triples")?
+        .build();
+        server.test_body(
+            request,
+            "<http://example.com> <http://example.com> <http://example.com> .\n",
+        )?;
+        let request = Request::builder(Method::GET, "http://localhost/store?default".parse()?)
+            .with_header(HeaderName::ACCEPT, "application/n-triples")?
+            .build();
+        server.test_body(request, "")?;
+
+        let request = Request::builder(
+            Method::DELETE,
+            format!("http://localhost/snapshot/{id}").parse()?,
+        )
+        .build();
+        server.test_status(request, Status::NO_CONTENT)?;
+        let request = Request::builder(
+            Method::GET,
+            format!("http://localhost/store?snapshot={id}").parse()?,
+        )
+        .build();
+        server.test_status(request, Status::NOT_FOUND)
+    }
+
+    #[test]
+    fn replica_refuses_queries_while_copying() -> Result<()> {
+        let (_, replica) = Replica::open("http://localhost:7878", None, None)?;
+        assert_eq!(
+            replica.check_lag().map_err(|(status, _)| status),
+            Err(Status::SERVICE_UNAVAILABLE)
+        );
+        replica.set_applied_sequence_number(Some(1))?;
+        replica.status()?.last_sync = Some(Instant::now());
+        assert_eq!(replica.check_lag().map_err(|(status, _)| status), Ok(()));
+
+        // The primary change log does not contain the following changes anymore
+        replica.set_applied_sequence_number(None)?;
+        assert!(replica.status()?.last_sync.is_none());
+        assert_eq!(
+            replica.check_lag().map_err(|(status, _)| status),
+            Err(Status::SERVICE_UNAVAILABLE)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn get_replication_changes() -> Result<()> {
+        let server = ServerTest::new()?;
+        let request =
+            Request::builder(Method::GET, "http://localhost/replication/changes".parse()?).build();
+        server.test_status(request, Status::NOT_FOUND)?;
+
+        server.store.enable_change_log()?;
+        let request = Request::builder(Method::POST, "http://localhost/update".parse()?)
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")?
+            .with_body("INSERT DATA { GRAPH <http://example.com/g> { <http://example.com/s> <http://example.com/p> <http://example.com/o> } }");
+        server.test_status(request, Status::NO_CONTENT)?;
+        let request = Request::builder(Method::POST, "http://localhost/update".parse()?)
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")?
+            .with_body("INSERT DATA { <http://example.com/s> <http://example.com/p> \"o\" }");
+        server.test_status(request, Status::NO_CONTENT)?;
+        let request =
+            Request::builder(Method::DELETE, "http://localhost/store?default".parse()?).build();
+        server.test_status(request, Status::NO_CONTENT)?;
+        let request = Request::builder(Method::POST, "http://localhost/update".parse()?)
+            .with_header(HeaderName::CONTENT_TYPE, "application/sparql-update")?
+            .with_body("CREATE GRAPH <http://example.com/e>; DROP GRAPH <http://example.com/e>");
+        server.test_status(request, Status::NO_CONTENT)?;
+
+        let mut response = server.exec(
+            Request::builder(
+                Method::GET,
+                "http://localhost/replication/changes?from=2".parse()?,
+            )
+            .build(),
+        );
+        assert_eq!(response.status(), Status::OK);
+        assert_eq!(
+            response
+                .header(&HeaderName::try_from(SEQUENCE_NUMBER_HEADER)?)
+                .map(AsRef::as_ref),
+            Some(b"5".as_slice())
+        );
+        let body = read_to_string(response.body_mut())?;
+        let quad = Quad::new(
+            NamedNode::new("http://example.com/s")?,
+            NamedNode::new("http://example.com/p")?,
+            Literal::from("o"),
+            GraphName::DefaultGraph,
+        );
+        let graph_name = NamedOrBlankNode::from(NamedNode::new("http://example.com/e")?);
+        assert_eq!(
+            body.lines().map(parse_change).collect::<Result<Vec<_>>>()?,
+            [
+                (2, ChangeOperation::Insert(quad.clone())),
+                (3, ChangeOperation::Remove(quad)),
+                (4, ChangeOperation::InsertNamedGraph(graph_name.clone())),
+                (5, ChangeOperation::RemoveNamedGraph(graph_name)),
+            ]
+        );
+
+        let mut response = server.exec(
+            Request::builder(
+                Method::GET,
+                "http://localhost/replication/snapshot".parse()?,
+            )
+            .build(),
+        );
+        assert_eq!(response.status(), Status::OK);
+        assert_eq!(
+            read_to_string(response.body_mut())?,
+            "AG <http://example.com/g> .\nA <http://example.com/s> <http://example.com/p> <http://example.com/o> <http://example.com/g> .\n"
+        );
+
+        server.store.truncate_changes(3)?;
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/replication/changes?from=2".parse()?,
+        )
+        .build();
+        server.test_status(request, Status::GONE)?;
+        let request = Request::builder(
+            Method::GET,
+            "http://localhost/replication/changes?from=6".parse()?,
+        )
+        .build();
+        server.test_body(request, "")
+    }
+
+    #[test]
+    fn post_snapshot_read_only() -> Result<()> {
+        let request = Request::builder(Method::POST, "http://localhost/snapshot".parse()?).build();
+        ServerTest::check_status(
+            ServerTest::new()?.exec_read_only(request),
+            Status::FORBIDDEN,
+        )
+    }
+
     #[test]
     fn get_query_accept_star() -> Result<()> {
         let request = Request::builder(
@@ -2968,23 +4561,41 @@ mod tests {
 
     struct ServerTest {
         store: Store,
+        snapshots: Snapshots,
+        query_timeout: Option<Duration>,
     }
 
     impl ServerTest {
         fn new() -> Result<Self> {
             Ok(Self {
                 store: Store::new()?,
+                snapshots: Snapshots::default(),
+                query_timeout: None,
             })
         }
 
         fn exec(&self, mut request: Request) -> Response {
-            handle_request(&mut request, self.store.clone(), false)
-                .unwrap_or_else(|(status, message)| error(status, message))
+            handle_request(
+                &mut request,
+                self.store.clone(),
+                &self.snapshots,
+                false,
+                self.query_timeout,
+                None,
+            )
+            .unwrap_or_else(|(status, message)| error(status, message))
         }
 
         fn exec_read_only(&self, mut request: Request) -> Response {
-            handle_request(&mut request, self.store.clone(), true)
-                .unwrap_or_else(|(status, message)| error(status, message))
+            handle_request(
+                &mut request,
+                self.store.clone(),
+                &self.snapshots,
+                true,
+                self.query_timeout,
+                None,
+            )
+            .unwrap_or_else(|(status, message)| error(status, message))
         }
 
         fn test_status(&self, request: Request, expected_status: Status) -> Result<()> {
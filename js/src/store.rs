@@ -1,10 +1,10 @@
 use crate::format_err;
 use crate::model::*;
 use crate::utils::to_err;
-use js_sys::{Array, Map};
+use js_sys::{Array, Function, Map, Object, Reflect};
 use oxigraph::io::{RdfFormat, RdfParser};
 use oxigraph::model::*;
-use oxigraph::sparql::QueryResults;
+use oxigraph::sparql::{AggregateFunctionAccumulator, QueryOptions, QueryResults, SparqlParser};
 use oxigraph::store::Store;
 use wasm_bindgen::prelude::*;
 
@@ -102,8 +102,33 @@ impl JsStore {
             .into_boxed_slice())
     }
 
-    pub fn query(&self, query: &str) -> Result<JsValue, JsValue> {
-        let results = self.store.query(query).map_err(to_err)?;
+    pub fn query(&self, query: &str, options: &JsValue) -> Result<JsValue, JsValue> {
+        let mut parser = SparqlParser::new();
+        let mut query_options = QueryOptions::default();
+        if !options.is_null() && !options.is_undefined() {
+            let custom_aggregate_functions =
+                Reflect::get(options, &JsValue::from_str("customAggregateFunctions"))?;
+            if !custom_aggregate_functions.is_null() && !custom_aggregate_functions.is_undefined() {
+                for entry in Object::entries(&Object::from(custom_aggregate_functions)).iter() {
+                    let entry = Array::from(&entry);
+                    let name = NamedNode::new(entry.get(0).as_string().ok_or_else(|| {
+                        format_err!("Custom aggregate function names should be strings")
+                    })?)
+                    .map_err(to_err)?;
+                    let factory = JsObject(entry.get(1).dyn_into::<Function>().map_err(|_| {
+                        format_err!("The custom aggregate function {name} should be a function")
+                    })?);
+                    parser = parser.with_custom_aggregate_function(name.clone());
+                    query_options = query_options.with_custom_aggregate_function(name, move || {
+                        Box::new(JsAggregateFunctionAccumulator {
+                            inner: factory.get().call0(&JsValue::NULL).ok(),
+                        })
+                    });
+                }
+            }
+        }
+        let query = parser.parse_query(query).map_err(to_err)?;
+        let results = self.store.query_opt(query, query_options).map_err(to_err)?;
         let output = match results {
             QueryResults::Solutions(solutions) => {
                 let results = Array::new();
@@ -186,6 +211,24 @@ impl JsStore {
             .map_err(to_err)?;
         String::from_utf8(buffer).map_err(to_err)
     }
+
+    #[wasm_bindgen(js_name = saveSnapshot)]
+    pub fn save_snapshot(&self) -> Result<Box<[u8]>, JsValue> {
+        Ok(self
+            .store
+            .save_to_write(Vec::new())
+            .map_err(to_err)?
+            .into_boxed_slice())
+    }
+
+    #[wasm_bindgen(js_name = loadSnapshot)]
+    pub fn load_snapshot(data: &[u8]) -> Result<JsStore, JsValue> {
+        console_error_panic_hook::set_once();
+
+        Ok(Self {
+            store: Store::load_snapshot_from_read(data).map_err(to_err)?,
+        })
+    }
 }
 
 fn rdf_format(format: &str) -> Result<RdfFormat, JsValue> {
@@ -197,3 +240,59 @@ fn rdf_format(format: &str) -> Result<RdfFormat, JsValue> {
             .ok_or_else(|| format_err!("Not supported RDF format extension: {format}"))
     }
 }
+
+/// Wraps a JavaScript object with `add(term)` and `finish()` methods.
+///
+/// If one of the JavaScript calls fails, the aggregate value is unbound.
+struct JsAggregateFunctionAccumulator {
+    inner: Option<JsValue>,
+}
+
+impl JsAggregateFunctionAccumulator {
+    fn call(&self, method: &str, args: &Array) -> Option<JsValue> {
+        let inner = self.inner.as_ref()?;
+        Reflect::get(inner, &JsValue::from_str(method))
+            .ok()?
+            .dyn_into::<Function>()
+            .ok()?
+            .apply(inner, args)
+            .ok()
+    }
+}
+
+impl AggregateFunctionAccumulator for JsAggregateFunctionAccumulator {
+    fn add(&mut self, element: Term) {
+        let args = Array::of1(&JsTerm::from(element).into());
+        if self.call("add", &args).is_none() {
+            self.inner = None;
+        }
+    }
+
+    fn finish(&mut self) -> Option<Term> {
+        let result = self.call("finish", &Array::new())?;
+        self.inner = None;
+        if result.is_null() || result.is_undefined() {
+            return None;
+        }
+        FROM_JS.with(|c| c.to_term(&result)).ok()?.try_into().ok()
+    }
+}
+
+/// A JavaScript value that is allowed to be shared by the query options.
+///
+/// JavaScript is single threaded so it is never sent to another thread.
+struct JsObject<T>(T);
+
+impl<T> JsObject<T> {
+    fn get(&self) -> &T {
+        &self.0
+    }
+}
+
+#[allow(unsafe_code)]
+// SAFETY: JavaScript is single threaded
+unsafe impl<T> Send for JsObject<T> {}
+
+#[allow(unsafe_code)]
+// SAFETY: JavaScript is single threaded
+unsafe impl<T> Sync for JsObject<T> {}
@@ -90,6 +90,36 @@ describe("Store", () => {
             const results = store.query("SELECT (RAND() AS ?y) WHERE {}");
             assert.strictEqual(1, results.length);
         });
+
+        it("SELECT with custom aggregate function", () => {
+            const store = new Store([
+                dataModel.quad(ex, ex, dataModel.literal("b")),
+                dataModel.quad(ex, ex, dataModel.literal("a")),
+            ]);
+            const results = store.query(
+                "SELECT (<http://example.com/concat>(?o) AS ?c) (<http://example.com/failing>(?o) AS ?f) WHERE { ?s ?p ?o }",
+                {
+                    customAggregateFunctions: {
+                        "http://example.com/concat": () => {
+                            const values = [];
+                            return {
+                                add: (term) => values.push(term.value),
+                                finish: () => dataModel.literal(values.sort().join("")),
+                            };
+                        },
+                        "http://example.com/failing": () => ({
+                            add: () => {
+                                throw new Error("failure");
+                            },
+                            finish: () => ex,
+                        }),
+                    },
+                },
+            );
+            assert.strictEqual(1, results.length);
+            assert(dataModel.literal("ab").equals(results[0].get("c")));
+            assert.strictEqual(undefined, results[0].get("f"));
+        });
     });
 
     describe("#update()", () => {
@@ -189,4 +219,13 @@ describe("Store", () => {
             assert.strictEqual("", store.dump("application/n-triples", dataModel.defaultGraph()));
         });
     });
+
+    describe("#saveSnapshot()", () => {
+        it("save and load back the store content", () => {
+            const store = new Store([dataModel.quad(ex, ex, ex, ex)]);
+            const restored = Store.loadSnapshot(store.saveSnapshot());
+            assert.strictEqual(1, restored.size);
+            assert(restored.has(dataModel.quad(ex, ex, ex, ex)));
+        });
+    });
 });
@@ -0,0 +1,986 @@
+//! Planar geometries used to implement [GeoSPARQL](https://docs.ogc.org/is/22-047r1/22-047r1.html) functions.
+//!
+//! Geometries are read from [WKT](https://www.ogc.org/standard/sfa/) literals.
+//! Only the default `CRS84` coordinate reference system is supported: the coordinates are longitudes and latitudes.
+//! Computations are done on the plane, except [`Geometry::distance_in_meters`].
+
+use std::fmt;
+use std::str::FromStr;
+
+pub const WKT_LITERAL: &str = "http://www.opengis.net/ont/geosparql#wktLiteral";
+const CRS84: &str = "http://www.opengis.net/def/crs/OGC/1.3/CRS84";
+const EARTH_RADIUS_IN_METERS: f64 = 6_371_008.8;
+/// Number of sides of the polygons approximating circles
+const CIRCLE_SIDES: u32 = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Coord {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Coord {
+    fn distance(self, other: Self) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+/// A geometry following the OGC simple features model.
+///
+/// Polygons are lists of rings, the first one being the exterior ring.
+/// Rings are closed: their first and last coordinates are equal.
+#[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)] // The variants are named after the simple features types
+pub enum Geometry {
+    Point(Coord),
+    LineString(Vec<Coord>),
+    Polygon(Vec<Vec<Coord>>),
+    MultiPoint(Vec<Coord>),
+    MultiLineString(Vec<Vec<Coord>>),
+    MultiPolygon(Vec<Vec<Vec<Coord>>>),
+    GeometryCollection(Vec<Geometry>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl BoundingBox {
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+    }
+
+    fn extend(&mut self, coord: Coord) {
+        self.min_x = self.min_x.min(coord.x);
+        self.min_y = self.min_y.min(coord.y);
+        self.max_x = self.max_x.max(coord.x);
+        self.max_y = self.max_y.max(coord.y);
+    }
+}
+
+/// The geometry split into points, line strings and polygons
+#[derive(Default)]
+struct Components<'a> {
+    points: Vec<Coord>,
+    lines: Vec<&'a [Coord]>,
+    polygons: Vec<&'a [Vec<Coord>]>,
+}
+
+impl<'a> Components<'a> {
+    fn new(geometry: &'a Geometry) -> Self {
+        let mut components = Self::default();
+        components.add(geometry);
+        components
+    }
+
+    fn add(&mut self, geometry: &'a Geometry) {
+        match geometry {
+            Geometry::Point(p) => self.points.push(*p),
+            Geometry::LineString(l) => self.add_line(l),
+            Geometry::Polygon(p) => self.add_polygon(p),
+            Geometry::MultiPoint(p) => self.points.extend_from_slice(p),
+            Geometry::MultiLineString(l) => {
+                for l in l {
+                    self.add_line(l);
+                }
+            }
+            Geometry::MultiPolygon(p) => {
+                for p in p {
+                    self.add_polygon(p);
+                }
+            }
+            Geometry::GeometryCollection(g) => {
+                for g in g {
+                    self.add(g);
+                }
+            }
+        }
+    }
+
+    fn add_line(&mut self, line: &'a [Coord]) {
+        match line {
+            [] => (),
+            [p] => self.points.push(*p),
+            _ => self.lines.push(line),
+        }
+    }
+
+    fn add_polygon(&mut self, polygon: &'a [Vec<Coord>]) {
+        if !polygon.is_empty() {
+            self.polygons.push(polygon);
+        }
+    }
+
+    fn segments(&self) -> impl Iterator<Item = (Coord, Coord)> + '_ {
+        self.lines
+            .iter()
+            .copied()
+            .chain(
+                self.polygons
+                    .iter()
+                    .flat_map(|p| p.iter().map(Vec::as_slice)),
+            )
+            .flat_map(segments)
+    }
+
+    fn vertices(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.points.iter().copied().chain(
+            self.lines
+                .iter()
+                .copied()
+                .chain(
+                    self.polygons
+                        .iter()
+                        .flat_map(|p| p.iter().map(Vec::as_slice)),
+                )
+                .flat_map(|l| l.iter().copied()),
+        )
+    }
+
+    fn is_empty(&self) -> bool {
+        self.points.is_empty() && self.lines.is_empty() && self.polygons.is_empty()
+    }
+
+    /// Returns if the point is in the closure of the geometry
+    fn covers_point(&self, point: Coord) -> bool {
+        self.points.contains(&point)
+            || self
+                .lines
+                .iter()
+                .any(|l| segments(l).any(|(a, b)| is_on_segment(point, a, b)))
+            || self
+                .polygons
+                .iter()
+                .any(|p| point_in_polygon(point, p) != Location::Outside)
+    }
+
+    /// Returns if the segment is in the closure of the geometry
+    fn covers_segment(&self, start: Coord, end: Coord) -> bool {
+        // We split the segment where it intersects the geometry boundaries and check each piece
+        let mut cuts = vec![0., 1.];
+        for (a, b) in self.segments() {
+            cuts.extend(segment_intersection_parameters(start, end, a, b));
+        }
+        cuts.sort_unstable_by(f64::total_cmp);
+        cuts.dedup();
+        let at = |t: f64| Coord {
+            x: start.x + t * (end.x - start.x),
+            y: start.y + t * (end.y - start.y),
+        };
+        cuts.iter().all(|t| self.covers_point(at(*t)))
+            && cuts
+                .iter()
+                .zip(cuts.iter().skip(1))
+                .all(|(t1, t2)| self.covers_point(at((t1 + t2) / 2.)))
+    }
+}
+
+#[derive(Eq, PartialEq)]
+enum Location {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+/// The segments between consecutive coordinates
+fn segments(line: &[Coord]) -> impl Iterator<Item = (Coord, Coord)> + '_ {
+    line.iter().copied().zip(line.iter().copied().skip(1))
+}
+
+fn orientation(a: Coord, b: Coord, c: Coord) -> f64 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+fn is_on_segment(p: Coord, a: Coord, b: Coord) -> bool {
+    orientation(a, b, p) == 0.
+        && p.x >= a.x.min(b.x)
+        && p.x <= a.x.max(b.x)
+        && p.y >= a.y.min(b.y)
+        && p.y <= a.y.max(b.y)
+}
+
+fn segments_intersect(a: Coord, b: Coord, c: Coord, d: Coord) -> bool {
+    let o1 = orientation(a, b, c);
+    let o2 = orientation(a, b, d);
+    let o3 = orientation(c, d, a);
+    let o4 = orientation(c, d, b);
+    if ((o1 > 0. && o2 < 0.) || (o1 < 0. && o2 > 0.))
+        && ((o3 > 0. && o4 < 0.) || (o3 < 0. && o4 > 0.))
+    {
+        return true;
+    }
+    is_on_segment(c, a, b)
+        || is_on_segment(d, a, b)
+        || is_on_segment(a, c, d)
+        || is_on_segment(b, c, d)
+}
+
+/// Returns the positions on the segment `[start, end]` (between 0 and 1) where it meets the segment `[other_start, other_end]`
+fn segment_intersection_parameters(
+    start: Coord,
+    end: Coord,
+    other_start: Coord,
+    other_end: Coord,
+) -> Vec<f64> {
+    let direction = Coord {
+        x: end.x - start.x,
+        y: end.y - start.y,
+    };
+    let other_direction = Coord {
+        x: other_end.x - other_start.x,
+        y: other_end.y - other_start.y,
+    };
+    let denominator = direction.x * other_direction.y - direction.y * other_direction.x;
+    let starts_offset = Coord {
+        x: other_start.x - start.x,
+        y: other_start.y - start.y,
+    };
+    if denominator == 0. {
+        // Parallel segments: the ends of the overlap if they are collinear
+        if starts_offset.x * direction.y - starts_offset.y * direction.x != 0. {
+            return Vec::new();
+        }
+        let length = direction.x * direction.x + direction.y * direction.y;
+        if length == 0. {
+            return Vec::new();
+        }
+        return [other_start, other_end]
+            .into_iter()
+            .map(|point| {
+                ((point.x - start.x) * direction.x + (point.y - start.y) * direction.y) / length
+            })
+            .filter(|position| (0. ..=1.).contains(position))
+            .collect();
+    }
+    let position =
+        (starts_offset.x * other_direction.y - starts_offset.y * other_direction.x) / denominator;
+    let other_position =
+        (starts_offset.x * direction.y - starts_offset.y * direction.x) / denominator;
+    if (0. ..=1.).contains(&position) && (0. ..=1.).contains(&other_position) {
+        vec![position]
+    } else {
+        Vec::new()
+    }
+}
+
+fn point_in_polygon(point: Coord, polygon: &[Vec<Coord>]) -> Location {
+    let mut inside = false;
+    for ring in polygon {
+        for (a, b) in segments(ring) {
+            if is_on_segment(point, a, b) {
+                return Location::Boundary;
+            }
+            if (a.y > point.y) != (b.y > point.y)
+                && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+            {
+                inside = !inside;
+            }
+        }
+    }
+    if inside {
+        Location::Inside
+    } else {
+        Location::Outside
+    }
+}
+
+fn point_segment_distance(p: Coord, a: Coord, b: Coord) -> (f64, Coord) {
+    let length = (b.x - a.x).powi(2) + (b.y - a.y).powi(2);
+    let closest = if length == 0. {
+        a
+    } else {
+        let t = (((p.x - a.x) * (b.x - a.x) + (p.y - a.y) * (b.y - a.y)) / length).clamp(0., 1.);
+        Coord {
+            x: a.x + t * (b.x - a.x),
+            y: a.y + t * (b.y - a.y),
+        }
+    };
+    (p.distance(closest), closest)
+}
+
+impl Geometry {
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let mut vertices = Components::new(self)
+            .vertices()
+            .collect::<Vec<_>>()
+            .into_iter();
+        let first = vertices.next()?;
+        let mut bbox = BoundingBox {
+            min_x: first.x,
+            min_y: first.y,
+            max_x: first.x,
+            max_y: first.y,
+        };
+        for v in vertices {
+            bbox.extend(v);
+        }
+        Some(bbox)
+    }
+
+    /// [`geof:sfIntersects`](https://docs.ogc.org/is/22-047r1/22-047r1.html#_simple_features_relation_family)
+    pub fn intersects(&self, other: &Self) -> bool {
+        let (Some(a_bbox), Some(b_bbox)) = (self.bounding_box(), other.bounding_box()) else {
+            return false;
+        };
+        if !a_bbox.intersects(&b_bbox) {
+            return false;
+        }
+        let a = Components::new(self);
+        let b = Components::new(other);
+        a.points.iter().any(|p| b.covers_point(*p))
+            || b.points.iter().any(|p| a.covers_point(*p))
+            || a.segments().any(|(a1, a2)| {
+                b.segments()
+                    .any(|(b1, b2)| segments_intersect(a1, a2, b1, b2))
+            })
+            || a.vertices().any(|p| {
+                b.polygons
+                    .iter()
+                    .any(|poly| point_in_polygon(p, poly) != Location::Outside)
+            })
+            || b.vertices().any(|p| {
+                a.polygons
+                    .iter()
+                    .any(|poly| point_in_polygon(p, poly) != Location::Outside)
+            })
+    }
+
+    /// [`geof:sfWithin`](https://docs.ogc.org/is/22-047r1/22-047r1.html#_simple_features_relation_family)
+    ///
+    /// The closures of the geometries are compared: a geometry on the boundary of a polygon is within it.
+    pub fn is_within(&self, other: &Self) -> bool {
+        let a = Components::new(self);
+        let b = Components::new(other);
+        if a.is_empty() || b.is_empty() {
+            return false;
+        }
+        if !a.polygons.is_empty() && b.polygons.is_empty() {
+            return false;
+        }
+        a.points.iter().all(|p| b.covers_point(*p))
+            && a.segments().all(|(s, e)| b.covers_segment(s, e))
+            // The holes of the other geometry must not be in the polygons of this one
+            && b.polygons.iter().all(|polygon| {
+                polygon.iter().skip(1).all(|hole| {
+                    !a.polygons.iter().any(|p| {
+                        hole.iter()
+                            .any(|v| point_in_polygon(*v, p) == Location::Inside)
+                    })
+                })
+            })
+    }
+
+    /// [`geof:sfEquals`](https://docs.ogc.org/is/22-047r1/22-047r1.html#_simple_features_relation_family)
+    pub fn topologically_equals(&self, other: &Self) -> bool {
+        self.is_within(other) && other.is_within(self)
+    }
+
+    /// [`geof:distance`](https://docs.ogc.org/is/22-047r1/22-047r1.html#_function_geofdistance) in degrees
+    pub fn distance(&self, other: &Self) -> Option<f64> {
+        self.closest_points(other).map(|(a, b)| {
+            if self.intersects(other) {
+                0.
+            } else {
+                a.distance(b)
+            }
+        })
+    }
+
+    /// The great circle distance in meters between the closest points of the geometries on the plane
+    pub fn distance_in_meters(&self, other: &Self) -> Option<f64> {
+        if self.intersects(other) {
+            return Some(0.);
+        }
+        let (a, b) = self.closest_points(other)?;
+        let (lat1, lat2) = (a.y.to_radians(), b.y.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (b.x - a.x).to_radians();
+        let h = (d_lat / 2.).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.).sin().powi(2);
+        Some(2. * EARTH_RADIUS_IN_METERS * h.sqrt().min(1.).asin())
+    }
+
+    fn closest_points(&self, other: &Self) -> Option<(Coord, Coord)> {
+        let a = Components::new(self);
+        let b = Components::new(other);
+        let mut best: Option<(f64, Coord, Coord)> = None;
+        let mut update = |distance: f64, p: Coord, q: Coord| {
+            if best.map_or(true, |(d, _, _)| distance < d) {
+                best = Some((distance, p, q));
+            }
+        };
+        let b_segments = b.segments().collect::<Vec<_>>();
+        let a_segments = a.segments().collect::<Vec<_>>();
+        for p in a.vertices() {
+            for q in b.vertices() {
+                update(p.distance(q), p, q);
+            }
+            for (s, e) in &b_segments {
+                let (d, q) = point_segment_distance(p, *s, *e);
+                update(d, p, q);
+            }
+        }
+        for q in b.vertices() {
+            for (s, e) in &a_segments {
+                let (d, p) = point_segment_distance(q, *s, *e);
+                update(d, p, q);
+            }
+        }
+        best.map(|(_, p, q)| (p, q))
+    }
+
+    /// [`geof:envelope`](https://docs.ogc.org/is/22-047r1/22-047r1.html#_function_geofenvelope)
+    pub fn envelope(&self) -> Option<Self> {
+        let bbox = self.bounding_box()?;
+        Some(if bbox.min_x == bbox.max_x && bbox.min_y == bbox.max_y {
+            Self::Point(Coord {
+                x: bbox.min_x,
+                y: bbox.min_y,
+            })
+        } else {
+            Self::Polygon(vec![vec![
+                Coord {
+                    x: bbox.min_x,
+                    y: bbox.min_y,
+                },
+                Coord {
+                    x: bbox.max_x,
+                    y: bbox.min_y,
+                },
+                Coord {
+                    x: bbox.max_x,
+                    y: bbox.max_y,
+                },
+                Coord {
+                    x: bbox.min_x,
+                    y: bbox.max_y,
+                },
+                Coord {
+                    x: bbox.min_x,
+                    y: bbox.min_y,
+                },
+            ]])
+        })
+    }
+
+    /// [`geof:convexHull`](https://docs.ogc.org/is/22-047r1/22-047r1.html#_function_geofconvexhull)
+    pub fn convex_hull(&self) -> Option<Self> {
+        convex_hull(Components::new(self).vertices().collect())
+    }
+
+    /// [`geof:buffer`](https://docs.ogc.org/is/22-047r1/22-047r1.html#_function_geofbuffer) with a radius in degrees
+    ///
+    /// Each component is buffered separately and the result is the union of the buffers, returned as a multi-polygon whose parts may overlap.
+    /// A convex polygon is buffered by the convex hull of polygons around its vertices,
+    /// other polygons by themselves and the buffers of their ring segments.
+    /// The buffer is exact up to the approximation of circles by polygons.
+    pub fn buffer(&self, radius: f64) -> Option<Self> {
+        if !radius.is_finite() || radius < 0. {
+            return None;
+        }
+        if radius == 0. {
+            return Some(self.clone());
+        }
+        let components = Components::new(self);
+        let mut parts = Vec::new();
+        for point in &components.points {
+            parts.extend(convex_hull_polygon(circle(*point, radius).collect()));
+        }
+        for line in &components.lines {
+            for (start, end) in segments(line) {
+                parts.extend(segment_buffer(start, end, radius));
+            }
+        }
+        for polygon in &components.polygons {
+            if is_convex(polygon) {
+                parts.extend(convex_hull_polygon(
+                    polygon
+                        .iter()
+                        .flatten()
+                        .flat_map(|v| circle(*v, radius))
+                        .collect(),
+                ));
+            } else {
+                parts.push(polygon.to_vec());
+                for ring in *polygon {
+                    for (start, end) in segments(ring) {
+                        parts.extend(segment_buffer(start, end, radius));
+                    }
+                }
+            }
+        }
+        if parts.len() > 1 {
+            Some(Self::MultiPolygon(parts))
+        } else {
+            parts.pop().map(Self::Polygon)
+        }
+    }
+
+    /// [`geof:buffer`](https://docs.ogc.org/is/22-047r1/22-047r1.html#_function_geofbuffer) with a radius in meters
+    ///
+    /// The radius is converted to degrees along a great circle: the buffer is stretched in longitude far from the equator.
+    pub fn buffer_in_meters(&self, radius: f64) -> Option<Self> {
+        self.buffer((radius / EARTH_RADIUS_IN_METERS).to_degrees())
+    }
+}
+
+/// The vertices of a polygon approximating the circle
+fn circle(center: Coord, radius: f64) -> impl Iterator<Item = Coord> {
+    (0..CIRCLE_SIDES).map(move |i| {
+        let angle = f64::from(i) * 2. * std::f64::consts::PI / f64::from(CIRCLE_SIDES);
+        Coord {
+            x: center.x + radius * angle.cos(),
+            y: center.y + radius * angle.sin(),
+        }
+    })
+}
+
+fn segment_buffer(start: Coord, end: Coord, radius: f64) -> Option<Vec<Vec<Coord>>> {
+    convex_hull_polygon(circle(start, radius).chain(circle(end, radius)).collect())
+}
+
+/// Returns if the polygon has no hole and its exterior ring is convex
+fn is_convex(polygon: &[Vec<Coord>]) -> bool {
+    let [ring] = polygon else {
+        return false;
+    };
+    let Some(Geometry::Polygon(hull)) = convex_hull(ring.clone()) else {
+        return false;
+    };
+    // All the vertices of the ring must be vertices of its convex hull
+    ring.iter().all(|v| hull.iter().flatten().any(|h| h == v))
+}
+
+fn convex_hull_polygon(points: Vec<Coord>) -> Option<Vec<Vec<Coord>>> {
+    if let Some(Geometry::Polygon(polygon)) = convex_hull(points) {
+        Some(polygon)
+    } else {
+        None
+    }
+}
+
+/// Andrew's monotone chain
+fn convex_hull(mut points: Vec<Coord>) -> Option<Geometry> {
+    points.sort_unstable_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    match points.as_slice() {
+        [] => return None,
+        [p] => return Some(Geometry::Point(*p)),
+        _ => (),
+    }
+    let mut hull: Vec<Coord> = Vec::with_capacity(2 * points.len());
+    for pass in [
+        points.as_slice(),
+        &points.iter().rev().copied().collect::<Vec<_>>(),
+    ] {
+        let start = hull.len();
+        for p in pass {
+            while hull.len() >= start + 2
+                && orientation(hull[hull.len() - 2], hull[hull.len() - 1], *p) <= 0.
+            {
+                hull.pop();
+            }
+            hull.push(*p);
+        }
+        hull.pop();
+    }
+    if hull.len() < 3 {
+        // All points are collinear
+        return Some(Geometry::LineString(vec![
+            points[0],
+            points[points.len() - 1],
+        ]));
+    }
+    hull.push(hull[0]);
+    Some(Geometry::Polygon(vec![hull]))
+}
+
+impl FromStr for Geometry {
+    type Err = WktParseError;
+
+    fn from_str(wkt: &str) -> Result<Self, WktParseError> {
+        let mut wkt = wkt.trim_start();
+        if let Some(rest) = wkt.strip_prefix('<') {
+            let (crs, rest) = rest
+                .split_once('>')
+                .ok_or_else(|| WktParseError::msg("The CRS IRI is not closed"))?;
+            if crs != CRS84 {
+                return Err(WktParseError::msg(format!(
+                    "The coordinate reference system {crs} is not supported, only {CRS84} is"
+                )));
+            }
+            wkt = rest;
+        }
+        let mut parser = WktParser { input: wkt };
+        let geometry = parser.parse_geometry()?;
+        if !parser.input.trim().is_empty() {
+            return Err(WktParseError::msg(
+                "Unexpected content after the WKT geometry",
+            ));
+        }
+        Ok(geometry)
+    }
+}
+
+struct WktParser<'a> {
+    input: &'a str,
+}
+
+impl WktParser<'_> {
+    fn parse_geometry(&mut self) -> Result<Geometry, WktParseError> {
+        let kind = self.parse_keyword()?.to_ascii_uppercase();
+        // We ignore the third and fourth dimensions
+        let dimensions = match self.peek_keyword().to_ascii_uppercase().as_str() {
+            "Z" | "M" => {
+                self.parse_keyword()?;
+                3
+            }
+            "ZM" => {
+                self.parse_keyword()?;
+                4
+            }
+            _ => 0,
+        };
+        if self.peek_keyword().eq_ignore_ascii_case("EMPTY") {
+            self.parse_keyword()?;
+            return match kind.as_str() {
+                "POINT" | "MULTIPOINT" => Ok(Geometry::MultiPoint(Vec::new())),
+                "LINESTRING" => Ok(Geometry::LineString(Vec::new())),
+                "POLYGON" => Ok(Geometry::Polygon(Vec::new())),
+                "MULTILINESTRING" => Ok(Geometry::MultiLineString(Vec::new())),
+                "MULTIPOLYGON" => Ok(Geometry::MultiPolygon(Vec::new())),
+                "GEOMETRYCOLLECTION" => Ok(Geometry::GeometryCollection(Vec::new())),
+                _ => Err(WktParseError::msg(format!(
+                    "Unsupported geometry type {kind}"
+                ))),
+            };
+        }
+        match kind.as_str() {
+            "POINT" => {
+                self.expect('(')?;
+                let point = self.parse_coord(dimensions)?;
+                self.expect(')')?;
+                Ok(Geometry::Point(point))
+            }
+            "LINESTRING" => Ok(Geometry::LineString(self.parse_line(dimensions)?)),
+            "POLYGON" => Ok(Geometry::Polygon(self.parse_polygon(dimensions)?)),
+            "MULTIPOINT" => {
+                self.expect('(')?;
+                let mut points = Vec::new();
+                loop {
+                    // The parentheses around each point are optional
+                    if self.eat('(') {
+                        points.push(self.parse_coord(dimensions)?);
+                        self.expect(')')?;
+                    } else {
+                        points.push(self.parse_coord(dimensions)?);
+                    }
+                    if !self.eat(',') {
+                        break;
+                    }
+                }
+                self.expect(')')?;
+                Ok(Geometry::MultiPoint(points))
+            }
+            "MULTILINESTRING" => Ok(Geometry::MultiLineString(
+                self.parse_list(|p| p.parse_line(dimensions))?,
+            )),
+            "MULTIPOLYGON" => Ok(Geometry::MultiPolygon(
+                self.parse_list(|p| p.parse_polygon(dimensions))?,
+            )),
+            "GEOMETRYCOLLECTION" => Ok(Geometry::GeometryCollection(
+                self.parse_list(Self::parse_geometry)?,
+            )),
+            _ => Err(WktParseError::msg(format!(
+                "Unsupported geometry type {kind}"
+            ))),
+        }
+    }
+
+    fn parse_list<T>(
+        &mut self,
+        mut parse_element: impl FnMut(&mut Self) -> Result<T, WktParseError>,
+    ) -> Result<Vec<T>, WktParseError> {
+        self.expect('(')?;
+        let mut elements = vec![parse_element(self)?];
+        while self.eat(',') {
+            elements.push(parse_element(self)?);
+        }
+        self.expect(')')?;
+        Ok(elements)
+    }
+
+    fn parse_line(&mut self, dimensions: usize) -> Result<Vec<Coord>, WktParseError> {
+        self.parse_list(|p| p.parse_coord(dimensions))
+    }
+
+    fn parse_polygon(&mut self, dimensions: usize) -> Result<Vec<Vec<Coord>>, WktParseError> {
+        self.parse_list(|p| {
+            let ring = p.parse_line(dimensions)?;
+            if ring.len() < 4 || ring.first() != ring.last() {
+                return Err(WktParseError::msg(
+                    "Polygon rings must be closed and have at least 4 coordinates",
+                ));
+            }
+            Ok(ring)
+        })
+    }
+
+    fn parse_coord(&mut self, dimensions: usize) -> Result<Coord, WktParseError> {
+        let x = self.parse_number()?;
+        let y = self.parse_number()?;
+        // Extra dimensions are ignored
+        let extra = if dimensions == 0 { 2 } else { dimensions - 2 };
+        for _ in 0..extra {
+            if self.peek_number() {
+                self.parse_number()?;
+            }
+        }
+        Ok(Coord { x, y })
+    }
+
+    fn peek_number(&self) -> bool {
+        self.input
+            .trim_start()
+            .starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.'))
+    }
+
+    fn parse_number(&mut self) -> Result<f64, WktParseError> {
+        self.input = self.input.trim_start();
+        let end = self
+            .input
+            .find(|c: char| c.is_whitespace() || matches!(c, ',' | '(' | ')'))
+            .unwrap_or(self.input.len());
+        let (number, rest) = self.input.split_at(end);
+        let number = number
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .ok_or_else(|| WktParseError::msg(format!("Invalid coordinate '{number}'")))?;
+        self.input = rest;
+        Ok(number)
+    }
+
+    fn peek_keyword(&self) -> &str {
+        let input = self.input.trim_start();
+        let end = input
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(input.len());
+        &input[..end]
+    }
+
+    fn parse_keyword(&mut self) -> Result<&str, WktParseError> {
+        self.input = self.input.trim_start();
+        let end = self
+            .input
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(self.input.len());
+        if end == 0 {
+            return Err(WktParseError::msg("A geometry type was expected"));
+        }
+        let (keyword, rest) = self.input.split_at(end);
+        self.input = rest;
+        Ok(keyword)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if let Some(rest) = self.input.trim_start().strip_prefix(c) {
+            self.input = rest;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), WktParseError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(WktParseError::msg(format!("'{c}' was expected")))
+        }
+    }
+}
+
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_coords(f: &mut fmt::Formatter<'_>, coords: &[Coord]) -> fmt::Result {
+            f.write_str("(")?;
+            for (i, c) in coords.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{} {}", c.x, c.y)?;
+            }
+            f.write_str(")")
+        }
+        fn write_list<T>(
+            f: &mut fmt::Formatter<'_>,
+            kind: &str,
+            elements: &[T],
+            mut write_element: impl FnMut(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+        ) -> fmt::Result {
+            f.write_str(kind)?;
+            if elements.is_empty() {
+                return f.write_str(" EMPTY");
+            }
+            f.write_str(" (")?;
+            for (i, e) in elements.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write_element(f, e)?;
+            }
+            f.write_str(")")
+        }
+        fn write_polygon(f: &mut fmt::Formatter<'_>, rings: &[Vec<Coord>]) -> fmt::Result {
+            f.write_str("(")?;
+            for (i, ring) in rings.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write_coords(f, ring)?;
+            }
+            f.write_str(")")
+        }
+
+        match self {
+            Self::Point(c) => write!(f, "POINT ({} {})", c.x, c.y),
+            Self::LineString(l) => {
+                if l.is_empty() {
+                    f.write_str("LINESTRING EMPTY")
+                } else {
+                    f.write_str("LINESTRING ")?;
+                    write_coords(f, l)
+                }
+            }
+            Self::Polygon(p) => {
+                if p.is_empty() {
+                    f.write_str("POLYGON EMPTY")
+                } else {
+                    f.write_str("POLYGON ")?;
+                    write_polygon(f, p)
+                }
+            }
+            Self::MultiPoint(p) => {
+                write_list(f, "MULTIPOINT", p, |f, c| write!(f, "({} {})", c.x, c.y))
+            }
+            Self::MultiLineString(l) => {
+                write_list(f, "MULTILINESTRING", l, |f, l| write_coords(f, l))
+            }
+            Self::MultiPolygon(p) => write_list(f, "MULTIPOLYGON", p, |f, p| write_polygon(f, p)),
+            Self::GeometryCollection(g) => {
+                write_list(f, "GEOMETRYCOLLECTION", g, |f, g| write!(f, "{g}"))
+            }
+        }
+    }
+}
+
+/// An error raised while parsing a WKT geometry
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct WktParseError(String);
+
+impl WktParseError {
+    fn msg(msg: impl Into<String>) -> Self {
+        Self(msg.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geometry(wkt: &str) -> Geometry {
+        wkt.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_serialize() {
+        for wkt in [
+            "POINT (1 2)",
+            "LINESTRING (0 0, 1 1.5)",
+            "POLYGON ((0 0, 2 0, 2 2, 0 2, 0 0), (1 1, 1.5 1, 1.5 1.5, 1 1))",
+            "MULTIPOINT ((1 2), (3 4))",
+            "MULTIPOLYGON (((0 0, 1 0, 1 1, 0 0)))",
+            "GEOMETRYCOLLECTION (POINT (1 2), LINESTRING (0 0, 1 1))",
+            "POLYGON EMPTY",
+        ] {
+            assert_eq!(geometry(wkt).to_string(), wkt);
+        }
+        assert_eq!(
+            geometry("<http://www.opengis.net/def/crs/OGC/1.3/CRS84> point z(1 2 3)").to_string(),
+            "POINT (1 2)"
+        );
+        assert_eq!(
+            geometry("MULTIPOINT (1 2, 3 4)"),
+            geometry("MULTIPOINT ((1 2), (3 4))")
+        );
+        "<http://www.opengis.net/def/crs/EPSG/0/4326> POINT (1 2)"
+            .parse::<Geometry>()
+            .unwrap_err();
+        "POLYGON ((0 0, 1 0, 1 1))".parse::<Geometry>().unwrap_err();
+        "POINT (1 2) foo".parse::<Geometry>().unwrap_err();
+    }
+
+    #[test]
+    fn relations() {
+        let square = geometry("POLYGON ((0 0, 4 0, 4 4, 0 4, 0 0))");
+        let holed = geometry("POLYGON ((0 0, 4 0, 4 4, 0 4, 0 0), (1 1, 3 1, 3 3, 1 3, 1 1))");
+        let inner = geometry("POLYGON ((1 1, 2 1, 2 2, 1 2, 1 1))");
+        let concave = geometry("POLYGON ((0 0, 4 0, 4 4, 3 4, 3 1, 1 1, 1 4, 0 4, 0 0))");
+        assert!(inner.is_within(&square));
+        assert!(square.intersects(&inner));
+        assert!(!square.is_within(&inner));
+        assert!(!inner.is_within(&holed));
+        assert!(inner.intersects(&holed));
+        assert!(geometry("POINT (2 2)").is_within(&square));
+        assert!(!geometry("POINT (2 2)").intersects(&holed));
+        assert!(geometry("POINT (4 2)").intersects(&square));
+        assert!(!geometry("LINESTRING (0.5 3, 3.5 3)").is_within(&concave));
+        assert!(geometry("LINESTRING (0.5 3, 0.5 0.5, 3.5 0.5)").is_within(&concave));
+        assert!(geometry("LINESTRING (-1 2, 5 2)").intersects(&square));
+        assert!(!geometry("LINESTRING (5 0, 5 4)").intersects(&square));
+        assert!(square.topologically_equals(&geometry("POLYGON ((4 4, 0 4, 0 0, 4 0, 4 4))")));
+    }
+
+    #[test]
+    fn distances() {
+        let square = geometry("POLYGON ((0 0, 1 0, 1 1, 0 1, 0 0))");
+        assert_eq!(square.distance(&geometry("POINT (3 1)")), Some(2.));
+        assert_eq!(square.distance(&geometry("POINT (0.5 0.5)")), Some(0.));
+        let meters = geometry("POINT (0 0)")
+            .distance_in_meters(&geometry("POINT (0 1)"))
+            .unwrap();
+        assert!((meters - 111_195.).abs() < 1., "{meters}");
+    }
+
+    #[test]
+    fn constructions() {
+        let line = geometry("LINESTRING (0 0, 2 1)");
+        assert_eq!(
+            line.envelope(),
+            Some(geometry("POLYGON ((0 0, 2 0, 2 1, 0 1, 0 0))"))
+        );
+        assert_eq!(
+            geometry("MULTIPOINT ((0 0), (2 0), (1 1), (1 0.5), (0 2))").convex_hull(),
+            Some(geometry("POLYGON ((0 0, 2 0, 0 2, 0 0))"))
+        );
+        let buffer = geometry("POINT (0 0)").buffer(1.).unwrap();
+        assert!(geometry("POINT (0.9 0)").is_within(&buffer));
+        assert!(!geometry("POINT (0.8 0.8)").is_within(&buffer));
+        let buffer = geometry("POLYGON ((0 0, 4 0, 4 4, 3 4, 3 1, 1 1, 1 4, 0 4, 0 0))")
+            .buffer(0.1)
+            .unwrap();
+        assert!(geometry("POINT (1.05 2)").is_within(&buffer));
+        assert!(!geometry("POINT (2 2)").is_within(&buffer));
+        let buffer = geometry("MULTIPOINT ((0 0), (4 0))").buffer(1.).unwrap();
+        assert!(geometry("POINT (4.5 0)").is_within(&buffer));
+        assert!(!geometry("POINT (2 0)").is_within(&buffer));
+    }
+}
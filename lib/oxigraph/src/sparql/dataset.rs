@@ -1,17 +1,22 @@
-use crate::model::TermRef;
+use crate::geo::BoundingBox;
+use crate::model::{NamedNode, TermRef};
 use crate::sparql::algebra::QueryDataset;
+use crate::sparql::eval::TEXT_SEARCH;
 use crate::sparql::EvaluationError;
 use crate::storage::numeric_encoder::{insert_term, EncodedQuad, EncodedTerm, StrHash, StrLookup};
-use crate::storage::{StorageError, StorageReader};
+use crate::storage::{ChainedDecodingQuadIterator, StorageError, StorageReader};
+use sparopt::{StatisticsProvider, TripleStatistics};
 use std::cell::RefCell;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::iter::empty;
+use std::rc::Rc;
 
 pub struct DatasetView {
     reader: StorageReader,
     extra: RefCell<HashMap<StrHash, String>>,
     dataset: EncodedDatasetSpec,
+    text_search_results: RefCell<HashMap<String, Rc<[(EncodedQuad, f32)]>>>,
 }
 
 impl DatasetView {
@@ -28,62 +33,107 @@ impl DatasetView {
             reader,
             extra: RefCell::new(HashMap::default()),
             dataset,
+            text_search_results: RefCell::new(HashMap::default()),
         }
     }
 
-    fn store_encoded_quads_for_pattern(
+    pub fn encoded_quads_for_pattern(
         &self,
         subject: Option<&EncodedTerm>,
         predicate: Option<&EncodedTerm>,
         object: Option<&EncodedTerm>,
         graph_name: Option<&EncodedTerm>,
-    ) -> impl Iterator<Item = Result<EncodedQuad, EvaluationError>> + 'static {
-        self.reader
-            .quads_for_pattern(subject, predicate, object, graph_name)
-            .map(|t| t.map_err(Into::into))
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad, EvaluationError>>> {
+        self.encoded_quads_in_dataset(graph_name, |graph_name| {
+            self.reader
+                .quads_for_pattern(subject, predicate, object, graph_name)
+        })
     }
 
-    #[allow(clippy::needless_collect)]
-    pub fn encoded_quads_for_pattern(
+    /// Returns the number of quads [`encoded_quads_for_pattern`](Self::encoded_quads_for_pattern) would return,
+    /// see [`StorageReader::count_for_pattern`].
+    pub fn encoded_count_for_pattern(
         &self,
         subject: Option<&EncodedTerm>,
         predicate: Option<&EncodedTerm>,
         object: Option<&EncodedTerm>,
         graph_name: Option<&EncodedTerm>,
+    ) -> Result<usize, EvaluationError> {
+        let store_count = |graph_name: Option<&EncodedTerm>| {
+            self.reader
+                .count_for_pattern(subject, predicate, object, graph_name)
+        };
+        let sum_counts = |graph_names: &[EncodedTerm]| {
+            graph_names
+                .iter()
+                .map(|graph_name| store_count(Some(graph_name)))
+                .sum::<Result<usize, _>>()
+        };
+        Ok(if let Some(graph_name) = graph_name {
+            if graph_name.is_default_graph() {
+                if let Some(default_graph_graphs) = &self.dataset.default {
+                    sum_counts(default_graph_graphs)?
+                } else {
+                    store_count(None)?
+                }
+            } else if self
+                .dataset
+                .named
+                .as_ref()
+                .map_or(true, |d| d.contains(graph_name))
+            {
+                store_count(Some(graph_name))?
+            } else {
+                0
+            }
+        } else if let Some(named_graphs) = &self.dataset.named {
+            sum_counts(named_graphs)?
+        } else {
+            store_count(None)? - store_count(Some(&EncodedTerm::DefaultGraph))?
+        })
+    }
+
+    /// Returns the quads with the given predicate whose object is between `start` and `end` included,
+    /// see [`StorageReader::quads_for_predicate_object_range`].
+    pub fn encoded_quads_for_predicate_object_range(
+        &self,
+        predicate: &EncodedTerm,
+        start: &EncodedTerm,
+        end: &EncodedTerm,
+        graph_name: Option<&EncodedTerm>,
     ) -> Box<dyn Iterator<Item = Result<EncodedQuad, EvaluationError>>> {
+        self.encoded_quads_in_dataset(graph_name, |graph_name| {
+            self.reader
+                .quads_for_predicate_object_range(predicate, start, end, graph_name)
+        })
+    }
+
+    /// Applies the query dataset to the quads returned by `store_quads` for a graph name of the store
+    #[allow(clippy::needless_collect)]
+    fn encoded_quads_in_dataset(
+        &self,
+        graph_name: Option<&EncodedTerm>,
+        store_quads: impl Fn(Option<&EncodedTerm>) -> ChainedDecodingQuadIterator,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad, EvaluationError>>> {
+        let store_quads = |graph_name| store_quads(graph_name).map(|t| t.map_err(Into::into));
         if let Some(graph_name) = graph_name {
             if graph_name.is_default_graph() {
                 if let Some(default_graph_graphs) = &self.dataset.default {
                     if default_graph_graphs.len() == 1 {
                         // Single graph optimization
-                        Box::new(
-                            self.store_encoded_quads_for_pattern(
-                                subject,
-                                predicate,
-                                object,
-                                Some(&default_graph_graphs[0]),
-                            )
-                            .map(|quad| {
-                                let quad = quad?;
-                                Ok(EncodedQuad::new(
-                                    quad.subject,
-                                    quad.predicate,
-                                    quad.object,
-                                    EncodedTerm::DefaultGraph,
-                                ))
-                            }),
-                        )
+                        Box::new(store_quads(Some(&default_graph_graphs[0])).map(|quad| {
+                            let quad = quad?;
+                            Ok(EncodedQuad::new(
+                                quad.subject,
+                                quad.predicate,
+                                quad.object,
+                                EncodedTerm::DefaultGraph,
+                            ))
+                        }))
                     } else {
                         let iters = default_graph_graphs
                             .iter()
-                            .map(|graph_name| {
-                                self.store_encoded_quads_for_pattern(
-                                    subject,
-                                    predicate,
-                                    object,
-                                    Some(graph_name),
-                                )
-                            })
+                            .map(|graph_name| store_quads(Some(graph_name)))
                             .collect::<Vec<_>>();
                         Box::new(iters.into_iter().flatten().map(|quad| {
                             let quad = quad?;
@@ -96,18 +146,15 @@ impl DatasetView {
                         }))
                     }
                 } else {
-                    Box::new(
-                        self.store_encoded_quads_for_pattern(subject, predicate, object, None)
-                            .map(|quad| {
-                                let quad = quad?;
-                                Ok(EncodedQuad::new(
-                                    quad.subject,
-                                    quad.predicate,
-                                    quad.object,
-                                    EncodedTerm::DefaultGraph,
-                                ))
-                            }),
-                    )
+                    Box::new(store_quads(None).map(|quad| {
+                        let quad = quad?;
+                        Ok(EncodedQuad::new(
+                            quad.subject,
+                            quad.predicate,
+                            quad.object,
+                            EncodedTerm::DefaultGraph,
+                        ))
+                    }))
                 }
             } else if self
                 .dataset
@@ -115,36 +162,139 @@ impl DatasetView {
                 .as_ref()
                 .map_or(true, |d| d.contains(graph_name))
             {
-                Box::new(self.store_encoded_quads_for_pattern(
-                    subject,
-                    predicate,
-                    object,
-                    Some(graph_name),
-                ))
+                Box::new(store_quads(Some(graph_name)))
             } else {
                 Box::new(empty())
             }
         } else if let Some(named_graphs) = &self.dataset.named {
             let iters = named_graphs
                 .iter()
-                .map(|graph_name| {
-                    self.store_encoded_quads_for_pattern(
-                        subject,
-                        predicate,
-                        object,
-                        Some(graph_name),
-                    )
-                })
+                .map(|graph_name| store_quads(Some(graph_name)))
                 .collect::<Vec<_>>();
             Box::new(iters.into_iter().flatten())
         } else {
-            Box::new(
-                self.store_encoded_quads_for_pattern(subject, predicate, object, None)
-                    .filter(|quad| match quad {
-                        Err(_) => true,
-                        Ok(quad) => !quad.graph_name.is_default_graph(),
-                    }),
-            )
+            Box::new(store_quads(None).filter(|quad| match quad {
+                Err(_) => true,
+                Ok(quad) => !quad.graph_name.is_default_graph(),
+            }))
+        }
+    }
+
+    /// Returns the quads of the store whose literal object matches the full-text query, with their score.
+    ///
+    /// The results are kept for the duration of the query evaluation.
+    fn text_search(&self, query: &str) -> Result<Rc<[(EncodedQuad, f32)]>, EvaluationError> {
+        if let Some(results) = self.text_search_results.borrow().get(query) {
+            return Ok(Rc::clone(results));
+        }
+        let results = Rc::<[_]>::from(self.reader.text_search(query)?);
+        self.text_search_results
+            .borrow_mut()
+            .insert(query.into(), Rc::clone(&results));
+        Ok(results)
+    }
+
+    /// Returns the subjects of the quads in `graph_name` whose literal object matches the full-text query.
+    ///
+    /// Each subject is returned once per graph, by decreasing score, with the best score of its literals.
+    /// The graph is returned as [`encoded_quads_for_pattern`](Self::encoded_quads_for_pattern) would.
+    pub fn text_search_subjects(
+        &self,
+        query: &str,
+        graph_name: Option<&EncodedTerm>,
+    ) -> Result<Vec<(EncodedTerm, EncodedTerm, f32)>, EvaluationError> {
+        let mut subjects = HashMap::<_, f32>::new();
+        for (quad, score) in self.text_search(query)?.iter() {
+            if let Some(graph_name) = self.visible_graph_name(&quad.graph_name, graph_name) {
+                let best = subjects
+                    .entry((quad.subject.clone(), graph_name))
+                    .or_insert(*score);
+                *best = best.max(*score);
+            }
+        }
+        let mut subjects = subjects
+            .into_iter()
+            .map(|((subject, graph_name), score)| (subject, graph_name, score))
+            .collect::<Vec<_>>();
+        subjects.sort_unstable_by(|(_, _, a), (_, _, b)| b.total_cmp(a));
+        Ok(subjects)
+    }
+
+    /// Returns the best score of the subject literals matching the full-text query in the default or named graphs.
+    pub fn text_search_score(
+        &self,
+        query: &str,
+        subject: &EncodedTerm,
+    ) -> Result<Option<f32>, EvaluationError> {
+        Ok(self
+            .text_search(query)?
+            .iter()
+            .filter(|(quad, _)| {
+                quad.subject == *subject
+                    && (self
+                        .visible_graph_name(&quad.graph_name, Some(&EncodedTerm::DefaultGraph))
+                        .is_some()
+                        || self.visible_graph_name(&quad.graph_name, None).is_some())
+            })
+            .map(|(_, score)| *score)
+            .reduce(f32::max))
+    }
+
+    /// Returns the quads in `graph_name` with a `geo:wktLiteral` object whose bounding box intersects `bbox`
+    /// or `None` if the spatial index is not enabled.
+    ///
+    /// The graph is returned as [`encoded_quads_for_pattern`](Self::encoded_quads_for_pattern) would.
+    pub fn spatial_search(
+        &self,
+        bbox: &BoundingBox,
+        graph_name: Option<&EncodedTerm>,
+    ) -> Result<Option<Vec<EncodedQuad>>, EvaluationError> {
+        if !self.reader.has_spatial_index()? {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.reader
+                .spatial_search(bbox)?
+                .into_iter()
+                .filter_map(|mut quad| {
+                    quad.graph_name = self.visible_graph_name(&quad.graph_name, graph_name)?;
+                    Some(quad)
+                })
+                .collect(),
+        ))
+    }
+
+    /// Returns the graph name to use for a quad of the store stored in `stored_graph_name`
+    /// if it is visible in the `graph_name` graph pattern
+    fn visible_graph_name(
+        &self,
+        stored_graph_name: &EncodedTerm,
+        graph_name: Option<&EncodedTerm>,
+    ) -> Option<EncodedTerm> {
+        if let Some(graph_name) = graph_name {
+            if graph_name.is_default_graph() {
+                self.dataset
+                    .default
+                    .as_ref()
+                    .map_or(true, |d| d.contains(stored_graph_name))
+                    .then_some(EncodedTerm::DefaultGraph)
+            } else {
+                (stored_graph_name == graph_name
+                    && self
+                        .dataset
+                        .named
+                        .as_ref()
+                        .map_or(true, |d| d.contains(graph_name)))
+                .then(|| graph_name.clone())
+            }
+        } else {
+            (!stored_graph_name.is_default_graph()
+                && self
+                    .dataset
+                    .named
+                    .as_ref()
+                    .map_or(true, |d| d.contains(stored_graph_name)))
+            .then(|| stored_graph_name.clone())
         }
     }
 
@@ -178,6 +328,43 @@ impl StrLookup for DatasetView {
     }
 }
 
+impl StatisticsProvider for DatasetView {
+    fn predicate_statistics(&self, predicate: &NamedNode) -> Option<TripleStatistics> {
+        // Statistics are only used for estimations, errors are ignored
+        // Predicates without saved statistics might have been added since they have been computed: they are unknown
+        self.reader
+            .predicate_statistics(&predicate.as_ref().into())
+            .ok()
+            .flatten()
+    }
+
+    fn graph_statistics(&self, graph_name: Option<&NamedNode>) -> Option<TripleStatistics> {
+        if let Some(graph_name) = graph_name {
+            return self
+                .reader
+                .graph_statistics(&graph_name.as_ref().into())
+                .ok()
+                .flatten();
+        }
+        let Some(default_graph_graphs) = &self.dataset.default else {
+            // The default graph is the union of all graphs
+            return self.reader.dataset_statistics().ok().flatten();
+        };
+        let mut sum = TripleStatistics::default();
+        for graph_name in default_graph_graphs {
+            let graph_statistics = self.reader.graph_statistics(graph_name).ok().flatten()?;
+            sum.count += graph_statistics.count;
+            sum.distinct_subjects += graph_statistics.distinct_subjects;
+            sum.distinct_objects += graph_statistics.distinct_objects;
+        }
+        Some(sum)
+    }
+
+    fn requires_bound_object(&self, predicate: &NamedNode) -> bool {
+        predicate.as_ref() == TEXT_SEARCH
+    }
+}
+
 struct EncodedDatasetSpec {
     default: Option<Vec<EncodedTerm>>,
     named: Option<Vec<EncodedTerm>>,
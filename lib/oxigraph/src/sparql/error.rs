@@ -50,6 +50,15 @@ pub enum EvaluationError {
     /// The results are not a RDF graph
     #[error("The query results are not a RDF graph")]
     NotAGraph,
+    /// The object of a `text:search` property function is not bound to a string literal
+    #[error("The text:search query must be bound to a string literal")]
+    InvalidTextSearchQuery,
+    /// The query evaluation has exceeded the timeout set with [`QueryOptions::with_timeout`](super::QueryOptions::with_timeout)
+    #[error("The query evaluation has timed out")]
+    Timeout,
+    /// The query evaluation has been cancelled using a [`CancellationToken`](super::CancellationToken)
+    #[error("The query evaluation has been cancelled")]
+    Cancelled,
 }
 
 impl From<Infallible> for EvaluationError {
@@ -78,7 +87,10 @@ impl From<EvaluationError> for io::Error {
             | EvaluationError::UnsupportedService(_)
             | EvaluationError::UnsupportedContentType(_)
             | EvaluationError::ServiceDoesNotReturnSolutions
+            | EvaluationError::InvalidTextSearchQuery
             | EvaluationError::NotAGraph => Self::new(io::ErrorKind::InvalidInput, error),
+            EvaluationError::Timeout => Self::new(io::ErrorKind::TimedOut, error),
+            EvaluationError::Cancelled => Self::new(io::ErrorKind::Interrupted, error),
         }
     }
 }
@@ -1,13 +1,19 @@
+use crate::geo::{BoundingBox, Geometry, WKT_LITERAL};
 use crate::model::vocab::{rdf, xsd};
-use crate::model::{BlankNode, LiteralRef, NamedNodeRef, Term, Triple};
+use crate::model::{BlankNode, LiteralRef, NamedNode, NamedNodeRef, Term, Triple};
 use crate::sparql::algebra::{Query, QueryDataset};
 use crate::sparql::dataset::DatasetView;
 use crate::sparql::error::EvaluationError;
 use crate::sparql::model::*;
 use crate::sparql::service::ServiceHandler;
-use crate::sparql::CustomFunctionRegistry;
+use crate::sparql::spill::{self, MemoryReservation, SpillWriter, TupleComparator};
+use crate::sparql::{
+    AggregateFunctionAccumulator, CancellationToken, CustomAggregateFunctionRegistry,
+    CustomFunctionRegistry,
+};
 use crate::storage::numeric_encoder::*;
 use crate::storage::small_string::SmallString;
+use crate::storage::StorageError;
 use digest::Digest;
 use json_event_parser::{JsonEvent, ToWriteJsonWriter};
 use md5::Md5;
@@ -28,17 +34,48 @@ use sparopt::algebra::{
     AggregateExpression, Expression, GraphPattern, JoinAlgorithm, LeftJoinAlgorithm,
     MinusAlgorithm, OrderExpression,
 };
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::cmp::Ordering;
 use std::collections::hash_map::DefaultHasher;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::hash::{Hash, Hasher};
 use std::iter::{empty, once, Iterator};
+use std::mem::size_of_val;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::{fmt, io, str};
 
 const REGEX_SIZE_LIMIT: usize = 1_000_000;
+/// Property function binding the subjects of the literals matching a full-text query
+pub const TEXT_SEARCH: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://oxigraph.org/text#search");
+/// Function returning the full-text score of a subject for a query
+const TEXT_SCORE: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://oxigraph.org/text#score");
+const GEOF_SF_INTERSECTS: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/function/geosparql/sfIntersects");
+const GEOF_SF_DISJOINT: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/function/geosparql/sfDisjoint");
+const GEOF_SF_WITHIN: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/function/geosparql/sfWithin");
+const GEOF_SF_CONTAINS: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/function/geosparql/sfContains");
+const GEOF_SF_EQUALS: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/function/geosparql/sfEquals");
+const GEOF_DISTANCE: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/function/geosparql/distance");
+const GEOF_BUFFER: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/function/geosparql/buffer");
+const GEOF_ENVELOPE: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/function/geosparql/envelope");
+const GEOF_CONVEX_HULL: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/function/geosparql/convexHull");
+const UOM_METRE: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/uom/OGC/1.0/metre");
+const UOM_DEGREE: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/uom/OGC/1.0/degree");
+const UOM_RADIAN: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("http://www.opengis.net/def/uom/OGC/1.0/radian");
 
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub struct EncodedTuple {
@@ -119,7 +156,7 @@ impl IntoIterator for EncodedTuple {
     }
 }
 
-type EncodedTuplesIterator = Box<dyn Iterator<Item = Result<EncodedTuple, EvaluationError>>>;
+pub type EncodedTuplesIterator = Box<dyn Iterator<Item = Result<EncodedTuple, EvaluationError>>>;
 
 #[derive(Clone)]
 pub struct SimpleEvaluator {
@@ -128,15 +165,24 @@ pub struct SimpleEvaluator {
     now: DateTime,
     service_handler: Arc<dyn ServiceHandler<Error = EvaluationError>>,
     custom_functions: Arc<CustomFunctionRegistry>,
+    custom_aggregate_functions: Arc<CustomAggregateFunctionRegistry>,
+    service_batch_size: usize,
+    limits: Option<Rc<EvaluationLimits>>,
+    /// Storage errors raised during expression evaluation, returned by the root node iterator
+    expression_error: Rc<RefCell<Option<EvaluationError>>>,
     run_stats: bool,
 }
 
 impl SimpleEvaluator {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         dataset: Rc<DatasetView>,
         base_iri: Option<Rc<Iri<String>>>,
         service_handler: Arc<dyn ServiceHandler<Error = EvaluationError>>,
         custom_functions: Arc<CustomFunctionRegistry>,
+        custom_aggregate_functions: Arc<CustomAggregateFunctionRegistry>,
+        service_batch_size: usize,
+        limits: Option<Rc<EvaluationLimits>>,
         run_stats: bool,
     ) -> Self {
         Self {
@@ -145,13 +191,17 @@ impl SimpleEvaluator {
             now: DateTime::now(),
             service_handler,
             custom_functions,
+            custom_aggregate_functions,
+            service_batch_size,
+            limits,
+            expression_error: Rc::default(),
             run_stats,
         }
     }
 
     pub fn evaluate_select(&self, pattern: &GraphPattern) -> (QueryResults, Rc<EvalNodeWithStats>) {
         let mut variables = Vec::new();
-        let (eval, stats) = self.graph_pattern_evaluator(pattern, &mut variables);
+        let (eval, stats) = self.root_graph_pattern_evaluator(pattern, &mut variables);
         let from = EncodedTuple::with_capacity(variables.len());
         (
             QueryResults::Solutions(decode_bindings(
@@ -168,7 +218,7 @@ impl SimpleEvaluator {
         pattern: &GraphPattern,
     ) -> (Result<QueryResults, EvaluationError>, Rc<EvalNodeWithStats>) {
         let mut variables = Vec::new();
-        let (eval, stats) = self.graph_pattern_evaluator(pattern, &mut variables);
+        let (eval, stats) = self.root_graph_pattern_evaluator(pattern, &mut variables);
         let from = EncodedTuple::with_capacity(variables.len());
         (
             match eval(from).next() {
@@ -186,7 +236,7 @@ impl SimpleEvaluator {
         template: &[TriplePattern],
     ) -> (QueryResults, Rc<EvalNodeWithStats>) {
         let mut variables = Vec::new();
-        let (eval, stats) = self.graph_pattern_evaluator(pattern, &mut variables);
+        let (eval, stats) = self.root_graph_pattern_evaluator(pattern, &mut variables);
         let mut bnodes = Vec::new();
         let template = template
             .iter()
@@ -225,7 +275,7 @@ impl SimpleEvaluator {
         pattern: &GraphPattern,
     ) -> (QueryResults, Rc<EvalNodeWithStats>) {
         let mut variables = Vec::new();
-        let (eval, stats) = self.graph_pattern_evaluator(pattern, &mut variables);
+        let (eval, stats) = self.root_graph_pattern_evaluator(pattern, &mut variables);
         let from = EncodedTuple::with_capacity(variables.len());
         (
             QueryResults::Graph(QueryTripleIter {
@@ -239,7 +289,31 @@ impl SimpleEvaluator {
         )
     }
 
-    pub fn graph_pattern_evaluator(
+    /// Builds the evaluator of the query root node
+    ///
+    /// Its iterators return the errors raised while evaluating the expressions of the plan.
+    pub fn root_graph_pattern_evaluator(
+        &self,
+        pattern: &GraphPattern,
+        encoded_variables: &mut Vec<Variable>,
+    ) -> (
+        Rc<dyn Fn(EncodedTuple) -> EncodedTuplesIterator>,
+        Rc<EvalNodeWithStats>,
+    ) {
+        let (evaluator, stats) = self.graph_pattern_evaluator(pattern, encoded_variables);
+        let expression_error = Rc::clone(&self.expression_error);
+        (
+            Rc::new(move |tuple| {
+                Box::new(ExpressionErrorIterator {
+                    inner: evaluator(tuple),
+                    expression_error: Rc::clone(&expression_error),
+                })
+            }),
+            stats,
+        )
+    }
+
+    fn graph_pattern_evaluator(
         &self,
         pattern: &GraphPattern,
         encoded_variables: &mut Vec<Variable>,
@@ -273,6 +347,21 @@ impl SimpleEvaluator {
                 })
             })
         }
+        if let Some(limits) = self
+            .limits
+            .as_ref()
+            .filter(|limits| limits.is_interruptible())
+        {
+            // Each plan node checks the limits so that long running nodes are interrupted
+            let limits = Rc::clone(limits);
+            evaluator = Rc::new(move |tuple| {
+                Box::new(LimitsCheckIterator {
+                    inner: evaluator(tuple),
+                    limits: Rc::clone(&limits),
+                    failed: false,
+                })
+            })
+        }
         (evaluator, stats)
     }
 
@@ -355,6 +444,51 @@ impl SimpleEvaluator {
                     }
                 })
             }
+            GraphPattern::QuadPattern {
+                subject,
+                predicate: NamedNodePattern::NamedNode(predicate),
+                object,
+                graph_name,
+            } if predicate.as_ref() == TEXT_SEARCH => {
+                let subject = TupleSelector::from_ground_term_pattern(
+                    subject,
+                    encoded_variables,
+                    &self.dataset,
+                );
+                let query = TupleSelector::from_ground_term_pattern(
+                    object,
+                    encoded_variables,
+                    &self.dataset,
+                );
+                let graph_name = TupleSelector::from_graph_name_pattern(
+                    graph_name,
+                    encoded_variables,
+                    &self.dataset,
+                );
+                let dataset = Rc::clone(&self.dataset);
+                Rc::new(move |from| {
+                    let Some(query) = query
+                        .get_pattern_value(&from)
+                        .and_then(|query| to_string(&dataset, &query))
+                    else {
+                        return Box::new(once(Err(EvaluationError::InvalidTextSearchQuery)));
+                    };
+                    let subjects = match dataset
+                        .text_search_subjects(&query, graph_name.get_pattern_value(&from).as_ref())
+                    {
+                        Ok(subjects) => subjects,
+                        Err(error) => return Box::new(once(Err(error))),
+                    };
+                    let subject = subject.clone();
+                    let graph_name = graph_name.clone();
+                    Box::new(subjects.into_iter().filter_map(move |(s, g, _)| {
+                        let mut new_tuple = from.clone();
+                        put_pattern_value(&subject, s, &mut new_tuple)?;
+                        put_pattern_value(&graph_name, g, &mut new_tuple)?;
+                        Some(Ok(new_tuple))
+                    }))
+                })
+            }
             GraphPattern::QuadPattern {
                 subject,
                 predicate,
@@ -576,6 +710,41 @@ impl SimpleEvaluator {
                 right,
                 algorithm,
             } => {
+                if let Some((input, service_name, service_inner, silent, keys)) =
+                    batched_service_join(left, right, algorithm)
+                {
+                    let (input, input_stats) =
+                        self.graph_pattern_evaluator(input, encoded_variables);
+                    stat_children.push(input_stats);
+                    self.build_graph_pattern_evaluator(
+                        service_inner,
+                        encoded_variables,
+                        &mut Vec::new(),
+                    ); // We call recursively to fill "encoded_variables"
+                    let keys = keys
+                        .iter()
+                        .map(|v| (encode_variable(encoded_variables, v), v.clone()))
+                        .collect::<Vec<_>>();
+                    let service_name = service_name.clone();
+                    let graph_pattern = spargebra::algebra::GraphPattern::from(service_inner);
+                    let variables = Rc::from(encoded_variables.as_slice());
+                    let eval = self.clone();
+                    return Rc::new(move |from| {
+                        Box::new(BatchedServiceJoinIterator {
+                            input: input(from),
+                            eval: eval.clone(),
+                            service_name: service_name.clone(),
+                            graph_pattern: graph_pattern.clone(),
+                            silent,
+                            keys: keys.clone(),
+                            variables: Rc::clone(&variables),
+                            input_done: false,
+                            pending_calls: VecDeque::new(),
+                            current: Box::new(empty()),
+                            buffered_results: Vec::new(),
+                        })
+                    });
+                }
                 let (left, left_stats) = self.graph_pattern_evaluator(left, encoded_variables);
                 stat_children.push(left_stats);
                 let (right, right_stats) = self.graph_pattern_evaluator(right, encoded_variables);
@@ -610,23 +779,28 @@ impl SimpleEvaluator {
                                 .iter()
                                 .map(|v| encode_variable(encoded_variables, v))
                                 .collect::<Vec<_>>();
-                            Rc::new(move |from| {
-                                let mut errors = Vec::default();
-                                let mut built_values = EncodedTupleSet::new(keys.clone());
-                                built_values.extend(build(from.clone()).filter_map(|result| {
-                                    match result {
-                                        Ok(result) => Some(result),
-                                        Err(error) => {
-                                            errors.push(Err(error));
-                                            None
-                                        }
-                                    }
-                                }));
+                            let join: Rc<
+                                dyn Fn(
+                                    EncodedTupleSet,
+                                    EncodedTuplesIterator,
+                                ) -> EncodedTuplesIterator,
+                            > = Rc::new(|built, probe_iter| {
                                 Box::new(HashJoinIterator {
-                                    probe_iter: probe(from),
-                                    built: built_values,
-                                    buffered_results: errors,
+                                    probe_iter,
+                                    built,
+                                    buffered_results: Vec::new(),
                                 })
+                            });
+                            let limits = self.limits.clone();
+                            Rc::new(move |from| {
+                                let probe = Rc::clone(&probe);
+                                spill::hash_join(
+                                    build(from.clone()),
+                                    move || probe(from),
+                                    keys.clone(),
+                                    limits.as_ref(),
+                                    Rc::clone(&join),
+                                )
                             })
                         }
                     }
@@ -743,29 +917,34 @@ impl SimpleEvaluator {
                             .iter()
                             .map(|v| encode_variable(encoded_variables, v))
                             .collect::<Vec<_>>();
-                        Rc::new(move |from| {
-                            let mut errors = Vec::default();
-                            let mut right_values = EncodedTupleSet::new(keys.clone());
-                            right_values.extend(right(from.clone()).filter_map(
-                                |result| match result {
-                                    Ok(result) => Some(result),
-                                    Err(error) => {
-                                        errors.push(Err(error));
-                                        None
-                                    }
-                                },
-                            ));
+                        let join: Rc<
+                            dyn Fn(EncodedTupleSet, EncodedTuplesIterator) -> EncodedTuplesIterator,
+                        > = Rc::new(move |right, left_iter| {
                             Box::new(HashLeftJoinIterator {
-                                left_iter: left(from),
-                                right: right_values,
-                                buffered_results: errors,
+                                left_iter,
+                                right,
+                                buffered_results: Vec::new(),
                                 expression: Rc::clone(&expression),
                             })
+                        });
+                        let limits = self.limits.clone();
+                        Rc::new(move |from| {
+                            let left = Rc::clone(&left);
+                            spill::hash_join(
+                                right(from.clone()),
+                                move || left(from),
+                                keys.clone(),
+                                limits.as_ref(),
+                                Rc::clone(&join),
+                            )
                         })
                     }
                 }
             }
             GraphPattern::Filter { inner, expression } => {
+                let index_lookup = self
+                    .spatial_index_lookup(inner, expression, encoded_variables)
+                    .or_else(|| self.range_index_lookup(inner, expression, encoded_variables));
                 let (child, child_stats) = self.graph_pattern_evaluator(inner, encoded_variables);
                 stat_children.push(child_stats);
                 let expression =
@@ -773,7 +952,11 @@ impl SimpleEvaluator {
 
                 Rc::new(move |from| {
                     let expression = Rc::clone(&expression);
-                    Box::new(child(from).filter(move |tuple| {
+                    let candidates = index_lookup
+                        .as_ref()
+                        .and_then(|lookup| lookup(&from))
+                        .unwrap_or_else(|| child(from));
+                    Box::new(candidates.filter(move |tuple| {
                         match tuple {
                             Ok(tuple) => expression(tuple)
                                 .and_then(|term| to_bool(&term))
@@ -828,61 +1011,26 @@ impl SimpleEvaluator {
             GraphPattern::OrderBy { inner, expression } => {
                 let (child, child_stats) = self.graph_pattern_evaluator(inner, encoded_variables);
                 stat_children.push(child_stats);
-                let by = expression
-                    .iter()
-                    .map(|comp| match comp {
-                        OrderExpression::Asc(expression) => ComparatorFunction::Asc(
-                            self.expression_evaluator(expression, encoded_variables, stat_children),
-                        ),
-                        OrderExpression::Desc(expression) => ComparatorFunction::Desc(
-                            self.expression_evaluator(expression, encoded_variables, stat_children),
-                        ),
-                    })
-                    .collect::<Vec<_>>();
-                let dataset = Rc::clone(&self.dataset);
+                let comparator =
+                    self.order_comparator(expression, encoded_variables, stat_children);
+                let limits = self.limits.clone();
                 Rc::new(move |from| {
-                    let mut errors = Vec::default();
-                    let mut values = child(from)
-                        .filter_map(|result| match result {
-                            Ok(result) => Some(result),
-                            Err(error) => {
-                                errors.push(Err(error));
-                                None
-                            }
-                        })
-                        .collect::<Vec<_>>();
-                    values.sort_unstable_by(|a, b| {
-                        for comp in &by {
-                            match comp {
-                                ComparatorFunction::Asc(expression) => {
-                                    match cmp_terms(
-                                        &dataset,
-                                        expression(a).as_ref(),
-                                        expression(b).as_ref(),
-                                    ) {
-                                        Ordering::Greater => return Ordering::Greater,
-                                        Ordering::Less => return Ordering::Less,
-                                        Ordering::Equal => (),
-                                    }
-                                }
-                                ComparatorFunction::Desc(expression) => {
-                                    match cmp_terms(
-                                        &dataset,
-                                        expression(a).as_ref(),
-                                        expression(b).as_ref(),
-                                    ) {
-                                        Ordering::Greater => return Ordering::Less,
-                                        Ordering::Less => return Ordering::Greater,
-                                        Ordering::Equal => (),
-                                    }
-                                }
-                            }
-                        }
-                        Ordering::Equal
-                    });
-                    Box::new(errors.into_iter().chain(values.into_iter().map(Ok)))
+                    spill::sort(child(from), Rc::clone(&comparator), limits.as_ref())
                 })
             }
+            GraphPattern::TopK {
+                inner,
+                expression,
+                start,
+                length,
+            } => {
+                let (child, child_stats) = self.graph_pattern_evaluator(inner, encoded_variables);
+                stat_children.push(child_stats);
+                let comparator =
+                    self.order_comparator(expression, encoded_variables, stat_children);
+                let (start, length) = (*start, *length);
+                Rc::new(move |from| top_k(child(from), &comparator, start, length))
+            }
             GraphPattern::Distinct { inner } => {
                 let (child, child_stats) = self.graph_pattern_evaluator(inner, encoded_variables);
                 stat_children.push(child_stats);
@@ -964,6 +1112,11 @@ impl SimpleEvaluator {
                 aggregates,
                 variables,
             } => {
+                if let Some(evaluator) =
+                    self.quad_pattern_count_evaluator(pattern, encoded_variables)
+                {
+                    return evaluator;
+                }
                 let (child, child_stats) = self.graph_pattern_evaluator(inner, encoded_variables);
                 stat_children.push(child_stats);
                 let key_variables = variables
@@ -981,78 +1134,104 @@ impl SimpleEvaluator {
                     .collect::<Vec<_>>();
                 let accumulator_builders = aggregates
                     .iter()
-                    .map(|(_, aggregate)| Self::accumulator_builder(&self.dataset, aggregate))
+                    .map(|(_, aggregate)| self.accumulator_builder(aggregate))
                     .collect::<Vec<_>>();
                 let accumulator_variables = aggregates
                     .iter()
                     .map(|(variable, _)| encode_variable(encoded_variables, variable))
                     .collect::<Vec<_>>();
+                let aggregator = Rc::new(GroupAggregator {
+                    key_variables,
+                    aggregate_input_expressions,
+                    accumulator_builders,
+                    accumulator_variables,
+                });
+                let limits = self.limits.clone();
                 Rc::new(move |from| {
                     let tuple_size = from.capacity();
-                    let key_variables = Rc::clone(&key_variables);
                     let mut errors = Vec::default();
                     let mut accumulators_for_group =
                         HashMap::<Vec<Option<EncodedTerm>>, Vec<Box<dyn Accumulator>>>::default();
-                    if key_variables.is_empty() {
+                    if aggregator.key_variables.is_empty() {
                         // There is always a single group if there is no GROUP BY
-                        accumulators_for_group.insert(
-                            Vec::new(),
-                            accumulator_builders.iter().map(|c| c()).collect::<Vec<_>>(),
-                        );
+                        accumulators_for_group.insert(Vec::new(), aggregator.new_accumulators());
                     }
-                    child(from)
-                        .filter_map(|result| match result {
-                            Ok(result) => Some(result),
+                    let mut reservation = limits
+                        .as_ref()
+                        .filter(|limits| limits.has_memory_limit())
+                        .map(|limits| MemoryReservation::new(Rc::clone(limits)));
+                    // When the memory budget is exceeded, the tuples of the groups not already in memory
+                    // are partitioned into temporary files and aggregated later partition by partition
+                    let mut spilled_groups: Option<Vec<SpillWriter>> = None;
+                    for result in child(from) {
+                        let tuple = match result {
+                            Ok(tuple) => tuple,
                             Err(error) => {
                                 errors.push(error);
-                                None
+                                continue;
                             }
-                        })
-                        .for_each(|tuple| {
-                            // TODO avoid copy for key?
-                            let key = key_variables
-                                .iter()
-                                .map(|v| tuple.get(*v).cloned())
-                                .collect();
-
-                            let key_accumulators =
-                                accumulators_for_group.entry(key).or_insert_with(|| {
-                                    accumulator_builders.iter().map(|c| c()).collect::<Vec<_>>()
-                                });
-                            for (accumulator, input_expression) in key_accumulators
-                                .iter_mut()
-                                .zip(&aggregate_input_expressions)
+                        };
+                        let key = aggregator.key(&tuple);
+                        if let Some(accumulators) = accumulators_for_group.get_mut(&key) {
+                            aggregator.add(accumulators, &tuple);
+                        } else if let Some(spilled_groups) = &mut spilled_groups {
+                            if let Err(error) = GroupAggregator::spill(spilled_groups, &key, &tuple)
                             {
-                                accumulator.add(
-                                    input_expression
-                                        .as_ref()
-                                        .and_then(|parameter| parameter(&tuple)),
-                                );
+                                errors.push(error);
+                                break;
+                            }
+                        } else {
+                            let mut accumulators = aggregator.new_accumulators();
+                            aggregator.add(&mut accumulators, &tuple);
+                            if let Some(reservation) = &mut reservation {
+                                if !reservation
+                                    .grow(GroupAggregator::memory_size(&key, &accumulators))
+                                {
+                                    match (0..GROUP_PARTITION_COUNT)
+                                        .map(|_| SpillWriter::new())
+                                        .collect::<io::Result<Vec<_>>>()
+                                    {
+                                        Ok(writers) => spilled_groups = Some(writers),
+                                        Err(error) => {
+                                            errors.push(StorageError::from(error).into());
+                                            break;
+                                        }
+                                    }
+                                }
                             }
+                            accumulators_for_group.insert(key, accumulators);
+                        }
+                    }
+                    let spilled_groups = spilled_groups
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(SpillWriter::finish)
+                        .collect::<io::Result<Vec<_>>>()
+                        .unwrap_or_else(|error| {
+                            errors.push(StorageError::from(error).into());
+                            Vec::new()
                         });
-                    let accumulator_variables = accumulator_variables.clone();
+                    let aggregator = Rc::clone(&aggregator);
+                    let in_memory_aggregator = Rc::clone(&aggregator);
                     Box::new(
                         errors
                             .into_iter()
                             .map(Err)
-                            .chain(accumulators_for_group.into_iter().map(
-                                move |(key, accumulators)| {
-                                    let mut result = EncodedTuple::with_capacity(tuple_size);
-                                    for (variable, value) in key_variables.iter().zip(key) {
-                                        if let Some(value) = value {
-                                            result.set(*variable, value);
-                                        }
-                                    }
-                                    for (accumulator, variable) in
-                                        accumulators.into_iter().zip(&accumulator_variables)
-                                    {
-                                        if let Some(value) = accumulator.state() {
-                                            result.set(*variable, value);
-                                        }
-                                    }
-                                    Ok(result)
-                                },
-                            )),
+                            .chain(spill::ReservedIterator::new(
+                                accumulators_for_group.into_iter().map(
+                                    move |(key, accumulators)| {
+                                        Ok(in_memory_aggregator.result(
+                                            key,
+                                            accumulators,
+                                            tuple_size,
+                                        ))
+                                    },
+                                ),
+                                reservation,
+                            ))
+                            .chain(spilled_groups.into_iter().flat_map(move |partition| {
+                                aggregator.aggregate_partition(partition, tuple_size)
+                            })),
                     )
                 })
             }
@@ -1088,11 +1267,51 @@ impl SimpleEvaluator {
         }
     }
 
+    /// Starts a call to the service with the given input solutions.
+    ///
+    /// The call is evaluated in a separated thread if possible.
+    fn start_service_batch_call(
+        &self,
+        service_name: NamedNode,
+        graph_pattern: &spargebra::algebra::GraphPattern,
+        input: Vec<QuerySolution>,
+    ) -> ServiceBatchCall {
+        let handler = Arc::clone(&self.service_handler);
+        let query = Query {
+            inner: spargebra::Query::Select {
+                dataset: None,
+                pattern: graph_pattern.clone(),
+                #[allow(clippy::useless_asref)]
+                base_iri: self.base_iri.as_ref().map(|iri| iri.as_ref().clone()),
+            },
+            dataset: QueryDataset::new(),
+            parsing_duration: None,
+        };
+        let call = move || {
+            if let QueryResults::Solutions(iter) =
+                handler.handle_batch(service_name, query, &input)?
+            {
+                iter.collect()
+            } else {
+                Err(EvaluationError::ServiceDoesNotReturnSolutions)
+            }
+        };
+        #[cfg(not(target_family = "wasm"))]
+        {
+            ServiceBatchCall(std::thread::spawn(call))
+        }
+        #[cfg(target_family = "wasm")]
+        {
+            ServiceBatchCall(call())
+        }
+    }
+
     #[allow(clippy::redundant_closure)] // False positive in 1.60
     fn accumulator_builder(
-        dataset: &Rc<DatasetView>,
+        &self,
         expression: &AggregateExpression,
     ) -> Box<dyn Fn() -> Box<dyn Accumulator>> {
+        let dataset = &self.dataset;
         let mut accumulator: Box<dyn Fn() -> Box<dyn Accumulator>> = match expression {
             AggregateExpression::CountSolutions { .. } => {
                 Box::new(|| Box::<CountAccumulator>::default())
@@ -1120,7 +1339,19 @@ impl SimpleEvaluator {
                         ))
                     })
                 }
-                AggregateFunction::Custom(_) => Box::new(|| Box::new(FailingAccumulator)),
+                AggregateFunction::Custom(name) => {
+                    if let Some(factory) = self.custom_aggregate_functions.get(name).cloned() {
+                        let dataset = Rc::clone(dataset);
+                        Box::new(move || {
+                            Box::new(CustomAccumulator {
+                                dataset: Rc::clone(&dataset),
+                                inner: factory(),
+                            })
+                        })
+                    } else {
+                        Box::new(|| Box::new(FailingAccumulator))
+                    }
+                }
             },
         };
         if matches!(
@@ -1133,6 +1364,242 @@ impl SimpleEvaluator {
         accumulator
     }
 
+    /// Returns a function listing the solutions of a filtered quad pattern from the spatial index
+    /// if the filter requires the quad pattern object to intersect a constant geometry.
+    ///
+    /// The function returns `None` if the spatial index should not be used:
+    /// the object is already bound or the index is not enabled.
+    /// The filter still needs to be evaluated on the returned solutions.
+    fn spatial_index_lookup(
+        &self,
+        inner: &GraphPattern,
+        expression: &Expression,
+        encoded_variables: &mut Vec<Variable>,
+    ) -> Option<Rc<dyn Fn(&EncodedTuple) -> Option<EncodedTuplesIterator>>> {
+        let GraphPattern::QuadPattern {
+            subject,
+            predicate,
+            object: object @ GroundTermPattern::Variable(object_variable),
+            graph_name,
+        } = inner
+        else {
+            return None;
+        };
+        let bbox = spatial_filter_bounding_box(expression, object_variable)?;
+        let subject =
+            TupleSelector::from_ground_term_pattern(subject, encoded_variables, &self.dataset);
+        let predicate =
+            TupleSelector::from_named_node_pattern(predicate, encoded_variables, &self.dataset);
+        let object =
+            TupleSelector::from_ground_term_pattern(object, encoded_variables, &self.dataset);
+        let graph_name =
+            TupleSelector::from_graph_name_pattern(graph_name, encoded_variables, &self.dataset);
+        let dataset = Rc::clone(&self.dataset);
+        Some(Rc::new(move |from| {
+            if object.get_pattern_value(from).is_some() {
+                return None;
+            }
+            let quads =
+                match dataset.spatial_search(&bbox, graph_name.get_pattern_value(from).as_ref()) {
+                    Ok(quads) => quads?,
+                    Err(error) => return Some(Box::new(once(Err(error)))),
+                };
+            let from = from.clone();
+            let subject = subject.clone();
+            let predicate = predicate.clone();
+            let object = object.clone();
+            let graph_name = graph_name.clone();
+            Some(Box::new(quads.into_iter().filter_map(move |quad| {
+                let mut new_tuple = from.clone();
+                put_pattern_value(&subject, quad.subject, &mut new_tuple)?;
+                put_pattern_value(&predicate, quad.predicate, &mut new_tuple)?;
+                put_pattern_value(&object, quad.object, &mut new_tuple)?;
+                put_pattern_value(&graph_name, quad.graph_name, &mut new_tuple)?;
+                Some(Ok(new_tuple))
+            })))
+        }))
+    }
+
+    /// Returns a function listing the solutions of a filtered quad pattern from range scans of the object values
+    /// if the filter compares the quad pattern object to numeric, date or date time constants.
+    ///
+    /// The function returns `None` if the ranges should not be used:
+    /// the object or the subject is already bound or the predicate is not.
+    /// The filter still needs to be evaluated on the returned solutions.
+    fn range_index_lookup(
+        &self,
+        inner: &GraphPattern,
+        expression: &Expression,
+        encoded_variables: &mut Vec<Variable>,
+    ) -> Option<Rc<dyn Fn(&EncodedTuple) -> Option<EncodedTuplesIterator>>> {
+        let GraphPattern::QuadPattern {
+            subject,
+            predicate,
+            object: object @ GroundTermPattern::Variable(object_variable),
+            graph_name,
+        } = inner
+        else {
+            return None;
+        };
+        let ranges = filter_object_ranges(expression, object_variable)?;
+        let subject =
+            TupleSelector::from_ground_term_pattern(subject, encoded_variables, &self.dataset);
+        let predicate =
+            TupleSelector::from_named_node_pattern(predicate, encoded_variables, &self.dataset);
+        let object =
+            TupleSelector::from_ground_term_pattern(object, encoded_variables, &self.dataset);
+        let graph_name =
+            TupleSelector::from_graph_name_pattern(graph_name, encoded_variables, &self.dataset);
+        let dataset = Rc::clone(&self.dataset);
+        Some(Rc::new(move |from| {
+            if subject.get_pattern_value(from).is_some() || object.get_pattern_value(from).is_some()
+            {
+                return None;
+            }
+            let predicate_value = predicate.get_pattern_value(from)?;
+            let graph_name_value = graph_name.get_pattern_value(from);
+            let quads = ranges
+                .iter()
+                .map(|(start, end)| {
+                    dataset.encoded_quads_for_predicate_object_range(
+                        &predicate_value,
+                        start,
+                        end,
+                        graph_name_value.as_ref(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let from = from.clone();
+            let subject = subject.clone();
+            let predicate = predicate.clone();
+            let object = object.clone();
+            let graph_name = graph_name.clone();
+            Some(Box::new(quads.into_iter().flatten().filter_map(
+                move |quad| match quad {
+                    Ok(quad) => {
+                        let mut new_tuple = from.clone();
+                        put_pattern_value(&subject, quad.subject, &mut new_tuple)?;
+                        put_pattern_value(&predicate, quad.predicate, &mut new_tuple)?;
+                        put_pattern_value(&object, quad.object, &mut new_tuple)?;
+                        put_pattern_value(&graph_name, quad.graph_name, &mut new_tuple)?;
+                        Some(Ok(new_tuple))
+                    }
+                    Err(error) => Some(Err(error)),
+                },
+            )))
+        }))
+    }
+
+    /// Returns a function evaluating a `COUNT(*)` over a single quad pattern from the number of matching index entries,
+    /// without listing the quads.
+    ///
+    /// The function returns `None` if the group is not such a count, see [`counted_quad_pattern`].
+    fn quad_pattern_count_evaluator(
+        &self,
+        pattern: &GraphPattern,
+        encoded_variables: &mut Vec<Variable>,
+    ) -> Option<Rc<dyn Fn(EncodedTuple) -> EncodedTuplesIterator>> {
+        let GraphPattern::QuadPattern {
+            subject,
+            predicate,
+            object,
+            graph_name,
+        } = counted_quad_pattern(pattern)?
+        else {
+            return None;
+        };
+        let GraphPattern::Group { aggregates, .. } = pattern else {
+            return None;
+        };
+        let subject =
+            TupleSelector::from_ground_term_pattern(subject, encoded_variables, &self.dataset);
+        let predicate =
+            TupleSelector::from_named_node_pattern(predicate, encoded_variables, &self.dataset);
+        let object =
+            TupleSelector::from_ground_term_pattern(object, encoded_variables, &self.dataset);
+        let graph_name =
+            TupleSelector::from_graph_name_pattern(graph_name, encoded_variables, &self.dataset);
+        let count_variables = aggregates
+            .iter()
+            .map(|(variable, _)| encode_variable(encoded_variables, variable))
+            .collect::<Vec<_>>();
+        let dataset = Rc::clone(&self.dataset);
+        Some(Rc::new(move |from| {
+            let count = match dataset.encoded_count_for_pattern(
+                subject.get_pattern_value(&from).as_ref(),
+                predicate.get_pattern_value(&from).as_ref(),
+                object.get_pattern_value(&from).as_ref(),
+                graph_name.get_pattern_value(&from).as_ref(),
+            ) {
+                Ok(count) => EncodedTerm::from(i64::try_from(count).unwrap_or(i64::MAX)),
+                Err(error) => return Box::new(once(Err(error))),
+            };
+            // As for the other aggregates, the output only contains the aggregate values
+            let mut result = EncodedTuple::with_capacity(from.capacity());
+            for variable in &count_variables {
+                result.set(*variable, count.clone());
+            }
+            Box::new(once(Ok(result)))
+        }))
+    }
+
+    /// Evaluates the expression to a geometry, constant WKT literals are only parsed once
+    fn geometry_evaluator(
+        &self,
+        expression: &Expression,
+        encoded_variables: &mut Vec<Variable>,
+        stat_children: &mut Vec<Rc<EvalNodeWithStats>>,
+    ) -> Rc<dyn Fn(&EncodedTuple) -> Option<Rc<Geometry>>> {
+        if let Expression::Literal(literal) = expression {
+            let geometry = parse_wkt_literal(literal.as_ref()).map(Rc::new);
+            return Rc::new(move |_| geometry.clone());
+        }
+        let e = self.expression_evaluator(expression, encoded_variables, stat_children);
+        let dataset = Rc::clone(&self.dataset);
+        Rc::new(move |tuple| to_geometry(&dataset, &e(tuple)?).map(Rc::new))
+    }
+
+    fn order_comparator(
+        &self,
+        expression: &[OrderExpression],
+        encoded_variables: &mut Vec<Variable>,
+        stat_children: &mut Vec<Rc<EvalNodeWithStats>>,
+    ) -> TupleComparator {
+        let by = expression
+            .iter()
+            .map(|comp| match comp {
+                OrderExpression::Asc(expression) => ComparatorFunction::Asc(
+                    self.expression_evaluator(expression, encoded_variables, stat_children),
+                ),
+                OrderExpression::Desc(expression) => ComparatorFunction::Desc(
+                    self.expression_evaluator(expression, encoded_variables, stat_children),
+                ),
+            })
+            .collect::<Vec<_>>();
+        let dataset = Rc::clone(&self.dataset);
+        Rc::new(move |a, b| {
+            for comp in &by {
+                match comp {
+                    ComparatorFunction::Asc(expression) => {
+                        match cmp_terms(&dataset, expression(a).as_ref(), expression(b).as_ref()) {
+                            Ordering::Greater => return Ordering::Greater,
+                            Ordering::Less => return Ordering::Less,
+                            Ordering::Equal => (),
+                        }
+                    }
+                    ComparatorFunction::Desc(expression) => {
+                        match cmp_terms(&dataset, expression(a).as_ref(), expression(b).as_ref()) {
+                            Ordering::Greater => return Ordering::Less,
+                            Ordering::Less => return Ordering::Greater,
+                            Ordering::Equal => (),
+                        }
+                    }
+                }
+            }
+            Ordering::Equal
+        })
+    }
+
     fn expression_evaluator(
         &self,
         expression: &Expression,
@@ -1385,7 +1852,8 @@ impl SimpleEvaluator {
                 Rc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::FloatLiteral(value) => Some(value.into()),
                     EncodedTerm::DoubleLiteral(value) => Some(value.into()),
-                    EncodedTerm::IntegerLiteral(value) => Some(value.into()),
+                    EncodedTerm::IntegerLiteral(value)
+                    | EncodedTerm::DerivedIntegerLiteral { value, .. } => Some(value.into()),
                     EncodedTerm::DecimalLiteral(value) => Some(value.into()),
                     EncodedTerm::DurationLiteral(value) => Some(value.into()),
                     EncodedTerm::YearMonthDurationLiteral(value) => Some(value.into()),
@@ -1398,7 +1866,10 @@ impl SimpleEvaluator {
                 Rc::new(move |tuple| match e(tuple)? {
                     EncodedTerm::FloatLiteral(value) => Some((-value).into()),
                     EncodedTerm::DoubleLiteral(value) => Some((-value).into()),
-                    EncodedTerm::IntegerLiteral(value) => Some(value.checked_neg()?.into()),
+                    EncodedTerm::IntegerLiteral(value)
+                    | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
+                        Some(value.checked_neg()?.into())
+                    }
                     EncodedTerm::DecimalLiteral(value) => Some(value.checked_neg()?.into()),
                     EncodedTerm::DurationLiteral(value) => Some(value.checked_neg()?.into()),
                     EncodedTerm::YearMonthDurationLiteral(value) => {
@@ -1578,7 +2049,10 @@ impl SimpleEvaluator {
                             stat_children,
                         );
                         Rc::new(move |tuple| match e(tuple)? {
-                            EncodedTerm::IntegerLiteral(value) => Some(value.checked_abs()?.into()),
+                            EncodedTerm::IntegerLiteral(value)
+                            | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
+                                Some(value.checked_abs()?.into())
+                            }
                             EncodedTerm::DecimalLiteral(value) => Some(value.checked_abs()?.into()),
                             EncodedTerm::FloatLiteral(value) => Some(value.abs().into()),
                             EncodedTerm::DoubleLiteral(value) => Some(value.abs().into()),
@@ -1592,7 +2066,10 @@ impl SimpleEvaluator {
                             stat_children,
                         );
                         Rc::new(move |tuple| match e(tuple)? {
-                            EncodedTerm::IntegerLiteral(value) => Some(value.into()),
+                            EncodedTerm::IntegerLiteral(value)
+                            | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
+                                Some(value.into())
+                            }
                             EncodedTerm::DecimalLiteral(value) => {
                                 Some(value.checked_ceil()?.into())
                             }
@@ -1608,7 +2085,10 @@ impl SimpleEvaluator {
                             stat_children,
                         );
                         Rc::new(move |tuple| match e(tuple)? {
-                            EncodedTerm::IntegerLiteral(value) => Some(value.into()),
+                            EncodedTerm::IntegerLiteral(value)
+                            | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
+                                Some(value.into())
+                            }
                             EncodedTerm::DecimalLiteral(value) => {
                                 Some(value.checked_floor()?.into())
                             }
@@ -1624,7 +2104,10 @@ impl SimpleEvaluator {
                             stat_children,
                         );
                         Rc::new(move |tuple| match e(tuple)? {
-                            EncodedTerm::IntegerLiteral(value) => Some(value.into()),
+                            EncodedTerm::IntegerLiteral(value)
+                            | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
+                                Some(value.into())
+                            }
                             EncodedTerm::DecimalLiteral(value) => {
                                 Some(value.checked_round()?.into())
                             }
@@ -1681,13 +2164,19 @@ impl SimpleEvaluator {
                                 to_string_and_language(&dataset, &source(tuple)?)?;
 
                             let starting_location: usize =
-                                if let EncodedTerm::IntegerLiteral(v) = starting_loc(tuple)? {
+                                if let EncodedTerm::IntegerLiteral(v)
+                                | EncodedTerm::DerivedIntegerLiteral { value: v, .. } =
+                                    starting_loc(tuple)?
+                                {
                                     i64::from(v).try_into().ok()?
                                 } else {
                                     return None;
                                 };
                             let length: Option<usize> = if let Some(length) = &length {
-                                if let EncodedTerm::IntegerLiteral(v) = length(tuple)? {
+                                if let EncodedTerm::IntegerLiteral(v)
+                                | EncodedTerm::DerivedIntegerLiteral { value: v, .. } =
+                                    length(tuple)?
+                                {
                                     Some(i64::from(v).try_into().ok()?)
                                 } else {
                                     return None;
@@ -2304,6 +2793,7 @@ impl SimpleEvaluator {
                                     EncodedTerm::FloatLiteral(_)
                                         | EncodedTerm::DoubleLiteral(_)
                                         | EncodedTerm::IntegerLiteral(_)
+                                        | EncodedTerm::DerivedIntegerLiteral { .. }
                                         | EncodedTerm::DecimalLiteral(_)
                                 )
                                 .into(),
@@ -2440,7 +2930,131 @@ impl SimpleEvaluator {
                                 Some(dataset.encode_term(&function(&args)?))
                             });
                         }
+                        if let (Some((relation, _)), [left, right]) = (
+                            geometry_relation(function_name.as_ref()),
+                            parameters.as_slice(),
+                        ) {
+                            let left =
+                                self.geometry_evaluator(left, encoded_variables, stat_children);
+                            let right =
+                                self.geometry_evaluator(right, encoded_variables, stat_children);
+                            return Rc::new(move |tuple| {
+                                Some(relation(&*left(tuple)?, &*right(tuple)?).into())
+                            });
+                        }
                         match function_name.as_ref() {
+                            TEXT_SCORE if parameters.len() == 2 => {
+                                let subject = self.expression_evaluator(
+                                    &parameters[0],
+                                    encoded_variables,
+                                    stat_children,
+                                );
+                                let query = self.expression_evaluator(
+                                    &parameters[1],
+                                    encoded_variables,
+                                    stat_children,
+                                );
+                                let dataset = Rc::clone(&self.dataset);
+                                let expression_error = Rc::clone(&self.expression_error);
+                                Rc::new(move |tuple| {
+                                    let query = to_string(&dataset, &query(tuple)?)?;
+                                    match dataset.text_search_score(&query, &subject(tuple)?) {
+                                        Ok(score) => Some(Float::from(score?).into()),
+                                        Err(error) => {
+                                            expression_error.borrow_mut().get_or_insert(error);
+                                            None
+                                        }
+                                    }
+                                })
+                            }
+                            GEOF_DISTANCE if parameters.len() == 3 => {
+                                let left = self.geometry_evaluator(
+                                    &parameters[0],
+                                    encoded_variables,
+                                    stat_children,
+                                );
+                                let right = self.geometry_evaluator(
+                                    &parameters[1],
+                                    encoded_variables,
+                                    stat_children,
+                                );
+                                let unit = self.expression_evaluator(
+                                    &parameters[2],
+                                    encoded_variables,
+                                    stat_children,
+                                );
+                                let metre = encode_named_node(&self.dataset, UOM_METRE);
+                                let degree = encode_named_node(&self.dataset, UOM_DEGREE);
+                                let radian = encode_named_node(&self.dataset, UOM_RADIAN);
+                                Rc::new(move |tuple| {
+                                    let (left, right) = (left(tuple)?, right(tuple)?);
+                                    let unit = unit(tuple)?;
+                                    let distance = if unit == metre {
+                                        left.distance_in_meters(&right)?
+                                    } else if unit == degree {
+                                        left.distance(&right)?
+                                    } else if unit == radian {
+                                        left.distance(&right)?.to_radians()
+                                    } else {
+                                        return None;
+                                    };
+                                    Some(Double::from(distance).into())
+                                })
+                            }
+                            GEOF_BUFFER if parameters.len() == 3 => {
+                                let geometry = self.geometry_evaluator(
+                                    &parameters[0],
+                                    encoded_variables,
+                                    stat_children,
+                                );
+                                let radius = self.expression_evaluator(
+                                    &parameters[1],
+                                    encoded_variables,
+                                    stat_children,
+                                );
+                                let unit = self.expression_evaluator(
+                                    &parameters[2],
+                                    encoded_variables,
+                                    stat_children,
+                                );
+                                let metre = encode_named_node(&self.dataset, UOM_METRE);
+                                let degree = encode_named_node(&self.dataset, UOM_DEGREE);
+                                let radian = encode_named_node(&self.dataset, UOM_RADIAN);
+                                let dataset = Rc::clone(&self.dataset);
+                                Rc::new(move |tuple| {
+                                    let radius = to_f64(&radius(tuple)?)?;
+                                    let unit = unit(tuple)?;
+                                    let buffer = if unit == metre {
+                                        geometry(tuple)?.buffer_in_meters(radius)?
+                                    } else if unit == degree {
+                                        geometry(tuple)?.buffer(radius)?
+                                    } else if unit == radian {
+                                        geometry(tuple)?.buffer(radius.to_degrees())?
+                                    } else {
+                                        return None;
+                                    };
+                                    Some(build_wkt_literal(&dataset, &buffer))
+                                })
+                            }
+                            GEOF_ENVELOPE | GEOF_CONVEX_HULL if parameters.len() == 1 => {
+                                let geometry = self.geometry_evaluator(
+                                    &parameters[0],
+                                    encoded_variables,
+                                    stat_children,
+                                );
+                                let operation = if function_name.as_ref() == GEOF_ENVELOPE {
+                                    Geometry::envelope
+                                } else {
+                                    Geometry::convex_hull
+                                };
+                                let dataset = Rc::clone(&self.dataset);
+                                Rc::new(move |tuple| {
+                                    Some(build_wkt_literal(
+                                        &dataset,
+                                        &operation(&*geometry(tuple)?)?,
+                                    ))
+                                })
+                            }
                             xsd::STRING => {
                                 let e = self.expression_evaluator(
                                     &parameters[0],
@@ -2469,7 +3083,8 @@ impl SimpleEvaluator {
                                     EncodedTerm::DoubleLiteral(value) => {
                                         Some(Boolean::from(value).into())
                                     }
-                                    EncodedTerm::IntegerLiteral(value) => {
+                                    EncodedTerm::IntegerLiteral(value)
+                                    | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
                                         Some(Boolean::from(value).into())
                                     }
                                     EncodedTerm::DecimalLiteral(value) => {
@@ -2493,7 +3108,8 @@ impl SimpleEvaluator {
                                         Some(Double::from(value).into())
                                     }
                                     EncodedTerm::DoubleLiteral(value) => Some(value.into()),
-                                    EncodedTerm::IntegerLiteral(value) => {
+                                    EncodedTerm::IntegerLiteral(value)
+                                    | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
                                         Some(Double::from(value).into())
                                     }
                                     EncodedTerm::DecimalLiteral(value) => {
@@ -2523,7 +3139,8 @@ impl SimpleEvaluator {
                                     EncodedTerm::DoubleLiteral(value) => {
                                         Some(Float::from(value).into())
                                     }
-                                    EncodedTerm::IntegerLiteral(value) => {
+                                    EncodedTerm::IntegerLiteral(value)
+                                    | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
                                         Some(Float::from(value).into())
                                     }
                                     EncodedTerm::DecimalLiteral(value) => {
@@ -2555,7 +3172,10 @@ impl SimpleEvaluator {
                                     EncodedTerm::DoubleLiteral(value) => {
                                         Some(Integer::try_from(value).ok()?.into())
                                     }
-                                    EncodedTerm::IntegerLiteral(value) => Some(value.into()),
+                                    EncodedTerm::IntegerLiteral(value)
+                                    | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
+                                        Some(value.into())
+                                    }
                                     EncodedTerm::DecimalLiteral(value) => {
                                         Some(Integer::try_from(value).ok()?.into())
                                     }
@@ -2585,7 +3205,8 @@ impl SimpleEvaluator {
                                     EncodedTerm::DoubleLiteral(value) => {
                                         Some(Decimal::try_from(value).ok()?.into())
                                     }
-                                    EncodedTerm::IntegerLiteral(value) => {
+                                    EncodedTerm::IntegerLiteral(value)
+                                    | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
                                         Some(Decimal::from(value).into())
                                     }
                                     EncodedTerm::DecimalLiteral(value) => Some(value.into()),
@@ -2879,7 +3500,9 @@ fn to_bool(term: &EncodedTerm) -> Option<bool> {
         }
         EncodedTerm::FloatLiteral(value) => Some(Boolean::from(*value).into()),
         EncodedTerm::DoubleLiteral(value) => Some(Boolean::from(*value).into()),
-        EncodedTerm::IntegerLiteral(value) => Some(Boolean::from(*value).into()),
+        EncodedTerm::IntegerLiteral(value) | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
+            Some(Boolean::from(*value).into())
+        }
         EncodedTerm::DecimalLiteral(value) => Some(Boolean::from(*value).into()),
         _ => None,
     }
@@ -2900,10 +3523,12 @@ fn to_string_id(dataset: &DatasetView, term: &EncodedTerm) -> Option<SmallString
         | EncodedTerm::BigBlankNode { .. }
         | EncodedTerm::Triple(_) => None,
         EncodedTerm::SmallStringLiteral(value)
+        | EncodedTerm::SmallAnyUriLiteral(value)
         | EncodedTerm::SmallSmallLangStringLiteral { value, .. }
         | EncodedTerm::SmallBigLangStringLiteral { value, .. }
         | EncodedTerm::SmallTypedLiteral { value, .. } => Some((*value).into()),
         EncodedTerm::BigStringLiteral { value_id }
+        | EncodedTerm::BigAnyUriLiteral { value_id }
         | EncodedTerm::BigSmallLangStringLiteral { value_id, .. }
         | EncodedTerm::BigBigLangStringLiteral { value_id, .. }
         | EncodedTerm::BigTypedLiteral { value_id, .. } => Some((*value_id).into()),
@@ -2913,7 +3538,9 @@ fn to_string_id(dataset: &DatasetView, term: &EncodedTerm) -> Option<SmallString
         )),
         EncodedTerm::FloatLiteral(value) => Some(build_string_id(dataset, &value.to_string())),
         EncodedTerm::DoubleLiteral(value) => Some(build_string_id(dataset, &value.to_string())),
-        EncodedTerm::IntegerLiteral(value) => Some(build_string_id(dataset, &value.to_string())),
+        EncodedTerm::IntegerLiteral(value) | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
+            Some(build_string_id(dataset, &value.to_string()))
+        }
         EncodedTerm::DecimalLiteral(value) => Some(build_string_id(dataset, &value.to_string())),
         EncodedTerm::DateTimeLiteral(value) => Some(build_string_id(dataset, &value.to_string())),
         EncodedTerm::TimeLiteral(value) => Some(build_string_id(dataset, &value.to_string())),
@@ -3011,6 +3638,261 @@ fn build_string_literal_from_id(id: SmallStringOrId) -> EncodedTerm {
     }
 }
 
+fn to_f64(term: &EncodedTerm) -> Option<f64> {
+    match term {
+        EncodedTerm::FloatLiteral(value) => Some(Double::from(*value).into()),
+        EncodedTerm::DoubleLiteral(value) => Some((*value).into()),
+        EncodedTerm::IntegerLiteral(value) | EncodedTerm::DerivedIntegerLiteral { value, .. } => {
+            Some(Double::from(*value).into())
+        }
+        EncodedTerm::DecimalLiteral(value) => Some(Double::from(*value).into()),
+        _ => None,
+    }
+}
+
+fn to_geometry(dataset: &DatasetView, term: &EncodedTerm) -> Option<Geometry> {
+    match term {
+        EncodedTerm::SmallTypedLiteral { value, datatype_id }
+            if *datatype_id == StrHash::new(WKT_LITERAL) =>
+        {
+            value.parse().ok()
+        }
+        EncodedTerm::BigTypedLiteral {
+            value_id,
+            datatype_id,
+        } if *datatype_id == StrHash::new(WKT_LITERAL) => {
+            dataset.get_str(value_id).ok()??.parse().ok()
+        }
+        _ => None,
+    }
+}
+
+fn parse_wkt_literal(literal: LiteralRef<'_>) -> Option<Geometry> {
+    if literal.datatype().as_str() == WKT_LITERAL {
+        literal.value().parse().ok()
+    } else {
+        None
+    }
+}
+
+fn build_wkt_literal(dataset: &DatasetView, geometry: &Geometry) -> EncodedTerm {
+    dataset.encode_term(LiteralRef::new_typed_literal(
+        &geometry.to_string(),
+        NamedNodeRef::new_unchecked(WKT_LITERAL),
+    ))
+}
+
+/// Returns the GeoSPARQL simple features relation implemented by the function
+/// and if it implies that the bounding boxes of the geometries intersect
+fn geometry_relation(
+    function: NamedNodeRef<'_>,
+) -> Option<(fn(&Geometry, &Geometry) -> bool, bool)> {
+    match function {
+        GEOF_SF_INTERSECTS => Some((Geometry::intersects, true)),
+        GEOF_SF_DISJOINT => Some((|a, b| !a.intersects(b), false)),
+        GEOF_SF_WITHIN => Some((Geometry::is_within, true)),
+        GEOF_SF_CONTAINS => Some((|a, b| b.is_within(a), true)),
+        GEOF_SF_EQUALS => Some((Geometry::topologically_equals, true)),
+        _ => None,
+    }
+}
+
+/// Returns the bounding box the value of `variable` must intersect for the filter to pass
+/// if the filter requires it to be related to a constant geometry
+fn spatial_filter_bounding_box(
+    expression: &Expression,
+    variable: &Variable,
+) -> Option<BoundingBox> {
+    match expression {
+        Expression::And(inner) => inner
+            .iter()
+            .find_map(|e| spatial_filter_bounding_box(e, variable)),
+        Expression::FunctionCall(Function::Custom(function), parameters) => {
+            let (_, implies_intersection) = geometry_relation(function.as_ref())?;
+            if !implies_intersection {
+                return None;
+            }
+            let ((Expression::Variable(v), Expression::Literal(literal))
+            | (Expression::Literal(literal), Expression::Variable(v))) =
+                (parameters.first()?, parameters.get(1)?)
+            else {
+                return None;
+            };
+            if v != variable {
+                return None;
+            }
+            parse_wkt_literal(literal.as_ref())?.bounding_box()
+        }
+        _ => None,
+    }
+}
+
+/// Returns ranges of literals of the same type containing all the values of `variable` passing the filter
+/// if the filter compares `variable` to numeric, date or date time constants.
+///
+/// The ranges are inclusive and might contain values not passing the filter, it must still be evaluated.
+fn filter_object_ranges(
+    expression: &Expression,
+    variable: &Variable,
+) -> Option<Vec<(EncodedTerm, EncodedTerm)>> {
+    let mut bounds = Vec::new();
+    collect_filter_bounds(expression, variable, &mut bounds);
+    // We only use the constants of the same kind as the first one, each bound being enough on its own
+    Some(match bounds.first()? {
+        (_, constant) if to_f64(constant).is_some() => numeric_ranges(&bounds)?,
+        (_, EncodedTerm::DateTimeLiteral(_)) => {
+            let bounds = bounds
+                .iter()
+                .filter_map(|(ordering, constant)| match constant {
+                    EncodedTerm::DateTimeLiteral(value) => Some((*ordering, *value)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            let (start, end) = temporal_range(&bounds, DateTime::MIN, DateTime::MAX, |value| {
+                Some((
+                    value.checked_sub_day_time_duration(DayTimeDuration::new(
+                        TIMEZONE_MARGIN_IN_SECONDS,
+                    ))?,
+                    value.checked_add_day_time_duration(DayTimeDuration::new(
+                        TIMEZONE_MARGIN_IN_SECONDS,
+                    ))?,
+                ))
+            });
+            vec![(start.into(), end.into())]
+        }
+        (_, EncodedTerm::DateLiteral(_)) => {
+            let bounds = bounds
+                .iter()
+                .filter_map(|(ordering, constant)| match constant {
+                    EncodedTerm::DateLiteral(value) => Some((*ordering, *value)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            let (start, end) = temporal_range(&bounds, Date::MIN, Date::MAX, |value| {
+                Some((
+                    value.checked_sub_day_time_duration(DayTimeDuration::new(
+                        TIMEZONE_MARGIN_IN_SECONDS,
+                    ))?,
+                    value.checked_add_day_time_duration(DayTimeDuration::new(
+                        TIMEZONE_MARGIN_IN_SECONDS,
+                    ))?,
+                ))
+            });
+            vec![(start.into(), end.into())]
+        }
+        _ => return None,
+    })
+}
+
+/// Collects the constants the filter requires `variable` to be greater (`Ordering::Greater`),
+/// lower (`Ordering::Less`) or equal to (`Ordering::Equal`)
+fn collect_filter_bounds(
+    expression: &Expression,
+    variable: &Variable,
+    bounds: &mut Vec<(Ordering, EncodedTerm)>,
+) {
+    let (left, right, ordering) = match expression {
+        Expression::And(inner) => {
+            for expression in inner {
+                collect_filter_bounds(expression, variable, bounds);
+            }
+            return;
+        }
+        Expression::Equal(left, right) => (left, right, Ordering::Equal),
+        Expression::Greater(left, right) | Expression::GreaterOrEqual(left, right) => {
+            (left, right, Ordering::Greater)
+        }
+        Expression::Less(left, right) | Expression::LessOrEqual(left, right) => {
+            (left, right, Ordering::Less)
+        }
+        _ => return,
+    };
+    match (&**left, &**right) {
+        (Expression::Variable(v), Expression::Literal(constant)) if v == variable => {
+            bounds.push((ordering, constant.as_ref().into()));
+        }
+        (Expression::Literal(constant), Expression::Variable(v)) if v == variable => {
+            bounds.push((ordering.reverse(), constant.as_ref().into()));
+        }
+        _ => (),
+    }
+}
+
+/// Returns a range for each numeric type containing the values allowed by the numeric bounds
+fn numeric_ranges(bounds: &[(Ordering, EncodedTerm)]) -> Option<Vec<(EncodedTerm, EncodedTerm)>> {
+    let mut min = f64::NEG_INFINITY;
+    let mut max = f64::INFINITY;
+    for (ordering, constant) in bounds {
+        let Some(value) = to_f64(constant) else {
+            continue;
+        };
+        if !value.is_finite() {
+            return None;
+        }
+        // The comparisons between numeric types round the values, we widen the bounds to cover it
+        let margin = value.abs() * 1e-6 + 1e-6;
+        if *ordering != Ordering::Less {
+            min = min.max(value - margin);
+        }
+        if *ordering != Ordering::Greater {
+            max = max.min(value + margin);
+        }
+    }
+    let (min, max) = (Double::from(min), Double::from(max));
+    let (integer_min, integer_max) = (
+        Integer::try_from(min.floor()).unwrap_or(Integer::MIN),
+        Integer::try_from(max.ceil()).unwrap_or(Integer::MAX),
+    );
+    Some(vec![
+        (integer_min.into(), integer_max.into()),
+        // The derived integer range keys do not contain the datatype, the range covers all of them
+        (
+            EncodedTerm::DerivedIntegerLiteral {
+                value: integer_min,
+                datatype: DerivedIntegerDatatype::Long,
+            },
+            EncodedTerm::DerivedIntegerLiteral {
+                value: integer_max,
+                datatype: DerivedIntegerDatatype::Long,
+            },
+        ),
+        (
+            Decimal::try_from(min).unwrap_or(Decimal::MIN).into(),
+            Decimal::try_from(max).unwrap_or(Decimal::MAX).into(),
+        ),
+        (Float::from(min).into(), Float::from(max).into()),
+        (min.into(), max.into()),
+    ])
+}
+
+/// A day is more than the 14 hours between a date or a date time without timezone offset
+/// and the same one with any timezone offset. Dates are shifted by full days.
+const TIMEZONE_MARGIN_IN_SECONDS: i64 = 24 * 3600;
+
+/// Returns the range of the dates or date times allowed by the bounds.
+///
+/// `widen` returns the bound value shifted by [`TIMEZONE_MARGIN_IN_SECONDS`] in both directions,
+/// a bound is ignored if it overflows.
+fn temporal_range<T: Copy + PartialOrd>(
+    bounds: &[(Ordering, T)],
+    mut start: T,
+    mut end: T,
+    widen: impl Fn(T) -> Option<(T, T)>,
+) -> (T, T) {
+    for (ordering, value) in bounds {
+        let Some((lower, upper)) = widen(*value) else {
+            continue;
+        };
+        if *ordering != Ordering::Less && lower > start {
+            start = lower;
+        }
+        if *ordering != Ordering::Greater && upper < end {
+            end = upper;
+        }
+    }
+    (start, end)
+}
+
 fn build_lang_string_literal(
     dataset: &DatasetView,
     value: &str,
@@ -3159,18 +4041,54 @@ fn encode_bindings(
     variables: Rc<[Variable]>,
     iter: QuerySolutionIter,
 ) -> EncodedTuplesIterator {
-    Box::new(iter.map(move |solution| {
-        let mut encoded_terms = EncodedTuple::with_capacity(variables.len());
-        for (variable, term) in solution?.iter() {
-            put_variable_value(
-                variable,
-                &variables,
-                dataset.encode_term(term),
-                &mut encoded_terms,
-            )
+    Box::new(iter.map(move |solution| Ok(encode_solution(&dataset, &variables, &solution?))))
+}
+
+fn encode_solution(
+    dataset: &DatasetView,
+    variables: &[Variable],
+    solution: &QuerySolution,
+) -> EncodedTuple {
+    let mut encoded_terms = EncodedTuple::with_capacity(variables.len());
+    for (variable, term) in solution.iter() {
+        put_variable_value(
+            variable,
+            variables,
+            dataset.encode_term(term),
+            &mut encoded_terms,
+        )
+    }
+    encoded_terms
+}
+
+/// Returns the input pattern, the service name, the service pattern, if the service is silent
+/// and the join keys if the join should be evaluated by sending batches of the input solutions to the service
+fn batched_service_join<'a>(
+    left: &'a GraphPattern,
+    right: &'a GraphPattern,
+    algorithm: &'a JoinAlgorithm,
+) -> Option<(
+    &'a GraphPattern,
+    &'a NamedNode,
+    &'a GraphPattern,
+    bool,
+    &'a [Variable],
+)> {
+    let JoinAlgorithm::HashBuildLeftProbeRight { keys } = algorithm;
+    if keys.is_empty() {
+        return None;
+    }
+    for (input, service) in [(left, right), (right, left)] {
+        if let GraphPattern::Service {
+            name: NamedNodePattern::NamedNode(name),
+            inner,
+            silent,
+        } = service
+        {
+            return Some((input, name, inner, *silent, keys));
         }
-        Ok(encoded_terms)
-    }))
+    }
+    None
 }
 
 fn equals(a: &EncodedTerm, b: &EncodedTerm) -> Option<bool> {
@@ -3194,6 +4112,16 @@ fn equals(a: &EncodedTerm, b: &EncodedTerm) -> Option<bool> {
             EncodedTerm::SmallTypedLiteral { .. } | EncodedTerm::BigTypedLiteral { .. } => None,
             _ => Some(false),
         },
+        EncodedTerm::SmallAnyUriLiteral(a) => match b {
+            EncodedTerm::SmallAnyUriLiteral(b) => Some(a == b),
+            _ if b.is_unknown_typed_literal() => None,
+            _ => Some(false),
+        },
+        EncodedTerm::BigAnyUriLiteral { value_id: a } => match b {
+            EncodedTerm::BigAnyUriLiteral { value_id: b } => Some(a == b),
+            _ if b.is_unknown_typed_literal() => None,
+            _ => Some(false),
+        },
         EncodedTerm::SmallTypedLiteral { .. } => match b {
             EncodedTerm::SmallTypedLiteral { .. } if a == b => Some(true),
             EncodedTerm::NamedNode { .. }
@@ -3228,7 +4156,8 @@ fn equals(a: &EncodedTerm, b: &EncodedTerm) -> Option<bool> {
         EncodedTerm::FloatLiteral(a) => match b {
             EncodedTerm::FloatLiteral(b) => Some(a == b),
             EncodedTerm::DoubleLiteral(b) => Some(Double::from(*a) == *b),
-            EncodedTerm::IntegerLiteral(b) => Some(*a == (*b).into()),
+            EncodedTerm::IntegerLiteral(b)
+            | EncodedTerm::DerivedIntegerLiteral { value: b, .. } => Some(*a == (*b).into()),
             EncodedTerm::DecimalLiteral(b) => Some(*a == (*b).into()),
             _ if b.is_unknown_typed_literal() => None,
             _ => Some(false),
@@ -3236,23 +4165,28 @@ fn equals(a: &EncodedTerm, b: &EncodedTerm) -> Option<bool> {
         EncodedTerm::DoubleLiteral(a) => match b {
             EncodedTerm::FloatLiteral(b) => Some(*a == (*b).into()),
             EncodedTerm::DoubleLiteral(b) => Some(a == b),
-            EncodedTerm::IntegerLiteral(b) => Some(*a == (*b).into()),
+            EncodedTerm::IntegerLiteral(b)
+            | EncodedTerm::DerivedIntegerLiteral { value: b, .. } => Some(*a == (*b).into()),
             EncodedTerm::DecimalLiteral(b) => Some(*a == (*b).into()),
             _ if b.is_unknown_typed_literal() => None,
             _ => Some(false),
         },
-        EncodedTerm::IntegerLiteral(a) => match b {
-            EncodedTerm::FloatLiteral(b) => Some(Float::from(*a) == *b),
-            EncodedTerm::DoubleLiteral(b) => Some(Double::from(*a) == *b),
-            EncodedTerm::IntegerLiteral(b) => Some(a == b),
-            EncodedTerm::DecimalLiteral(b) => Some(Decimal::from(*a) == *b),
-            _ if b.is_unknown_typed_literal() => None,
-            _ => Some(false),
-        },
+        EncodedTerm::IntegerLiteral(a) | EncodedTerm::DerivedIntegerLiteral { value: a, .. } => {
+            match b {
+                EncodedTerm::FloatLiteral(b) => Some(Float::from(*a) == *b),
+                EncodedTerm::DoubleLiteral(b) => Some(Double::from(*a) == *b),
+                EncodedTerm::IntegerLiteral(b)
+                | EncodedTerm::DerivedIntegerLiteral { value: b, .. } => Some(a == b),
+                EncodedTerm::DecimalLiteral(b) => Some(Decimal::from(*a) == *b),
+                _ if b.is_unknown_typed_literal() => None,
+                _ => Some(false),
+            }
+        }
         EncodedTerm::DecimalLiteral(a) => match b {
             EncodedTerm::FloatLiteral(b) => Some(Float::from(*a) == *b),
             EncodedTerm::DoubleLiteral(b) => Some(Double::from(*a) == *b),
-            EncodedTerm::IntegerLiteral(b) => Some(*a == (*b).into()),
+            EncodedTerm::IntegerLiteral(b)
+            | EncodedTerm::DerivedIntegerLiteral { value: b, .. } => Some(*a == (*b).into()),
             EncodedTerm::DecimalLiteral(b) => Some(a == b),
             _ if b.is_unknown_typed_literal() => None,
             _ => Some(false),
@@ -3456,6 +4390,16 @@ fn partial_cmp_literals(
             EncodedTerm::BigStringLiteral { value_id: b } => compare_str_ids(dataset, a, b),
             _ => None,
         },
+        EncodedTerm::SmallAnyUriLiteral(a) => match b {
+            EncodedTerm::SmallAnyUriLiteral(b) => a.partial_cmp(b),
+            EncodedTerm::BigAnyUriLiteral { value_id: b } => compare_str_str_id(dataset, a, b),
+            _ => None,
+        },
+        EncodedTerm::BigAnyUriLiteral { value_id: a } => match b {
+            EncodedTerm::SmallAnyUriLiteral(b) => compare_str_id_str(dataset, a, b),
+            EncodedTerm::BigAnyUriLiteral { value_id: b } => compare_str_ids(dataset, a, b),
+            _ => None,
+        },
         EncodedTerm::SmallSmallLangStringLiteral {
             value: a,
             language: la,
@@ -3515,28 +4459,40 @@ fn partial_cmp_literals(
         EncodedTerm::FloatLiteral(a) => match b {
             EncodedTerm::FloatLiteral(b) => a.partial_cmp(b),
             EncodedTerm::DoubleLiteral(b) => Double::from(*a).partial_cmp(b),
-            EncodedTerm::IntegerLiteral(b) => a.partial_cmp(&Float::from(*b)),
+            EncodedTerm::IntegerLiteral(b)
+            | EncodedTerm::DerivedIntegerLiteral { value: b, .. } => {
+                a.partial_cmp(&Float::from(*b))
+            }
             EncodedTerm::DecimalLiteral(b) => a.partial_cmp(&(*b).into()),
             _ => None,
         },
         EncodedTerm::DoubleLiteral(a) => match b {
             EncodedTerm::FloatLiteral(b) => a.partial_cmp(&(*b).into()),
             EncodedTerm::DoubleLiteral(b) => a.partial_cmp(b),
-            EncodedTerm::IntegerLiteral(b) => a.partial_cmp(&Double::from(*b)),
+            EncodedTerm::IntegerLiteral(b)
+            | EncodedTerm::DerivedIntegerLiteral { value: b, .. } => {
+                a.partial_cmp(&Double::from(*b))
+            }
             EncodedTerm::DecimalLiteral(b) => a.partial_cmp(&(*b).into()),
             _ => None,
         },
-        EncodedTerm::IntegerLiteral(a) => match b {
-            EncodedTerm::FloatLiteral(b) => Float::from(*a).partial_cmp(b),
-            EncodedTerm::DoubleLiteral(b) => Double::from(*a).partial_cmp(b),
-            EncodedTerm::IntegerLiteral(b) => a.partial_cmp(b),
-            EncodedTerm::DecimalLiteral(b) => Decimal::from(*a).partial_cmp(b),
-            _ => None,
-        },
+        EncodedTerm::IntegerLiteral(a) | EncodedTerm::DerivedIntegerLiteral { value: a, .. } => {
+            match b {
+                EncodedTerm::FloatLiteral(b) => Float::from(*a).partial_cmp(b),
+                EncodedTerm::DoubleLiteral(b) => Double::from(*a).partial_cmp(b),
+                EncodedTerm::IntegerLiteral(b)
+                | EncodedTerm::DerivedIntegerLiteral { value: b, .. } => a.partial_cmp(b),
+                EncodedTerm::DecimalLiteral(b) => Decimal::from(*a).partial_cmp(b),
+                _ => None,
+            }
+        }
         EncodedTerm::DecimalLiteral(a) => match b {
             EncodedTerm::FloatLiteral(b) => Float::from(*a).partial_cmp(b),
             EncodedTerm::DoubleLiteral(b) => Double::from(*a).partial_cmp(b),
-            EncodedTerm::IntegerLiteral(b) => a.partial_cmp(&Decimal::from(*b)),
+            EncodedTerm::IntegerLiteral(b)
+            | EncodedTerm::DerivedIntegerLiteral { value: b, .. } => {
+                a.partial_cmp(&Decimal::from(*b))
+            }
             EncodedTerm::DecimalLiteral(b) => a.partial_cmp(b),
             _ => None,
         },
@@ -3642,6 +4598,9 @@ fn datatype(dataset: &DatasetView, value: &EncodedTerm) -> Option<EncodedTerm> {
         EncodedTerm::SmallStringLiteral(_) | EncodedTerm::BigStringLiteral { .. } => {
             Some(encode_named_node(dataset, xsd::STRING))
         }
+        EncodedTerm::SmallAnyUriLiteral(_) | EncodedTerm::BigAnyUriLiteral { .. } => {
+            Some(encode_named_node(dataset, xsd::ANY_URI))
+        }
         EncodedTerm::SmallSmallLangStringLiteral { .. }
         | EncodedTerm::SmallBigLangStringLiteral { .. }
         | EncodedTerm::BigSmallLangStringLiteral { .. }
@@ -3656,6 +4615,9 @@ fn datatype(dataset: &DatasetView, value: &EncodedTerm) -> Option<EncodedTerm> {
         EncodedTerm::FloatLiteral(..) => Some(encode_named_node(dataset, xsd::FLOAT)),
         EncodedTerm::DoubleLiteral(..) => Some(encode_named_node(dataset, xsd::DOUBLE)),
         EncodedTerm::IntegerLiteral(..) => Some(encode_named_node(dataset, xsd::INTEGER)),
+        EncodedTerm::DerivedIntegerLiteral { datatype, .. } => {
+            Some(encode_named_node(dataset, datatype.iri()))
+        }
         EncodedTerm::DecimalLiteral(..) => Some(encode_named_node(dataset, xsd::DECIMAL)),
         EncodedTerm::DateTimeLiteral(..) => Some(encode_named_node(dataset, xsd::DATE_TIME)),
         EncodedTerm::TimeLiteral(..) => Some(encode_named_node(dataset, xsd::TIME)),
@@ -3705,9 +4667,11 @@ impl NumericBinaryOperands {
             (EncodedTerm::FloatLiteral(v1), EncodedTerm::DoubleLiteral(v2)) => {
                 Some(Self::Double(v1.into(), v2))
             }
-            (EncodedTerm::FloatLiteral(v1), EncodedTerm::IntegerLiteral(v2)) => {
-                Some(Self::Float(v1, v2.into()))
-            }
+            (
+                EncodedTerm::FloatLiteral(v1),
+                EncodedTerm::IntegerLiteral(v2)
+                | EncodedTerm::DerivedIntegerLiteral { value: v2, .. },
+            ) => Some(Self::Float(v1, v2.into())),
             (EncodedTerm::FloatLiteral(v1), EncodedTerm::DecimalLiteral(v2)) => {
                 Some(Self::Float(v1, v2.into()))
             }
@@ -3717,33 +4681,46 @@ impl NumericBinaryOperands {
             (EncodedTerm::DoubleLiteral(v1), EncodedTerm::DoubleLiteral(v2)) => {
                 Some(Self::Double(v1, v2))
             }
-            (EncodedTerm::DoubleLiteral(v1), EncodedTerm::IntegerLiteral(v2)) => {
-                Some(Self::Double(v1, v2.into()))
-            }
+            (
+                EncodedTerm::DoubleLiteral(v1),
+                EncodedTerm::IntegerLiteral(v2)
+                | EncodedTerm::DerivedIntegerLiteral { value: v2, .. },
+            ) => Some(Self::Double(v1, v2.into())),
             (EncodedTerm::DoubleLiteral(v1), EncodedTerm::DecimalLiteral(v2)) => {
                 Some(Self::Double(v1, v2.into()))
             }
-            (EncodedTerm::IntegerLiteral(v1), EncodedTerm::FloatLiteral(v2)) => {
-                Some(Self::Float(v1.into(), v2))
-            }
-            (EncodedTerm::IntegerLiteral(v1), EncodedTerm::DoubleLiteral(v2)) => {
-                Some(Self::Double(v1.into(), v2))
-            }
-            (EncodedTerm::IntegerLiteral(v1), EncodedTerm::IntegerLiteral(v2)) => {
-                Some(Self::Integer(v1, v2))
-            }
-            (EncodedTerm::IntegerLiteral(v1), EncodedTerm::DecimalLiteral(v2)) => {
-                Some(Self::Decimal(v1.into(), v2))
-            }
+            (
+                EncodedTerm::IntegerLiteral(v1)
+                | EncodedTerm::DerivedIntegerLiteral { value: v1, .. },
+                EncodedTerm::FloatLiteral(v2),
+            ) => Some(Self::Float(v1.into(), v2)),
+            (
+                EncodedTerm::IntegerLiteral(v1)
+                | EncodedTerm::DerivedIntegerLiteral { value: v1, .. },
+                EncodedTerm::DoubleLiteral(v2),
+            ) => Some(Self::Double(v1.into(), v2)),
+            (
+                EncodedTerm::IntegerLiteral(v1)
+                | EncodedTerm::DerivedIntegerLiteral { value: v1, .. },
+                EncodedTerm::IntegerLiteral(v2)
+                | EncodedTerm::DerivedIntegerLiteral { value: v2, .. },
+            ) => Some(Self::Integer(v1, v2)),
+            (
+                EncodedTerm::IntegerLiteral(v1)
+                | EncodedTerm::DerivedIntegerLiteral { value: v1, .. },
+                EncodedTerm::DecimalLiteral(v2),
+            ) => Some(Self::Decimal(v1.into(), v2)),
             (EncodedTerm::DecimalLiteral(v1), EncodedTerm::FloatLiteral(v2)) => {
                 Some(Self::Float(v1.into(), v2))
             }
             (EncodedTerm::DecimalLiteral(v1), EncodedTerm::DoubleLiteral(v2)) => {
                 Some(Self::Double(v1.into(), v2))
             }
-            (EncodedTerm::DecimalLiteral(v1), EncodedTerm::IntegerLiteral(v2)) => {
-                Some(Self::Decimal(v1, v2.into()))
-            }
+            (
+                EncodedTerm::DecimalLiteral(v1),
+                EncodedTerm::IntegerLiteral(v2)
+                | EncodedTerm::DerivedIntegerLiteral { value: v2, .. },
+            ) => Some(Self::Decimal(v1, v2.into())),
             (EncodedTerm::DecimalLiteral(v1), EncodedTerm::DecimalLiteral(v2)) => {
                 Some(Self::Decimal(v1, v2))
             }
@@ -4796,6 +5773,156 @@ impl Iterator for HashJoinIterator {
     }
 }
 
+/// Maximal number of concurrent calls to a service done by a [`BatchedServiceJoinIterator`]
+const MAX_CONCURRENT_SERVICE_CALLS: usize = 4;
+
+/// A service call started by [`SimpleEvaluator::start_service_batch_call`]
+#[cfg(not(target_family = "wasm"))]
+struct ServiceBatchCall(std::thread::JoinHandle<Result<Vec<QuerySolution>, EvaluationError>>);
+
+#[cfg(target_family = "wasm")]
+struct ServiceBatchCall(Result<Vec<QuerySolution>, EvaluationError>);
+
+impl ServiceBatchCall {
+    fn join(self) -> Result<Vec<QuerySolution>, EvaluationError> {
+        #[cfg(not(target_family = "wasm"))]
+        {
+            self.0
+                .join()
+                .unwrap_or_else(|e| std::panic::resume_unwind(e))
+        }
+        #[cfg(target_family = "wasm")]
+        {
+            self.0
+        }
+    }
+}
+
+/// Joins the input solutions with a service by sending them to the service in batches
+struct BatchedServiceJoinIterator {
+    input: EncodedTuplesIterator,
+    eval: SimpleEvaluator,
+    service_name: NamedNode,
+    graph_pattern: spargebra::algebra::GraphPattern,
+    silent: bool,
+    keys: Vec<(usize, Variable)>,
+    variables: Rc<[Variable]>,
+    input_done: bool,
+    pending_calls: VecDeque<(Vec<EncodedTuple>, ServiceBatchCall)>,
+    current: EncodedTuplesIterator,
+    buffered_results: Vec<Result<EncodedTuple, EvaluationError>>,
+}
+
+impl BatchedServiceJoinIterator {
+    /// Reads the next batch of input solutions and starts the service call for it
+    fn start_next_call(&mut self) {
+        let mut batch = Vec::new();
+        let mut solutions = Vec::new();
+        let mut seen_keys = HashSet::new();
+        let variables = self
+            .keys
+            .iter()
+            .map(|(_, v)| v.clone())
+            .collect::<Arc<[_]>>();
+        while batch.len() < self.eval.service_batch_size {
+            let Some(tuple) = self.input.next() else {
+                self.input_done = true;
+                break;
+            };
+            let tuple = match tuple {
+                Ok(tuple) => tuple,
+                Err(error) => {
+                    self.buffered_results.push(Err(error));
+                    continue;
+                }
+            };
+            let key = self
+                .keys
+                .iter()
+                .map(|(i, _)| tuple.get(*i).cloned())
+                .collect::<Vec<_>>();
+            if !seen_keys.contains(&key) {
+                match key
+                    .iter()
+                    .map(|term| {
+                        term.as_ref()
+                            .map(|term| self.eval.dataset.decode_term(term))
+                            .transpose()
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                {
+                    Ok(values) => {
+                        solutions.push(QuerySolution::from((Arc::clone(&variables), values)))
+                    }
+                    Err(error) => {
+                        self.buffered_results.push(Err(error.into()));
+                        continue;
+                    }
+                }
+                seen_keys.insert(key);
+            }
+            batch.push(tuple);
+        }
+        if !batch.is_empty() {
+            let call = self.eval.start_service_batch_call(
+                self.service_name.clone(),
+                &self.graph_pattern,
+                solutions,
+            );
+            self.pending_calls.push_back((batch, call));
+        }
+    }
+}
+
+impl Iterator for BatchedServiceJoinIterator {
+    type Item = Result<EncodedTuple, EvaluationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.buffered_results.pop() {
+                return Some(result);
+            }
+            if let Some(result) = self.current.next() {
+                return Some(result);
+            }
+            while !self.input_done && self.pending_calls.len() < MAX_CONCURRENT_SERVICE_CALLS {
+                self.start_next_call();
+            }
+            let Some((batch, call)) = self.pending_calls.pop_front() else {
+                // We return the remaining errors if any
+                return self.buffered_results.pop();
+            };
+            match call.join() {
+                Ok(solutions) => {
+                    let mut built =
+                        EncodedTupleSet::new(self.keys.iter().map(|(i, _)| *i).collect());
+                    for tuple in batch {
+                        built.insert(tuple);
+                    }
+                    let dataset = Rc::clone(&self.eval.dataset);
+                    let variables = Rc::clone(&self.variables);
+                    self.current = Box::new(HashJoinIterator {
+                        probe_iter: Box::new(
+                            solutions
+                                .into_iter()
+                                .map(move |s| Ok(encode_solution(&dataset, &variables, &s))),
+                        ),
+                        built,
+                        buffered_results: Vec::new(),
+                    });
+                }
+                Err(error) => {
+                    if self.silent {
+                        self.current = Box::new(batch.into_iter().map(Ok));
+                    } else {
+                        self.buffered_results.push(Err(error));
+                    }
+                }
+            }
+        }
+    }
+}
+
 struct HashLeftJoinIterator {
     left_iter: EncodedTuplesIterator,
     right: EncodedTupleSet,
@@ -5160,6 +6287,76 @@ fn look_in_transitive_closure<
     Ok(false)
 }
 
+/// Sorts the input and returns its solutions from `start` to `start + length`.
+///
+/// Only the `start + length` smallest solutions are kept in memory using a bounded max-heap.
+fn top_k(
+    input: EncodedTuplesIterator,
+    comparator: &TupleComparator,
+    start: usize,
+    length: usize,
+) -> EncodedTuplesIterator {
+    let capacity = start.saturating_add(length);
+    let mut errors = Vec::new();
+    let mut heap = BinaryHeap::new();
+    for (position, result) in input.enumerate() {
+        let tuple = match result {
+            Ok(tuple) => tuple,
+            Err(error) => {
+                errors.push(Err(error));
+                continue;
+            }
+        };
+        if heap.len() < capacity {
+            heap.push(TopKEntry {
+                tuple,
+                position,
+                comparator: Rc::clone(comparator),
+            });
+        } else if let Some(mut greatest) = heap.peek_mut() {
+            if comparator(&tuple, &greatest.tuple) == Ordering::Less {
+                // We keep the position so that equal solutions are returned in input order
+                greatest.tuple = tuple;
+                greatest.position = position;
+            }
+        }
+    }
+    Box::new(
+        errors.into_iter().chain(
+            heap.into_sorted_vec()
+                .into_iter()
+                .skip(start)
+                .map(|entry| Ok(entry.tuple)),
+        ),
+    )
+}
+
+struct TopKEntry {
+    tuple: EncodedTuple,
+    position: usize,
+    comparator: TupleComparator,
+}
+
+impl PartialEq for TopKEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TopKEntry {}
+
+impl PartialOrd for TopKEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TopKEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.comparator)(&self.tuple, &other.tuple).then(self.position.cmp(&other.position))
+    }
+}
+
 fn hash_deduplicate<T: Eq + Hash + Clone>(
     iter: impl Iterator<Item = Result<T, EvaluationError>>,
 ) -> impl Iterator<Item = Result<T, EvaluationError>> {
@@ -5239,7 +6436,7 @@ impl<
 trait Accumulator {
     fn add(&mut self, element: Option<EncodedTerm>);
 
-    fn state(&self) -> Option<EncodedTerm>;
+    fn state(&mut self) -> Option<EncodedTerm>;
 }
 
 struct Deduplicate {
@@ -5263,7 +6460,7 @@ impl Accumulator for Deduplicate {
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.inner.state()
     }
 }
@@ -5278,7 +6475,7 @@ impl Accumulator for CountAccumulator {
         self.count += 1;
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         Some(self.count.into())
     }
 }
@@ -5321,7 +6518,7 @@ impl Accumulator for SumAccumulator {
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.sum.clone()
     }
 }
@@ -5338,7 +6535,7 @@ impl Accumulator for AvgAccumulator {
         self.count += 1;
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         let sum = self.sum.state()?;
         if self.count == 0 {
             Some(0.into())
@@ -5349,7 +6546,8 @@ impl Accumulator for AvgAccumulator {
             match sum {
                 EncodedTerm::FloatLiteral(sum) => Some((sum / Float::from(count)).into()),
                 EncodedTerm::DoubleLiteral(sum) => Some((sum / Double::from(count)).into()),
-                EncodedTerm::IntegerLiteral(sum) => {
+                EncodedTerm::IntegerLiteral(sum)
+                | EncodedTerm::DerivedIntegerLiteral { value: sum, .. } => {
                     Some(Decimal::from(sum).checked_div(count)?.into())
                 }
                 EncodedTerm::DecimalLiteral(sum) => Some(sum.checked_div(count)?.into()),
@@ -5382,7 +6580,7 @@ impl Accumulator for MinAccumulator {
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.min.clone().and_then(|v| v)
     }
 }
@@ -5410,7 +6608,7 @@ impl Accumulator for MaxAccumulator {
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.max.clone().and_then(|v| v)
     }
 }
@@ -5427,7 +6625,7 @@ impl Accumulator for SampleAccumulator {
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.value.clone()
     }
 }
@@ -5470,19 +6668,36 @@ impl Accumulator for GroupConcatAccumulator {
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.concat
             .as_ref()
             .map(|result| build_plain_literal(&self.dataset, result, self.language.and_then(|v| v)))
     }
 }
 
+struct CustomAccumulator {
+    dataset: Rc<DatasetView>,
+    inner: Box<dyn AggregateFunctionAccumulator>,
+}
+
+impl Accumulator for CustomAccumulator {
+    fn add(&mut self, element: Option<EncodedTerm>) {
+        if let Some(element) = element.and_then(|e| self.dataset.decode_term(&e).ok()) {
+            self.inner.add(element)
+        }
+    }
+
+    fn state(&mut self) -> Option<EncodedTerm> {
+        Some(self.dataset.encode_term(&self.inner.finish()?))
+    }
+}
+
 struct FailingAccumulator;
 
 impl Accumulator for FailingAccumulator {
     fn add(&mut self, _: Option<EncodedTerm>) {}
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         None
     }
 }
@@ -5565,19 +6780,123 @@ impl From<StrHash> for SmallStringOrId {
     }
 }
 
+/// Number of temporary files used by GROUP BY when the memory budget is exceeded
+const GROUP_PARTITION_COUNT: usize = 32;
+
+struct GroupAggregator {
+    key_variables: Rc<[usize]>,
+    aggregate_input_expressions: Vec<Option<Rc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm>>>>,
+    accumulator_builders: Vec<Box<dyn Fn() -> Box<dyn Accumulator>>>,
+    accumulator_variables: Vec<usize>,
+}
+
+impl GroupAggregator {
+    fn key(&self, tuple: &EncodedTuple) -> Vec<Option<EncodedTerm>> {
+        self.key_variables
+            .iter()
+            .map(|v| tuple.get(*v).cloned())
+            .collect()
+    }
+
+    fn new_accumulators(&self) -> Vec<Box<dyn Accumulator>> {
+        self.accumulator_builders.iter().map(|c| c()).collect()
+    }
+
+    fn add(&self, accumulators: &mut [Box<dyn Accumulator>], tuple: &EncodedTuple) {
+        for (accumulator, input_expression) in accumulators
+            .iter_mut()
+            .zip(&self.aggregate_input_expressions)
+        {
+            accumulator.add(
+                input_expression
+                    .as_ref()
+                    .and_then(|parameter| parameter(tuple)),
+            );
+        }
+    }
+
+    fn result(
+        &self,
+        key: Vec<Option<EncodedTerm>>,
+        accumulators: Vec<Box<dyn Accumulator>>,
+        tuple_size: usize,
+    ) -> EncodedTuple {
+        let mut result = EncodedTuple::with_capacity(tuple_size);
+        for (variable, value) in self.key_variables.iter().zip(key) {
+            if let Some(value) = value {
+                result.set(*variable, value);
+            }
+        }
+        for (mut accumulator, variable) in accumulators.into_iter().zip(&self.accumulator_variables)
+        {
+            if let Some(value) = accumulator.state() {
+                result.set(*variable, value);
+            }
+        }
+        result
+    }
+
+    /// Approximate size in memory of a group
+    fn memory_size(key: &[Option<EncodedTerm>], accumulators: &[Box<dyn Accumulator>]) -> usize {
+        // We count a small constant size for each accumulator state
+        size_of_val(key) + accumulators.len() * 64
+    }
+
+    fn spill(
+        partitions: &mut [SpillWriter],
+        key: &[Option<EncodedTerm>],
+        tuple: &EncodedTuple,
+    ) -> Result<(), EvaluationError> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let hash = hasher.finish().to_le_bytes();
+        let partition = usize::from(u16::from_le_bytes([hash[0], hash[1]])) % partitions.len();
+        partitions[partition]
+            .write(tuple)
+            .map_err(|e| StorageError::from(e).into())
+    }
+
+    fn aggregate_partition(
+        &self,
+        partition: impl Iterator<Item = Result<EncodedTuple, EvaluationError>>,
+        tuple_size: usize,
+    ) -> Vec<Result<EncodedTuple, EvaluationError>> {
+        let mut results = Vec::new();
+        let mut accumulators_for_group =
+            HashMap::<Vec<Option<EncodedTerm>>, Vec<Box<dyn Accumulator>>>::default();
+        for tuple in partition {
+            match tuple {
+                Ok(tuple) => self.add(
+                    accumulators_for_group
+                        .entry(self.key(&tuple))
+                        .or_insert_with(|| self.new_accumulators()),
+                    &tuple,
+                ),
+                Err(error) => results.push(Err(error)),
+            }
+        }
+        results.extend(
+            accumulators_for_group
+                .into_iter()
+                .map(|(key, accumulators)| Ok(self.result(key, accumulators, tuple_size))),
+        );
+        results
+    }
+}
+
 pub enum ComparatorFunction {
     Asc(Rc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm>>),
     Desc(Rc<dyn Fn(&EncodedTuple) -> Option<EncodedTerm>>),
 }
 
-struct EncodedTupleSet {
+pub struct EncodedTupleSet {
     key: Vec<usize>,
     map: HashMap<u64, Vec<EncodedTuple>>,
     len: usize,
 }
 
 impl EncodedTupleSet {
-    fn new(key: Vec<usize>) -> Self {
+    pub fn new(key: Vec<usize>) -> Self {
         Self {
             key,
             map: HashMap::new(),
@@ -5585,7 +6904,7 @@ impl EncodedTupleSet {
         }
     }
 
-    fn insert(&mut self, tuple: EncodedTuple) {
+    pub fn insert(&mut self, tuple: EncodedTuple) {
         self.map
             .entry(self.tuple_key(&tuple))
             .or_default()
@@ -5610,6 +6929,20 @@ impl EncodedTupleSet {
     fn len(&self) -> usize {
         self.len
     }
+
+    pub fn keys(&self) -> &[usize] {
+        &self.key
+    }
+
+    /// Returns the partition among `partition_count` of the tuple using its key hash
+    pub fn partition(&self, tuple: &EncodedTuple, partition_count: usize) -> usize {
+        let hash = self.tuple_key(tuple).to_le_bytes();
+        usize::from(u16::from_le_bytes([hash[0], hash[1]])) % partition_count
+    }
+
+    pub fn into_tuples(self) -> impl Iterator<Item = EncodedTuple> {
+        self.map.into_values().flatten()
+    }
 }
 
 impl Extend<EncodedTuple> for EncodedTupleSet {
@@ -5646,6 +6979,142 @@ impl Iterator for StatsIterator {
     }
 }
 
+/// Number of checks between two lookups of the current time
+const DEADLINE_CHECK_INTERVAL: u16 = 64;
+
+/// The timeout, cancellation and memory budget state of a query evaluation
+pub struct EvaluationLimits {
+    deadline: Option<DateTime>,
+    cancellation_token: Option<CancellationToken>,
+    checks_before_deadline_lookup: Cell<u16>,
+    exceeded: Cell<Option<ExceededLimit>>,
+    memory_budget: Option<usize>,
+    memory_used: Cell<usize>,
+}
+
+#[derive(Clone, Copy)]
+enum ExceededLimit {
+    Timeout,
+    Cancelled,
+}
+
+impl EvaluationLimits {
+    /// Returns `None` if there is nothing to check
+    pub fn new(
+        timeout: Option<std::time::Duration>,
+        cancellation_token: Option<CancellationToken>,
+        memory_budget: Option<usize>,
+    ) -> Option<Self> {
+        if timeout.is_none() && cancellation_token.is_none() && memory_budget.is_none() {
+            return None;
+        }
+        Some(Self {
+            // Timeouts too large to be represented never expire
+            deadline: timeout.and_then(|timeout| {
+                DateTime::now()
+                    .checked_add_day_time_duration(DayTimeDuration::try_from(timeout).ok()?)
+            }),
+            cancellation_token,
+            checks_before_deadline_lookup: Cell::new(0),
+            exceeded: Cell::new(None),
+            memory_budget,
+            memory_used: Cell::new(0),
+        })
+    }
+
+    /// Returns if the evaluation might time out or be cancelled
+    fn is_interruptible(&self) -> bool {
+        self.deadline.is_some() || self.cancellation_token.is_some()
+    }
+
+    pub fn has_memory_limit(&self) -> bool {
+        self.memory_budget.is_some()
+    }
+
+    /// Counts `size` more bytes as used and returns `false` if the memory budget is exceeded
+    pub fn reserve_memory(&self, size: usize) -> bool {
+        let used = self.memory_used.get().saturating_add(size);
+        self.memory_used.set(used);
+        self.memory_budget.map_or(true, |budget| used <= budget)
+    }
+
+    pub fn release_memory(&self, size: usize) {
+        self.memory_used
+            .set(self.memory_used.get().saturating_sub(size));
+    }
+
+    fn check(&self) -> Result<(), EvaluationError> {
+        if self.exceeded.get().is_none() {
+            if self
+                .cancellation_token
+                .as_ref()
+                .map_or(false, CancellationToken::is_cancelled)
+            {
+                self.exceeded.set(Some(ExceededLimit::Cancelled));
+            } else if let Some(deadline) = self.deadline {
+                let checks = self.checks_before_deadline_lookup.get();
+                if checks == 0 {
+                    self.checks_before_deadline_lookup
+                        .set(DEADLINE_CHECK_INTERVAL);
+                    if DateTime::now() >= deadline {
+                        self.exceeded.set(Some(ExceededLimit::Timeout));
+                    }
+                } else {
+                    self.checks_before_deadline_lookup.set(checks - 1);
+                }
+            }
+        }
+        match self.exceeded.get() {
+            None => Ok(()),
+            Some(ExceededLimit::Timeout) => Err(EvaluationError::Timeout),
+            Some(ExceededLimit::Cancelled) => Err(EvaluationError::Cancelled),
+        }
+    }
+}
+
+struct LimitsCheckIterator {
+    inner: EncodedTuplesIterator,
+    limits: Rc<EvaluationLimits>,
+    failed: bool,
+}
+
+impl Iterator for LimitsCheckIterator {
+    type Item = Result<EncodedTuple, EvaluationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if let Err(error) = self.limits.check() {
+            // The error is returned only once to not loop forever in the nodes collecting errors
+            self.failed = true;
+            return Some(Err(error));
+        }
+        self.inner.next()
+    }
+}
+
+/// Returns the error stored by the expression evaluators after the current solution
+struct ExpressionErrorIterator {
+    inner: EncodedTuplesIterator,
+    expression_error: Rc<RefCell<Option<EvaluationError>>>,
+}
+
+impl Iterator for ExpressionErrorIterator {
+    type Item = Result<EncodedTuple, EvaluationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.expression_error.borrow_mut().take() {
+            return Some(Err(error));
+        }
+        let next = self.inner.next();
+        if next.is_none() {
+            return self.expression_error.borrow_mut().take().map(Err);
+        }
+        next
+    }
+}
+
 pub struct EvalNodeWithStats {
     pub label: String,
     pub children: Vec<Rc<EvalNodeWithStats>>,
@@ -5698,7 +7167,75 @@ impl fmt::Debug for EvalNodeWithStats {
     }
 }
 
+/// Returns the quad pattern of a group computing only `COUNT(*)` aggregates over it without `GROUP BY`
+/// if the count can be read from the storage indexes.
+///
+/// The pattern variables must be distinct and must not be in quoted triples so that all the quads with the pattern prefix match.
+fn counted_quad_pattern(pattern: &GraphPattern) -> Option<&GraphPattern> {
+    let GraphPattern::Group {
+        inner,
+        variables,
+        aggregates,
+    } = pattern
+    else {
+        return None;
+    };
+    if !variables.is_empty()
+        || aggregates.is_empty()
+        || !aggregates.iter().all(|(_, aggregate)| {
+            matches!(
+                aggregate,
+                AggregateExpression::CountSolutions { distinct: false }
+            )
+        })
+    {
+        return None;
+    }
+    let GraphPattern::QuadPattern {
+        subject,
+        predicate,
+        object,
+        graph_name,
+    } = inner.as_ref()
+    else {
+        return None;
+    };
+    let mut pattern_variables = Vec::new();
+    for term in [subject, object] {
+        match term {
+            GroundTermPattern::Variable(variable) => pattern_variables.push(variable),
+            GroundTermPattern::Triple(_) => return None,
+            GroundTermPattern::NamedNode(_) | GroundTermPattern::Literal(_) => (),
+        }
+    }
+    for name in [Some(predicate), graph_name.as_ref()].into_iter().flatten() {
+        match name {
+            NamedNodePattern::Variable(variable) => pattern_variables.push(variable),
+            NamedNodePattern::NamedNode(name) if name.as_ref() == TEXT_SEARCH => return None,
+            NamedNodePattern::NamedNode(_) => (),
+        }
+    }
+    pattern_variables
+        .iter()
+        .enumerate()
+        .all(|(i, variable)| !pattern_variables[..i].contains(variable))
+        .then_some(inner.as_ref())
+}
+
 fn eval_node_label(node: &GraphPattern) -> String {
+    if let Some(GraphPattern::QuadPattern {
+        subject,
+        predicate,
+        object,
+        graph_name,
+    }) = counted_quad_pattern(node)
+    {
+        return if let Some(graph_name) = graph_name {
+            format!("CountQuadPattern({subject} {predicate} {object} {graph_name})")
+        } else {
+            format!("CountQuadPattern({subject} {predicate} {object})")
+        };
+    }
     match node {
         GraphPattern::Distinct { .. } => "Distinct(Hash)".to_owned(),
         GraphPattern::Extend {
@@ -5728,7 +7265,25 @@ fn eval_node_label(node: &GraphPattern) -> String {
                 ))
             )
         }
-        GraphPattern::Join { algorithm, .. } => match algorithm {
+        GraphPattern::Join {
+            left,
+            right,
+            algorithm,
+        } => match algorithm {
+            JoinAlgorithm::HashBuildLeftProbeRight { keys }
+                if batched_service_join(left, right, algorithm).is_some() =>
+            {
+                let service = if matches!(right.as_ref(), GraphPattern::Service { .. }) {
+                    right
+                } else {
+                    left
+                };
+                format!(
+                    "BatchedServiceJoin({}, keys = {})",
+                    eval_node_label(service),
+                    format_list(keys)
+                )
+            }
             JoinAlgorithm::HashBuildLeftProbeRight { keys } => format!(
                 "LeftJoin(HashBuildLeftProbeRight, keys = {})",
                 format_list(keys)
@@ -5778,6 +7333,19 @@ fn eval_node_label(node: &GraphPattern) -> String {
                 )
             )
         }
+        GraphPattern::TopK {
+            expression,
+            start,
+            length,
+            ..
+        } => format!(
+            "TopK(start = {start}, length = {length}, {})",
+            format_list(
+                expression
+                    .iter()
+                    .map(spargebra::algebra::OrderExpression::from)
+            )
+        ),
         GraphPattern::Path {
             subject,
             path,
@@ -5799,10 +7367,16 @@ fn eval_node_label(node: &GraphPattern) -> String {
             object,
             graph_name,
         } => {
+            let name = match predicate {
+                NamedNodePattern::NamedNode(predicate) if predicate.as_ref() == TEXT_SEARCH => {
+                    "TextSearch"
+                }
+                _ => "QuadPattern",
+            };
             if let Some(graph_name) = graph_name {
-                format!("QuadPattern({subject} {predicate} {object} {graph_name})")
+                format!("{name}({subject} {predicate} {object} {graph_name})")
             } else {
-                format!("QuadPattern({subject} {predicate} {object})")
+                format!("{name}({subject} {predicate} {object})")
             }
         }
         GraphPattern::Reduced { .. } => "Reduced".to_owned(),
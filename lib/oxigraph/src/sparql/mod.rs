@@ -10,13 +10,14 @@ mod http;
 mod model;
 pub mod results;
 mod service;
+mod spill;
 mod update;
 
 use crate::model::{NamedNode, Term};
 pub use crate::sparql::algebra::{Query, QueryDataset, Update};
 use crate::sparql::dataset::DatasetView;
 pub use crate::sparql::error::EvaluationError;
-use crate::sparql::eval::{EvalNodeWithStats, SimpleEvaluator, Timer};
+use crate::sparql::eval::{EvalNodeWithStats, EvaluationLimits, SimpleEvaluator, Timer};
 pub use crate::sparql::model::{QueryResults, QuerySolution, QuerySolutionIter, QueryTripleIter};
 pub use crate::sparql::service::ServiceHandler;
 use crate::sparql::service::{EmptyServiceHandler, ErrorConversionServiceHandler};
@@ -25,11 +26,12 @@ use crate::storage::StorageReader;
 use json_event_parser::{JsonEvent, ToWriteJsonWriter};
 pub use oxrdf::{Variable, VariableNameParseError};
 use oxsdatatypes::{DayTimeDuration, Float};
-pub use spargebra::SparqlSyntaxError;
+pub use spargebra::{SparqlParser, SparqlSyntaxError};
 use sparopt::algebra::GraphPattern;
 use sparopt::Optimizer;
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use std::{fmt, io};
@@ -42,6 +44,8 @@ pub(crate) fn evaluate_query(
     run_stats: bool,
 ) -> Result<(Result<QueryResults, EvaluationError>, QueryExplanation), EvaluationError> {
     let query = query.try_into().map_err(Into::into)?;
+    let limits = options.evaluation_limits();
+    let service_batch_size = options.service_batch_size();
     let dataset = DatasetView::new(reader, &query.dataset);
     let start_planning = Timer::now();
     let (results, plan_node_with_stats, planning_duration) = match query.inner {
@@ -50,7 +54,7 @@ pub(crate) fn evaluate_query(
         } => {
             let mut pattern = GraphPattern::from(&pattern);
             if !options.without_optimizations {
-                pattern = Optimizer::optimize_graph_pattern(pattern);
+                pattern = Optimizer::optimize_graph_pattern_with_statistics(pattern, &dataset);
             }
             let planning_duration = start_planning.elapsed();
             let (results, explanation) = SimpleEvaluator::new(
@@ -58,6 +62,9 @@ pub(crate) fn evaluate_query(
                 base_iri.map(Rc::new),
                 options.service_handler(),
                 Arc::new(options.custom_functions),
+                Arc::new(options.custom_aggregate_functions),
+                service_batch_size,
+                limits,
                 run_stats,
             )
             .evaluate_select(&pattern);
@@ -68,9 +75,12 @@ pub(crate) fn evaluate_query(
         } => {
             let mut pattern = GraphPattern::from(&pattern);
             if !options.without_optimizations {
-                pattern = Optimizer::optimize_graph_pattern(GraphPattern::Reduced {
-                    inner: Box::new(pattern),
-                });
+                pattern = Optimizer::optimize_graph_pattern_with_statistics(
+                    GraphPattern::Reduced {
+                        inner: Box::new(pattern),
+                    },
+                    &dataset,
+                );
             }
             let planning_duration = start_planning.elapsed();
             let (results, explanation) = SimpleEvaluator::new(
@@ -78,6 +88,9 @@ pub(crate) fn evaluate_query(
                 base_iri.map(Rc::new),
                 options.service_handler(),
                 Arc::new(options.custom_functions),
+                Arc::new(options.custom_aggregate_functions),
+                service_batch_size,
+                limits,
                 run_stats,
             )
             .evaluate_ask(&pattern);
@@ -91,9 +104,12 @@ pub(crate) fn evaluate_query(
         } => {
             let mut pattern = GraphPattern::from(&pattern);
             if !options.without_optimizations {
-                pattern = Optimizer::optimize_graph_pattern(GraphPattern::Reduced {
-                    inner: Box::new(pattern),
-                });
+                pattern = Optimizer::optimize_graph_pattern_with_statistics(
+                    GraphPattern::Reduced {
+                        inner: Box::new(pattern),
+                    },
+                    &dataset,
+                );
             }
             let planning_duration = start_planning.elapsed();
             let (results, explanation) = SimpleEvaluator::new(
@@ -101,6 +117,9 @@ pub(crate) fn evaluate_query(
                 base_iri.map(Rc::new),
                 options.service_handler(),
                 Arc::new(options.custom_functions),
+                Arc::new(options.custom_aggregate_functions),
+                service_batch_size,
+                limits,
                 run_stats,
             )
             .evaluate_construct(&pattern, &template);
@@ -111,9 +130,12 @@ pub(crate) fn evaluate_query(
         } => {
             let mut pattern = GraphPattern::from(&pattern);
             if !options.without_optimizations {
-                pattern = Optimizer::optimize_graph_pattern(GraphPattern::Reduced {
-                    inner: Box::new(pattern),
-                });
+                pattern = Optimizer::optimize_graph_pattern_with_statistics(
+                    GraphPattern::Reduced {
+                        inner: Box::new(pattern),
+                    },
+                    &dataset,
+                );
             }
             let planning_duration = start_planning.elapsed();
             let (results, explanation) = SimpleEvaluator::new(
@@ -121,6 +143,9 @@ pub(crate) fn evaluate_query(
                 base_iri.map(Rc::new),
                 options.service_handler(),
                 Arc::new(options.custom_functions),
+                Arc::new(options.custom_aggregate_functions),
+                service_batch_size,
+                limits,
                 run_stats,
             )
             .evaluate_describe(&pattern);
@@ -158,13 +183,22 @@ pub(crate) fn evaluate_query(
 pub struct QueryOptions {
     service_handler: Option<Arc<dyn ServiceHandler<Error = EvaluationError>>>,
     custom_functions: CustomFunctionRegistry,
+    custom_aggregate_functions: CustomAggregateFunctionRegistry,
     http_timeout: Option<Duration>,
     http_redirection_limit: usize,
+    timeout: Option<Duration>,
+    cancellation_token: Option<CancellationToken>,
+    memory_budget: Option<usize>,
+    service_batch_size: Option<usize>,
     without_optimizations: bool,
 }
 
+const DEFAULT_SERVICE_BATCH_SIZE: usize = 100;
+
 pub(crate) type CustomFunctionRegistry =
     HashMap<NamedNode, Arc<dyn (Fn(&[Term]) -> Option<Term>) + Send + Sync>>;
+pub(crate) type CustomAggregateFunctionRegistry =
+    HashMap<NamedNode, Arc<dyn (Fn() -> Box<dyn AggregateFunctionAccumulator>) + Send + Sync>>;
 
 impl QueryOptions {
     /// Use a given [`ServiceHandler`] to execute [SPARQL 1.1 Federated Query](https://www.w3.org/TR/sparql11-federated-query/) SERVICE calls.
@@ -205,6 +239,119 @@ impl QueryOptions {
         self
     }
 
+    /// Sets a timeout for the query evaluation.
+    ///
+    /// The timeout starts when the query evaluation begins and covers the iteration of the results.
+    /// When it is exceeded, the evaluation fails with an [`EvaluationError::Timeout`] error.
+    ///
+    /// ```
+    /// use oxigraph::sparql::{EvaluationError, QueryOptions, QueryResults};
+    /// use oxigraph::store::Store;
+    /// use std::time::Duration;
+    ///
+    /// let store = Store::new()?;
+    /// if let QueryResults::Solutions(mut solutions) = store.query_opt(
+    ///     "SELECT * WHERE { ?s ?p ?o }",
+    ///     QueryOptions::default().with_timeout(Duration::ZERO),
+    /// )? {
+    ///     assert!(matches!(
+    ///         solutions.next(),
+    ///         Some(Err(EvaluationError::Timeout))
+    ///     ));
+    /// }
+    /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Allows to cancel the query evaluation using the given [`CancellationToken`].
+    ///
+    /// When the token is cancelled, the evaluation fails with an [`EvaluationError::Cancelled`] error.
+    ///
+    /// ```
+    /// use oxigraph::sparql::{CancellationToken, EvaluationError, QueryOptions, QueryResults};
+    /// use oxigraph::store::Store;
+    ///
+    /// let store = Store::new()?;
+    /// let token = CancellationToken::new();
+    /// let results = store.query_opt(
+    ///     "SELECT * WHERE { ?s ?p ?o }",
+    ///     QueryOptions::default().with_cancellation_token(token.clone()),
+    /// )?;
+    /// token.cancel();
+    /// if let QueryResults::Solutions(mut solutions) = results {
+    ///     assert!(matches!(
+    ///         solutions.next(),
+    ///         Some(Err(EvaluationError::Cancelled))
+    ///     ));
+    /// }
+    /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = Some(cancellation_token);
+        self
+    }
+
+    /// Sets an approximate budget in bytes for the intermediate results kept in memory during the query evaluation.
+    ///
+    /// When it is exceeded, `ORDER BY` falls back to an external merge sort, `GROUP BY` and hash joins
+    /// partition their input and the intermediate results are written to temporary files
+    /// in [the system temporary directory](std::env::temp_dir).
+    /// The budget only covers these operations and not all the memory allocated by the query evaluation.
+    ///
+    /// ```
+    /// use oxigraph::sparql::{QueryOptions, QueryResults};
+    /// use oxigraph::store::Store;
+    ///
+    /// let store = Store::new()?;
+    /// if let QueryResults::Solutions(solutions) = store.query_opt(
+    ///     "SELECT ?s WHERE { ?s ?p ?o } ORDER BY ?s",
+    ///     QueryOptions::default().with_memory_budget(64 * 1024 * 1024),
+    /// )? {
+    ///     assert_eq!(solutions.count(), 0);
+    /// }
+    /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Sets the maximal number of solutions sent to a `SERVICE` in a single call when it is joined with other patterns.
+    ///
+    /// The solutions of the other patterns are sent in batches using a `VALUES` clause
+    /// (see [`ServiceHandler::handle_batch`]) and up to a few batches are evaluated concurrently.
+    ///
+    /// By default this value is `100`. It must be at least `1`.
+    ///
+    /// ```
+    /// use oxigraph::sparql::{QueryOptions, QueryResults};
+    /// use oxigraph::store::Store;
+    ///
+    /// let store = Store::new()?;
+    /// if let QueryResults::Solutions(solutions) = store.query_opt(
+    ///     "SELECT * WHERE { ?s ?p ?o SERVICE <https://query.wikidata.org/sparql> { ?s ?p2 ?o2 } }",
+    ///     QueryOptions::default().with_service_batch_size(500),
+    /// )? {
+    ///     assert_eq!(solutions.count(), 0);
+    /// }
+    /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_service_batch_size(mut self, batch_size: usize) -> Self {
+        self.service_batch_size = Some(batch_size.max(1));
+        self
+    }
+
     /// Adds a custom SPARQL evaluation function.
     ///
     /// Example with a function serializing terms to N-Triples:
@@ -240,6 +387,73 @@ impl QueryOptions {
         self
     }
 
+    /// Adds a custom SPARQL aggregate function.
+    ///
+    /// The `factory` is called to build a new [`AggregateFunctionAccumulator`] for each group of solutions.
+    ///
+    /// Calls to the function without the `DISTINCT` keyword are only parsed as aggregations
+    /// if the function is also declared to the [`SparqlParser`] used to parse the query.
+    ///
+    /// Example with an aggregate function returning the median of numbers encoded as strings:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::sparql::{
+    ///     AggregateFunctionAccumulator, QueryOptions, QueryResults, SparqlParser,
+    /// };
+    /// use oxigraph::store::Store;
+    ///
+    /// #[derive(Default)]
+    /// struct MedianAccumulator {
+    ///     values: Vec<f64>,
+    /// }
+    ///
+    /// impl AggregateFunctionAccumulator for MedianAccumulator {
+    ///     fn add(&mut self, element: Term) {
+    ///         if let Term::Literal(literal) = element {
+    ///             if let Ok(value) = literal.value().parse() {
+    ///                 self.values.push(value);
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     fn finish(&mut self) -> Option<Term> {
+    ///         self.values.sort_by(f64::total_cmp);
+    ///         Some(Literal::from(*self.values.get(self.values.len() / 2)?).into())
+    ///     }
+    /// }
+    ///
+    /// let store = Store::new()?;
+    /// let median = NamedNode::new("http://example.com/median")?;
+    ///
+    /// let query = SparqlParser::new()
+    ///     .with_custom_aggregate_function(median.clone())
+    ///     .parse_query(
+    ///         "SELECT (<http://example.com/median>(?v) AS ?m) WHERE { VALUES ?v { \"1\" \"10\" \"2\" } }",
+    ///     )?;
+    /// if let QueryResults::Solutions(mut solutions) = store.query_opt(
+    ///     query,
+    ///     QueryOptions::default()
+    ///         .with_custom_aggregate_function(median, || Box::<MedianAccumulator>::default()),
+    /// )? {
+    ///     assert_eq!(
+    ///         solutions.next().unwrap()?.get("m"),
+    ///         Some(&Literal::from(2.).into())
+    ///     );
+    /// }
+    /// # Result::<_,Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_custom_aggregate_function(
+        mut self,
+        name: NamedNode,
+        factory: impl Fn() -> Box<dyn AggregateFunctionAccumulator> + Send + Sync + 'static,
+    ) -> Self {
+        self.custom_aggregate_functions
+            .insert(name, Arc::new(factory));
+        self
+    }
+
     fn service_handler(&self) -> Arc<dyn ServiceHandler<Error = EvaluationError>> {
         self.service_handler.clone().unwrap_or_else(|| {
             if cfg!(feature = "http-client") {
@@ -253,6 +467,20 @@ impl QueryOptions {
         })
     }
 
+    fn service_batch_size(&self) -> usize {
+        self.service_batch_size
+            .unwrap_or(DEFAULT_SERVICE_BATCH_SIZE)
+    }
+
+    fn evaluation_limits(&self) -> Option<Rc<EvaluationLimits>> {
+        EvaluationLimits::new(
+            self.timeout,
+            self.cancellation_token.clone(),
+            self.memory_budget,
+        )
+        .map(Rc::new)
+    }
+
     #[doc(hidden)]
     #[inline]
     #[must_use]
@@ -262,6 +490,48 @@ impl QueryOptions {
     }
 }
 
+/// The state of a custom aggregate function while aggregating a group of solutions.
+///
+/// See [`QueryOptions::with_custom_aggregate_function`] for an example.
+pub trait AggregateFunctionAccumulator {
+    /// Adds a value of the aggregated expression.
+    ///
+    /// Unbound values and evaluation errors are skipped.
+    fn add(&mut self, element: Term);
+
+    /// Returns the aggregate value of the group or `None` if it is unbound.
+    fn finish(&mut self) -> Option<Term>;
+}
+
+/// A token allowing to cancel query evaluations, possibly from an other thread.
+///
+/// Clones share the same state: cancelling one of them cancels the evaluations using any of them.
+/// See [`QueryOptions::with_cancellation_token`] for an example.
+#[derive(Clone, Default, Debug)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Builds a new token that is not cancelled.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the query evaluations using this token.
+    #[inline]
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns if [`cancel`](Self::cancel) has been called on this token or one of its clones.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
 /// Options for SPARQL update evaluation.
 #[derive(Clone, Default)]
 pub struct UpdateOptions {
@@ -2,8 +2,10 @@ use crate::model::NamedNode;
 use crate::sparql::algebra::Query;
 use crate::sparql::error::EvaluationError;
 use crate::sparql::http::Client;
-use crate::sparql::model::QueryResults;
+use crate::sparql::model::{QueryResults, QuerySolution};
 use crate::sparql::results::QueryResultsFormat;
+use spargebra::algebra::GraphPattern;
+use spargebra::term::GroundTerm;
 use std::error::Error;
 use std::time::Duration;
 
@@ -60,6 +62,50 @@ pub trait ServiceHandler: Send + Sync {
 
     /// Evaluates a [`Query`] against a given service identified by a [`NamedNode`].
     fn handle(&self, service_name: NamedNode, query: Query) -> Result<QueryResults, Self::Error>;
+
+    /// Evaluates a [`Query`] against a given service identified by a [`NamedNode`] for a batch of input solutions.
+    ///
+    /// It is used to evaluate a SERVICE call joined with other patterns: the returned solutions are then joined with the input solutions.
+    /// All the input solutions have the same variables.
+    ///
+    /// The default implementation adds the input solutions to the query using a `VALUES` clause and calls [`handle`](Self::handle).
+    fn handle_batch(
+        &self,
+        service_name: NamedNode,
+        query: Query,
+        input: &[QuerySolution],
+    ) -> Result<QueryResults, Self::Error> {
+        self.handle(service_name, add_values_clause(query, input))
+    }
+}
+
+/// Joins the query pattern with a `VALUES` clause containing the given solutions.
+///
+/// Blank nodes can't be used in `VALUES` clauses so they are left unbound.
+fn add_values_clause(mut query: Query, input: &[QuerySolution]) -> Query {
+    let Some(first) = input.first() else {
+        return query;
+    };
+    if let spargebra::Query::Select { pattern, .. } = &mut query.inner {
+        let values = GraphPattern::Values {
+            variables: first.variables().to_vec(),
+            bindings: input
+                .iter()
+                .map(|solution| {
+                    solution
+                        .values()
+                        .iter()
+                        .map(|value| GroundTerm::try_from(value.clone()?).ok())
+                        .collect()
+                })
+                .collect(),
+        };
+        *pattern = GraphPattern::Join {
+            left: Box::new(values),
+            right: Box::new(pattern.clone()),
+        };
+    }
+    query
 }
 
 pub struct EmptyServiceHandler;
@@ -90,6 +136,17 @@ impl<S: ServiceHandler> ServiceHandler for ErrorConversionServiceHandler<S> {
             .handle(service_name, query)
             .map_err(|e| EvaluationError::Service(Box::new(e)))
     }
+
+    fn handle_batch(
+        &self,
+        service_name: NamedNode,
+        query: Query,
+        input: &[QuerySolution],
+    ) -> Result<QueryResults, Self::Error> {
+        self.handler
+            .handle_batch(service_name, query, input)
+            .map_err(|e| EvaluationError::Service(Box::new(e)))
+    }
 }
 
 pub struct SimpleServiceHandler {
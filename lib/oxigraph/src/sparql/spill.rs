@@ -0,0 +1,492 @@
+//! Fallbacks writing intermediate results to temporary files when the query memory budget is exceeded.
+
+use crate::sparql::error::EvaluationError;
+use crate::sparql::eval::{EncodedTuple, EncodedTupleSet, EncodedTuplesIterator, EvaluationLimits};
+use crate::storage::binary_encoder::{write_term, TermReader};
+use crate::storage::numeric_encoder::EncodedTerm;
+use crate::storage::StorageError;
+#[cfg(not(target_family = "wasm"))]
+use rand::random;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+#[cfg(not(target_family = "wasm"))]
+use std::fs::{remove_file, File, OpenOptions};
+#[cfg(not(target_family = "wasm"))]
+use std::io::{BufReader, BufWriter, Seek, SeekFrom};
+use std::io::{Read, Write};
+use std::mem::size_of;
+#[cfg(not(target_family = "wasm"))]
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::{io, iter};
+
+/// Number of partitions used by the grace hash join
+const PARTITION_COUNT: usize = 32;
+/// Maximal number of sorted runs merged at the same time
+const MAX_MERGE_FAN_IN: usize = 64;
+
+const UNBOUND_SLOT: u8 = 0;
+const BOUND_SLOT: u8 = 1;
+const DEFAULT_GRAPH_SLOT: u8 = 2;
+const END_OF_TUPLE: u8 = 3;
+
+pub type TupleComparator = Rc<dyn Fn(&EncodedTuple, &EncodedTuple) -> Ordering>;
+
+/// Approximate size in memory of a tuple
+pub fn tuple_memory_size(tuple: &EncodedTuple) -> usize {
+    size_of::<EncodedTuple>() + tuple.capacity() * size_of::<Option<EncodedTerm>>()
+}
+
+/// Memory counted in the query memory budget, released when dropped
+pub struct MemoryReservation {
+    limits: Rc<EvaluationLimits>,
+    size: usize,
+}
+
+impl MemoryReservation {
+    pub fn new(limits: Rc<EvaluationLimits>) -> Self {
+        Self { limits, size: 0 }
+    }
+
+    /// Adds `size` bytes to the reservation and returns `false` if the budget is now exceeded
+    pub fn grow(&mut self, size: usize) -> bool {
+        self.size += size;
+        self.limits.reserve_memory(size)
+    }
+
+    pub fn clear(&mut self) {
+        self.limits.release_memory(self.size);
+        self.size = 0;
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.clear()
+    }
+}
+
+/// Writes tuples to a temporary file
+pub struct SpillWriter {
+    #[cfg(not(target_family = "wasm"))]
+    file: BufWriter<File>,
+    #[cfg(not(target_family = "wasm"))]
+    path: TemporaryPath,
+    #[cfg(target_family = "wasm")]
+    file: Vec<u8>,
+    buffer: Vec<u8>,
+}
+
+impl SpillWriter {
+    pub fn new() -> io::Result<Self> {
+        #[cfg(not(target_family = "wasm"))]
+        {
+            let path = TemporaryPath(
+                std::env::temp_dir().join(format!("oxigraph-spill-{:x}", random::<u128>())),
+            );
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path.0)?;
+            Ok(Self {
+                file: BufWriter::new(file),
+                path,
+                buffer: Vec::new(),
+            })
+        }
+        #[cfg(target_family = "wasm")]
+        {
+            Ok(Self {
+                file: Vec::new(),
+                buffer: Vec::new(),
+            })
+        }
+    }
+
+    pub fn write(&mut self, tuple: &EncodedTuple) -> io::Result<()> {
+        self.buffer.clear();
+        for value in tuple.iter() {
+            match value {
+                None => self.buffer.push(UNBOUND_SLOT),
+                Some(EncodedTerm::DefaultGraph) => self.buffer.push(DEFAULT_GRAPH_SLOT),
+                Some(term) => {
+                    self.buffer.push(BOUND_SLOT);
+                    write_term(&mut self.buffer, &term);
+                }
+            }
+        }
+        self.buffer.push(END_OF_TUPLE);
+        self.file.write_all(&self.buffer)
+    }
+
+    /// Returns a reader over the written tuples
+    pub fn finish(self) -> io::Result<SpillReader> {
+        #[cfg(not(target_family = "wasm"))]
+        {
+            let mut file = self
+                .file
+                .into_inner()
+                .map_err(io::IntoInnerError::into_error)?;
+            file.seek(SeekFrom::Start(0))?;
+            Ok(SpillReader {
+                file: BufReader::new(file),
+                _path: self.path,
+            })
+        }
+        #[cfg(target_family = "wasm")]
+        {
+            Ok(SpillReader {
+                file: io::Cursor::new(self.file),
+            })
+        }
+    }
+}
+
+/// Reads tuples written by a [`SpillWriter`]
+pub struct SpillReader {
+    #[cfg(not(target_family = "wasm"))]
+    file: BufReader<File>,
+    #[cfg(not(target_family = "wasm"))]
+    _path: TemporaryPath,
+    #[cfg(target_family = "wasm")]
+    file: io::Cursor<Vec<u8>>,
+}
+
+impl SpillReader {
+    fn read_tuple(&mut self) -> Result<Option<EncodedTuple>, StorageError> {
+        let mut tuple = EncodedTuple::with_capacity(0);
+        let mut position = 0;
+        loop {
+            let mut kind = [0];
+            if self.file.read(&mut kind)? == 0 {
+                if position == 0 {
+                    return Ok(None); // End of file
+                }
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            match kind[0] {
+                UNBOUND_SLOT => (),
+                BOUND_SLOT => tuple.set(position, self.file.read_term()?),
+                DEFAULT_GRAPH_SLOT => tuple.set(position, EncodedTerm::DefaultGraph),
+                END_OF_TUPLE => return Ok(Some(tuple)),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Invalid temporary tuple file",
+                    )
+                    .into())
+                }
+            }
+            position += 1;
+        }
+    }
+}
+
+impl Iterator for SpillReader {
+    type Item = Result<EncodedTuple, EvaluationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_tuple().map_err(Into::into).transpose()
+    }
+}
+
+/// A path in the temporary directory whose file is removed when dropped
+#[cfg(not(target_family = "wasm"))]
+struct TemporaryPath(PathBuf);
+
+#[cfg(not(target_family = "wasm"))]
+impl Drop for TemporaryPath {
+    fn drop(&mut self) {
+        drop(remove_file(&self.0));
+    }
+}
+
+/// Sorts the input in memory or, if the memory budget is exceeded, using an external merge sort.
+pub fn sort(
+    input: EncodedTuplesIterator,
+    comparator: TupleComparator,
+    limits: Option<&Rc<EvaluationLimits>>,
+) -> EncodedTuplesIterator {
+    let mut errors = Vec::new();
+    let mut values = Vec::new();
+    let mut runs = Vec::new();
+    let mut reservation = limits
+        .filter(|limits| limits.has_memory_limit())
+        .map(|limits| MemoryReservation::new(Rc::clone(limits)));
+    for result in input {
+        let tuple = match result {
+            Ok(tuple) => tuple,
+            Err(error) => {
+                errors.push(Err(error));
+                continue;
+            }
+        };
+        let size = tuple_memory_size(&tuple);
+        values.push(tuple);
+        if let Some(reservation) = &mut reservation {
+            if !reservation.grow(size) {
+                values.sort_unstable_by(|a, b| comparator(a, b));
+                match write_run(values.drain(..).map(Ok)) {
+                    Ok(run) => runs.push(run),
+                    Err(error) => {
+                        return Box::new(errors.into_iter().chain(iter::once(Err(error))))
+                    }
+                }
+                reservation.clear();
+            }
+        }
+    }
+    values.sort_unstable_by(|a, b| comparator(a, b));
+    if runs.is_empty() {
+        return Box::new(ReservedIterator::new(
+            errors.into_iter().chain(values.into_iter().map(Ok)),
+            reservation,
+        ));
+    }
+    while runs.len() >= MAX_MERGE_FAN_IN {
+        let merged = MergeIterator::new(
+            runs.drain(..MAX_MERGE_FAN_IN)
+                .map(|run| -> EncodedTuplesIterator { Box::new(run) })
+                .collect(),
+            Rc::clone(&comparator),
+        );
+        match write_run(merged) {
+            Ok(run) => runs.push(run),
+            Err(error) => return Box::new(errors.into_iter().chain(iter::once(Err(error)))),
+        }
+    }
+    let mut sources = runs
+        .into_iter()
+        .map(|run| -> EncodedTuplesIterator { Box::new(run) })
+        .collect::<Vec<_>>();
+    sources.push(Box::new(ReservedIterator::new(
+        values.into_iter().map(Ok),
+        reservation,
+    )));
+    Box::new(
+        errors
+            .into_iter()
+            .chain(MergeIterator::new(sources, comparator)),
+    )
+}
+
+fn write_run(
+    tuples: impl Iterator<Item = Result<EncodedTuple, EvaluationError>>,
+) -> Result<SpillReader, EvaluationError> {
+    let mut writer = SpillWriter::new().map_err(StorageError::from)?;
+    for tuple in tuples {
+        writer.write(&tuple?).map_err(StorageError::from)?;
+    }
+    Ok(writer.finish().map_err(StorageError::from)?)
+}
+
+/// Merges sorted iterators
+struct MergeIterator {
+    sources: Vec<EncodedTuplesIterator>,
+    heap: BinaryHeap<MergeEntry>,
+    comparator: TupleComparator,
+    errors: Vec<EvaluationError>,
+}
+
+impl MergeIterator {
+    fn new(mut sources: Vec<EncodedTuplesIterator>, comparator: TupleComparator) -> Self {
+        let mut heap = BinaryHeap::with_capacity(sources.len());
+        let mut errors = Vec::new();
+        for (source, iter) in sources.iter_mut().enumerate() {
+            match iter.next() {
+                Some(Ok(tuple)) => heap.push(MergeEntry {
+                    tuple,
+                    source,
+                    comparator: Rc::clone(&comparator),
+                }),
+                Some(Err(error)) => errors.push(error),
+                None => (),
+            }
+        }
+        Self {
+            sources,
+            heap,
+            comparator,
+            errors,
+        }
+    }
+}
+
+impl Iterator for MergeIterator {
+    type Item = Result<EncodedTuple, EvaluationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.errors.pop() {
+            return Some(Err(error));
+        }
+        let MergeEntry { tuple, source, .. } = self.heap.pop()?;
+        match self.sources[source].next() {
+            Some(Ok(next)) => self.heap.push(MergeEntry {
+                tuple: next,
+                source,
+                comparator: Rc::clone(&self.comparator),
+            }),
+            Some(Err(error)) => self.errors.push(error),
+            None => (),
+        }
+        Some(Ok(tuple))
+    }
+}
+
+struct MergeEntry {
+    tuple: EncodedTuple,
+    source: usize,
+    comparator: TupleComparator,
+}
+
+impl PartialEq for MergeEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MergeEntry {}
+
+impl PartialOrd for MergeEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max heap and we want the smallest tuple first
+        (self.comparator)(&other.tuple, &self.tuple).then(other.source.cmp(&self.source))
+    }
+}
+
+/// Builds a hash table from the build side and joins the probe side with it using `join`.
+///
+/// If the memory budget is exceeded, both sides are partitioned into temporary files by join key hash
+/// and the partitions are joined one after the other.
+pub fn hash_join(
+    mut build: EncodedTuplesIterator,
+    probe: impl FnOnce() -> EncodedTuplesIterator,
+    keys: Vec<usize>,
+    limits: Option<&Rc<EvaluationLimits>>,
+    join: Rc<dyn Fn(EncodedTupleSet, EncodedTuplesIterator) -> EncodedTuplesIterator>,
+) -> EncodedTuplesIterator {
+    let mut errors = Vec::new();
+    let mut built = EncodedTupleSet::new(keys.clone());
+    let mut reservation = limits
+        .filter(|limits| limits.has_memory_limit())
+        .map(|limits| MemoryReservation::new(Rc::clone(limits)));
+    let mut exceeded = false;
+    for result in build.by_ref() {
+        let tuple = match result {
+            Ok(tuple) => tuple,
+            Err(error) => {
+                errors.push(Err(error));
+                continue;
+            }
+        };
+        let size = tuple_memory_size(&tuple);
+        built.insert(tuple);
+        if let Some(reservation) = &mut reservation {
+            if !reservation.grow(size) {
+                exceeded = true;
+                break;
+            }
+        }
+    }
+    if !exceeded {
+        return Box::new(ReservedIterator::new(
+            errors.into_iter().chain(join(built, probe())),
+            reservation,
+        ));
+    }
+    let partitioner = EncodedTupleSet::new(keys);
+    let partitions = partition(built.into_tuples().map(Ok).chain(build), &partitioner).and_then(
+        |build_partitions| {
+            drop(reservation);
+            Ok(build_partitions
+                .into_iter()
+                .zip(partition(probe(), &partitioner)?)
+                .collect::<Vec<_>>())
+        },
+    );
+    match partitions {
+        Ok(partitions) => Box::new(errors.into_iter().chain(partitions.into_iter().flat_map(
+            move |((build, build_errors), (probe, probe_errors))| {
+                let mut errors = build_errors;
+                errors.extend(probe_errors);
+                let mut built = EncodedTupleSet::new(partitioner.keys().to_vec());
+                for tuple in build {
+                    match tuple {
+                        Ok(tuple) => built.insert(tuple),
+                        Err(error) => errors.push(error),
+                    }
+                }
+                errors
+                    .into_iter()
+                    .map(Err)
+                    .chain(join(built, Box::new(probe)))
+            },
+        ))),
+        Err(error) => Box::new(errors.into_iter().chain(iter::once(Err(error)))),
+    }
+}
+
+/// Writes the tuples into [`PARTITION_COUNT`] temporary files by join key hash.
+///
+/// The evaluation errors of the input are returned with the partitions to not be lost.
+fn partition(
+    input: impl Iterator<Item = Result<EncodedTuple, EvaluationError>>,
+    partitioner: &EncodedTupleSet,
+) -> Result<Vec<(SpillReader, Vec<EvaluationError>)>, EvaluationError> {
+    let mut writers = (0..PARTITION_COUNT)
+        .map(|_| SpillWriter::new())
+        .collect::<io::Result<Vec<_>>>()
+        .map_err(StorageError::from)?;
+    let mut errors = Vec::new();
+    for tuple in input {
+        match tuple {
+            Ok(tuple) => {
+                let partition = partitioner.partition(&tuple, PARTITION_COUNT);
+                writers[partition]
+                    .write(&tuple)
+                    .map_err(StorageError::from)?;
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+    let mut partitions = writers
+        .into_iter()
+        .map(|writer| Ok((writer.finish().map_err(StorageError::from)?, Vec::new())))
+        .collect::<Result<Vec<_>, EvaluationError>>()?;
+    partitions[0].1 = errors;
+    Ok(partitions)
+}
+
+/// Keeps a memory reservation alive as long as the iterator
+pub struct ReservedIterator<I> {
+    inner: I,
+    _reservation: Option<MemoryReservation>,
+}
+
+impl<I> ReservedIterator<I> {
+    pub fn new(inner: I, reservation: Option<MemoryReservation>) -> Self {
+        Self {
+            inner,
+            _reservation: reservation,
+        }
+    }
+}
+
+impl<I: Iterator> Iterator for ReservedIterator<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
@@ -2,7 +2,7 @@ use crate::io::{RdfFormat, RdfParser};
 use crate::model::{GraphName as OxGraphName, GraphNameRef, Quad as OxQuad};
 use crate::sparql::algebra::QueryDataset;
 use crate::sparql::dataset::DatasetView;
-use crate::sparql::eval::{EncodedTuple, SimpleEvaluator};
+use crate::sparql::eval::{EncodedTuple, EvaluationLimits, SimpleEvaluator};
 use crate::sparql::http::Client;
 use crate::sparql::{EvaluationError, Update, UpdateOptions};
 use crate::storage::numeric_encoder::{Decoder, EncodedTerm};
@@ -30,6 +30,7 @@ pub fn evaluate_update<'a, 'b: 'a>(
         transaction,
         base_iri: update.inner.base_iri.clone().map(Rc::new),
         options: options.clone(),
+        limits: options.query_options.evaluation_limits(),
         client: Client::new(
             options.query_options.http_timeout,
             options.query_options.http_redirection_limit,
@@ -42,6 +43,7 @@ struct SimpleUpdateEvaluator<'a, 'b> {
     transaction: &'a mut StorageWriter<'b>,
     base_iri: Option<Rc<Iri<String>>>,
     options: UpdateOptions,
+    limits: Option<Rc<EvaluationLimits>>,
     client: Client,
 }
 
@@ -124,20 +126,31 @@ impl<'a, 'b: 'a> SimpleUpdateEvaluator<'a, 'b> {
         let dataset = Rc::new(DatasetView::new(self.transaction.reader(), using));
         let mut pattern = sparopt::algebra::GraphPattern::from(algebra);
         if !self.options.query_options.without_optimizations {
-            pattern = Optimizer::optimize_graph_pattern(sparopt::algebra::GraphPattern::Reduced {
-                inner: Box::new(pattern),
-            });
+            pattern = Optimizer::optimize_graph_pattern_with_statistics(
+                sparopt::algebra::GraphPattern::Reduced {
+                    inner: Box::new(pattern),
+                },
+                dataset.as_ref(),
+            );
         }
         let evaluator = SimpleEvaluator::new(
             Rc::clone(&dataset),
             self.base_iri.clone(),
             self.options.query_options.service_handler(),
             Arc::new(self.options.query_options.custom_functions.clone()),
+            Arc::new(
+                self.options
+                    .query_options
+                    .custom_aggregate_functions
+                    .clone(),
+            ),
+            self.options.query_options.service_batch_size(),
+            self.limits.clone(),
             false,
         );
         let mut variables = Vec::new();
         let mut bnodes = HashMap::new();
-        let (eval, _) = evaluator.graph_pattern_evaluator(&pattern, &mut variables);
+        let (eval, _) = evaluator.root_graph_pattern_evaluator(&pattern, &mut variables);
         let tuples =
             eval(EncodedTuple::with_capacity(variables.len())).collect::<Result<Vec<_>, _>>()?; // TODO: would be much better to stream
         for tuple in tuples {
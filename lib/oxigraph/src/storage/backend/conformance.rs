@@ -0,0 +1,339 @@
+//! Checks that a [`StorageBackend`] behaves as the store expects.
+
+use crate::storage::backend::{StorageBackend, StorageBackendIter};
+use crate::storage::StorageError;
+use std::fmt::Debug;
+
+type Entries = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Checks that a [`StorageBackend`] implementation behaves as a [`Store`](crate::store::Store) expects.
+///
+/// `new_backend` must return a new empty backend on each call.
+/// Column families, ordered and bounded scans, transactions, snapshot isolation, ingestion and range removals are checked.
+/// The first unexpected behavior is returned as an error.
+///
+/// See [`StorageBackend`] for a usage example.
+pub fn check_storage_backend<B: StorageBackend>(
+    mut new_backend: impl FnMut() -> Result<B, StorageError>,
+) -> Result<(), StorageError> {
+    check_column_families(&new_backend()?)?;
+    check_scans(&new_backend()?)?;
+    check_transactions(&new_backend()?)?;
+    check_snapshots(&new_backend()?)?;
+    check_ingestion(&new_backend()?)?;
+    check_range_removals(&new_backend()?)
+}
+
+fn check_column_families(backend: &impl StorageBackend) -> Result<(), StorageError> {
+    backend.create_column_family("a")?;
+    backend.create_column_family("b")?;
+    backend.create_column_family("a")?;
+    let snapshot = backend.snapshot();
+    ensure_eq(
+        &snapshot.get("a", b"k")?,
+        &None,
+        "get in a new column family",
+    )?;
+    ensure_eq(
+        &collect(snapshot.scan("a", &[], None)?)?,
+        &Vec::new(),
+        "scan of a new column family",
+    )?;
+    let mut transaction = backend.transaction()?;
+    transaction.insert("a", b"k", b"1")?;
+    transaction.insert("b", b"k", b"2")?;
+    transaction.commit()?;
+    let snapshot = backend.snapshot();
+    ensure_eq(
+        &snapshot.get("a", b"k")?,
+        &Some(b"1".to_vec()),
+        "get in the first column family",
+    )?;
+    ensure_eq(
+        &snapshot.get("b", b"k")?,
+        &Some(b"2".to_vec()),
+        "get in the second column family",
+    )?;
+    ensure_eq(
+        &collect(snapshot.scan("a", &[], None)?)?,
+        &vec![(b"k".to_vec(), b"1".to_vec())],
+        "scan of a column family sharing its keys with an other one",
+    )
+}
+
+fn check_scans(backend: &impl StorageBackend) -> Result<(), StorageError> {
+    let keys: [&[u8]; 10] = [
+        &[],
+        &[0],
+        &[0, 0],
+        &[0, 1],
+        &[1],
+        &[1, 0xFF],
+        &[1, 0xFF, 0xFF],
+        &[2],
+        &[0xFF],
+        &[0xFF, 0xFF],
+    ];
+    let entries = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key.to_vec(), vec![0; i]))
+        .collect::<Entries>();
+    let entries_between = |start: &[u8], end: Option<&[u8]>| {
+        entries
+            .iter()
+            .filter(|(k, _)| k.as_slice() >= start && end.map_or(true, |end| k.as_slice() < end))
+            .cloned()
+            .collect::<Entries>()
+    };
+    let ranges: [(&[u8], Option<&[u8]>); 7] = [
+        (&[], None),
+        (&[1], None),
+        (&[0, 0xFF], None),
+        (&[0, 1], Some(&[1, 0xFF])),
+        (&[1, 0xFF], Some(&[2])),
+        (&[0xFF], Some(&[0xFF, 0xFF, 0])),
+        (&[2], Some(&[2])),
+    ];
+    backend.create_column_family("s")?;
+
+    // We write half of the entries in a transaction and keep the other half uncommitted
+    let mut transaction = backend.transaction()?;
+    for (key, value) in entries.iter().rev().step_by(2) {
+        transaction.insert("s", key, value)?;
+    }
+    transaction.commit()?;
+    let mut transaction = backend.transaction()?;
+    for (key, value) in entries.iter().rev().skip(1).step_by(2) {
+        transaction.insert("s", key, value)?;
+    }
+    for (start, end) in ranges {
+        ensure_eq(
+            &collect(transaction.scan("s", start, end)?)?,
+            &entries_between(start, end),
+            &format!("transaction scan from {start:?} to {end:?}"),
+        )?;
+    }
+    transaction.commit()?;
+
+    let snapshot = backend.snapshot();
+    for (key, value) in &entries {
+        ensure_eq(
+            &snapshot.get("s", key)?,
+            &Some(value.clone()),
+            &format!("get of {key:?}"),
+        )?;
+    }
+    ensure_eq(&snapshot.get("s", &[3])?, &None, "get of a missing key")?;
+    for (start, end) in ranges {
+        ensure_eq(
+            &collect(snapshot.scan("s", start, end)?)?,
+            &entries_between(start, end),
+            &format!("snapshot scan from {start:?} to {end:?}"),
+        )?;
+    }
+    Ok(())
+}
+
+fn check_transactions(backend: &impl StorageBackend) -> Result<(), StorageError> {
+    backend.create_column_family("t")?;
+
+    let mut transaction = backend.transaction()?;
+    transaction.insert("t", b"k1", b"v1")?;
+    transaction.insert("t", b"k2", b"v1")?;
+    transaction.insert("t", b"k2", b"v2")?;
+    ensure_eq(
+        &transaction.get("t", b"k2")?,
+        &Some(b"v2".to_vec()),
+        "get of a value written by the transaction",
+    )?;
+    ensure_eq(
+        &backend.snapshot().get("t", b"k1")?,
+        &None,
+        "get of a value written by a running transaction",
+    )?;
+    transaction.commit()?;
+    ensure_eq(
+        &collect(backend.snapshot().scan("t", &[], None)?)?,
+        &vec![
+            (b"k1".to_vec(), b"v1".to_vec()),
+            (b"k2".to_vec(), b"v2".to_vec()),
+        ],
+        "scan after commit",
+    )?;
+
+    let mut transaction = backend.transaction()?;
+    transaction.insert("t", b"k3", b"v3")?;
+    transaction.remove("t", b"k1")?;
+    drop(transaction);
+    ensure_eq(
+        &collect(backend.snapshot().scan("t", &[], None)?)?,
+        &vec![
+            (b"k1".to_vec(), b"v1".to_vec()),
+            (b"k2".to_vec(), b"v2".to_vec()),
+        ],
+        "scan after a transaction dropped without commit",
+    )?;
+
+    let mut transaction = backend.transaction()?;
+    transaction.remove("t", b"k1")?;
+    transaction.remove("t", b"k4")?;
+    ensure_eq(
+        &transaction.get("t", b"k1")?,
+        &None,
+        "get of a value removed by the transaction",
+    )?;
+    ensure_eq(
+        &collect(transaction.scan("t", &[], None)?)?,
+        &vec![(b"k2".to_vec(), b"v2".to_vec())],
+        "scan in a transaction after a removal",
+    )?;
+    transaction.commit()?;
+    ensure_eq(
+        &collect(backend.snapshot().scan("t", &[], None)?)?,
+        &vec![(b"k2".to_vec(), b"v2".to_vec())],
+        "scan after a committed removal",
+    )
+}
+
+fn check_snapshots(backend: &impl StorageBackend) -> Result<(), StorageError> {
+    backend.create_column_family("i")?;
+    let mut transaction = backend.transaction()?;
+    transaction.insert("i", b"k1", b"v1")?;
+    transaction.commit()?;
+
+    let snapshot = backend.snapshot();
+    let iter = snapshot.scan("i", &[], None)?;
+    let mut transaction = backend.transaction()?;
+    transaction.insert("i", b"k1", b"v2")?;
+    transaction.insert("i", b"k2", b"v2")?;
+    transaction.commit()?;
+    ensure_eq(
+        &snapshot.get("i", b"k1")?,
+        &Some(b"v1".to_vec()),
+        "get from a snapshot taken before an update",
+    )?;
+    ensure_eq(
+        &snapshot.get("i", b"k2")?,
+        &None,
+        "get from a snapshot taken before an insertion",
+    )?;
+    ensure_eq(
+        &collect(iter)?,
+        &vec![(b"k1".to_vec(), b"v1".to_vec())],
+        "iterator built before an insertion",
+    )?;
+
+    let mut transaction = backend.transaction()?;
+    transaction.remove("i", b"k1")?;
+    transaction.commit()?;
+    ensure_eq(
+        &collect(snapshot.scan("i", &[], None)?)?,
+        &vec![(b"k1".to_vec(), b"v1".to_vec())],
+        "scan of a snapshot taken before a removal",
+    )?;
+    ensure_eq(
+        &collect(backend.snapshot().scan("i", &[], None)?)?,
+        &vec![(b"k2".to_vec(), b"v2".to_vec())],
+        "scan of a new snapshot",
+    )
+}
+
+fn check_ingestion(backend: &impl StorageBackend) -> Result<(), StorageError> {
+    backend.create_column_family("a")?;
+    backend.create_column_family("b")?;
+    let mut transaction = backend.transaction()?;
+    transaction.insert("a", b"k1", b"v0")?;
+    transaction.commit()?;
+
+    let snapshot = backend.snapshot();
+    backend.ingest(vec![
+        (
+            "a",
+            vec![
+                (b"k1".to_vec(), b"v1".to_vec()),
+                (b"k2".to_vec(), b"v2".to_vec()),
+            ],
+        ),
+        ("b", vec![(b"k3".to_vec(), Vec::new())]),
+    ])?;
+    ensure_eq(
+        &collect(snapshot.scan("a", &[], None)?)?,
+        &vec![(b"k1".to_vec(), b"v0".to_vec())],
+        "scan of a snapshot taken before ingestion",
+    )?;
+    let snapshot = backend.snapshot();
+    ensure_eq(
+        &collect(snapshot.scan("a", &[], None)?)?,
+        &vec![
+            (b"k1".to_vec(), b"v1".to_vec()),
+            (b"k2".to_vec(), b"v2".to_vec()),
+        ],
+        "scan after ingestion",
+    )?;
+    ensure_eq(
+        &snapshot.get("b", b"k3")?,
+        &Some(Vec::new()),
+        "get of an ingested empty value",
+    )
+}
+
+fn check_range_removals(backend: &impl StorageBackend) -> Result<(), StorageError> {
+    backend.create_column_family("a")?;
+    backend.create_column_family("b")?;
+    let mut transaction = backend.transaction()?;
+    for key in [&[0][..], &[1], &[1, 0], &[1, 0xFF], &[2], &[2, 0]] {
+        transaction.insert("a", key, b"v")?;
+        transaction.insert("b", key, b"v")?;
+    }
+    transaction.commit()?;
+
+    let snapshot = backend.snapshot();
+    backend.remove_ranges(&[("a", &[1], &[2]), ("b", &[2], &[2, 0])])?;
+    ensure_eq(
+        &collect(snapshot.scan("a", &[], None)?)?.len(),
+        &6,
+        "scan of a snapshot taken before a range removal",
+    )?;
+    let snapshot = backend.snapshot();
+    ensure_eq(
+        &collect(snapshot.scan("a", &[], None)?)?,
+        &vec![
+            (vec![0], b"v".to_vec()),
+            (vec![2], b"v".to_vec()),
+            (vec![2, 0], b"v".to_vec()),
+        ],
+        "scan after a range removal",
+    )?;
+    ensure_eq(
+        &collect(snapshot.scan("b", &[], None)?)?.len(),
+        &5,
+        "scan after a range removal of a single key",
+    )?;
+    ensure_eq(
+        &snapshot.get("b", &[2, 0])?,
+        &Some(b"v".to_vec()),
+        "get of the end key of a range removal",
+    )
+}
+
+fn collect(iter: StorageBackendIter) -> Result<Entries, StorageError> {
+    iter.collect()
+}
+
+fn ensure_eq<T: Debug + PartialEq>(
+    actual: &T,
+    expected: &T,
+    operation: &str,
+) -> Result<(), StorageError> {
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(StorageError::Other(
+            format!(
+                "Unexpected storage backend behavior on {operation}: {actual:?} returned instead of {expected:?}"
+            )
+            .into(),
+        ))
+    }
+}
@@ -0,0 +1,526 @@
+//! Storage on top of a user provided key-value store.
+
+use crate::storage::backend::{prefix_upper_bound, ColumnFamilyDefinition};
+use crate::storage::StorageError;
+use std::cell::RefCell;
+use std::error::Error;
+use std::iter::empty;
+use std::marker::PhantomData;
+use std::mem::transmute;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+
+/// An iterator on key-value pairs returned by [`StorageBackendSnapshot::scan`] and [`StorageBackendTransaction::scan`].
+pub type StorageBackendIter =
+    Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>), StorageError>> + Send>;
+
+/// A key-value store a [`Store`](crate::store::Store) might be built on.
+///
+/// The content is split into column families identified by their names.
+/// In each column family, the keys are sorted in the lexicographic order of their bytes.
+///
+/// The store only writes using transactions and [`ingest`](Self::ingest).
+/// Write transactions must be isolated from each other, for example by allowing only one of them at a time.
+///
+/// [`check_storage_backend`](crate::store::check_storage_backend) checks that an implementation behaves as the store expects.
+///
+/// An implementation keeping everything in memory:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::store::{
+///     check_storage_backend, StorageBackend, StorageBackendIter, StorageBackendSnapshot,
+///     StorageBackendTransaction, StorageError, Store,
+/// };
+/// use std::collections::BTreeMap;
+/// use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+///
+/// type Tables = BTreeMap<String, BTreeMap<Vec<u8>, Vec<u8>>>;
+///
+/// /// Copies the whole content on each transaction
+/// #[derive(Default)]
+/// struct MemoryBackend {
+///     tables: RwLock<Arc<Tables>>,
+///     writer: Mutex<()>,
+/// }
+///
+/// struct MemorySnapshot(Arc<Tables>);
+///
+/// struct MemoryTransaction<'a> {
+///     backend: &'a MemoryBackend,
+///     tables: Tables,
+///     _writer: MutexGuard<'a, ()>,
+/// }
+///
+/// fn get(tables: &Tables, column_family: &str, key: &[u8]) -> Option<Vec<u8>> {
+///     tables.get(column_family)?.get(key).cloned()
+/// }
+///
+/// fn scan(tables: &Tables, column_family: &str, start: &[u8], end: Option<&[u8]>) -> StorageBackendIter {
+///     let entries = tables
+///         .get(column_family)
+///         .into_iter()
+///         .flat_map(|table| table.range(start.to_vec()..))
+///         .take_while(|(k, _)| end.map_or(true, |end| k.as_slice() < end))
+///         .map(|(k, v)| Ok((k.clone(), v.clone())))
+///         .collect::<Vec<_>>();
+///     Box::new(entries.into_iter())
+/// }
+///
+/// impl StorageBackend for MemoryBackend {
+///     fn create_column_family(&self, name: &str) -> Result<(), StorageError> {
+///         let mut tables = self.tables.write().unwrap();
+///         Arc::make_mut(&mut tables).entry(name.into()).or_default();
+///         Ok(())
+///     }
+///
+///     fn snapshot(&self) -> Box<dyn StorageBackendSnapshot> {
+///         Box::new(MemorySnapshot(Arc::clone(&self.tables.read().unwrap())))
+///     }
+///
+///     fn transaction(&self) -> Result<Box<dyn StorageBackendTransaction + '_>, StorageError> {
+///         let writer = self.writer.lock().unwrap();
+///         Ok(Box::new(MemoryTransaction {
+///             backend: self,
+///             tables: Tables::clone(&self.tables.read().unwrap()),
+///             _writer: writer,
+///         }))
+///     }
+/// }
+///
+/// impl StorageBackendSnapshot for MemorySnapshot {
+///     fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+///         Ok(get(&self.0, column_family, key))
+///     }
+///
+///     fn scan(&self, column_family: &str, start: &[u8], end: Option<&[u8]>) -> Result<StorageBackendIter, StorageError> {
+///         Ok(scan(&self.0, column_family, start, end))
+///     }
+/// }
+///
+/// impl StorageBackendTransaction for MemoryTransaction<'_> {
+///     fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+///         Ok(get(&self.tables, column_family, key))
+///     }
+///
+///     fn scan(&self, column_family: &str, start: &[u8], end: Option<&[u8]>) -> Result<StorageBackendIter, StorageError> {
+///         Ok(scan(&self.tables, column_family, start, end))
+///     }
+///
+///     fn insert(&mut self, column_family: &str, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+///         self.tables.entry(column_family.into()).or_default().insert(key.into(), value.into());
+///         Ok(())
+///     }
+///
+///     fn remove(&mut self, column_family: &str, key: &[u8]) -> Result<(), StorageError> {
+///         if let Some(table) = self.tables.get_mut(column_family) {
+///             table.remove(key);
+///         }
+///         Ok(())
+///     }
+///
+///     fn commit(self: Box<Self>) -> Result<(), StorageError> {
+///         let this = *self;
+///         *this.backend.tables.write().unwrap() = Arc::new(this.tables);
+///         Ok(())
+///     }
+/// }
+///
+/// check_storage_backend(|| Ok(MemoryBackend::default()))?;
+///
+/// let store = Store::open_with_backend(MemoryBackend::default())?;
+/// let ex = NamedNodeRef::new("http://example.com")?;
+/// store.insert(QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph))?;
+/// assert_eq!(store.len()?, 1);
+/// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+/// ```
+pub trait StorageBackend: Send + Sync + 'static {
+    /// Creates the column family if it does not exist yet.
+    ///
+    /// It is called for each column family used by the store when the store is opened.
+    fn create_column_family(&self, name: &str) -> Result<(), StorageError>;
+
+    /// Returns a view of the current content that is not affected by the transactions committed later.
+    ///
+    /// The errors should be returned when reading from the snapshot.
+    fn snapshot(&self) -> Box<dyn StorageBackendSnapshot>;
+
+    /// Starts a write transaction.
+    ///
+    /// Its writes must only become visible to the other transactions and snapshots after [`StorageBackendTransaction::commit`] succeeds.
+    fn transaction(&self) -> Result<Box<dyn StorageBackendTransaction + '_>, StorageError>;
+
+    /// Atomically inserts the given entries, replacing the existing values.
+    ///
+    /// The entries of each column family are sorted by key and their keys are unique.
+    /// It is used by the bulk loader in place of RocksDB SST files ingestion.
+    /// By default, all the entries are inserted in a single transaction.
+    fn ingest(
+        &self,
+        column_families: Vec<(&str, Vec<(Vec<u8>, Vec<u8>)>)>,
+    ) -> Result<(), StorageError> {
+        let mut transaction = self.transaction()?;
+        for (column_family, entries) in column_families {
+            for (key, value) in entries {
+                transaction.insert(column_family, &key, &value)?;
+            }
+        }
+        transaction.commit()
+    }
+
+    /// Atomically removes the entries with a key greater or equal to `start` and lower than `end` for each of the given ranges.
+    ///
+    /// It is used to remove whole graphs.
+    /// By default, the entries are scanned and removed in a single transaction.
+    fn remove_ranges(&self, ranges: &[(&str, &[u8], &[u8])]) -> Result<(), StorageError> {
+        let mut transaction = self.transaction()?;
+        for (column_family, start, end) in ranges {
+            let keys = transaction
+                .scan(column_family, start, Some(end))?
+                .map(|entry| entry.map(|(key, _)| key))
+                .collect::<Result<Vec<_>, _>>()?;
+            for key in keys {
+                transaction.remove(column_family, &key)?;
+            }
+        }
+        transaction.commit()
+    }
+
+    /// Makes sure the committed transactions are persisted.
+    ///
+    /// Does nothing by default.
+    fn flush(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Optimizes the storage of the column family.
+    ///
+    /// Does nothing by default.
+    fn compact(&self, _column_family: &str) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+/// A stable view of the content of a [`StorageBackend`].
+///
+/// It might be kept around and shared between threads.
+pub trait StorageBackendSnapshot: Send + Sync {
+    /// Returns the value associated with the key, if any.
+    fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Iterates in key order on the entries with a key greater or equal to `start` and lower than `end` if set.
+    fn scan(
+        &self,
+        column_family: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<StorageBackendIter, StorageError>;
+}
+
+/// A write transaction on a [`StorageBackend`].
+///
+/// Its reads must see its own writes.
+/// Dropping it without calling [`commit`](Self::commit) must discard its writes.
+pub trait StorageBackendTransaction {
+    /// Returns the value associated with the key, if any.
+    fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Iterates in key order on the entries with a key greater or equal to `start` and lower than `end` if set.
+    ///
+    /// The writes done by the transaction after this call do not have to be visible from the returned iterator.
+    fn scan(
+        &self,
+        column_family: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<StorageBackendIter, StorageError>;
+
+    /// Sets the value associated with the key.
+    fn insert(&mut self, column_family: &str, key: &[u8], value: &[u8])
+        -> Result<(), StorageError>;
+
+    /// Removes the key if it exists.
+    fn remove(&mut self, column_family: &str, key: &[u8]) -> Result<(), StorageError>;
+
+    /// Atomically applies the writes of the transaction.
+    fn commit(self: Box<Self>) -> Result<(), StorageError>;
+}
+
+#[derive(Clone)]
+pub struct Db {
+    backend: Arc<dyn StorageBackend>,
+    is_writable: bool,
+}
+
+impl Db {
+    pub fn new(
+        backend: Arc<dyn StorageBackend>,
+        column_families: Vec<ColumnFamilyDefinition>,
+        is_writable: bool,
+    ) -> Result<Self, StorageError> {
+        backend.create_column_family("default")?;
+        for column_family in column_families {
+            backend.create_column_family(column_family.name)?;
+        }
+        Ok(Self {
+            backend,
+            is_writable,
+        })
+    }
+
+    /// Returns if the backend accepts writes
+    #[cfg(not(target_family = "wasm"))]
+    pub fn is_writable(&self) -> bool {
+        self.is_writable
+    }
+
+    pub fn snapshot(&self) -> Reader {
+        self.shared_snapshot().reader()
+    }
+
+    pub fn shared_snapshot(&self) -> SharedSnapshot {
+        SharedSnapshot(self.backend.snapshot().into())
+    }
+
+    #[allow(unsafe_code)]
+    pub fn transaction<'a, 'b: 'a, T, E: Error + 'static + From<StorageError>>(
+        &'b self,
+        f: impl Fn(Transaction<'a>) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let transaction = self.backend.transaction()?;
+        // SAFETY: the readers only keep weak references to the transaction that can't be upgraded once the `Transaction` objects, bound to 'a, are dropped.
+        // So, the transaction is never used after the end of the borrow of the backend.
+        let transaction = unsafe {
+            transmute::<
+                Box<dyn StorageBackendTransaction + 'b>,
+                Box<dyn StorageBackendTransaction + 'static>,
+            >(transaction)
+        };
+        let state = Rc::new(RefCell::new(transaction));
+        let result = f(Transaction {
+            state: Rc::clone(&state),
+            _lifetime: PhantomData,
+        })?;
+        Rc::try_unwrap(state)
+            .map_err(|_| StorageError::Other("The transaction is still in use".into()))?
+            .into_inner()
+            .commit()?;
+        Ok(result)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn insert(
+        &self,
+        column_family: &str,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        let mut transaction = self.backend.transaction()?;
+        transaction.insert(column_family, key, value)?;
+        transaction.commit()
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn ingest(
+        &self,
+        column_families: Vec<(&str, Vec<(Vec<u8>, Vec<u8>)>)>,
+    ) -> Result<(), StorageError> {
+        self.backend.ingest(column_families)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn remove_ranges(&self, ranges: &[(&str, &[u8], &[u8])]) -> Result<(), StorageError> {
+        self.backend.remove_ranges(ranges)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn flush(&self) -> Result<(), StorageError> {
+        self.backend.flush()
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn compact(&self, column_family: &str) -> Result<(), StorageError> {
+        self.backend.compact(column_family)
+    }
+}
+
+/// A snapshot that might be kept around and shared between threads
+#[derive(Clone)]
+pub struct SharedSnapshot(Arc<dyn StorageBackendSnapshot>);
+
+impl SharedSnapshot {
+    pub fn reader(&self) -> Reader {
+        Reader(InnerReader::Snapshot(Arc::clone(&self.0)))
+    }
+}
+
+#[derive(Clone)]
+pub struct Reader(InnerReader);
+
+#[derive(Clone)]
+enum InnerReader {
+    Snapshot(Arc<dyn StorageBackendSnapshot>),
+    Transaction(Weak<RefCell<Box<dyn StorageBackendTransaction>>>),
+}
+
+impl Reader {
+    pub fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        match &self.0 {
+            InnerReader::Snapshot(snapshot) => snapshot.get(column_family, key),
+            InnerReader::Transaction(transaction) => transaction
+                .upgrade()
+                .ok_or_else(transaction_ended_error)?
+                .borrow()
+                .get(column_family, key),
+        }
+    }
+
+    pub fn contains_key(&self, column_family: &str, key: &[u8]) -> Result<bool, StorageError> {
+        Ok(self.get(column_family, key)?.is_some())
+    }
+
+    #[allow(clippy::iter_not_returning_iterator)]
+    pub fn iter(&self, column_family: &str) -> Result<Iter, StorageError> {
+        self.scan(column_family, &[], None)
+    }
+
+    pub fn scan_prefix(&self, column_family: &str, prefix: &[u8]) -> Result<Iter, StorageError> {
+        self.scan(column_family, prefix, prefix_upper_bound(prefix).as_deref())
+    }
+
+    pub fn scan_from(&self, column_family: &str, start: &[u8]) -> Result<Iter, StorageError> {
+        self.scan(column_family, start, None)
+    }
+
+    pub fn scan_range(
+        &self,
+        column_family: &str,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Iter, StorageError> {
+        self.scan(column_family, start, Some(end))
+    }
+
+    fn scan(
+        &self,
+        column_family: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<Iter, StorageError> {
+        let iter = match &self.0 {
+            InnerReader::Snapshot(snapshot) => snapshot.scan(column_family, start, end)?,
+            InnerReader::Transaction(transaction) => transaction
+                .upgrade()
+                .ok_or_else(transaction_ended_error)?
+                .borrow()
+                .scan(column_family, start, end)?,
+        };
+        Ok(Iter::new(iter))
+    }
+
+    pub fn len(&self, column_family: &str) -> Result<usize, StorageError> {
+        let mut count = 0;
+        let mut iter = self.iter(column_family)?;
+        while iter.key().is_some() {
+            count += 1;
+            iter.next();
+        }
+        iter.status()?;
+        Ok(count)
+    }
+
+    pub fn is_empty(&self, column_family: &str) -> Result<bool, StorageError> {
+        let mut iter = self.iter(column_family)?;
+        let is_empty = iter.key().is_none();
+        iter.status()?;
+        Ok(is_empty)
+    }
+}
+
+fn transaction_ended_error() -> StorageError {
+    StorageError::Other("The transaction is already ended".into())
+}
+
+pub struct Transaction<'a> {
+    state: Rc<RefCell<Box<dyn StorageBackendTransaction>>>,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl Transaction<'_> {
+    pub fn reader(&self) -> Reader {
+        Reader(InnerReader::Transaction(Rc::downgrade(&self.state)))
+    }
+
+    pub fn get_for_update(
+        &self,
+        column_family: &str,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, StorageError> {
+        self.state.borrow().get(column_family, key)
+    }
+
+    pub fn contains_key_for_update(
+        &self,
+        column_family: &str,
+        key: &[u8],
+    ) -> Result<bool, StorageError> {
+        Ok(self.get_for_update(column_family, key)?.is_some())
+    }
+
+    pub fn insert(
+        &mut self,
+        column_family: &str,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        self.state.borrow_mut().insert(column_family, key, value)
+    }
+
+    pub fn remove(&mut self, column_family: &str, key: &[u8]) -> Result<(), StorageError> {
+        self.state.borrow_mut().remove(column_family, key)
+    }
+}
+
+pub struct Iter {
+    iter: StorageBackendIter,
+    current: Option<(Vec<u8>, Vec<u8>)>,
+    error: Option<StorageError>,
+}
+
+impl Iter {
+    fn new(iter: StorageBackendIter) -> Self {
+        let mut iter = Self {
+            iter,
+            current: None,
+            error: None,
+        };
+        iter.next();
+        iter
+    }
+
+    pub fn key(&self) -> Option<&[u8]> {
+        Some(&self.current.as_ref()?.0)
+    }
+
+    pub fn value(&self) -> Option<&[u8]> {
+        Some(&self.current.as_ref()?.1)
+    }
+
+    pub fn next(&mut self) {
+        self.current = match self.iter.next() {
+            Some(Ok(entry)) => Some(entry),
+            Some(Err(error)) => {
+                self.error = Some(error);
+                self.iter = Box::new(empty());
+                None
+            }
+            None => None,
+        };
+    }
+
+    /// Returns the error that stopped the iteration, if any
+    ///
+    /// The error is only returned once.
+    pub fn status(&mut self) -> Result<(), StorageError> {
+        self.error.take().map_or(Ok(()), Err)
+    }
+}
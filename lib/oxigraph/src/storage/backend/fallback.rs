@@ -1,13 +1,19 @@
-//! TODO: This storage is dramatically naive.
+//! In-memory storage with multi-version concurrency control.
+//!
+//! Each key keeps the list of its values tagged by the version that wrote them.
+//! Readers get a stable view of the database at a given version and are never blocked by writers for longer than the time to apply a commit.
+//! Write transactions are serialized and buffer their writes until they commit.
+//! Old versions are removed as soon as no snapshot can see them anymore.
 
+use crate::storage::backend::prefix_upper_bound;
 use crate::storage::StorageError;
 use crate::store::CorruptionError;
 use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::error::Error;
-use std::mem::transmute;
+use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
-use std::sync::{Arc, RwLock, RwLockWriteGuard};
+use std::sync::{Arc, Mutex, RwLock};
 
 pub struct ColumnFamilyDefinition {
     pub name: &'static str,
@@ -16,8 +22,26 @@ pub struct ColumnFamilyDefinition {
     pub unordered_writes: bool,
 }
 
+/// The values of a key, sorted by increasing version. `None` is a deletion.
+type Versions = Vec<(u64, Option<Vec<u8>>)>;
+
 #[derive(Clone)]
-pub struct Db(Arc<RwLock<HashMap<ColumnFamily, BTreeMap<Vec<u8>, Vec<u8>>>>>);
+pub struct Db(Arc<DbInner>);
+
+struct DbInner {
+    state: RwLock<DbState>,
+    /// Serializes write transactions.
+    writer: Mutex<()>,
+    /// The number of live snapshots for each version.
+    snapshots: Mutex<BTreeMap<u64, usize>>,
+}
+
+struct DbState {
+    version: u64,
+    trees: HashMap<ColumnFamily, BTreeMap<Vec<u8>, Versions>>,
+    /// The keys with versions to prune once there is no snapshot older than the given version anymore.
+    garbage: VecDeque<(u64, ColumnFamily, Vec<u8>)>,
+}
 
 impl Db {
     #[allow(clippy::unnecessary_wraps)]
@@ -27,13 +51,28 @@ impl Db {
             trees.insert(ColumnFamily(cf.name), BTreeMap::default());
         }
         trees.entry(ColumnFamily("default")).or_default(); // We make sure that "default" key exists.
-        Ok(Self(Arc::new(RwLock::new(trees))))
+        Ok(Self(Arc::new(DbInner {
+            state: RwLock::new(DbState {
+                version: 0,
+                trees,
+                garbage: VecDeque::new(),
+            }),
+            writer: Mutex::new(()),
+            snapshots: Mutex::new(BTreeMap::new()),
+        })))
     }
 
     #[allow(clippy::unwrap_in_result)]
     pub fn column_family(&self, name: &'static str) -> Result<ColumnFamily, StorageError> {
         let column_family = ColumnFamily(name);
-        if self.0.read().unwrap().contains_key(&column_family) {
+        if self
+            .0
+            .state
+            .read()
+            .unwrap()
+            .trees
+            .contains_key(&column_family)
+        {
             Ok(column_family)
         } else {
             Err(CorruptionError::from_missing_column_family_name(name).into())
@@ -42,7 +81,29 @@ impl Db {
 
     #[must_use]
     pub fn snapshot(&self) -> Reader {
-        Reader(InnerReader::Simple(Arc::clone(&self.0)))
+        self.new_shared_snapshot().reader()
+    }
+
+    /// Builds a snapshot that might be kept around and shared between threads
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn shared_snapshot(&self) -> Result<SharedSnapshot, StorageError> {
+        Ok(self.new_shared_snapshot())
+    }
+
+    fn new_shared_snapshot(&self) -> SharedSnapshot {
+        let state = self.0.state.read().unwrap();
+        // We register the snapshot while holding the state lock so that no commit could remove the versions it sees
+        *self
+            .0
+            .snapshots
+            .lock()
+            .unwrap()
+            .entry(state.version)
+            .or_default() += 1;
+        SharedSnapshot(Arc::new(Snapshot {
+            db: Arc::clone(&self.0),
+            version: state.version,
+        }))
     }
 
     #[allow(clippy::unwrap_in_result)]
@@ -50,20 +111,218 @@ impl Db {
         &'b self,
         f: impl Fn(Transaction<'a>) -> Result<T, E>,
     ) -> Result<T, E> {
-        f(Transaction(Rc::new(RefCell::new(self.0.write().unwrap()))))
+        let _writer = self.0.writer.lock().unwrap();
+        let state = Rc::new(RefCell::new(TransactionState {
+            db: Arc::clone(&self.0),
+            version: self.0.state.read().unwrap().version,
+            writes: HashMap::new(),
+        }));
+        let result = f(Transaction {
+            state: Rc::clone(&state),
+            _lifetime: PhantomData,
+        })?;
+        let writes = std::mem::take(&mut state.borrow_mut().writes);
+        self.0.commit(writes);
+        Ok(result)
+    }
+}
+
+impl DbInner {
+    fn commit(&self, writes: HashMap<ColumnFamily, BTreeMap<Vec<u8>, Option<Vec<u8>>>>) {
+        if writes.values().all(BTreeMap::is_empty) {
+            return;
+        }
+        let mut state_guard = self.state.write().unwrap();
+        let state = &mut *state_guard;
+        let version = state.version + 1;
+        // Versions older than the oldest live snapshot are not visible anymore
+        let oldest_visible = self
+            .snapshots
+            .lock()
+            .unwrap()
+            .keys()
+            .next()
+            .map_or(version, |v| (*v).min(version));
+        for (column_family, writes) in writes {
+            let tree = state.trees.entry(column_family.clone()).or_default();
+            for (key, value) in writes {
+                let versions = tree.entry(key.clone()).or_default();
+                versions.push((version, value));
+                if prune_versions(versions, oldest_visible) {
+                    tree.remove(&key);
+                } else if versions.len() > 1 || versions[0].1.is_none() {
+                    // Some versions will be removable once the snapshots older than this commit are gone
+                    state
+                        .garbage
+                        .push_back((version, column_family.clone(), key));
+                }
+            }
+        }
+        while state
+            .garbage
+            .front()
+            .map_or(false, |(v, _, _)| *v <= oldest_visible)
+        {
+            let Some((_, column_family, key)) = state.garbage.pop_front() else {
+                break;
+            };
+            let Some(tree) = state.trees.get_mut(&column_family) else {
+                continue;
+            };
+            if tree
+                .get_mut(&key)
+                .map_or(false, |versions| prune_versions(versions, oldest_visible))
+            {
+                tree.remove(&key);
+            }
+        }
+        state.version = version;
     }
 }
 
+/// Removes the versions that are not visible from any snapshot at `oldest_visible` or later.
+///
+/// Returns `true` if the key is deleted for all these snapshots and might be removed.
+fn prune_versions(versions: &mut Versions, oldest_visible: u64) -> bool {
+    let first_visible = versions
+        .iter()
+        .rposition(|(v, _)| *v <= oldest_visible)
+        .unwrap_or(0);
+    versions.drain(..first_visible);
+    versions.len() == 1 && versions[0].1.is_none() && versions[0].0 <= oldest_visible
+}
+
 #[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct ColumnFamily(&'static str);
 
 pub struct Reader(InnerReader);
 
 enum InnerReader {
-    Simple(Arc<RwLock<HashMap<ColumnFamily, BTreeMap<Vec<u8>, Vec<u8>>>>>),
-    Transaction(
-        Weak<RefCell<RwLockWriteGuard<'static, HashMap<ColumnFamily, BTreeMap<Vec<u8>, Vec<u8>>>>>>,
-    ),
+    Snapshot(Arc<Snapshot>),
+    Transaction(Weak<RefCell<TransactionState>>),
+}
+
+struct Snapshot {
+    db: Arc<DbInner>,
+    version: u64,
+}
+
+/// A snapshot that might be kept around and shared between threads
+#[derive(Clone)]
+pub struct SharedSnapshot(Arc<Snapshot>);
+
+impl SharedSnapshot {
+    pub fn reader(&self) -> Reader {
+        Reader(InnerReader::Snapshot(Arc::clone(&self.0)))
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        let mut snapshots = self.db.snapshots.lock().unwrap();
+        if let Some(count) = snapshots.get_mut(&self.version) {
+            *count -= 1;
+            if *count == 0 {
+                snapshots.remove(&self.version);
+            }
+        }
+    }
+}
+
+struct TransactionState {
+    db: Arc<DbInner>,
+    version: u64,
+    writes: HashMap<ColumnFamily, BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+}
+
+impl TransactionState {
+    fn get(&self, column_family: &ColumnFamily, key: &[u8]) -> Option<Vec<u8>> {
+        if let Some(value) = self
+            .writes
+            .get(column_family)
+            .and_then(|writes| writes.get(key))
+        {
+            return value.clone();
+        }
+        get_at_version(
+            &self.db.state.read().unwrap(),
+            column_family,
+            key,
+            self.version,
+        )
+    }
+
+    fn scan(
+        &self,
+        column_family: &ColumnFamily,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let base = scan_at_version(
+            &self.db.state.read().unwrap(),
+            column_family,
+            start,
+            end,
+            self.version,
+        );
+        let Some(writes) = self
+            .writes
+            .get(column_family)
+            .filter(|writes| !writes.is_empty())
+        else {
+            return base;
+        };
+        let mut data = base.into_iter().collect::<BTreeMap<_, _>>();
+        for (key, value) in writes
+            .range(start.to_vec()..)
+            .take_while(|(k, _)| is_before(k, end))
+        {
+            if let Some(value) = value {
+                data.insert(key.clone(), value.clone());
+            } else {
+                data.remove(key);
+            }
+        }
+        data.into_iter().collect()
+    }
+}
+
+fn visible_value(versions: &Versions, version: u64) -> Option<&Vec<u8>> {
+    versions
+        .iter()
+        .rev()
+        .find(|(v, _)| *v <= version)
+        .and_then(|(_, value)| value.as_ref())
+}
+
+fn get_at_version(
+    state: &DbState,
+    column_family: &ColumnFamily,
+    key: &[u8],
+    version: u64,
+) -> Option<Vec<u8>> {
+    visible_value(state.trees.get(column_family)?.get(key)?, version).cloned()
+}
+
+/// Returns the keys greater or equal to `start` and lower than `end`
+fn scan_at_version(
+    state: &DbState,
+    column_family: &ColumnFamily,
+    start: &[u8],
+    end: Option<&[u8]>,
+    version: u64,
+) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let Some(tree) = state.trees.get(column_family) else {
+        return Vec::new();
+    };
+    tree.range(start.to_vec()..)
+        .take_while(|(k, _)| is_before(k, end))
+        .filter_map(|(k, versions)| Some((k.clone(), visible_value(versions, version)?.clone())))
+        .collect()
+}
+
+fn is_before(key: &[u8], end: Option<&[u8]>) -> bool {
+    end.map_or(true, |end| key < end)
 }
 
 impl Reader {
@@ -74,17 +333,15 @@ impl Reader {
         key: &[u8],
     ) -> Result<Option<Vec<u8>>, StorageError> {
         match &self.0 {
-            InnerReader::Simple(reader) => Ok(reader
-                .read()
-                .unwrap()
-                .get(column_family)
-                .and_then(|cf| cf.get(key).cloned())),
+            InnerReader::Snapshot(snapshot) => Ok(get_at_version(
+                &snapshot.db.state.read().unwrap(),
+                column_family,
+                key,
+                snapshot.version,
+            )),
             InnerReader::Transaction(reader) => {
                 if let Some(reader) = reader.upgrade() {
-                    Ok((*reader)
-                        .borrow()
-                        .get(column_family)
-                        .and_then(|cf| cf.get(key).cloned()))
+                    Ok((*reader).borrow().get(column_family, key))
                 } else {
                     Err(StorageError::Other(
                         "The transaction is already ended".into(),
@@ -94,31 +351,12 @@ impl Reader {
         }
     }
 
-    #[allow(clippy::unwrap_in_result)]
     pub fn contains_key(
         &self,
         column_family: &ColumnFamily,
         key: &[u8],
     ) -> Result<bool, StorageError> {
-        match &self.0 {
-            InnerReader::Simple(reader) => Ok(reader
-                .read()
-                .unwrap()
-                .get(column_family)
-                .map_or(false, |cf| cf.contains_key(key))),
-            InnerReader::Transaction(reader) => {
-                if let Some(reader) = reader.upgrade() {
-                    Ok((*reader)
-                        .borrow()
-                        .get(column_family)
-                        .map_or(false, |cf| cf.contains_key(key)))
-                } else {
-                    Err(StorageError::Other(
-                        "The transaction is already ended".into(),
-                    ))
-                }
-            }
-        }
+        Ok(self.get(column_family, key)?.is_some())
     }
 
     #[allow(clippy::iter_not_returning_iterator)]
@@ -126,51 +364,56 @@ impl Reader {
         self.scan_prefix(column_family, &[])
     }
 
-    #[allow(clippy::unwrap_in_result)]
     pub fn scan_prefix(
         &self,
         column_family: &ColumnFamily,
         prefix: &[u8],
     ) -> Result<Iter, StorageError> {
-        let data: Vec<_> = match &self.0 {
-            InnerReader::Simple(reader) => {
-                let trees = reader.read().unwrap();
-                let Some(tree) = trees.get(column_family) else {
-                    return Ok(Iter {
-                        iter: Vec::new().into_iter(),
-                        current: None,
-                    });
-                };
-                if prefix.is_empty() {
-                    tree.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
-                } else {
-                    tree.range(prefix.to_vec()..)
-                        .take_while(|(k, _)| k.starts_with(prefix))
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                        .collect()
-                }
-            }
+        self.scan(column_family, prefix, prefix_upper_bound(prefix).as_deref())
+    }
+
+    /// Iterates on all the keys greater or equal to `start`
+    pub fn scan_from(
+        &self,
+        column_family: &ColumnFamily,
+        start: &[u8],
+    ) -> Result<Iter, StorageError> {
+        self.scan(column_family, start, None)
+    }
+
+    /// Iterates on all the keys greater or equal to `start` and lower than `end`
+    pub fn scan_range(
+        &self,
+        column_family: &ColumnFamily,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Iter, StorageError> {
+        self.scan(column_family, start, Some(end))
+    }
+
+    #[allow(clippy::unwrap_in_result)]
+    fn scan(
+        &self,
+        column_family: &ColumnFamily,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<Iter, StorageError> {
+        let data = match &self.0 {
+            InnerReader::Snapshot(snapshot) => scan_at_version(
+                &snapshot.db.state.read().unwrap(),
+                column_family,
+                start,
+                end,
+                snapshot.version,
+            ),
             InnerReader::Transaction(reader) => {
                 let Some(reader) = reader.upgrade() else {
                     return Err(StorageError::Other(
                         "The transaction is already ended".into(),
                     ));
                 };
-                let trees = (*reader).borrow();
-                let Some(tree) = trees.get(column_family) else {
-                    return Ok(Iter {
-                        iter: Vec::new().into_iter(),
-                        current: None,
-                    });
-                };
-                if prefix.is_empty() {
-                    tree.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
-                } else {
-                    tree.range(prefix.to_vec()..)
-                        .take_while(|(k, _)| k.starts_with(prefix))
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                        .collect()
-                }
+                let data = (*reader).borrow().scan(column_family, start, end);
+                data
             }
         };
         let mut iter = data.into_iter();
@@ -178,64 +421,38 @@ impl Reader {
         Ok(Iter { iter, current })
     }
 
-    #[allow(clippy::unwrap_in_result)]
     pub fn len(&self, column_family: &ColumnFamily) -> Result<usize, StorageError> {
-        match &self.0 {
-            InnerReader::Simple(reader) => Ok(reader
-                .read()
-                .unwrap()
-                .get(column_family)
-                .map_or(0, BTreeMap::len)),
-            InnerReader::Transaction(reader) => {
-                if let Some(reader) = reader.upgrade() {
-                    Ok((*reader)
-                        .borrow()
-                        .get(column_family)
-                        .map_or(0, BTreeMap::len))
-                } else {
-                    Err(StorageError::Other(
-                        "The transaction is already ended".into(),
-                    ))
-                }
-            }
+        let mut count = 0;
+        let mut iter = self.iter(column_family)?;
+        while iter.key().is_some() {
+            count += 1;
+            iter.next();
         }
+        Ok(count)
     }
 
-    #[allow(clippy::unwrap_in_result)]
     pub fn is_empty(&self, column_family: &ColumnFamily) -> Result<bool, StorageError> {
-        match &self.0 {
-            InnerReader::Simple(reader) => Ok(reader
-                .read()
-                .unwrap()
-                .get(column_family)
-                .map_or(true, BTreeMap::is_empty)),
-            InnerReader::Transaction(reader) => {
-                if let Some(reader) = reader.upgrade() {
-                    Ok((*reader)
-                        .borrow()
-                        .get(column_family)
-                        .map_or(true, BTreeMap::is_empty))
-                } else {
-                    Err(StorageError::Other(
-                        "The transaction is already ended".into(),
-                    ))
-                }
-            }
-        }
+        Ok(self.iter(column_family)?.key().is_none())
     }
 }
 
-pub struct Transaction<'a>(
-    Rc<RefCell<RwLockWriteGuard<'a, HashMap<ColumnFamily, BTreeMap<Vec<u8>, Vec<u8>>>>>>,
-);
+pub struct Transaction<'a> {
+    state: Rc<RefCell<TransactionState>>,
+    _lifetime: PhantomData<&'a ()>,
+}
 
 impl Transaction<'_> {
-    #[allow(unsafe_code, clippy::useless_transmute)]
     pub fn reader(&self) -> Reader {
-        // SAFETY: This transmute is safe because we take a weak reference and the only Rc reference used is guarded by the lifetime.
-        Reader(InnerReader::Transaction(Rc::downgrade(unsafe {
-            transmute(&self.0)
-        })))
+        Reader(InnerReader::Transaction(Rc::downgrade(&self.state)))
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn get_for_update(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok((*self.state).borrow().get(column_family, key))
     }
 
     #[allow(clippy::unnecessary_wraps)]
@@ -244,24 +461,22 @@ impl Transaction<'_> {
         column_family: &ColumnFamily,
         key: &[u8],
     ) -> Result<bool, StorageError> {
-        Ok((*self.0)
-            .borrow()
-            .get(column_family)
-            .map_or(false, |cf| cf.contains_key(key)))
+        Ok((*self.state).borrow().get(column_family, key).is_some())
     }
 
-    #[allow(clippy::unnecessary_wraps, clippy::unwrap_in_result)]
+    #[allow(clippy::unnecessary_wraps)]
     pub fn insert(
         &mut self,
         column_family: &ColumnFamily,
         key: &[u8],
         value: &[u8],
     ) -> Result<(), StorageError> {
-        self.0
+        self.state
             .borrow_mut()
-            .get_mut(column_family)
-            .unwrap()
-            .insert(key.into(), value.into());
+            .writes
+            .entry(column_family.clone())
+            .or_default()
+            .insert(key.into(), Some(value.into()));
         Ok(())
     }
 
@@ -273,13 +488,14 @@ impl Transaction<'_> {
         self.insert(column_family, key, &[])
     }
 
-    #[allow(clippy::unnecessary_wraps, clippy::unwrap_in_result)]
+    #[allow(clippy::unnecessary_wraps)]
     pub fn remove(&mut self, column_family: &ColumnFamily, key: &[u8]) -> Result<(), StorageError> {
-        self.0
+        self.state
             .borrow_mut()
-            .get_mut(column_family)
-            .unwrap()
-            .remove(key);
+            .writes
+            .entry(column_family.clone())
+            .or_default()
+            .insert(key.into(), None);
         Ok(())
     }
 }
@@ -294,7 +510,6 @@ impl Iter {
         Some(&self.current.as_ref()?.0)
     }
 
-    #[allow(dead_code)]
     pub fn value(&self) -> Option<&[u8]> {
         Some(&self.current.as_ref()?.1)
     }
@@ -308,3 +523,174 @@ impl Iter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::panic_in_result_fn, clippy::unwrap_in_result)]
+
+    use super::*;
+
+    fn new_db() -> Result<(Db, ColumnFamily), StorageError> {
+        let db = Db::new(vec![ColumnFamilyDefinition {
+            name: "test",
+            use_iter: true,
+            min_prefix_size: 0,
+            unordered_writes: false,
+        }])?;
+        let column_family = db.column_family("test")?;
+        Ok((db, column_family))
+    }
+
+    fn keys(reader: &Reader, column_family: &ColumnFamily) -> Result<Vec<Vec<u8>>, StorageError> {
+        let mut iter = reader.iter(column_family)?;
+        let mut keys = Vec::new();
+        while let Some(key) = iter.key() {
+            keys.push(key.to_vec());
+            iter.next();
+        }
+        Ok(keys)
+    }
+
+    #[test]
+    fn snapshot_isolation() -> Result<(), StorageError> {
+        let (db, cf) = new_db()?;
+        db.transaction(|mut t| {
+            t.insert_empty(&cf, b"x")?;
+            t.insert(&cf, b"y", b"1")
+        })?;
+        let s1 = db.snapshot();
+        db.transaction(|mut t| {
+            t.remove(&cf, b"x")?;
+            t.insert(&cf, b"y", b"2")?;
+            t.insert_empty(&cf, b"z")?;
+            // The transaction sees its own writes
+            let reader = t.reader();
+            assert_eq!(keys(&reader, &cf)?, [b"y".to_vec(), b"z".to_vec()]);
+            assert_eq!(reader.get(&cf, b"y")?, Some(b"2".to_vec()));
+            // The other readers do not see the uncommitted writes
+            assert_eq!(keys(&s1, &cf)?, [b"x".to_vec(), b"y".to_vec()]);
+            assert_eq!(keys(&db.snapshot(), &cf)?, [b"x".to_vec(), b"y".to_vec()]);
+            Ok::<_, StorageError>(())
+        })?;
+        assert_eq!(keys(&s1, &cf)?, [b"x".to_vec(), b"y".to_vec()]);
+        assert_eq!(s1.get(&cf, b"y")?, Some(b"1".to_vec()));
+        let s2 = db.snapshot();
+        assert_eq!(keys(&s2, &cf)?, [b"y".to_vec(), b"z".to_vec()]);
+        assert_eq!(s2.len(&cf)?, 2);
+
+        drop(s1);
+        db.transaction(|mut t| t.insert(&cf, b"y", b"3"))?;
+        assert_eq!(s2.get(&cf, b"y")?, Some(b"2".to_vec()));
+        assert!(!s2.contains_key(&cf, b"x")?);
+
+        // A failed transaction does not write anything
+        db.transaction(|mut t| {
+            t.insert_empty(&cf, b"w")?;
+            Err::<(), _>(StorageError::Other("error".into()))
+        })
+        .unwrap_err();
+        assert_eq!(keys(&db.snapshot(), &cf)?, [b"y".to_vec(), b"z".to_vec()]);
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_snapshot() -> Result<(), StorageError> {
+        let (db, cf) = new_db()?;
+        let snapshot = db.shared_snapshot()?;
+        let writer = {
+            let db = db.clone();
+            let cf = cf.clone();
+            std::thread::spawn(move || {
+                db.transaction(|mut t| {
+                    for i in 0..100_u8 {
+                        t.insert_empty(&cf, &[i])?;
+                    }
+                    Ok::<_, StorageError>(())
+                })
+            })
+        };
+        writer
+            .join()
+            .map_err(|_| StorageError::Other("The writer thread panicked".into()))??;
+        assert!(snapshot.reader().is_empty(&cf)?);
+        assert_eq!(db.snapshot().len(&cf)?, 100);
+        Ok(())
+    }
+
+    #[test]
+    fn old_versions_are_pruned() -> Result<(), StorageError> {
+        let (db, cf) = new_db()?;
+        let versions = |key: &[u8]| {
+            db.0.state.read().unwrap().trees[&cf]
+                .get(key)
+                .map_or(0, Vec::len)
+        };
+        db.transaction(|mut t| {
+            t.insert(&cf, b"x", b"1")?;
+            t.insert_empty(&cf, b"y")
+        })?;
+        let snapshot = db.snapshot();
+        db.transaction(|mut t| {
+            t.insert(&cf, b"x", b"2")?;
+            t.remove(&cf, b"y")
+        })?;
+        // The old versions are kept for the snapshot
+        assert_eq!(snapshot.get(&cf, b"x")?, Some(b"1".to_vec()));
+        assert!(snapshot.contains_key(&cf, b"y")?);
+        assert_eq!(versions(b"x"), 2);
+        assert_eq!(versions(b"y"), 2);
+
+        // They are removed on the next commit once the snapshot is gone
+        drop(snapshot);
+        db.transaction(|mut t| t.insert_empty(&cf, b"z"))?;
+        assert_eq!(versions(b"x"), 1);
+        assert_eq!(versions(b"y"), 0);
+        assert!(db.0.state.read().unwrap().garbage.is_empty());
+        assert_eq!(db.snapshot().get(&cf, b"x")?, Some(b"2".to_vec()));
+        Ok(())
+    }
+
+    #[test]
+    fn scan_from() -> Result<(), StorageError> {
+        let (db, cf) = new_db()?;
+        db.transaction(|mut t| {
+            t.insert(&cf, b"", b"0")?;
+            t.insert(&cf, b"a", b"1")?;
+            t.insert(&cf, b"c", b"2")?;
+            assert_eq!(t.get_for_update(&cf, b"")?, Some(b"0".to_vec()));
+            let mut iter = t.reader().scan_from(&cf, b"b")?;
+            assert_eq!(iter.key(), Some(b"c".as_slice()));
+            iter.next();
+            assert_eq!(iter.key(), None);
+            Ok::<_, StorageError>(())
+        })?;
+        let mut iter = db.snapshot().scan_from(&cf, b"a")?;
+        assert_eq!(iter.key(), Some(b"a".as_slice()));
+        assert_eq!(iter.value(), Some(b"1".as_slice()));
+        iter.next();
+        assert_eq!(iter.key(), Some(b"c".as_slice()));
+        Ok(())
+    }
+
+    #[test]
+    fn scan_range() -> Result<(), StorageError> {
+        let (db, cf) = new_db()?;
+        db.transaction(|mut t| {
+            t.insert_empty(&cf, b"a")?;
+            t.insert_empty(&cf, b"b")?;
+            t.insert_empty(&cf, b"c")?;
+            let mut iter = t.reader().scan_range(&cf, b"a", b"b")?;
+            assert_eq!(iter.key(), Some(b"a".as_slice()));
+            iter.next();
+            assert_eq!(iter.key(), None);
+            Ok::<_, StorageError>(())
+        })?;
+        let mut iter = db.snapshot().scan_range(&cf, b"b", b"d")?;
+        assert_eq!(iter.key(), Some(b"b".as_slice()));
+        iter.next();
+        assert_eq!(iter.key(), Some(b"c".as_slice()));
+        iter.next();
+        assert_eq!(iter.key(), None);
+        Ok(())
+    }
+}
@@ -1,12 +1,768 @@
 //! A storage backend
 //! RocksDB is available, if not in memory
+//! Any other key-value store implementing [`StorageBackend`] might be used instead
 
+use crate::storage::StorageError;
+pub use conformance::check_storage_backend;
+pub use custom::{
+    StorageBackend, StorageBackendIter, StorageBackendSnapshot, StorageBackendTransaction,
+};
 #[cfg(target_family = "wasm")]
-pub use fallback::{ColumnFamily, ColumnFamilyDefinition, Db, Iter, Reader, Transaction};
+use fallback as native;
+#[cfg(target_family = "wasm")]
+pub use fallback::ColumnFamilyDefinition;
+#[cfg(not(target_family = "wasm"))]
+use rand::random;
+#[cfg(not(target_family = "wasm"))]
+use rocksdb as native;
+#[cfg(not(target_family = "wasm"))]
+pub use rocksdb::{BackupEngine, BackupInfo, ColumnFamilyDefinition, DbOptions};
+use std::borrow::Borrow;
+#[cfg(not(target_family = "wasm"))]
+use std::env::temp_dir;
+use std::error::Error;
+#[cfg(not(target_family = "wasm"))]
+use std::fs;
+use std::ops::Deref;
 #[cfg(not(target_family = "wasm"))]
-pub use rocksdb::{ColumnFamily, ColumnFamilyDefinition, Db, Iter, Reader, Transaction};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-#[cfg(target_family = "wasm")]
+mod conformance;
+mod custom;
+#[cfg(any(target_family = "wasm", test))]
+#[cfg_attr(not(target_family = "wasm"), allow(dead_code))]
 mod fallback;
 #[cfg(not(target_family = "wasm"))]
 mod rocksdb;
+
+/// The key-value database, either the built-in one or a [`StorageBackend`]
+#[derive(Clone)]
+pub struct Db(InnerDb);
+
+#[derive(Clone)]
+enum InnerDb {
+    Native(native::Db),
+    Custom(custom::Db),
+}
+
+impl Db {
+    pub fn new(column_families: Vec<ColumnFamilyDefinition>) -> Result<Self, StorageError> {
+        Ok(Self(InnerDb::Native(native::Db::new(column_families)?)))
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn open_read_write(
+        path: Option<&Path>,
+        column_families: Vec<ColumnFamilyDefinition>,
+        options: &DbOptions,
+    ) -> Result<Self, StorageError> {
+        Ok(Self(InnerDb::Native(native::Db::open_read_write(
+            path,
+            column_families,
+            options,
+        )?)))
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn open_secondary(
+        primary_path: &Path,
+        secondary_path: Option<&Path>,
+        column_families: Vec<ColumnFamilyDefinition>,
+    ) -> Result<Self, StorageError> {
+        Ok(Self(InnerDb::Native(native::Db::open_secondary(
+            primary_path,
+            secondary_path,
+            column_families,
+        )?)))
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn open_read_only(
+        path: &Path,
+        column_families: Vec<ColumnFamilyDefinition>,
+    ) -> Result<Self, StorageError> {
+        Ok(Self(InnerDb::Native(native::Db::open_read_only(
+            path,
+            column_families,
+        )?)))
+    }
+
+    /// Opens a database stored in the given [`StorageBackend`], creating the missing column families
+    pub fn open_backend(
+        backend: Arc<dyn StorageBackend>,
+        column_families: Vec<ColumnFamilyDefinition>,
+    ) -> Result<Self, StorageError> {
+        Ok(Self(InnerDb::Custom(custom::Db::new(
+            backend,
+            column_families,
+            true,
+        )?)))
+    }
+
+    /// Opens a database stored in the given [`StorageBackend`] that does not support writes
+    #[cfg(not(target_family = "wasm"))]
+    pub fn open_read_only_backend(
+        backend: Arc<dyn StorageBackend>,
+        column_families: Vec<ColumnFamilyDefinition>,
+    ) -> Result<Self, StorageError> {
+        Ok(Self(InnerDb::Custom(custom::Db::new(
+            backend,
+            column_families,
+            false,
+        )?)))
+    }
+
+    pub fn column_family(&self, name: &'static str) -> Result<ColumnFamily, StorageError> {
+        Ok(ColumnFamily(match &self.0 {
+            InnerDb::Native(db) => InnerColumnFamily::Native(db.column_family(name)?),
+            InnerDb::Custom(_) => InnerColumnFamily::Custom(name),
+        }))
+    }
+
+    /// Returns if the database has been opened in read-write mode
+    #[cfg(not(target_family = "wasm"))]
+    pub fn is_writable(&self) -> bool {
+        match &self.0 {
+            InnerDb::Native(db) => db.is_writable(),
+            InnerDb::Custom(db) => db.is_writable(),
+        }
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> Reader {
+        Reader(match &self.0 {
+            InnerDb::Native(db) => InnerReader::Native(db.snapshot()),
+            InnerDb::Custom(db) => InnerReader::Custom(db.snapshot()),
+        })
+    }
+
+    /// Builds a snapshot that might be kept around and shared between threads
+    pub fn shared_snapshot(&self) -> Result<SharedSnapshot, StorageError> {
+        Ok(SharedSnapshot(match &self.0 {
+            InnerDb::Native(db) => InnerSharedSnapshot::Native(db.shared_snapshot()?),
+            InnerDb::Custom(db) => InnerSharedSnapshot::Custom(db.shared_snapshot()),
+        }))
+    }
+
+    pub fn transaction<'a, 'b: 'a, T, E: Error + 'static + From<StorageError>>(
+        &'b self,
+        f: impl Fn(Transaction<'a>) -> Result<T, E>,
+    ) -> Result<T, E> {
+        match &self.0 {
+            InnerDb::Native(db) => {
+                db.transaction(|transaction| f(Transaction(InnerTransaction::Native(transaction))))
+            }
+            InnerDb::Custom(db) => {
+                db.transaction(|transaction| f(Transaction(InnerTransaction::Custom(transaction))))
+            }
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn get(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<Option<Slice>, StorageError> {
+        Ok(match &self.0 {
+            InnerDb::Native(db) => db.get(column_family.native()?, key)?.map(Slice::native),
+            InnerDb::Custom(db) => db
+                .snapshot()
+                .get(column_family.custom()?, key)?
+                .map(Slice::owned),
+        })
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn contains_key(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<bool, StorageError> {
+        match &self.0 {
+            InnerDb::Native(db) => db.contains_key(column_family.native()?, key),
+            InnerDb::Custom(db) => db.snapshot().contains_key(column_family.custom()?, key),
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn insert(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        match &self.0 {
+            InnerDb::Native(db) => db.insert(column_family.native()?, key, value),
+            InnerDb::Custom(db) => db.insert(column_family.custom()?, key, value),
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn flush(&self) -> Result<(), StorageError> {
+        match &self.0 {
+            InnerDb::Native(db) => db.flush(),
+            InnerDb::Custom(db) => db.flush(),
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn compact(&self, column_family: &ColumnFamily) -> Result<(), StorageError> {
+        match &self.0 {
+            InnerDb::Native(db) => db.compact(column_family.native()?),
+            InnerDb::Custom(db) => db.compact(column_family.custom()?),
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn new_sst_file(&self) -> Result<SstFileWriter, StorageError> {
+        Ok(SstFileWriter(match &self.0 {
+            InnerDb::Native(db) => InnerSstFileWriter::Native(db.new_sst_file()?),
+            InnerDb::Custom(_) => InnerSstFileWriter::Custom(Vec::new()),
+        }))
+    }
+
+    /// Returns the path of a new temporary file, in the database directory if it is the built-in one
+    #[cfg(not(target_family = "wasm"))]
+    pub fn new_temporary_file_path(&self) -> Result<PathBuf, StorageError> {
+        match &self.0 {
+            InnerDb::Native(db) => db.new_temporary_file_path(),
+            InnerDb::Custom(_) => Ok(temp_dir().join(format!("oxigraph-{}", random::<u128>()))),
+        }
+    }
+
+    /// Atomically inserts the content of the given files
+    #[cfg(not(target_family = "wasm"))]
+    pub fn insert_stt_files(
+        &self,
+        ssts_for_cf: Vec<(&ColumnFamily, SstFile)>,
+    ) -> Result<(), StorageError> {
+        match &self.0 {
+            InnerDb::Native(db) => db.insert_stt_files(
+                &ssts_for_cf
+                    .into_iter()
+                    .map(|(column_family, file)| match file.0 {
+                        InnerSstFile::Native(path) => Ok((column_family.native()?, path)),
+                        InnerSstFile::Custom(_) => Err(wrong_backend_error()),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            InnerDb::Custom(db) => db.ingest(
+                ssts_for_cf
+                    .into_iter()
+                    .map(|(column_family, file)| match file.0 {
+                        InnerSstFile::Native(_) => Err(wrong_backend_error()),
+                        InnerSstFile::Custom(entries) => Ok((column_family.custom()?, entries)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+        }
+    }
+
+    /// Atomically removes all the keys in the given `[start, end)` ranges
+    ///
+    /// It is done outside of any transaction, the caller must ensure that no concurrent write happens in these ranges.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn remove_ranges(
+        &self,
+        ranges: &[(&ColumnFamily, &[u8], &[u8])],
+    ) -> Result<(), StorageError> {
+        match &self.0 {
+            InnerDb::Native(db) => db.remove_ranges(
+                &ranges
+                    .iter()
+                    .map(|(column_family, start, end)| Ok((column_family.native()?, *start, *end)))
+                    .collect::<Result<Vec<_>, StorageError>>()?,
+            ),
+            InnerDb::Custom(db) => db.remove_ranges(
+                &ranges
+                    .iter()
+                    .map(|(column_family, start, end)| Ok((column_family.custom()?, *start, *end)))
+                    .collect::<Result<Vec<_>, StorageError>>()?,
+            ),
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn backup(&self, target_directory: &Path) -> Result<(), StorageError> {
+        match &self.0 {
+            InnerDb::Native(db) => db.backup(target_directory),
+            InnerDb::Custom(_) => Err(backups_not_supported_error()),
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn create_new_backup(&self, engine: &BackupEngine) -> Result<u32, StorageError> {
+        match &self.0 {
+            InnerDb::Native(db) => engine.create_new_backup(db),
+            InnerDb::Custom(_) => Err(backups_not_supported_error()),
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn backups_not_supported_error() -> StorageError {
+    StorageError::Other("Backups are only supported by the built-in storage".into())
+}
+
+fn wrong_backend_error() -> StorageError {
+    StorageError::Other("The object has been built by an other storage backend".into())
+}
+
+#[derive(Clone)]
+pub struct ColumnFamily(InnerColumnFamily);
+
+#[derive(Clone)]
+enum InnerColumnFamily {
+    Native(native::ColumnFamily),
+    Custom(&'static str),
+}
+
+impl ColumnFamily {
+    fn native(&self) -> Result<&native::ColumnFamily, StorageError> {
+        match &self.0 {
+            InnerColumnFamily::Native(column_family) => Ok(column_family),
+            InnerColumnFamily::Custom(_) => Err(wrong_backend_error()),
+        }
+    }
+
+    fn custom(&self) -> Result<&'static str, StorageError> {
+        match &self.0 {
+            InnerColumnFamily::Native(_) => Err(wrong_backend_error()),
+            InnerColumnFamily::Custom(name) => Ok(name),
+        }
+    }
+}
+
+/// A snapshot that might be kept around and shared between threads
+#[derive(Clone)]
+pub struct SharedSnapshot(InnerSharedSnapshot);
+
+#[derive(Clone)]
+enum InnerSharedSnapshot {
+    Native(native::SharedSnapshot),
+    Custom(custom::SharedSnapshot),
+}
+
+impl SharedSnapshot {
+    pub fn reader(&self) -> Reader {
+        Reader(match &self.0 {
+            InnerSharedSnapshot::Native(snapshot) => InnerReader::Native(snapshot.reader()),
+            InnerSharedSnapshot::Custom(snapshot) => InnerReader::Custom(snapshot.reader()),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Reader(InnerReader);
+
+#[derive(Clone)]
+enum InnerReader {
+    Native(native::Reader),
+    Custom(custom::Reader),
+}
+
+impl Reader {
+    pub fn get(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<Option<Slice>, StorageError> {
+        Ok(match &self.0 {
+            InnerReader::Native(reader) => {
+                reader.get(column_family.native()?, key)?.map(Slice::native)
+            }
+            InnerReader::Custom(reader) => {
+                reader.get(column_family.custom()?, key)?.map(Slice::owned)
+            }
+        })
+    }
+
+    pub fn contains_key(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<bool, StorageError> {
+        match &self.0 {
+            InnerReader::Native(reader) => reader.contains_key(column_family.native()?, key),
+            InnerReader::Custom(reader) => reader.contains_key(column_family.custom()?, key),
+        }
+    }
+
+    #[allow(clippy::iter_not_returning_iterator)]
+    pub fn iter(&self, column_family: &ColumnFamily) -> Result<Iter, StorageError> {
+        Ok(Iter(match &self.0 {
+            InnerReader::Native(reader) => InnerIter::Native(reader.iter(column_family.native()?)?),
+            InnerReader::Custom(reader) => InnerIter::Custom(reader.iter(column_family.custom()?)?),
+        }))
+    }
+
+    pub fn scan_prefix(
+        &self,
+        column_family: &ColumnFamily,
+        prefix: &[u8],
+    ) -> Result<Iter, StorageError> {
+        Ok(Iter(match &self.0 {
+            InnerReader::Native(reader) => {
+                InnerIter::Native(reader.scan_prefix(column_family.native()?, prefix)?)
+            }
+            InnerReader::Custom(reader) => {
+                InnerIter::Custom(reader.scan_prefix(column_family.custom()?, prefix)?)
+            }
+        }))
+    }
+
+    /// Iterates on all the keys greater or equal to `start`
+    pub fn scan_from(
+        &self,
+        column_family: &ColumnFamily,
+        start: &[u8],
+    ) -> Result<Iter, StorageError> {
+        Ok(Iter(match &self.0 {
+            InnerReader::Native(reader) => {
+                InnerIter::Native(reader.scan_from(column_family.native()?, start)?)
+            }
+            InnerReader::Custom(reader) => {
+                InnerIter::Custom(reader.scan_from(column_family.custom()?, start)?)
+            }
+        }))
+    }
+
+    /// Iterates on all the keys greater or equal to `start` and lower than `end`
+    pub fn scan_range(
+        &self,
+        column_family: &ColumnFamily,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Iter, StorageError> {
+        Ok(Iter(match &self.0 {
+            InnerReader::Native(reader) => {
+                InnerIter::Native(reader.scan_range(column_family.native()?, start, end)?)
+            }
+            InnerReader::Custom(reader) => {
+                InnerIter::Custom(reader.scan_range(column_family.custom()?, start, end)?)
+            }
+        }))
+    }
+
+    pub fn len(&self, column_family: &ColumnFamily) -> Result<usize, StorageError> {
+        match &self.0 {
+            InnerReader::Native(reader) => reader.len(column_family.native()?),
+            InnerReader::Custom(reader) => reader.len(column_family.custom()?),
+        }
+    }
+
+    pub fn is_empty(&self, column_family: &ColumnFamily) -> Result<bool, StorageError> {
+        match &self.0 {
+            InnerReader::Native(reader) => reader.is_empty(column_family.native()?),
+            InnerReader::Custom(reader) => reader.is_empty(column_family.custom()?),
+        }
+    }
+}
+
+pub struct Transaction<'a>(InnerTransaction<'a>);
+
+enum InnerTransaction<'a> {
+    Native(native::Transaction<'a>),
+    Custom(custom::Transaction<'a>),
+}
+
+impl Transaction<'_> {
+    pub fn reader(&self) -> Reader {
+        Reader(match &self.0 {
+            InnerTransaction::Native(transaction) => InnerReader::Native(transaction.reader()),
+            InnerTransaction::Custom(transaction) => InnerReader::Custom(transaction.reader()),
+        })
+    }
+
+    pub fn get_for_update(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<Option<Slice>, StorageError> {
+        Ok(match &self.0 {
+            InnerTransaction::Native(transaction) => transaction
+                .get_for_update(column_family.native()?, key)?
+                .map(Slice::native),
+            InnerTransaction::Custom(transaction) => transaction
+                .get_for_update(column_family.custom()?, key)?
+                .map(Slice::owned),
+        })
+    }
+
+    pub fn contains_key_for_update(
+        &self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<bool, StorageError> {
+        match &self.0 {
+            InnerTransaction::Native(transaction) => {
+                transaction.contains_key_for_update(column_family.native()?, key)
+            }
+            InnerTransaction::Custom(transaction) => {
+                transaction.contains_key_for_update(column_family.custom()?, key)
+            }
+        }
+    }
+
+    pub fn insert(
+        &mut self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        match &mut self.0 {
+            InnerTransaction::Native(transaction) => {
+                transaction.insert(column_family.native()?, key, value)
+            }
+            InnerTransaction::Custom(transaction) => {
+                transaction.insert(column_family.custom()?, key, value)
+            }
+        }
+    }
+
+    pub fn insert_empty(
+        &mut self,
+        column_family: &ColumnFamily,
+        key: &[u8],
+    ) -> Result<(), StorageError> {
+        match &mut self.0 {
+            InnerTransaction::Native(transaction) => {
+                transaction.insert_empty(column_family.native()?, key)
+            }
+            InnerTransaction::Custom(transaction) => {
+                transaction.insert(column_family.custom()?, key, &[])
+            }
+        }
+    }
+
+    pub fn remove(&mut self, column_family: &ColumnFamily, key: &[u8]) -> Result<(), StorageError> {
+        match &mut self.0 {
+            InnerTransaction::Native(transaction) => {
+                transaction.remove(column_family.native()?, key)
+            }
+            InnerTransaction::Custom(transaction) => {
+                transaction.remove(column_family.custom()?, key)
+            }
+        }
+    }
+
+    /// Inserts an entry whose value never changes so it does not need the transaction isolation
+    ///
+    /// RocksDB writes it directly in the database to avoid conflicts between the transactions writing it.
+    /// Other backends write it with the transaction.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn insert_idempotent(
+        &mut self,
+        db: &Db,
+        column_family: &ColumnFamily,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        match &mut self.0 {
+            InnerTransaction::Native(_) => db.insert(column_family, key, value),
+            InnerTransaction::Custom(transaction) => {
+                transaction.insert(column_family.custom()?, key, value)
+            }
+        }
+    }
+
+    /// Inserts the content of the given files while the transaction is running
+    ///
+    /// RocksDB ingests them directly in the database, the transaction is only used for its locks.
+    /// Other backends write them with the transaction.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn insert_stt_files(
+        &mut self,
+        db: &Db,
+        ssts_for_cf: Vec<(&ColumnFamily, SstFile)>,
+    ) -> Result<(), StorageError> {
+        match &mut self.0 {
+            InnerTransaction::Native(_) => db.insert_stt_files(ssts_for_cf),
+            InnerTransaction::Custom(transaction) => {
+                for (column_family, file) in ssts_for_cf {
+                    let InnerSstFile::Custom(entries) = file.0 else {
+                        return Err(wrong_backend_error());
+                    };
+                    for (key, value) in entries {
+                        transaction.insert(column_family.custom()?, &key, &value)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct Iter(InnerIter);
+
+enum InnerIter {
+    Native(native::Iter),
+    Custom(custom::Iter),
+}
+
+impl Iter {
+    pub fn key(&self) -> Option<&[u8]> {
+        match &self.0 {
+            InnerIter::Native(iter) => iter.key(),
+            InnerIter::Custom(iter) => iter.key(),
+        }
+    }
+
+    pub fn value(&self) -> Option<&[u8]> {
+        match &self.0 {
+            InnerIter::Native(iter) => iter.value(),
+            InnerIter::Custom(iter) => iter.value(),
+        }
+    }
+
+    pub fn next(&mut self) {
+        match &mut self.0 {
+            InnerIter::Native(iter) => iter.next(),
+            InnerIter::Custom(iter) => iter.next(),
+        }
+    }
+
+    /// Returns the error that stopped the iteration, if any
+    pub fn status(&mut self) -> Result<(), StorageError> {
+        match &mut self.0 {
+            InnerIter::Native(iter) => iter.status(),
+            InnerIter::Custom(iter) => iter.status(),
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub struct SstFileWriter(InnerSstFileWriter);
+
+#[cfg(not(target_family = "wasm"))]
+enum InnerSstFileWriter {
+    Native(native::SstFileWriter),
+    Custom(Vec<(Vec<u8>, Vec<u8>)>),
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl SstFileWriter {
+    /// Adds an entry, the keys must be inserted in increasing order
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        match &mut self.0 {
+            InnerSstFileWriter::Native(writer) => writer.insert(key, value),
+            InnerSstFileWriter::Custom(entries) => {
+                entries.push((key.into(), value.into()));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn insert_empty(&mut self, key: &[u8]) -> Result<(), StorageError> {
+        match &mut self.0 {
+            InnerSstFileWriter::Native(writer) => writer.insert_empty(key),
+            InnerSstFileWriter::Custom(entries) => {
+                entries.push((key.into(), Vec::new()));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn finish(self) -> Result<SstFile, StorageError> {
+        Ok(SstFile(match self.0 {
+            InnerSstFileWriter::Native(writer) => InnerSstFile::Native(writer.finish()?),
+            InnerSstFileWriter::Custom(entries) => InnerSstFile::Custom(entries),
+        }))
+    }
+}
+
+/// Sorted entries ready to be inserted with [`Db::insert_stt_files`]
+#[cfg(not(target_family = "wasm"))]
+#[derive(Clone)]
+pub struct SstFile(InnerSstFile);
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(Clone)]
+enum InnerSstFile {
+    Native(PathBuf),
+    Custom(Vec<(Vec<u8>, Vec<u8>)>),
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl SstFile {
+    /// Deletes the file if it has not been inserted
+    pub fn remove(self) -> Result<(), StorageError> {
+        if let InnerSstFile::Native(path) = self.0 {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// A value read from the database
+pub struct Slice(InnerSlice);
+
+enum InnerSlice {
+    #[cfg(not(target_family = "wasm"))]
+    Native(native::PinnableSlice),
+    Owned(Vec<u8>),
+}
+
+impl Slice {
+    #[cfg(not(target_family = "wasm"))]
+    fn native(value: native::PinnableSlice) -> Self {
+        Self(InnerSlice::Native(value))
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn native(value: Vec<u8>) -> Self {
+        Self::owned(value)
+    }
+
+    fn owned(value: Vec<u8>) -> Self {
+        Self(InnerSlice::Owned(value))
+    }
+}
+
+impl Deref for Slice {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.0 {
+            #[cfg(not(target_family = "wasm"))]
+            InnerSlice::Native(value) => value,
+            InnerSlice::Owned(value) => value,
+        }
+    }
+}
+
+impl AsRef<[u8]> for Slice {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Borrow<[u8]> for Slice {
+    fn borrow(&self) -> &[u8] {
+        self
+    }
+}
+
+impl From<Slice> for Vec<u8> {
+    fn from(value: Slice) -> Self {
+        match value.0 {
+            #[cfg(not(target_family = "wasm"))]
+            InnerSlice::Native(value) => value.into(),
+            InnerSlice::Owned(value) => value,
+        }
+    }
+}
+
+/// The first key after all the keys starting with the prefix, `None` if there is none
+pub fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut bound = prefix.to_vec();
+    while let Some(last) = bound.pop() {
+        if last < u8::MAX {
+            bound.push(last + 1);
+            return Some(bound);
+        }
+    }
+    None
+}
@@ -26,6 +26,7 @@ use std::path::{Path, PathBuf};
 use std::rc::{Rc, Weak};
 use std::sync::{Arc, OnceLock};
 use std::thread::{available_parallelism, yield_now};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{fmt, io, ptr, slice};
 
 macro_rules! ffi_result {
@@ -52,6 +53,16 @@ pub struct ColumnFamilyDefinition {
     pub unordered_writes: bool,
 }
 
+/// Tuning options of a read-write database, [`None`] values keep the default choices
+#[derive(Clone, Debug, Default)]
+pub struct DbOptions {
+    pub block_cache_size: Option<usize>,
+    pub compression: Option<bool>,
+    pub max_open_files: Option<u32>,
+    pub write_buffer_size: Option<usize>,
+    pub bloom_filter_bits_per_key: Option<f64>,
+}
+
 #[derive(Clone)]
 pub struct Db {
     inner: DbKind,
@@ -149,12 +160,13 @@ impl Drop for RoDbHandler {
 
 impl Db {
     pub fn new(column_families: Vec<ColumnFamilyDefinition>) -> Result<Self, StorageError> {
-        Self::open_read_write(None, column_families)
+        Self::open_read_write(None, column_families, &DbOptions::default())
     }
 
     pub fn open_read_write(
         path: Option<&Path>,
         column_families: Vec<ColumnFamilyDefinition>,
+        db_options: &DbOptions,
     ) -> Result<Self, StorageError> {
         let (path, in_memory) = if let Some(path) = path {
             (path.to_path_buf(), false)
@@ -163,15 +175,15 @@ impl Db {
         };
         let c_path = path_to_cstring(&path)?;
         unsafe {
-            let options = Self::db_options(true, in_memory)?;
+            let options = Self::db_options(true, in_memory, db_options)?;
             rocksdb_options_set_create_if_missing(options, 1);
             rocksdb_options_set_create_missing_column_families(options, 1);
             rocksdb_options_set_compression(
                 options,
-                if in_memory {
-                    rocksdb_no_compression
-                } else {
+                if db_options.compression.unwrap_or(!in_memory) {
                     rocksdb_lz4_compression
+                } else {
+                    rocksdb_no_compression
                 }
                 .try_into()
                 .unwrap(),
@@ -186,6 +198,23 @@ impl Db {
                 block_based_table_options,
                 16,
             );
+            if let Some(block_cache_size) = db_options.block_cache_size {
+                let block_cache = rocksdb_cache_create_lru(block_cache_size);
+                assert!(
+                    !block_cache.is_null(),
+                    "rocksdb_cache_create_lru returned null"
+                );
+                // The table options keep their own reference to the cache
+                rocksdb_block_based_options_set_block_cache(block_based_table_options, block_cache);
+                rocksdb_cache_destroy(block_cache);
+            }
+            if let Some(bits_per_key) = db_options.bloom_filter_bits_per_key {
+                // The table options take the ownership of the filter policy
+                rocksdb_block_based_options_set_filter_policy(
+                    block_based_table_options,
+                    rocksdb_filterpolicy_create_bloom(bits_per_key),
+                );
+            }
             rocksdb_options_set_block_based_table_factory(options, block_based_table_options);
             #[cfg(feature = "rocksdb-debug")]
             {
@@ -195,7 +224,7 @@ impl Db {
             }
 
             let (column_family_names, c_column_family_names, cf_options) =
-                Self::column_families_names_and_options(column_families, options);
+                Self::column_families_names_and_options(column_families, options, db_options);
             let mut cf_handles: Vec<*mut rocksdb_column_family_handle_t> =
                 vec![ptr::null_mut(); column_family_names.len()];
             let c_num_column_families = c_column_family_names.len().try_into().unwrap();
@@ -318,9 +347,20 @@ impl Db {
         };
         let c_secondary_path = path_to_cstring(&secondary_path)?;
         unsafe {
-            let options = Self::db_options(false, false)?;
+            let options = Self::db_options(false, false, &DbOptions::default())?;
+            let column_families =
+                Self::existing_column_families(column_families, options, &c_primary_path).map_err(
+                    |e| {
+                        rocksdb_options_destroy(options);
+                        e
+                    },
+                )?;
             let (column_family_names, c_column_family_names, cf_options) =
-                Self::column_families_names_and_options(column_families, options);
+                Self::column_families_names_and_options(
+                    column_families,
+                    options,
+                    &DbOptions::default(),
+                );
             let mut cf_handles: Vec<*mut rocksdb_column_family_handle_t> =
                 vec![ptr::null_mut(); column_family_names.len()];
             let c_num_column_families = c_column_family_names.len().try_into().unwrap();
@@ -380,9 +420,18 @@ impl Db {
     ) -> Result<Self, StorageError> {
         unsafe {
             let c_path = path_to_cstring(path)?;
-            let options = Self::db_options(true, false)?;
+            let options = Self::db_options(true, false, &DbOptions::default())?;
+            let column_families = Self::existing_column_families(column_families, options, &c_path)
+                .map_err(|e| {
+                    rocksdb_options_destroy(options);
+                    e
+                })?;
             let (column_family_names, c_column_family_names, cf_options) =
-                Self::column_families_names_and_options(column_families, options);
+                Self::column_families_names_and_options(
+                    column_families,
+                    options,
+                    &DbOptions::default(),
+                );
             let mut cf_handles: Vec<*mut rocksdb_column_family_handle_t> =
                 vec![ptr::null_mut(); column_family_names.len()];
             let c_num_column_families = c_column_family_names.len().try_into().unwrap();
@@ -440,6 +489,7 @@ impl Db {
     fn db_options(
         limit_max_open_files: bool,
         in_memory: bool,
+        db_options: &DbOptions,
     ) -> Result<*mut rocksdb_options_t, StorageError> {
         static ROCKSDB_ENV: OnceLock<UnsafeEnv> = OnceLock::new();
         static ROCKSDB_MEM_ENV: OnceLock<UnsafeEnv> = OnceLock::new();
@@ -448,11 +498,19 @@ impl Db {
             let options = rocksdb_options_create();
             assert!(!options.is_null(), "rocksdb_options_create returned null");
             rocksdb_options_optimize_level_style_compaction(options, 512 * 1024 * 1024);
+            if let Some(write_buffer_size) = db_options.write_buffer_size {
+                rocksdb_options_set_write_buffer_size(options, write_buffer_size);
+            }
             rocksdb_options_increase_parallelism(
                 options,
                 available_parallelism()?.get().try_into().unwrap(),
             );
-            if limit_max_open_files {
+            if let Some(max_open_files) = db_options.max_open_files {
+                rocksdb_options_set_max_open_files(
+                    options,
+                    max_open_files.try_into().unwrap_or(libc::c_int::MAX),
+                );
+            } else if limit_max_open_files {
                 if let Some(available_fd) = available_file_descriptors()? {
                     if available_fd < 96 {
                         rocksdb_options_destroy(options);
@@ -498,9 +556,36 @@ impl Db {
         }
     }
 
+    /// Filters out the column families that do not exist in the database
+    ///
+    /// Useful to open read-only databases created before the addition of some column families.
+    unsafe fn existing_column_families(
+        mut column_families: Vec<ColumnFamilyDefinition>,
+        options: *const rocksdb_options_t,
+        path: &CStr,
+    ) -> Result<Vec<ColumnFamilyDefinition>, StorageError> {
+        let mut len = 0;
+        let names = ffi_result!(rocksdb_list_column_families_with_status(
+            options,
+            path.as_ptr(),
+            &mut len
+        ))?;
+        if names.is_null() {
+            return Ok(Vec::new());
+        }
+        let existing = slice::from_raw_parts(names, len)
+            .iter()
+            .map(|name| CStr::from_ptr(*name).to_bytes().to_vec())
+            .collect::<Vec<_>>();
+        rocksdb_list_column_families_destroy(names, len);
+        column_families.retain(|cf| existing.iter().any(|name| name == cf.name.as_bytes()));
+        Ok(column_families)
+    }
+
     fn column_families_names_and_options(
         mut column_families: Vec<ColumnFamilyDefinition>,
         base_options: *mut rocksdb_options_t,
+        db_options: &DbOptions,
     ) -> (Vec<&'static str>, Vec<CString>, Vec<*mut rocksdb_options_t>) {
         if !column_families.iter().any(|c| c.name == "default") {
             column_families.push(ColumnFamilyDefinition {
@@ -521,7 +606,13 @@ impl Db {
             .map(|cf| unsafe {
                 let options = rocksdb_options_create_copy(base_options);
                 if !cf.use_iter {
-                    rocksdb_options_optimize_for_point_lookup(options, 128);
+                    // The point lookup optimization uses its own block cache
+                    rocksdb_options_optimize_for_point_lookup(
+                        options,
+                        db_options.block_cache_size.map_or(128, |size| {
+                            (size / (1024 * 1024)).try_into().unwrap_or(u64::MAX)
+                        }),
+                    );
                 }
                 if cf.min_prefix_size > 0 {
                     rocksdb_options_set_prefix_extractor(
@@ -551,38 +642,55 @@ impl Db {
         Err(CorruptionError::from_missing_column_family_name(name).into())
     }
 
+    /// Returns if the database has been opened in read-write mode
+    pub fn is_writable(&self) -> bool {
+        matches!(self.inner, DbKind::ReadWrite(_))
+    }
+
     #[must_use]
     pub fn snapshot(&self) -> Reader {
+        if let DbKind::ReadOnly(db) = &self.inner {
+            if db.is_secondary {
+                // We try to refresh (and ignore the errors)
+                drop(unsafe { ffi_result!(rocksdb_try_catch_up_with_primary_with_status(db.db)) });
+            }
+        }
+        self.new_shared_snapshot().reader()
+    }
+
+    /// Builds a snapshot that might be kept around and shared between threads
+    ///
+    /// Secondary instances are not supported because their content changes on each catch up with the primary.
+    pub fn shared_snapshot(&self) -> Result<SharedSnapshot, StorageError> {
+        if let DbKind::ReadOnly(db) = &self.inner {
+            if db.is_secondary {
+                return Err(StorageError::Other(
+                    "Snapshots are not supported by secondary instances".into(),
+                ));
+            }
+        }
+        Ok(self.new_shared_snapshot())
+    }
+
+    fn new_shared_snapshot(&self) -> SharedSnapshot {
         unsafe {
             match &self.inner {
+                // The content of read-only databases never changes
                 DbKind::ReadOnly(db) => {
-                    if db.is_secondary {
-                        // We try to refresh (and ignore the errors)
-                        drop(ffi_result!(rocksdb_try_catch_up_with_primary_with_status(
-                            db.db
-                        )));
-                    }
-                    let options = rocksdb_readoptions_create_copy(db.read_options);
-                    Reader {
-                        inner: InnerReader::PlainDb(Arc::clone(db)),
-                        options,
-                    }
+                    SharedSnapshot(InnerSharedSnapshot::PlainDb(Arc::clone(db)))
                 }
                 DbKind::ReadWrite(db) => {
-                    let options = rocksdb_readoptions_create_copy(db.read_options);
                     let snapshot = rocksdb_transactiondb_create_snapshot(db.db);
                     assert!(
                         !snapshot.is_null(),
                         "rocksdb_transactiondb_create_snapshot returned null"
                     );
-                    rocksdb_readoptions_set_snapshot(options, snapshot);
-                    Reader {
-                        inner: InnerReader::TransactionalSnapshot(Rc::new(TransactionalSnapshot {
+                    SharedSnapshot(InnerSharedSnapshot::Transactional(Arc::new(
+                        TransactionalSnapshot {
                             db: Arc::clone(db),
                             snapshot,
-                        })),
-                        options,
-                    }
+                        },
+                    )))
                 }
             }
         }
@@ -738,6 +846,51 @@ impl Db {
         }
     }
 
+    /// Atomically removes all the keys in the given `[start, end)` ranges
+    ///
+    /// It is done outside of any transaction, the caller must ensure that no concurrent write happens in these ranges.
+    pub fn remove_ranges(
+        &self,
+        ranges: &[(&ColumnFamily, &[u8], &[u8])],
+    ) -> Result<(), StorageError> {
+        if let DbKind::ReadWrite(db) = &self.inner {
+            let column_families = ranges.iter().map(|(cf, _, _)| cf.0).collect::<Vec<_>>();
+            let start_keys = ranges
+                .iter()
+                .map(|(_, start, _)| start.as_ptr().cast())
+                .collect::<Vec<_>>();
+            let start_keys_len = ranges
+                .iter()
+                .map(|(_, start, _)| start.len())
+                .collect::<Vec<_>>();
+            let end_keys = ranges
+                .iter()
+                .map(|(_, _, end)| end.as_ptr().cast())
+                .collect::<Vec<_>>();
+            let end_keys_len = ranges
+                .iter()
+                .map(|(_, _, end)| end.len())
+                .collect::<Vec<_>>();
+            unsafe {
+                ffi_result!(rocksdb_transactiondb_delete_ranges_cf_with_status(
+                    db.db,
+                    db.write_options,
+                    column_families.as_ptr(),
+                    start_keys.as_ptr(),
+                    start_keys_len.as_ptr(),
+                    end_keys.as_ptr(),
+                    end_keys_len.as_ptr(),
+                    ranges.len(),
+                ))
+            }?;
+            Ok(())
+        } else {
+            Err(StorageError::Other(
+                "Range removals are only possible on read-write instances".into(),
+            ))
+        }
+    }
+
     pub fn flush(&self) -> Result<(), StorageError> {
         if let DbKind::ReadWrite(db) = &self.inner {
             unsafe {
@@ -777,6 +930,17 @@ impl Db {
         }
     }
 
+    /// Returns the path of a new temporary file in the database directory
+    pub fn new_temporary_file_path(&self) -> Result<PathBuf, StorageError> {
+        if let DbKind::ReadWrite(db) = &self.inner {
+            Ok(db.path.join(random::<u128>().to_string()))
+        } else {
+            Err(StorageError::Other(
+                "Temporary files are only possible on read-write instances".into(),
+            ))
+        }
+    }
+
     pub fn new_sst_file(&self) -> Result<SstFileWriter, StorageError> {
         if let DbKind::ReadWrite(db) = &self.inner {
             let path = db.path.join(random::<u128>().to_string());
@@ -884,7 +1048,7 @@ pub struct Reader {
 
 #[derive(Clone)]
 enum InnerReader {
-    TransactionalSnapshot(Rc<TransactionalSnapshot>),
+    TransactionalSnapshot(Arc<TransactionalSnapshot>),
     Transaction(Weak<*mut rocksdb_transaction_t>),
     PlainDb(Arc<RoDbHandler>),
 }
@@ -894,12 +1058,47 @@ struct TransactionalSnapshot {
     snapshot: *const rocksdb_snapshot_t,
 }
 
+unsafe impl Send for TransactionalSnapshot {}
+
+unsafe impl Sync for TransactionalSnapshot {}
+
 impl Drop for TransactionalSnapshot {
     fn drop(&mut self) {
         unsafe { rocksdb_transactiondb_release_snapshot(self.db.db, self.snapshot) }
     }
 }
 
+/// A snapshot that, unlike [`Reader`], can be sent to other threads
+#[derive(Clone)]
+pub struct SharedSnapshot(InnerSharedSnapshot);
+
+#[derive(Clone)]
+enum InnerSharedSnapshot {
+    Transactional(Arc<TransactionalSnapshot>),
+    PlainDb(Arc<RoDbHandler>),
+}
+
+impl SharedSnapshot {
+    pub fn reader(&self) -> Reader {
+        unsafe {
+            match &self.0 {
+                InnerSharedSnapshot::Transactional(snapshot) => {
+                    let options = rocksdb_readoptions_create_copy(snapshot.db.read_options);
+                    rocksdb_readoptions_set_snapshot(options, snapshot.snapshot);
+                    Reader {
+                        inner: InnerReader::TransactionalSnapshot(Arc::clone(snapshot)),
+                        options,
+                    }
+                }
+                InnerSharedSnapshot::PlainDb(db) => Reader {
+                    inner: InnerReader::PlainDb(Arc::clone(db)),
+                    options: rocksdb_readoptions_create_copy(db.read_options),
+                },
+            }
+        }
+    }
+}
+
 impl Clone for Reader {
     fn clone(&self) -> Self {
         Self {
@@ -995,7 +1194,34 @@ impl Reader {
             }
             found.then_some(bound)
         };
+        self.scan(column_family, prefix, upper_bound)
+    }
+
+    /// Iterates on all the keys greater or equal to `start`
+    pub fn scan_from(
+        &self,
+        column_family: &ColumnFamily,
+        start: &[u8],
+    ) -> Result<Iter, StorageError> {
+        self.scan(column_family, start, None)
+    }
 
+    /// Iterates on all the keys greater or equal to `start` and lower than `end`
+    pub fn scan_range(
+        &self,
+        column_family: &ColumnFamily,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<Iter, StorageError> {
+        self.scan(column_family, start, Some(end.to_vec()))
+    }
+
+    fn scan(
+        &self,
+        column_family: &ColumnFamily,
+        start: &[u8],
+        upper_bound: Option<Vec<u8>>,
+    ) -> Result<Iter, StorageError> {
         unsafe {
             let options = rocksdb_readoptions_create_copy(self.options);
             assert!(
@@ -1026,10 +1252,10 @@ impl Reader {
                 }
             };
             assert!(!iter.is_null(), "rocksdb_create_iterator returned null");
-            if prefix.is_empty() {
+            if start.is_empty() {
                 rocksdb_iter_seek_to_first(iter);
             } else {
-                rocksdb_iter_seek(iter, prefix.as_ptr().cast(), prefix.len());
+                rocksdb_iter_seek(iter, start.as_ptr().cast(), start.len());
             }
             let is_currently_valid = rocksdb_iter_valid(iter) != 0;
             Ok(Iter {
@@ -1274,6 +1500,18 @@ impl Iter {
             None
         }
     }
+
+    pub fn value(&self) -> Option<&[u8]> {
+        if self.is_valid() {
+            unsafe {
+                let mut len = 0;
+                let val = rocksdb_iter_value(self.iter, &mut len);
+                Some(slice::from_raw_parts(val.cast(), len))
+            }
+        } else {
+            None
+        }
+    }
 }
 
 pub struct SstFileWriter {
@@ -1315,6 +1553,144 @@ impl SstFileWriter {
     }
 }
 
+/// Information about a backup stored in an incremental backups directory.
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct BackupInfo {
+    /// The backup identifier, increasing with each new backup.
+    pub id: u32,
+    /// When the backup has been created.
+    pub timestamp: SystemTime,
+    /// The size of the backup in bytes, including the files shared with the other backups.
+    pub size: u64,
+    /// The number of files of the backup, including the files shared with the other backups.
+    pub number_of_files: u32,
+}
+
+/// A directory of incremental backups: the files shared by multiple backups are only stored once.
+pub struct BackupEngine(*mut rocksdb_backup_engine_t);
+
+unsafe impl Send for BackupEngine {}
+
+impl Drop for BackupEngine {
+    fn drop(&mut self) {
+        unsafe {
+            rocksdb_backup_engine_close(self.0);
+        }
+    }
+}
+
+impl BackupEngine {
+    /// Opens the backups directory and creates it if it does not exist
+    pub fn open(backup_directory: &Path) -> Result<Self, StorageError> {
+        let c_path = path_to_cstring(backup_directory)?;
+        unsafe {
+            let options = rocksdb_options_create();
+            assert!(!options.is_null(), "rocksdb_options_create returned null");
+            let engine = ffi_result!(rocksdb_backup_engine_open_with_status(
+                options,
+                c_path.as_ptr()
+            ));
+            rocksdb_options_destroy(options);
+            let engine = engine?;
+            assert!(
+                !engine.is_null(),
+                "rocksdb_backup_engine_open_with_status returned null"
+            );
+            Ok(Self(engine))
+        }
+    }
+
+    /// Creates a new backup of the database, only the files not already in the backups directory are copied
+    pub fn create_new_backup(&self, db: &Db) -> Result<u32, StorageError> {
+        match &db.inner {
+            DbKind::ReadOnly(db) => unsafe {
+                if db.is_secondary {
+                    ffi_result!(rocksdb_try_catch_up_with_primary_with_status(db.db))?;
+                }
+                Ok(ffi_result!(
+                    rocksdb_backup_engine_create_new_backup_flush_with_status(self.0, db.db, 0)
+                )?)
+            },
+            DbKind::ReadWrite(db) => {
+                if db.in_memory {
+                    return Err(StorageError::Other(
+                        "It is not possible to backup an in-memory database".into(),
+                    ));
+                }
+                unsafe {
+                    Ok(ffi_result!(
+                        rocksdb_transactiondb_create_new_backup_with_status(self.0, db.db)
+                    )?)
+                }
+            }
+        }
+    }
+
+    /// Returns the stored backups from the oldest to the newest
+    pub fn backups(&self) -> Vec<BackupInfo> {
+        unsafe {
+            let info = rocksdb_backup_engine_get_backup_info(self.0);
+            assert!(
+                !info.is_null(),
+                "rocksdb_backup_engine_get_backup_info returned null"
+            );
+            let backups = (0..rocksdb_backup_engine_info_count(info))
+                .map(|i| BackupInfo {
+                    id: rocksdb_backup_engine_info_backup_id(info, i),
+                    timestamp: UNIX_EPOCH
+                        + Duration::from_secs(
+                            rocksdb_backup_engine_info_timestamp(info, i)
+                                .try_into()
+                                .unwrap_or(0),
+                        ),
+                    size: rocksdb_backup_engine_info_size(info, i),
+                    number_of_files: rocksdb_backup_engine_info_number_files(info, i),
+                })
+                .collect();
+            rocksdb_backup_engine_info_destroy(info);
+            backups
+        }
+    }
+
+    /// Removes the oldest backups to only keep the `num_backups_to_keep` newest ones
+    pub fn purge_old_backups(&self, num_backups_to_keep: u32) -> Result<(), StorageError> {
+        unsafe {
+            ffi_result!(rocksdb_backup_engine_purge_old_backups_with_status(
+                self.0,
+                num_backups_to_keep
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the backup files exist and have the expected checksums
+    pub fn verify_backup(&self, backup_id: u32) -> Result<(), StorageError> {
+        unsafe {
+            ffi_result!(rocksdb_backup_engine_verify_backup_with_status(
+                self.0, backup_id
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Restores the given backup into the `target_directory`
+    pub fn restore_backup(
+        &self,
+        backup_id: u32,
+        target_directory: &Path,
+    ) -> Result<(), StorageError> {
+        let c_path = path_to_cstring(target_directory)?;
+        unsafe {
+            ffi_result!(rocksdb_backup_engine_restore_db_from_backup_with_status(
+                self.0,
+                c_path.as_ptr(),
+                backup_id
+            ))?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(thiserror::Error)]
 #[error("{}", self.message())]
 struct ErrorStatus(rocksdb_status_t);
@@ -1,12 +1,16 @@
+#[cfg(not(target_family = "wasm"))]
+use crate::model::vocab::xsd;
 use crate::storage::error::{CorruptionError, StorageError};
-use crate::storage::numeric_encoder::{EncodedQuad, EncodedTerm, EncodedTriple, StrHash};
+use crate::storage::numeric_encoder::{
+    DerivedIntegerDatatype, EncodedQuad, EncodedTerm, EncodedTriple, StrHash,
+};
 use crate::storage::small_string::SmallString;
 use oxsdatatypes::*;
 use std::io::Read;
 use std::mem::size_of;
 
 #[cfg(not(target_family = "wasm"))]
-pub const LATEST_STORAGE_VERSION: u64 = 1;
+pub const LATEST_STORAGE_VERSION: u64 = 3;
 pub const WRITTEN_TERM_MAX_SIZE: usize = size_of::<u8>() + 2 * size_of::<StrHash>();
 
 // Encoded term type blocks
@@ -23,6 +27,8 @@ const TYPE_SMALL_BLANK_NODE_ID: u8 = 9;
 const TYPE_BIG_BLANK_NODE_ID: u8 = 10;
 const TYPE_SMALL_STRING_LITERAL: u8 = 16;
 const TYPE_BIG_STRING_LITERAL: u8 = 17;
+const TYPE_SMALL_ANY_URI_LITERAL: u8 = 18;
+const TYPE_BIG_ANY_URI_LITERAL: u8 = 19;
 const TYPE_SMALL_SMALL_LANG_STRING_LITERAL: u8 = 20;
 const TYPE_SMALL_BIG_LANG_STRING_LITERAL: u8 = 21;
 const TYPE_BIG_SMALL_LANG_STRING_LITERAL: u8 = 22;
@@ -46,8 +52,31 @@ const TYPE_G_MONTH_LITERAL: u8 = 41;
 const TYPE_DURATION_LITERAL: u8 = 42;
 const TYPE_YEAR_MONTH_DURATION_LITERAL: u8 = 43;
 const TYPE_DAY_TIME_DURATION_LITERAL: u8 = 44;
+const TYPE_DERIVED_INTEGER_LITERAL: u8 = 45;
 const TYPE_TRIPLE: u8 = 48;
 
+/// The prefixes of the terms whose encoding changed in the storage version 2:
+/// the numeric, date, time and duration literals and the triples that might contain them
+#[cfg(not(target_family = "wasm"))]
+pub fn v2_upgraded_term_prefixes() -> Vec<Vec<u8>> {
+    (TYPE_FLOAT_LITERAL..=TYPE_DAY_TIME_DURATION_LITERAL)
+        .chain([TYPE_TRIPLE])
+        .map(|type_id| vec![type_id])
+        .collect()
+}
+
+/// The prefixes of the terms whose encoding changed in the storage version 3:
+/// the typed literals with the `xsd:anyURI` datatype and the triples that might contain them
+#[cfg(not(target_family = "wasm"))]
+pub fn v3_upgraded_term_prefixes() -> Vec<Vec<u8>> {
+    let any_uri_id = StrHash::new(xsd::ANY_URI.as_str()).to_be_bytes();
+    vec![
+        [[TYPE_SMALL_TYPED_LITERAL].as_slice(), &any_uri_id].concat(),
+        [[TYPE_BIG_TYPED_LITERAL].as_slice(), &any_uri_id].concat(),
+        vec![TYPE_TRIPLE],
+    ]
+}
+
 #[derive(Clone, Copy)]
 pub enum QuadEncoding {
     Spog,
@@ -311,83 +340,87 @@ impl<R: Read> TermReader for R {
                     value_id: StrHash::from_be_bytes(buffer),
                 })
             }
+            TYPE_SMALL_ANY_URI_LITERAL => {
+                let mut buffer = [0; 16];
+                self.read_exact(&mut buffer)?;
+                Ok(EncodedTerm::SmallAnyUriLiteral(
+                    SmallString::from_be_bytes(buffer).map_err(CorruptionError::new)?,
+                ))
+            }
+            TYPE_BIG_ANY_URI_LITERAL => {
+                let mut buffer = [0; 16];
+                self.read_exact(&mut buffer)?;
+                Ok(EncodedTerm::BigAnyUriLiteral {
+                    value_id: StrHash::from_be_bytes(buffer),
+                })
+            }
             TYPE_BOOLEAN_LITERAL_TRUE => Ok(true.into()),
             TYPE_BOOLEAN_LITERAL_FALSE => Ok(false.into()),
             TYPE_FLOAT_LITERAL => {
-                let mut buffer = [0; 4];
-                self.read_exact(&mut buffer)?;
-                Ok(Float::from_be_bytes(buffer).into())
+                Ok(Float::from_be_bytes(read_ordered_value(self, TYPE_FLOAT_LITERAL)?).into())
             }
             TYPE_DOUBLE_LITERAL => {
-                let mut buffer = [0; 8];
-                self.read_exact(&mut buffer)?;
-                Ok(Double::from_be_bytes(buffer).into())
+                Ok(Double::from_be_bytes(read_ordered_value(self, TYPE_DOUBLE_LITERAL)?).into())
             }
             TYPE_INTEGER_LITERAL => {
-                let mut buffer = [0; 8];
-                self.read_exact(&mut buffer)?;
-                Ok(Integer::from_be_bytes(buffer).into())
+                Ok(Integer::from_be_bytes(read_ordered_value(self, TYPE_INTEGER_LITERAL)?).into())
             }
-            TYPE_DECIMAL_LITERAL => {
-                let mut buffer = [0; 16];
-                self.read_exact(&mut buffer)?;
-                Ok(Decimal::from_be_bytes(buffer).into())
+            TYPE_DERIVED_INTEGER_LITERAL => {
+                let value =
+                    Integer::from_be_bytes(read_ordered_value(self, TYPE_DERIVED_INTEGER_LITERAL)?);
+                let mut datatype_buffer = [0];
+                self.read_exact(&mut datatype_buffer)?;
+                Ok(EncodedTerm::DerivedIntegerLiteral {
+                    value,
+                    datatype: decode_derived_integer_datatype(datatype_buffer[0])?,
+                })
             }
-            TYPE_DATE_TIME_LITERAL => {
-                let mut buffer = [0; 18];
-                self.read_exact(&mut buffer)?;
-                Ok(DateTime::from_be_bytes(buffer).into())
+            TYPE_DECIMAL_LITERAL => {
+                Ok(Decimal::from_be_bytes(read_ordered_value(self, TYPE_DECIMAL_LITERAL)?).into())
             }
+            TYPE_DATE_TIME_LITERAL => Ok(DateTime::from_be_bytes(read_ordered_value(
+                self,
+                TYPE_DATE_TIME_LITERAL,
+            )?)
+            .into()),
             TYPE_TIME_LITERAL => {
-                let mut buffer = [0; 18];
-                self.read_exact(&mut buffer)?;
-                Ok(Time::from_be_bytes(buffer).into())
+                Ok(Time::from_be_bytes(read_ordered_value(self, TYPE_TIME_LITERAL)?).into())
             }
             TYPE_DATE_LITERAL => {
-                let mut buffer = [0; 18];
-                self.read_exact(&mut buffer)?;
-                Ok(Date::from_be_bytes(buffer).into())
-            }
-            TYPE_G_YEAR_MONTH_LITERAL => {
-                let mut buffer = [0; 18];
-                self.read_exact(&mut buffer)?;
-                Ok(GYearMonth::from_be_bytes(buffer).into())
+                Ok(Date::from_be_bytes(read_ordered_value(self, TYPE_DATE_LITERAL)?).into())
             }
+            TYPE_G_YEAR_MONTH_LITERAL => Ok(GYearMonth::from_be_bytes(read_ordered_value(
+                self,
+                TYPE_G_YEAR_MONTH_LITERAL,
+            )?)
+            .into()),
             TYPE_G_YEAR_LITERAL => {
-                let mut buffer = [0; 18];
-                self.read_exact(&mut buffer)?;
-                Ok(GYear::from_be_bytes(buffer).into())
-            }
-            TYPE_G_MONTH_DAY_LITERAL => {
-                let mut buffer = [0; 18];
-                self.read_exact(&mut buffer)?;
-                Ok(GMonthDay::from_be_bytes(buffer).into())
+                Ok(GYear::from_be_bytes(read_ordered_value(self, TYPE_G_YEAR_LITERAL)?).into())
             }
+            TYPE_G_MONTH_DAY_LITERAL => Ok(GMonthDay::from_be_bytes(read_ordered_value(
+                self,
+                TYPE_G_MONTH_DAY_LITERAL,
+            )?)
+            .into()),
             TYPE_G_DAY_LITERAL => {
-                let mut buffer = [0; 18];
-                self.read_exact(&mut buffer)?;
-                Ok(GDay::from_be_bytes(buffer).into())
+                Ok(GDay::from_be_bytes(read_ordered_value(self, TYPE_G_DAY_LITERAL)?).into())
             }
             TYPE_G_MONTH_LITERAL => {
-                let mut buffer = [0; 18];
-                self.read_exact(&mut buffer)?;
-                Ok(GMonth::from_be_bytes(buffer).into())
-            }
-            TYPE_DURATION_LITERAL => {
-                let mut buffer = [0; 24];
-                self.read_exact(&mut buffer)?;
-                Ok(Duration::from_be_bytes(buffer).into())
-            }
-            TYPE_YEAR_MONTH_DURATION_LITERAL => {
-                let mut buffer = [0; 8];
-                self.read_exact(&mut buffer)?;
-                Ok(YearMonthDuration::from_be_bytes(buffer).into())
-            }
-            TYPE_DAY_TIME_DURATION_LITERAL => {
-                let mut buffer = [0; 16];
-                self.read_exact(&mut buffer)?;
-                Ok(DayTimeDuration::from_be_bytes(buffer).into())
+                Ok(GMonth::from_be_bytes(read_ordered_value(self, TYPE_G_MONTH_LITERAL)?).into())
             }
+            TYPE_DURATION_LITERAL => Ok(Duration::from_be_bytes(read_ordered_value(
+                self,
+                TYPE_DURATION_LITERAL,
+            )?)
+            .into()),
+            TYPE_YEAR_MONTH_DURATION_LITERAL => Ok(YearMonthDuration::from_be_bytes(
+                read_ordered_value(self, TYPE_YEAR_MONTH_DURATION_LITERAL)?,
+            )
+            .into()),
+            TYPE_DAY_TIME_DURATION_LITERAL => Ok(DayTimeDuration::from_be_bytes(
+                read_ordered_value(self, TYPE_DAY_TIME_DURATION_LITERAL)?,
+            )
+            .into()),
             TYPE_TRIPLE => Ok(EncodedTriple {
                 subject: self.read_term()?,
                 predicate: self.read_term()?,
@@ -399,6 +432,15 @@ impl<R: Read> TermReader for R {
     }
 }
 
+/// Decodes a quad written by [`write_spog_quad`], the default graph being written as an empty graph name
+pub fn decode_spog_quad(mut buffer: &[u8]) -> Result<EncodedQuad, StorageError> {
+    let mut quad = buffer.read_dspo_quad()?;
+    if !buffer.is_empty() {
+        quad.graph_name = buffer.read_term()?;
+    }
+    Ok(quad)
+}
+
 pub fn write_spog_quad(sink: &mut Vec<u8>, quad: &EncodedQuad) {
     write_term(sink, &quad.subject);
     write_term(sink, &quad.predicate);
@@ -521,6 +563,14 @@ pub fn write_term(sink: &mut Vec<u8>, term: &EncodedTerm) {
             sink.push(TYPE_BIG_STRING_LITERAL);
             sink.extend_from_slice(&value_id.to_be_bytes());
         }
+        EncodedTerm::SmallAnyUriLiteral(value) => {
+            sink.push(TYPE_SMALL_ANY_URI_LITERAL);
+            sink.extend_from_slice(&value.to_be_bytes())
+        }
+        EncodedTerm::BigAnyUriLiteral { value_id } => {
+            sink.push(TYPE_BIG_ANY_URI_LITERAL);
+            sink.extend_from_slice(&value_id.to_be_bytes());
+        }
         EncodedTerm::SmallSmallLangStringLiteral { value, language } => {
             sink.push(TYPE_SMALL_SMALL_LANG_STRING_LITERAL);
             sink.extend_from_slice(&language.to_be_bytes());
@@ -563,64 +613,54 @@ pub fn write_term(sink: &mut Vec<u8>, term: &EncodedTerm) {
             TYPE_BOOLEAN_LITERAL_FALSE
         }),
         EncodedTerm::FloatLiteral(value) => {
-            sink.push(TYPE_FLOAT_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_FLOAT_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::DoubleLiteral(value) => {
-            sink.push(TYPE_DOUBLE_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_DOUBLE_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::IntegerLiteral(value) => {
-            sink.push(TYPE_INTEGER_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_INTEGER_LITERAL, value.to_be_bytes())
+        }
+        EncodedTerm::DerivedIntegerLiteral { value, datatype } => {
+            // The datatype is written after the value to sort all the derived integers by value
+            write_ordered_value(sink, TYPE_DERIVED_INTEGER_LITERAL, value.to_be_bytes());
+            sink.push(encode_derived_integer_datatype(*datatype));
         }
         EncodedTerm::DecimalLiteral(value) => {
-            sink.push(TYPE_DECIMAL_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_DECIMAL_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::DateTimeLiteral(value) => {
-            sink.push(TYPE_DATE_TIME_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_DATE_TIME_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::TimeLiteral(value) => {
-            sink.push(TYPE_TIME_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_TIME_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::DurationLiteral(value) => {
-            sink.push(TYPE_DURATION_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_DURATION_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::DateLiteral(value) => {
-            sink.push(TYPE_DATE_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_DATE_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::GYearMonthLiteral(value) => {
-            sink.push(TYPE_G_YEAR_MONTH_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_G_YEAR_MONTH_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::GYearLiteral(value) => {
-            sink.push(TYPE_G_YEAR_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_G_YEAR_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::GMonthDayLiteral(value) => {
-            sink.push(TYPE_G_MONTH_DAY_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_G_MONTH_DAY_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::GDayLiteral(value) => {
-            sink.push(TYPE_G_DAY_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_G_DAY_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::GMonthLiteral(value) => {
-            sink.push(TYPE_G_MONTH_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_G_MONTH_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::YearMonthDurationLiteral(value) => {
-            sink.push(TYPE_YEAR_MONTH_DURATION_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_YEAR_MONTH_DURATION_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::DayTimeDurationLiteral(value) => {
-            sink.push(TYPE_DAY_TIME_DURATION_LITERAL);
-            sink.extend_from_slice(&value.to_be_bytes())
+            write_ordered_value(sink, TYPE_DAY_TIME_DURATION_LITERAL, value.to_be_bytes())
         }
         EncodedTerm::Triple(value) => {
             sink.push(TYPE_TRIPLE);
@@ -631,6 +671,232 @@ pub fn write_term(sink: &mut Vec<u8>, term: &EncodedTerm) {
     }
 }
 
+/// Encodes the part of a literal that determines its position in the value order.
+///
+/// It is the term encoding without the timezone offset of the date and time literals
+/// and without the datatype of the integers derived from `xsd:integer`:
+/// all the literals of the same type whose value is between two literals have their encoding
+/// between the order keys of these two literals (the second one being used as a prefix).
+pub fn encode_term_order_key(term: &EncodedTerm) -> Vec<u8> {
+    let mut key = encode_term(term);
+    match key.first() {
+        Some(TYPE_DATE_TIME_LITERAL..=TYPE_G_MONTH_LITERAL) => {
+            key.truncate(1 + size_of::<Decimal>())
+        }
+        Some(&TYPE_DERIVED_INTEGER_LITERAL) => key.truncate(1 + size_of::<Integer>()),
+        _ => (),
+    }
+    key
+}
+
+fn encode_derived_integer_datatype(datatype: DerivedIntegerDatatype) -> u8 {
+    match datatype {
+        DerivedIntegerDatatype::Byte => 1,
+        DerivedIntegerDatatype::Short => 2,
+        DerivedIntegerDatatype::Int => 3,
+        DerivedIntegerDatatype::Long => 4,
+        DerivedIntegerDatatype::UnsignedByte => 5,
+        DerivedIntegerDatatype::UnsignedShort => 6,
+        DerivedIntegerDatatype::UnsignedInt => 7,
+        DerivedIntegerDatatype::UnsignedLong => 8,
+        DerivedIntegerDatatype::PositiveInteger => 9,
+        DerivedIntegerDatatype::NegativeInteger => 10,
+        DerivedIntegerDatatype::NonPositiveInteger => 11,
+        DerivedIntegerDatatype::NonNegativeInteger => 12,
+    }
+}
+
+fn decode_derived_integer_datatype(id: u8) -> Result<DerivedIntegerDatatype, StorageError> {
+    Ok(match id {
+        1 => DerivedIntegerDatatype::Byte,
+        2 => DerivedIntegerDatatype::Short,
+        3 => DerivedIntegerDatatype::Int,
+        4 => DerivedIntegerDatatype::Long,
+        5 => DerivedIntegerDatatype::UnsignedByte,
+        6 => DerivedIntegerDatatype::UnsignedShort,
+        7 => DerivedIntegerDatatype::UnsignedInt,
+        8 => DerivedIntegerDatatype::UnsignedLong,
+        9 => DerivedIntegerDatatype::PositiveInteger,
+        10 => DerivedIntegerDatatype::NegativeInteger,
+        11 => DerivedIntegerDatatype::NonPositiveInteger,
+        12 => DerivedIntegerDatatype::NonNegativeInteger,
+        _ => {
+            return Err(
+                CorruptionError::msg("the term buffer has an invalid integer datatype id").into(),
+            )
+        }
+    })
+}
+
+/// Writes the big endian bytes of a literal value so that the byte order follows the value order
+fn write_ordered_value<const N: usize>(sink: &mut Vec<u8>, type_id: u8, mut value: [u8; N]) {
+    to_ordered_bytes(type_id, &mut value);
+    sink.push(type_id);
+    sink.extend_from_slice(&value);
+}
+
+/// Reads the big endian bytes of a literal value written by [`write_ordered_value`]
+fn read_ordered_value<const N: usize>(
+    read: &mut impl Read,
+    type_id: u8,
+) -> Result<[u8; N], StorageError> {
+    let mut value = [0; N];
+    read.read_exact(&mut value)?;
+    from_ordered_bytes(type_id, &mut value);
+    Ok(value)
+}
+
+/// Maps the big endian bytes of a number to bytes sorted in the same order as the numbers.
+///
+/// The sign bit of the signed integers is flipped.
+/// All the bits of the negative floats are flipped and the sign bit of the positive ones is set.
+/// The date and times are a signed decimal followed by the timezone offset
+/// and the durations a signed number of months followed by a signed decimal.
+fn to_ordered_bytes(type_id: u8, value: &mut [u8]) {
+    match type_id {
+        TYPE_FLOAT_LITERAL | TYPE_DOUBLE_LITERAL => {
+            if value[0] & 0x80 == 0 {
+                value[0] |= 0x80;
+            } else {
+                for byte in value {
+                    *byte = !*byte;
+                }
+            }
+        }
+        TYPE_DURATION_LITERAL => {
+            value[0] ^= 0x80;
+            value[size_of::<i64>()] ^= 0x80;
+        }
+        _ => value[0] ^= 0x80,
+    }
+}
+
+/// Reverses [`to_ordered_bytes`]
+fn from_ordered_bytes(type_id: u8, value: &mut [u8]) {
+    match type_id {
+        TYPE_FLOAT_LITERAL | TYPE_DOUBLE_LITERAL => {
+            if value[0] & 0x80 == 0 {
+                for byte in value {
+                    *byte = !*byte;
+                }
+            } else {
+                value[0] &= 0x7F;
+            }
+        }
+        _ => to_ordered_bytes(type_id, value),
+    }
+}
+
+/// Rewrites a sequence of terms written by the storage version 1 into the current encoding.
+///
+/// The version 1 wrote the literal values as plain big endian bytes.
+#[cfg(not(target_family = "wasm"))]
+pub fn upgrade_v1_terms(terms: &[u8]) -> Result<Vec<u8>, StorageError> {
+    let mut upgraded = terms.to_vec();
+    rewrite_literal_values_in_place(&mut upgraded, to_ordered_bytes)?;
+    Ok(upgraded)
+}
+
+/// Rewrites a sequence of terms written with the current encoding into the encoding of the storage version 1.
+///
+/// Reverses [`upgrade_v1_terms`].
+#[cfg(not(target_family = "wasm"))]
+pub fn downgrade_to_v1_terms(terms: &[u8]) -> Result<Vec<u8>, StorageError> {
+    let mut downgraded = terms.to_vec();
+    rewrite_literal_values_in_place(&mut downgraded, from_ordered_bytes)?;
+    Ok(downgraded)
+}
+
+/// Rewrites a sequence of terms written by the storage version 2 into the current encoding.
+///
+/// The version 2 wrote the `xsd:anyURI` literals like the other typed literals.
+/// It wrote the integers of the datatypes derived from `xsd:integer` as `xsd:integer` literals:
+/// their datatype is lost and they stay `xsd:integer` literals.
+#[cfg(not(target_family = "wasm"))]
+pub fn upgrade_v2_terms(terms: &[u8]) -> Result<Vec<u8>, StorageError> {
+    let any_uri_id = StrHash::new(xsd::ANY_URI.as_str());
+    rewrite_terms(terms, &|term| match term {
+        EncodedTerm::SmallTypedLiteral { value, datatype_id } if datatype_id == any_uri_id => {
+            EncodedTerm::SmallAnyUriLiteral(value)
+        }
+        EncodedTerm::BigTypedLiteral {
+            value_id,
+            datatype_id,
+        } if datatype_id == any_uri_id => EncodedTerm::BigAnyUriLiteral { value_id },
+        _ => term,
+    })
+}
+
+/// Rewrites a sequence of terms written with the current encoding into the encoding of the storage version 2.
+///
+/// Reverses [`upgrade_v2_terms`] and writes the integers of the datatypes derived from `xsd:integer` as `xsd:integer` literals.
+/// The `xsd:anyURI` datatype IRI must be added to the string store.
+#[cfg(not(target_family = "wasm"))]
+pub fn downgrade_to_v2_terms(terms: &[u8]) -> Result<Vec<u8>, StorageError> {
+    let any_uri_id = StrHash::new(xsd::ANY_URI.as_str());
+    rewrite_terms(terms, &|term| match term {
+        EncodedTerm::SmallAnyUriLiteral(value) => EncodedTerm::SmallTypedLiteral {
+            value,
+            datatype_id: any_uri_id,
+        },
+        EncodedTerm::BigAnyUriLiteral { value_id } => EncodedTerm::BigTypedLiteral {
+            value_id,
+            datatype_id: any_uri_id,
+        },
+        EncodedTerm::DerivedIntegerLiteral { value, .. } => EncodedTerm::IntegerLiteral(value),
+        _ => term,
+    })
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn rewrite_terms(
+    mut terms: &[u8],
+    rewrite: &dyn Fn(EncodedTerm) -> EncodedTerm,
+) -> Result<Vec<u8>, StorageError> {
+    let mut rewritten = Vec::with_capacity(terms.len());
+    while !terms.is_empty() {
+        write_term(&mut rewritten, &rewrite_term(terms.read_term()?, rewrite));
+    }
+    Ok(rewritten)
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn rewrite_term(term: EncodedTerm, rewrite: &dyn Fn(EncodedTerm) -> EncodedTerm) -> EncodedTerm {
+    if let EncodedTerm::Triple(triple) = term {
+        EncodedTriple::new(
+            rewrite_term(triple.subject.clone(), rewrite),
+            rewrite_term(triple.predicate.clone(), rewrite),
+            rewrite_term(triple.object.clone(), rewrite),
+        )
+        .into()
+    } else {
+        rewrite(term)
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn rewrite_literal_values_in_place(
+    mut terms: &mut [u8],
+    rewrite: fn(u8, &mut [u8]),
+) -> Result<(), StorageError> {
+    while !terms.is_empty() {
+        // The term layout has not changed, only the bytes of the values
+        let mut rest = &*terms;
+        rest.read_term()?;
+        let term_len = terms.len() - rest.len();
+        let (term, rest) = std::mem::take(&mut terms).split_at_mut(term_len);
+        if let Some((type_id, value)) = term.split_first_mut() {
+            match *type_id {
+                TYPE_FLOAT_LITERAL..=TYPE_DAY_TIME_DURATION_LITERAL => rewrite(*type_id, value),
+                TYPE_TRIPLE => rewrite_literal_values_in_place(value, rewrite)?,
+                _ => (),
+            }
+        }
+        terms = rest;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::panic_in_result_fn)]
@@ -716,6 +982,20 @@ mod tests {
             Literal::new_typed_literal("PT1S", xsd::DURATION).into(),
             Literal::new_typed_literal("PT1S", xsd::DAY_TIME_DURATION).into(),
             Literal::new_typed_literal("P1Y", xsd::YEAR_MONTH_DURATION).into(),
+            Literal::new_typed_literal("-12", xsd::INT).into(),
+            Literal::new_typed_literal("255", xsd::UNSIGNED_BYTE).into(),
+            Literal::new_typed_literal("256", xsd::UNSIGNED_BYTE).into(),
+            Literal::new_typed_literal("0", xsd::POSITIVE_INTEGER).into(),
+            Literal::new_typed_literal("http://foo.com", xsd::ANY_URI).into(),
+            Literal::new_typed_literal(
+                "http://foo.com/thisisaverybiganyurisharingitsstringwithanamednode",
+                xsd::ANY_URI,
+            )
+            .into(),
+            NamedNode::new_unchecked(
+                "http://foo.com/thisisaverybiganyurisharingitsstringwithanamednode",
+            )
+            .into(),
             Literal::new_typed_literal("-foo", NamedNode::new_unchecked("http://foo.com")).into(),
             Literal::new_typed_literal(
                 "-foo-thisisaverybigtypedliteralwiththefoodatatype",
@@ -740,4 +1020,159 @@ mod tests {
             assert_eq!(encoded, buffer.as_slice().read_term().unwrap());
         }
     }
+
+    #[test]
+    fn test_encoding_order() {
+        let sorted_terms: Vec<Vec<EncodedTerm>> = vec![
+            [
+                f32::NEG_INFINITY,
+                -1e10,
+                -1.5,
+                -0.,
+                0.,
+                1e-40,
+                1.5,
+                1e10,
+                f32::INFINITY,
+            ]
+            .into_iter()
+            .map(|v| Float::from(v).into())
+            .collect(),
+            [
+                f64::NEG_INFINITY,
+                -1e300,
+                -1.,
+                -1e-300,
+                0.,
+                1e-300,
+                1.,
+                f64::INFINITY,
+            ]
+            .into_iter()
+            .map(|v| Double::from(v).into())
+            .collect(),
+            [i64::MIN, -256, -1, 0, 1, 255, 256, i64::MAX]
+                .into_iter()
+                .map(|v| Integer::from(v).into())
+                .collect(),
+            [
+                (i64::MIN, DerivedIntegerDatatype::Long),
+                (-1, DerivedIntegerDatatype::Int),
+                (-1, DerivedIntegerDatatype::NegativeInteger),
+                (0, DerivedIntegerDatatype::UnsignedByte),
+                (1, DerivedIntegerDatatype::Byte),
+            ]
+            .into_iter()
+            .map(|(value, datatype)| EncodedTerm::DerivedIntegerLiteral {
+                value: value.into(),
+                datatype,
+            })
+            .collect(),
+            [
+                Decimal::MIN,
+                Decimal::from(-2),
+                Decimal::from(0),
+                "0.5".parse().unwrap(),
+                Decimal::MAX,
+            ]
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+            [
+                "-2000-01-01T00:00:00Z",
+                "1969-12-31T23:59:59Z",
+                "1970-01-01T00:00:00Z",
+                "2020-01-01T00:00:00+01:00",
+                "2020-01-01T00:00:00Z",
+            ]
+            .into_iter()
+            .map(|v| v.parse::<DateTime>().unwrap().into())
+            .collect(),
+            ["-P1D", "PT0S", "PT1S", "P1D"]
+                .into_iter()
+                .map(|v| v.parse::<DayTimeDuration>().unwrap().into())
+                .collect(),
+        ];
+        for terms in sorted_terms {
+            let encoded = terms.iter().map(encode_term).collect::<Vec<_>>();
+            for (term, encoded) in terms.iter().zip(&encoded) {
+                assert_eq!(*term, decode_term(encoded).unwrap());
+            }
+            for (previous, next) in encoded.iter().zip(&encoded[1..]) {
+                assert!(previous < next, "{previous:?} is not before {next:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_upgrade_v1_terms() {
+        let integer = Integer::from(-1);
+        let date_time = "2020-01-01T00:00:00Z".parse::<DateTime>().unwrap();
+        let mut v1_quad = vec![TYPE_TRIPLE, TYPE_DOUBLE_LITERAL];
+        v1_quad.extend_from_slice(&Double::from(-2.).to_be_bytes());
+        v1_quad.push(TYPE_BOOLEAN_LITERAL_TRUE);
+        v1_quad.push(TYPE_INTEGER_LITERAL);
+        v1_quad.extend_from_slice(&integer.to_be_bytes());
+        v1_quad.push(TYPE_SMALL_STRING_LITERAL);
+        v1_quad.extend_from_slice(&SmallString::try_from("foo").unwrap().to_be_bytes());
+        v1_quad.push(TYPE_DATE_TIME_LITERAL);
+        v1_quad.extend_from_slice(&date_time.to_be_bytes());
+
+        let quad = EncodedQuad::new(
+            EncodedTriple::new(Double::from(-2.).into(), true.into(), integer.into()).into(),
+            EncodedTerm::SmallStringLiteral(SmallString::try_from("foo").unwrap()),
+            date_time.into(),
+            EncodedTerm::DefaultGraph,
+        );
+        let mut v2_quad = Vec::new();
+        write_spog_quad(&mut v2_quad, &quad);
+        assert_eq!(upgrade_v1_terms(&v1_quad).unwrap(), v2_quad);
+        assert_eq!(downgrade_to_v1_terms(&v2_quad).unwrap(), v1_quad);
+    }
+
+    #[test]
+    fn test_upgrade_v2_terms() {
+        let any_uri = SmallString::try_from("http://foo.com").unwrap();
+        let value_id = StrHash::new("http://foo.com/thisisaverybiganyuri");
+        let any_uri_id = StrHash::new(xsd::ANY_URI.as_str());
+        let v2_quad = encode_term_quad(
+            &EncodedTriple::new(
+                EncodedTerm::SmallBlankNode(SmallString::try_from("foo").unwrap()),
+                EncodedTerm::NamedNode { iri_id: value_id },
+                EncodedTerm::SmallTypedLiteral {
+                    value: any_uri,
+                    datatype_id: any_uri_id,
+                },
+            )
+            .into(),
+            &EncodedTerm::NamedNode { iri_id: value_id },
+            &EncodedTerm::BigTypedLiteral {
+                value_id,
+                datatype_id: any_uri_id,
+            },
+            &Integer::from(12).into(),
+        );
+        let v3_quad = encode_term_quad(
+            &EncodedTriple::new(
+                EncodedTerm::SmallBlankNode(SmallString::try_from("foo").unwrap()),
+                EncodedTerm::NamedNode { iri_id: value_id },
+                EncodedTerm::SmallAnyUriLiteral(any_uri),
+            )
+            .into(),
+            &EncodedTerm::NamedNode { iri_id: value_id },
+            &EncodedTerm::BigAnyUriLiteral { value_id },
+            &Integer::from(12).into(),
+        );
+        assert_eq!(upgrade_v2_terms(&v2_quad).unwrap(), v3_quad);
+        assert_eq!(downgrade_to_v2_terms(&v3_quad).unwrap(), v2_quad);
+
+        let derived = encode_term(&EncodedTerm::DerivedIntegerLiteral {
+            value: Integer::from(12),
+            datatype: DerivedIntegerDatatype::Int,
+        });
+        assert_eq!(
+            downgrade_to_v2_terms(&derived).unwrap(),
+            encode_term(&Integer::from(12).into())
+        );
+    }
 }
@@ -0,0 +1,179 @@
+//! Binary image of the storage key-value content.
+//!
+//! The image starts with a magic number and the storage encoding version.
+//! Each column family is then written with its name followed by its key-value pairs in the key order.
+//! An entry is a big endian `u32` key length, the key, a big endian `u32` value length and the value.
+//! The end of a column family is marked by an [`END_OF_COLUMN_FAMILY`] key length and the end of the image by an empty column family name.
+
+use crate::storage::backend::{ColumnFamily, Db, Reader};
+use crate::storage::binary_encoder::LATEST_STORAGE_VERSION;
+use crate::storage::{CorruptionError, StorageError};
+use std::io::{Read, Write};
+
+const MAGIC_NUMBER: &[u8; 8] = b"OXIGRAPH";
+const END_OF_COLUMN_FAMILY: u32 = u32::MAX;
+
+/// Writes the content of the given column families into `write`.
+pub fn write_image<W: Write>(
+    reader: &Reader,
+    column_families: &[(&'static str, &ColumnFamily)],
+    mut write: W,
+) -> Result<W, StorageError> {
+    write.write_all(MAGIC_NUMBER)?;
+    write.write_all(&LATEST_STORAGE_VERSION.to_be_bytes())?;
+    for (name, column_family) in column_families {
+        write.write_all(&[name.len().try_into().map_err(|_| {
+            StorageError::Other(format!("Too long column family name {name}").into())
+        })?])?;
+        write.write_all(name.as_bytes())?;
+        let mut iter = reader.iter(column_family)?;
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            write_slice(&mut write, key)?;
+            write_slice(&mut write, value)?;
+            iter.next();
+        }
+        iter.status()?;
+        write.write_all(&END_OF_COLUMN_FAMILY.to_be_bytes())?;
+    }
+    write.write_all(&[0])?;
+    Ok(write)
+}
+
+/// Loads an image written by [`write_image`] into the given column families.
+///
+/// The entries are loaded as they are: the returned storage encoding version of the image might be older than the current one.
+/// The database should be empty.
+pub fn read_image(
+    db: &Db,
+    column_families: &[(&'static str, &ColumnFamily)],
+    mut read: impl Read,
+) -> Result<u64, StorageError> {
+    let mut magic_number = [0; 8];
+    read.read_exact(&mut magic_number)?;
+    if &magic_number != MAGIC_NUMBER {
+        return Err(CorruptionError::msg("The file is not an Oxigraph store image").into());
+    }
+    let mut version = [0; 8];
+    read.read_exact(&mut version)?;
+    let version = u64::from_be_bytes(version);
+    if !(1..=LATEST_STORAGE_VERSION).contains(&version) {
+        return Err(CorruptionError::msg(format!(
+                "The store image is using the encoding version {version} but only versions 1 to {LATEST_STORAGE_VERSION} are supported"
+            ))
+            .into());
+    }
+    #[cfg(target_family = "wasm")]
+    if version != LATEST_STORAGE_VERSION {
+        return Err(CorruptionError::msg(format!(
+                "The store image is using the encoding version {version} that must be migrated. Please load it with Store::load_snapshot in a native Oxigraph build and save it again with Store::save_to_file"
+            ))
+            .into());
+    }
+    #[cfg(not(target_family = "wasm"))]
+    let mut ssts = Vec::new();
+    loop {
+        let mut name_len = [0];
+        read.read_exact(&mut name_len)?;
+        if name_len[0] == 0 {
+            break;
+        }
+        let mut name = vec![0; name_len[0].into()];
+        read.read_exact(&mut name)?;
+        let column_family = column_families
+            .iter()
+            .find(|(n, _)| n.as_bytes() == name)
+            .map(|(_, cf)| *cf);
+        if column_family.is_none() && name != b"default" {
+            // The default column family only contains metadata that might not be supported by the current platform
+            return Err(CorruptionError::msg(format!(
+                "The store image contains the unknown column family {}",
+                String::from_utf8_lossy(&name)
+            ))
+            .into());
+        }
+        #[cfg(not(target_family = "wasm"))]
+        {
+            let mut sst = None;
+            let mut previous_key = None;
+            while let Some((key, value)) = read_entry(&mut read, previous_key.as_deref())? {
+                if let Some(column_family) = column_family {
+                    if sst.is_none() {
+                        sst = Some((column_family, db.new_sst_file()?));
+                    }
+                    if let Some((_, sst)) = &mut sst {
+                        sst.insert(&key, &value)?;
+                    }
+                }
+                previous_key = Some(key);
+            }
+            if let Some((column_family, sst)) = sst {
+                ssts.push((column_family, sst.finish()?));
+            }
+        }
+        #[cfg(target_family = "wasm")]
+        {
+            let mut entries = Vec::new();
+            while let Some(entry) = read_entry(
+                &mut read,
+                entries
+                    .last()
+                    .map(|(k, _): &(Vec<u8>, Vec<u8>)| k.as_slice()),
+            )? {
+                entries.push(entry);
+            }
+            if let Some(column_family) = column_family {
+                db.transaction(|mut transaction| {
+                    for (key, value) in &entries {
+                        transaction.insert(column_family, key, value)?;
+                    }
+                    Ok::<_, StorageError>(())
+                })?;
+            }
+        }
+    }
+    #[cfg(not(target_family = "wasm"))]
+    db.insert_stt_files(ssts)?;
+    Ok(version)
+}
+
+fn write_slice(write: &mut impl Write, slice: &[u8]) -> Result<(), StorageError> {
+    let len = u32::try_from(slice.len())
+        .ok()
+        .filter(|len| *len != END_OF_COLUMN_FAMILY)
+        .ok_or_else(|| StorageError::Other("Too long key or value to be saved".into()))?;
+    write.write_all(&len.to_be_bytes())?;
+    write.write_all(slice)?;
+    Ok(())
+}
+
+/// Reads the next entry of a column family, checking that the keys are strictly increasing.
+fn read_entry(
+    read: &mut impl Read,
+    previous_key: Option<&[u8]>,
+) -> Result<Option<(Vec<u8>, Vec<u8>)>, StorageError> {
+    let Some(key) = read_slice(read)? else {
+        return Ok(None);
+    };
+    if previous_key.map_or(false, |previous_key| previous_key >= key.as_slice()) {
+        return Err(CorruptionError::msg("The store image keys are not sorted").into());
+    }
+    let value = read_slice(read)?
+        .ok_or_else(|| CorruptionError::msg("The store image ends in the middle of an entry"))?;
+    Ok(Some((key, value)))
+}
+
+fn read_slice(read: &mut impl Read) -> Result<Option<Vec<u8>>, StorageError> {
+    let mut len = [0; 4];
+    read.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len);
+    if len == END_OF_COLUMN_FAMILY {
+        return Ok(None);
+    }
+    // We do not trust the length to allocate the buffer: the image might be truncated or corrupted
+    let mut slice = Vec::new();
+    read.by_ref().take(len.into()).read_to_end(&mut slice)?;
+    if slice.len() != usize::try_from(len).map_err(|_| CorruptionError::msg("Too long entry"))? {
+        return Err(CorruptionError::msg("The store image ends in the middle of an entry").into());
+    }
+    Ok(Some(slice))
+}
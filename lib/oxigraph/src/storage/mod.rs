@@ -1,40 +1,87 @@
 #![allow(clippy::same_name_method)]
+use crate::geo::BoundingBox;
+#[cfg(not(target_family = "wasm"))]
+use crate::model::vocab::xsd;
 #[cfg(not(target_family = "wasm"))]
 use crate::model::Quad;
-use crate::model::{GraphNameRef, NamedOrBlankNodeRef, QuadRef, TermRef};
+use crate::model::{GraphNameRef, NamedOrBlankNodeRef, QuadRef, Term, TermRef};
 use crate::storage::backend::{Reader, Transaction};
 #[cfg(not(target_family = "wasm"))]
-use crate::storage::binary_encoder::LATEST_STORAGE_VERSION;
+pub use crate::storage::binary_encoder::LATEST_STORAGE_VERSION;
+use crate::storage::binary_encoder::{
+    decode_spog_quad, decode_term, encode_term, encode_term_order_key, encode_term_pair,
+    encode_term_quad, encode_term_triple, write_gosp_quad, write_gpos_quad, write_gspo_quad,
+    write_osp_quad, write_ospg_quad, write_pos_quad, write_posg_quad, write_spo_quad,
+    write_spog_quad, write_term, QuadEncoding, WRITTEN_TERM_MAX_SIZE,
+};
+#[cfg(not(target_family = "wasm"))]
 use crate::storage::binary_encoder::{
-    decode_term, encode_term, encode_term_pair, encode_term_quad, encode_term_triple,
-    write_gosp_quad, write_gpos_quad, write_gspo_quad, write_osp_quad, write_ospg_quad,
-    write_pos_quad, write_posg_quad, write_spo_quad, write_spog_quad, write_term, QuadEncoding,
-    WRITTEN_TERM_MAX_SIZE,
+    downgrade_to_v1_terms, downgrade_to_v2_terms, upgrade_v1_terms, upgrade_v2_terms,
+    v2_upgraded_term_prefixes, v3_upgraded_term_prefixes,
 };
 pub use crate::storage::error::{CorruptionError, LoaderError, SerializerError, StorageError};
 #[cfg(not(target_family = "wasm"))]
+use crate::storage::numeric_encoder::for_each_str_hash;
 use crate::storage::numeric_encoder::Decoder;
 use crate::storage::numeric_encoder::{insert_term, EncodedQuad, EncodedTerm, StrHash, StrLookup};
-use backend::{ColumnFamily, ColumnFamilyDefinition, Db, Iter};
 #[cfg(not(target_family = "wasm"))]
-use std::collections::VecDeque;
+use crate::storage::spatial::rewrite_key_terms as rewrite_spatial_key_terms;
+use crate::storage::spatial::{
+    decode_entry as decode_spatial_entry, is_wkt_literal, spatial_index_entry, SpatialScan,
+    SPATIAL_INDEX_ENABLED_KEY,
+};
+#[cfg(not(target_family = "wasm"))]
+use crate::storage::text::rewrite_key_terms as rewrite_text_key_terms;
+pub use crate::storage::text::TextIndexConfig;
+use crate::storage::text::{
+    decode_entry_score, text_index_entries, token_prefix, tokenize, TEXT_INDEX_CONFIG_KEY,
+};
+#[cfg(not(target_family = "wasm"))]
+use backend::BackupEngine;
+pub use backend::{
+    check_storage_backend, StorageBackend, StorageBackendIter, StorageBackendSnapshot,
+    StorageBackendTransaction,
+};
+#[cfg(not(target_family = "wasm"))]
+use backend::{prefix_upper_bound, SstFile};
+#[cfg(not(target_family = "wasm"))]
+pub use backend::{BackupInfo, DbOptions};
+use backend::{ColumnFamily, ColumnFamilyDefinition, Db, Iter, SharedSnapshot};
+#[cfg(not(target_family = "wasm"))]
+use rand::random;
+use sparopt::TripleStatistics;
+use std::collections::HashMap;
+#[cfg(not(target_family = "wasm"))]
+use std::collections::HashSet;
 #[cfg(not(target_family = "wasm"))]
-use std::collections::{HashMap, HashSet};
+use std::collections::VecDeque;
 use std::error::Error;
 #[cfg(not(target_family = "wasm"))]
+use std::fs::{self, remove_dir_all, rename, File, OpenOptions};
+#[cfg(not(target_family = "wasm"))]
+use std::io::{BufReader, BufWriter};
+use std::io::{Read, Write};
+#[cfg(not(target_family = "wasm"))]
 use std::mem::{swap, take};
 #[cfg(not(target_family = "wasm"))]
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, RwLock, RwLockWriteGuard};
 #[cfg(not(target_family = "wasm"))]
-use std::sync::Mutex;
+use std::sync::{Mutex, MutexGuard, RwLockReadGuard, Weak};
 #[cfg(not(target_family = "wasm"))]
 use std::{io, thread};
 
 mod backend;
-mod binary_encoder;
+pub mod binary_encoder;
 mod error;
+mod image;
 pub mod numeric_encoder;
+#[cfg(not(target_family = "wasm"))]
+mod packed;
 pub mod small_string;
+mod spatial;
+mod text;
 
 const ID2STR_CF: &str = "id2str";
 const SPOG_CF: &str = "spog";
@@ -47,10 +94,69 @@ const DSPO_CF: &str = "dspo";
 const DPOS_CF: &str = "dpos";
 const DOSP_CF: &str = "dosp";
 const GRAPHS_CF: &str = "graphs";
+const CHANGES_CF: &str = "changes";
+const TEXT_CF: &str = "text";
+const SPATIAL_CF: &str = "spatial";
 #[cfg(not(target_family = "wasm"))]
 const DEFAULT_CF: &str = "default";
 #[cfg(not(target_family = "wasm"))]
 const DEFAULT_BULK_LOAD_BATCH_SIZE: usize = 1_000_000;
+#[cfg(not(target_family = "wasm"))]
+const BULK_REMOVE_BATCH_SIZE: usize = 100_000;
+#[cfg(not(target_family = "wasm"))]
+const DROP_GRAPH_BATCH_SIZE: usize = 100_000;
+#[cfg(not(target_family = "wasm"))]
+const GARBAGE_COLLECTION_BATCH_SIZE: usize = 100_000;
+const CHANGE_LOG_BATCH_SIZE: usize = 100_000;
+const INDEX_BUILD_BATCH_SIZE: usize = 100_000;
+#[cfg(not(target_family = "wasm"))]
+const STATISTICS_PREFIX: &[u8] = b"stats";
+#[cfg(not(target_family = "wasm"))]
+const DATASET_STATISTICS_KEY: &[u8] = b"statsd";
+#[cfg(not(target_family = "wasm"))]
+const PREDICATE_STATISTICS_PREFIX: &[u8] = b"statsp";
+#[cfg(not(target_family = "wasm"))]
+const GRAPH_STATISTICS_PREFIX: &[u8] = b"statsg";
+/// Key of the `changes` column family storing the last used change sequence number.
+///
+/// It is only present if the change log is enabled.
+const CHANGE_COUNTER_KEY: &[u8] = b"";
+/// Key of the `default` column family storing the storage version
+#[cfg(not(target_family = "wasm"))]
+const VERSION_KEY: &[u8] = b"oxversion";
+/// Key of the `default` column family storing the target version of the running migration
+/// and the number of column families already rewritten by it, both as big endian `u64`,
+/// followed by the [`MigrationPosition`] in the column family being rewritten.
+///
+/// The migrations to the version 2 written before the support of downgrades only store the number of column families.
+#[cfg(not(target_family = "wasm"))]
+const MIGRATION_PROGRESS_KEY: &[u8] = b"oxmigration";
+/// Prefix of the keys of the `default` column family storing the rewritten entries whose key changed
+/// until the column family being rewritten has been fully read.
+#[cfg(not(target_family = "wasm"))]
+const MIGRATION_STAGING_PREFIX: &[u8] = b"oxstaged";
+/// Prefix of the keys of the `default` column family marking the graphs dropped by [`Storage::drop_graph`]
+/// whose quads are not all removed yet.
+///
+/// The key suffix is the encoded graph name and the value the key of the last removed quad in the `gspo` or `dspo` index.
+/// The readers ignore the quads of these graphs.
+#[cfg(not(target_family = "wasm"))]
+const DROPPED_GRAPH_PREFIX: &[u8] = b"oxdropped";
+#[cfg(not(target_family = "wasm"))]
+const MIGRATION_PROGRESS_BATCH_SIZE: u64 = 100_000;
+/// Maximal number of entries rewritten by a migration in a single transaction
+#[cfg(all(not(target_family = "wasm"), not(test)))]
+const MIGRATION_BATCH_SIZE: usize = 100_000;
+#[cfg(test)]
+const MIGRATION_BATCH_SIZE: usize = 3;
+#[cfg(not(target_family = "wasm"))]
+const MIGRATION_POSITION_NEXT: u8 = 0;
+#[cfg(not(target_family = "wasm"))]
+const MIGRATION_POSITION_MOVING_BACK: u8 = 1;
+const CHANGE_INSERT: u8 = 1;
+const CHANGE_REMOVE: u8 = 2;
+const CHANGE_INSERT_NAMED_GRAPH: u8 = 3;
+const CHANGE_REMOVE_NAMED_GRAPH: u8 = 4;
 
 /// Low level storage primitives
 #[derive(Clone)]
@@ -69,6 +175,18 @@ pub struct Storage {
     dpos_cf: ColumnFamily,
     dosp_cf: ColumnFamily,
     graphs_cf: ColumnFamily,
+    /// Missing if the store has been created before the change log support and is opened in read-only mode
+    changes_cf: Option<ColumnFamily>,
+    /// Missing if the store has been created before the text index support and is opened in read-only mode
+    text_cf: Option<ColumnFamily>,
+    /// Missing if the store has been created before the spatial index support and is opened in read-only mode
+    spatial_cf: Option<ColumnFamily>,
+    #[cfg(not(target_family = "wasm"))]
+    str_gc: Arc<StrGarbageCollector>,
+    is_change_log_enabled: Arc<AtomicBool>,
+    /// The configuration of the text index if it is enabled
+    text_index: Arc<RwLock<Option<Arc<TextIndexConfig>>>>,
+    is_spatial_index_enabled: Arc<AtomicBool>,
 }
 
 impl Storage {
@@ -76,9 +194,17 @@ impl Storage {
         Self::setup(Db::new(Self::column_families())?)
     }
 
+    pub fn open_with_backend(backend: Arc<dyn StorageBackend>) -> Result<Self, StorageError> {
+        Self::setup(Db::open_backend(backend, Self::column_families())?)
+    }
+
     #[cfg(not(target_family = "wasm"))]
-    pub fn open(path: &Path) -> Result<Self, StorageError> {
-        Self::setup(Db::open_read_write(Some(path), Self::column_families())?)
+    pub fn open(path: &Path, options: &DbOptions) -> Result<Self, StorageError> {
+        Self::setup(Db::open_read_write(
+            Some(path),
+            Self::column_families(),
+            options,
+        )?)
     }
 
     #[cfg(not(target_family = "wasm"))]
@@ -107,6 +233,32 @@ impl Storage {
         Self::setup(Db::open_read_only(path, Self::column_families())?)
     }
 
+    /// Opens a file written by [`StorageReader::save_packed_to_write`]
+    #[cfg(not(target_family = "wasm"))]
+    pub fn open_packed(path: &Path) -> Result<Self, StorageError> {
+        Self::setup(Db::open_read_only_backend(
+            Arc::new(packed::PackedBackend::open(path)?),
+            Self::column_families(),
+        )?)
+    }
+
+    /// Opens an existing database in read-write mode without migrating it to the latest storage version
+    #[cfg(not(target_family = "wasm"))]
+    pub fn open_without_migration(path: &Path) -> Result<Self, StorageError> {
+        if !path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("The database {} does not exist", path.display()),
+            )
+            .into());
+        }
+        Self::init(Db::open_read_write(
+            Some(path),
+            Self::column_families(),
+            &DbOptions::default(),
+        )?)
+    }
+
     fn column_families() -> Vec<ColumnFamilyDefinition> {
         vec![
             ColumnFamilyDefinition {
@@ -175,10 +327,68 @@ impl Storage {
                 min_prefix_size: 17, // named or blank node start
                 unordered_writes: false,
             },
+            ColumnFamilyDefinition {
+                name: CHANGES_CF,
+                use_iter: true,
+                min_prefix_size: 0,
+                unordered_writes: false,
+            },
+            ColumnFamilyDefinition {
+                name: TEXT_CF,
+                use_iter: true,
+                min_prefix_size: 0,
+                unordered_writes: false,
+            },
+            ColumnFamilyDefinition {
+                name: SPATIAL_CF,
+                use_iter: true,
+                min_prefix_size: 0,
+                unordered_writes: false,
+            },
         ]
     }
 
+    fn column_families_by_name(&self) -> Vec<(&'static str, &ColumnFamily)> {
+        let mut column_families = vec![
+            #[cfg(not(target_family = "wasm"))]
+            (DEFAULT_CF, &self.default_cf),
+            (ID2STR_CF, &self.id2str_cf),
+            (SPOG_CF, &self.spog_cf),
+            (POSG_CF, &self.posg_cf),
+            (OSPG_CF, &self.ospg_cf),
+            (GSPO_CF, &self.gspo_cf),
+            (GPOS_CF, &self.gpos_cf),
+            (GOSP_CF, &self.gosp_cf),
+            (DSPO_CF, &self.dspo_cf),
+            (DPOS_CF, &self.dpos_cf),
+            (DOSP_CF, &self.dosp_cf),
+            (GRAPHS_CF, &self.graphs_cf),
+        ];
+        if let Some(changes_cf) = &self.changes_cf {
+            column_families.push((CHANGES_CF, changes_cf));
+        }
+        if let Some(text_cf) = &self.text_cf {
+            column_families.push((TEXT_CF, text_cf));
+        }
+        if let Some(spatial_cf) = &self.spatial_cf {
+            column_families.push((SPATIAL_CF, spatial_cf));
+        }
+        column_families
+    }
+
     fn setup(db: Db) -> Result<Self, StorageError> {
+        let this = Self::init(db)?;
+        #[cfg(not(target_family = "wasm"))]
+        {
+            this.migrate()?;
+            if this.db.is_writable() {
+                this.complete_graph_drops()?;
+            }
+        }
+        Ok(this)
+    }
+
+    fn init(db: Db) -> Result<Self, StorageError> {
         let this = Self {
             #[cfg(not(target_family = "wasm"))]
             default_cf: db.column_family(DEFAULT_CF)?,
@@ -193,123 +403,1250 @@ impl Storage {
             dpos_cf: db.column_family(DPOS_CF)?,
             dosp_cf: db.column_family(DOSP_CF)?,
             graphs_cf: db.column_family(GRAPHS_CF)?,
+            changes_cf: db.column_family(CHANGES_CF).ok(),
+            text_cf: db.column_family(TEXT_CF).ok(),
+            spatial_cf: db.column_family(SPATIAL_CF).ok(),
+            #[cfg(not(target_family = "wasm"))]
+            str_gc: Arc::default(),
+            is_change_log_enabled: Arc::default(),
+            text_index: Arc::default(),
+            is_spatial_index_enabled: Arc::default(),
             db,
         };
-        #[cfg(not(target_family = "wasm"))]
-        this.migrate()?;
+        this.is_change_log_enabled
+            .store(this.has_change_counter()?, Ordering::Release);
+        this.load_text_index_config()?;
+        this.is_spatial_index_enabled
+            .store(this.has_spatial_index()?, Ordering::Release);
         Ok(this)
     }
 
+    /// Migrates the read-write stores to the latest storage version and checks that the other ones can be read
     #[cfg(not(target_family = "wasm"))]
     fn migrate(&self) -> Result<(), StorageError> {
+        let version = self.ensure_version()?;
+        if version > LATEST_STORAGE_VERSION {
+            return Err(CorruptionError::msg(format!(
+                "The RocksDB database is using the too recent version {version}. Upgrade to the latest Oxigraph version to load this database"
+            )).into());
+        }
+        if self.db.is_writable() {
+            return self.migrate_to(LATEST_STORAGE_VERSION, &|_, _| ());
+        }
+        if self
+            .migration_progress()?
+            .map_or(false, |(target_version, _, _)| target_version != version)
+        {
+            return Err(CorruptionError::msg(
+                "The RocksDB database migration has been interrupted. Please open it once in read-write mode to complete it"
+            ).into());
+        }
+        match version {
+            LATEST_STORAGE_VERSION => Ok(()),
+            // Nothing to rewrite, the store can be read with the latest encoding
+            1 if !self.has_values_to_upgrade(
+                &[v2_upgraded_term_prefixes(), v3_upgraded_term_prefixes()].concat(),
+                |terms| upgrade_v2_terms(&upgrade_v1_terms(terms)?),
+            )? =>
+            {
+                Ok(())
+            }
+            2 if !self.has_values_to_upgrade(&v3_upgraded_term_prefixes(), upgrade_v2_terms)? => {
+                Ok(())
+            }
+            _ => Err(CorruptionError::msg(format!(
+                "The RocksDB database is using the encoding version {version} that must be migrated. Please open it once in read-write mode to migrate it"
+            )).into()),
+        }
+    }
+
+    /// Returns the version of the storage format
+    #[cfg(not(target_family = "wasm"))]
+    pub fn storage_version(&self) -> Result<u64, StorageError> {
+        let version = self.db.get(&self.default_cf, VERSION_KEY)?.ok_or_else(|| {
+            CorruptionError::msg("The storage version is not written in the database")
+        })?;
+        Ok(u64::from_be_bytes(version.as_ref().try_into().map_err(
+            |e| CorruptionError::new(format!("Error while parsing the version key: {e}")),
+        )?))
+    }
+
+    /// Migrates the storage to `target_version`, upgrading or downgrading it one version at a time.
+    ///
+    /// An interrupted migration is first completed.
+    /// `on_progress` is called from time to time with the version the storage is being migrated to
+    /// and the number of entries processed by the current step.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn migrate_to(
+        &self,
+        target_version: u64,
+        on_progress: &dyn Fn(u64, u64),
+    ) -> Result<(), StorageError> {
+        if !(1..=LATEST_STORAGE_VERSION).contains(&target_version) {
+            return Err(StorageError::Other(
+                format!("The migration to the storage version {target_version} is not supported, the supported versions are 1 to {LATEST_STORAGE_VERSION}").into(),
+            ));
+        }
         let mut version = self.ensure_version()?;
-        if version == 0 {
-            // We migrate to v1
-            let mut graph_names = HashSet::new();
-            for quad in self.snapshot().quads() {
-                let quad = quad?;
-                if !quad.graph_name.is_default_graph() {
-                    graph_names.insert(quad.graph_name);
+        if version > LATEST_STORAGE_VERSION {
+            return Err(CorruptionError::msg(format!(
+                "The RocksDB database is using the too recent version {version}. Upgrade to the latest Oxigraph version to migrate this database"
+            )).into());
+        }
+        if let Some((pending_version, _, _)) = self.migration_progress()? {
+            if pending_version != version {
+                self.migrate_step(version, pending_version, on_progress)?;
+                version = pending_version;
+            }
+        }
+        while version != target_version {
+            let next_version = if version < target_version {
+                version + 1
+            } else {
+                version - 1
+            };
+            self.migrate_step(version, next_version, on_progress)?;
+            version = next_version;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn migrate_step(
+        &self,
+        from_version: u64,
+        to_version: u64,
+        on_progress: &dyn Fn(u64, u64),
+    ) -> Result<(), StorageError> {
+        match (from_version, to_version) {
+            (0, 1) => self.build_graphs_index()?,
+            // The literal values are written in an order preserving way
+            (1, 2) => self.rewrite_values(to_version, upgrade_v1_terms, on_progress)?,
+            (2, 1) => self.rewrite_values(to_version, downgrade_to_v1_terms, on_progress)?,
+            // The xsd:anyURI literals and the integers of the xsd:integer derived datatypes are written inline
+            (2, 3) => self.rewrite_values(to_version, upgrade_v2_terms, on_progress)?,
+            (3, 2) => {
+                self.insert_any_uri_datatype()?;
+                self.rewrite_values(to_version, downgrade_to_v2_terms, on_progress)?
+            }
+            _ => {
+                return Err(CorruptionError::msg(format!(
+                    "The migration from the storage version {from_version} to {to_version} is not supported"
+                ))
+                .into())
+            }
+        }
+        self.update_version(to_version)
+    }
+
+    /// Adds the `xsd:anyURI` IRI to the string store, the versions before 3 refer to it in the `xsd:anyURI` literals
+    #[cfg(not(target_family = "wasm"))]
+    fn insert_any_uri_datatype(&self) -> Result<(), StorageError> {
+        self.db.transaction(|mut transaction| {
+            transaction.insert(
+                &self.id2str_cf,
+                &StrHash::new(xsd::ANY_URI.as_str()).to_be_bytes(),
+                xsd::ANY_URI.as_str().as_bytes(),
+            )
+        })
+    }
+
+    /// Fills the `graphs` column family added by the version 1
+    #[cfg(not(target_family = "wasm"))]
+    fn build_graphs_index(&self) -> Result<(), StorageError> {
+        let mut graph_names = HashSet::new();
+        for quad in self.snapshot().quads() {
+            let quad = quad?;
+            if !quad.graph_name.is_default_graph() {
+                graph_names.insert(quad.graph_name);
+            }
+        }
+        let mut graph_names = graph_names
+            .into_iter()
+            .map(|g| encode_term(&g))
+            .collect::<Vec<_>>();
+        graph_names.sort_unstable();
+        let mut stt_file = self.db.new_sst_file()?;
+        for k in graph_names {
+            stt_file.insert_empty(&k)?;
+        }
+        self.db
+            .insert_stt_files(vec![(&self.graphs_cf, stt_file.finish()?)])
+    }
+
+    /// Rewrites the literal values of all the entries using `rewrite_terms` to migrate them to `target_version`.
+    ///
+    /// The column families are rewritten one after the other in batches of entries.
+    /// Each batch is written in a transaction that also stores the migration progress
+    /// so that an interrupted migration resumes after the last written batch.
+    /// The entries whose key changes are staged in the `default` column family
+    /// and only moved back once the column family has been fully read so that they are never rewritten twice.
+    #[cfg(not(target_family = "wasm"))]
+    fn rewrite_values(
+        &self,
+        target_version: u64,
+        rewrite_terms: TermsRewrite,
+        on_progress: &dyn Fn(u64, u64),
+    ) -> Result<(), StorageError> {
+        let rewrite_quad_entry: EntryRewrite = rewrite_quad_entry;
+        let mut column_families: Vec<(&ColumnFamily, EntryRewrite)> = [
+            &self.spog_cf,
+            &self.posg_cf,
+            &self.ospg_cf,
+            &self.gspo_cf,
+            &self.gpos_cf,
+            &self.gosp_cf,
+            &self.dspo_cf,
+            &self.dpos_cf,
+            &self.dosp_cf,
+        ]
+        .into_iter()
+        .map(|cf| (cf, rewrite_quad_entry))
+        .collect();
+        column_families.push((self.changes_cf()?, rewrite_change_entry));
+        column_families.push((self.text_cf()?, rewrite_text_entry));
+        column_families.push((self.spatial_cf()?, rewrite_spatial_entry));
+
+        let (progress, mut position) = match self.migration_progress()? {
+            Some((pending_version, progress, position)) if pending_version == target_version => (
+                usize::try_from(progress).map_err(CorruptionError::new)?,
+                position,
+            ),
+            _ => (0, MigrationPosition::Start),
+        };
+        let mut processed = 0;
+        for (i, (column_family, rewrite)) in column_families.into_iter().enumerate().skip(progress)
+        {
+            let i = u64::try_from(i).map_err(CorruptionError::new)?;
+            let staging_prefix = [MIGRATION_STAGING_PREFIX, &i.to_be_bytes()].concat();
+            // We read the column family from the position of the interrupted migration if any
+            let start = match &position {
+                MigrationPosition::Start => Some(Vec::new()),
+                MigrationPosition::Next(key) => Some(key.clone()),
+                MigrationPosition::MovingBack => None,
+            };
+            if let Some(start) = start {
+                let snapshot = self.db.snapshot();
+                let mut iter = snapshot.scan_from(column_family, &start)?;
+                loop {
+                    let mut entries = Vec::new();
+                    while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                        if entries.len() >= MIGRATION_BATCH_SIZE {
+                            break;
+                        }
+                        let (new_key, new_value) = rewrite(key, value, rewrite_terms)?;
+                        if new_key != key || new_value != value {
+                            entries.push((key.to_vec(), new_key, new_value));
+                        }
+                        processed += 1;
+                        if processed % MIGRATION_PROGRESS_BATCH_SIZE == 0 {
+                            on_progress(target_version, processed);
+                        }
+                        iter.next();
+                    }
+                    iter.status()?;
+                    let next_position = if let Some(key) = iter.key() {
+                        MigrationPosition::Next(key.to_vec())
+                    } else {
+                        MigrationPosition::MovingBack
+                    };
+                    self.db.transaction(|mut transaction| {
+                        for (old_key, new_key, new_value) in &entries {
+                            if new_key == old_key {
+                                transaction.insert(column_family, new_key, new_value)?;
+                            } else {
+                                transaction.remove(column_family, old_key)?;
+                                transaction.insert(
+                                    &self.default_cf,
+                                    &[staging_prefix.as_slice(), new_key].concat(),
+                                    new_value,
+                                )?;
+                            }
+                        }
+                        self.write_migration_progress(
+                            &mut transaction,
+                            target_version,
+                            i,
+                            &next_position,
+                        )
+                    })?;
+                    if matches!(next_position, MigrationPosition::MovingBack) {
+                        break;
+                    }
                 }
             }
-            let mut graph_names = graph_names
-                .into_iter()
-                .map(|g| encode_term(&g))
-                .collect::<Vec<_>>();
-            graph_names.sort_unstable();
-            let mut stt_file = self.db.new_sst_file()?;
-            for k in graph_names {
-                stt_file.insert_empty(&k)?;
+            // We move back the staged entries
+            loop {
+                let snapshot = self.db.snapshot();
+                let mut iter = snapshot.scan_prefix(&self.default_cf, &staging_prefix)?;
+                let mut entries = Vec::new();
+                while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                    if entries.len() >= MIGRATION_BATCH_SIZE {
+                        break;
+                    }
+                    entries.push((key.to_vec(), value.to_vec()));
+                    iter.next();
+                }
+                iter.status()?;
+                let is_done = iter.key().is_none();
+                self.db.transaction(|mut transaction| {
+                    for (staged_key, value) in &entries {
+                        transaction.remove(&self.default_cf, staged_key)?;
+                        transaction.insert(
+                            column_family,
+                            &staged_key[staging_prefix.len()..],
+                            value,
+                        )?;
+                    }
+                    if is_done {
+                        self.write_migration_progress(
+                            &mut transaction,
+                            target_version,
+                            i + 1,
+                            &MigrationPosition::Start,
+                        )?;
+                    }
+                    Ok::<_, StorageError>(())
+                })?;
+                if is_done {
+                    break;
+                }
             }
+            position = MigrationPosition::Start;
+        }
+        on_progress(target_version, processed);
+        Ok(())
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn write_migration_progress(
+        &self,
+        transaction: &mut Transaction<'_>,
+        target_version: u64,
+        rewritten_column_families: u64,
+        position: &MigrationPosition,
+    ) -> Result<(), StorageError> {
+        let mut progress = target_version.to_be_bytes().to_vec();
+        progress.extend_from_slice(&rewritten_column_families.to_be_bytes());
+        match position {
+            MigrationPosition::Start => (),
+            MigrationPosition::Next(key) => {
+                progress.push(MIGRATION_POSITION_NEXT);
+                progress.extend_from_slice(key);
+            }
+            MigrationPosition::MovingBack => progress.push(MIGRATION_POSITION_MOVING_BACK),
+        }
+        transaction.insert(&self.default_cf, MIGRATION_PROGRESS_KEY, &progress)
+    }
+
+    /// Returns the target version of the running migration, the number of column families it already rewrote
+    /// and its position in the column family being rewritten
+    #[cfg(not(target_family = "wasm"))]
+    fn migration_progress(&self) -> Result<Option<(u64, u64, MigrationPosition)>, StorageError> {
+        let Some(progress) = self.db.get(&self.default_cf, MIGRATION_PROGRESS_KEY)? else {
+            return Ok(None);
+        };
+        let progress = progress.as_ref();
+        if progress.len() == 8 {
+            // Migration to the version 2 written before the support of downgrades
+            return Ok(Some((
+                2,
+                u64::from_be_bytes(progress.try_into().map_err(CorruptionError::new)?),
+                MigrationPosition::Start,
+            )));
+        }
+        let (version, progress) = progress.split_at(progress.len().min(8));
+        let (count, position) = progress.split_at(progress.len().min(8));
+        Ok(Some((
+            u64::from_be_bytes(version.try_into().map_err(CorruptionError::new)?),
+            u64::from_be_bytes(count.try_into().map_err(CorruptionError::new)?),
+            match position {
+                [] => MigrationPosition::Start,
+                [MIGRATION_POSITION_NEXT, key @ ..] => MigrationPosition::Next(key.to_vec()),
+                [MIGRATION_POSITION_MOVING_BACK] => MigrationPosition::MovingBack,
+                _ => return Err(CorruptionError::msg("Invalid migration position").into()),
+            },
+        )))
+    }
+
+    /// Returns if `upgrade_terms` rewrites some quads or change log entries
+    ///
+    /// Only the quads with a subject or an object starting with one of the `upgraded_prefixes` are read.
+    #[cfg(not(target_family = "wasm"))]
+    fn has_values_to_upgrade(
+        &self,
+        upgraded_prefixes: &[Vec<u8>],
+        upgrade_terms: TermsRewrite,
+    ) -> Result<bool, StorageError> {
+        let snapshot = self.db.snapshot();
+        for column_family in [&self.spog_cf, &self.ospg_cf, &self.dspo_cf, &self.dosp_cf] {
+            for prefix in upgraded_prefixes {
+                let mut iter = snapshot.scan_prefix(column_family, prefix)?;
+                while let Some(key) = iter.key() {
+                    if upgrade_terms(key)? != key {
+                        return Ok(true);
+                    }
+                    iter.next();
+                }
+                iter.status()?;
+            }
+        }
+        let Some(changes_cf) = &self.changes_cf else {
+            return Ok(false);
+        };
+        let mut iter = snapshot.scan_from(changes_cf, &1_u64.to_be_bytes())?;
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            if rewrite_change_entry(key, value, upgrade_terms)?.1 != value {
+                return Ok(true);
+            }
+            iter.next();
+        }
+        iter.status()?;
+        Ok(false)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn ensure_version(&self) -> Result<u64, StorageError> {
+        if self.db.get(&self.default_cf, VERSION_KEY)?.is_none() {
+            self.update_version(LATEST_STORAGE_VERSION)?;
+        }
+        self.storage_version()
+    }
+
+    /// Writes the storage version and removes the progress of the migration to it
+    #[cfg(not(target_family = "wasm"))]
+    fn update_version(&self, version: u64) -> Result<(), StorageError> {
+        self.db.transaction(|mut transaction| {
+            transaction.insert(&self.default_cf, VERSION_KEY, &version.to_be_bytes())?;
+            transaction.remove(&self.default_cf, MIGRATION_PROGRESS_KEY)
+        })?;
+        self.db.flush()
+    }
+
+    pub fn snapshot(&self) -> StorageReader {
+        StorageReader {
+            reader: self.db.snapshot(),
+            storage: self.clone(),
+            _snapshot: None,
+            dropped_graphs: OnceLock::new(),
+        }
+    }
+
+    /// Builds a snapshot that might be kept for a long time and shared between threads
+    pub fn shared_snapshot(&self) -> Result<StorageSnapshot, StorageError> {
+        #[cfg(not(target_family = "wasm"))]
+        let snapshot = {
+            // The snapshot is registered while taken so that a concurrent garbage collection is aware of it
+            let mut snapshots = self.str_gc.snapshots()?;
+            let snapshot = Arc::new(self.db.shared_snapshot()?);
+            snapshots.retain(|s| s.strong_count() > 0);
+            snapshots.push(Arc::downgrade(&snapshot));
+            snapshot
+        };
+        #[cfg(target_family = "wasm")]
+        let snapshot = Arc::new(self.db.shared_snapshot()?);
+        Ok(StorageSnapshot {
+            snapshot,
+            storage: self.clone(),
+        })
+    }
+
+    pub fn transaction<'a, 'b: 'a, T, E: Error + 'static + From<StorageError>>(
+        &'b self,
+        f: impl Fn(StorageWriter<'a>) -> Result<T, E>,
+    ) -> Result<T, E> {
+        #[cfg(not(target_family = "wasm"))]
+        let _write_guard = self.str_gc.start_write()?;
+        let text_index = self.text_index_config()?;
+        self.db.transaction(|transaction| {
+            f(StorageWriter {
+                buffer: Vec::new(),
+                transaction,
+                storage: self,
+                next_change_sequence_number: None,
+                text_index: text_index.clone(),
+            })
+        })
+    }
+
+    /// Removes all the quads of a graph and, if `remove_graph` is set, the named graph itself.
+    ///
+    /// The graph is first marked as dropped: the readers do not see its quads anymore.
+    /// The graph-prefixed indexes are then cleared with range removals.
+    /// The other indexes, the change log and the literal indexes are updated by batches of quads,
+    /// this step is skipped for the default graph if the change log and the literal indexes are disabled.
+    /// An interrupted drop is completed when the storage is opened again in read-write mode.
+    ///
+    /// All the writes are blocked during the operation.
+    ///
+    /// Returns `false` if the named graph does not exist.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn drop_graph(
+        &self,
+        graph_name: &EncodedTerm,
+        remove_graph: bool,
+    ) -> Result<bool, StorageError> {
+        let _writes_guard = self.str_gc.stop_writes()?;
+        if !graph_name.is_default_graph() && !self.snapshot().contains_named_graph(graph_name)? {
+            return Ok(false);
+        }
+        let graph_key = encode_term(graph_name);
+        self.db.transaction(|mut transaction| {
+            transaction.insert(
+                &self.default_cf,
+                &[DROPPED_GRAPH_PREFIX, &graph_key].concat(),
+                &[],
+            )?;
+            if remove_graph && !graph_name.is_default_graph() {
+                transaction.remove(&self.graphs_cf, &graph_key)?;
+            }
+            Ok::<_, StorageError>(())
+        })?;
+        self.complete_graph_drop(graph_name)?;
+        Ok(true)
+    }
+
+    /// Completes the drops of the graphs interrupted before the removal of all their quads
+    #[cfg(not(target_family = "wasm"))]
+    fn complete_graph_drops(&self) -> Result<(), StorageError> {
+        for graph_name in self.dropped_graphs(&self.db.snapshot())? {
+            self.complete_graph_drop(&graph_name)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the quads of a graph marked as dropped and then the mark
+    #[cfg(not(target_family = "wasm"))]
+    fn complete_graph_drop(&self, graph_name: &EncodedTerm) -> Result<(), StorageError> {
+        let graph_key = encode_term(graph_name);
+        let mark_key = [DROPPED_GRAPH_PREFIX, &graph_key].concat();
+        let (column_family, encoding, start, end) = if graph_name.is_default_graph() {
+            // The encoded terms never start with 0xFF
+            (&self.dspo_cf, QuadEncoding::Dspo, Vec::new(), vec![u8::MAX])
+        } else {
+            let end = prefix_upper_bound(&graph_key)
+                .ok_or_else(|| CorruptionError::msg("Invalid encoded graph name"))?;
+            (&self.gspo_cf, QuadEncoding::Gspo, graph_key.clone(), end)
+        };
+        let text_index = self.text_index_config()?;
+        if !graph_name.is_default_graph()
+            || text_index.is_some()
+            || self.is_change_log_enabled.load(Ordering::Acquire)
+            || self.is_spatial_index_enabled.load(Ordering::Acquire)
+        {
+            let snapshot = self.db.snapshot();
+            // We restart after the last quad removed by an interrupted drop
+            let scan_start = match snapshot.get(&self.default_cf, &mark_key)? {
+                Some(last) if !last.is_empty() => [&*last, &[0]].concat(),
+                _ => start.clone(),
+            };
+            let mut iter = snapshot.scan_range(column_family, &scan_start, &end)?;
+            let mut batch = Vec::with_capacity(DROP_GRAPH_BATCH_SIZE);
+            let mut last_key = Vec::new();
+            while let Some(key) = iter.key() {
+                batch.push(encoding.decode(key)?);
+                if batch.len() >= DROP_GRAPH_BATCH_SIZE {
+                    self.remove_before_range_removal(&batch, &text_index, &mark_key, key)?;
+                    batch.clear();
+                }
+                last_key.clear();
+                last_key.extend_from_slice(key);
+                iter.next();
+            }
+            iter.status()?;
+            self.remove_before_range_removal(&batch, &text_index, &mark_key, &last_key)?;
+        }
+        if graph_name.is_default_graph() {
+            self.db.remove_ranges(&[
+                (&self.dspo_cf, &start, &end),
+                (&self.dpos_cf, &start, &end),
+                (&self.dosp_cf, &start, &end),
+            ])?;
+        } else {
+            self.db.remove_ranges(&[
+                (&self.gspo_cf, &start, &end),
+                (&self.gpos_cf, &start, &end),
+                (&self.gosp_cf, &start, &end),
+            ])?;
+        }
+        self.db.transaction(|transaction| {
+            let mut writer = StorageWriter {
+                buffer: Vec::new(),
+                transaction,
+                storage: self,
+                next_change_sequence_number: None,
+                text_index: text_index.clone(),
+            };
+            // The named graph removal is logged after the removals of its quads
+            if !graph_name.is_default_graph()
+                && !writer
+                    .transaction
+                    .contains_key_for_update(&self.graphs_cf, &graph_key)?
+            {
+                writer.log_named_graph_change(CHANGE_REMOVE_NAMED_GRAPH, graph_name)?;
+            }
+            writer.transaction.remove(&self.default_cf, &mark_key)
+        })
+    }
+
+    /// Removes the quads from the indexes that are not cleared by range removals
+    /// and saves `last_key` as the drop progress in the `mark_key` entry
+    #[cfg(not(target_family = "wasm"))]
+    fn remove_before_range_removal(
+        &self,
+        quads: &[EncodedQuad],
+        text_index: &Option<Arc<TextIndexConfig>>,
+        mark_key: &[u8],
+        last_key: &[u8],
+    ) -> Result<(), StorageError> {
+        if quads.is_empty() {
+            return Ok(());
+        }
+        self.db.transaction(|transaction| {
+            let mut writer = StorageWriter {
+                buffer: Vec::new(),
+                transaction,
+                storage: self,
+                next_change_sequence_number: None,
+                text_index: text_index.clone(),
+            };
+            for quad in quads {
+                writer.remove_encoded_before_range_removal(quad)?;
+            }
+            writer
+                .transaction
+                .insert(&self.default_cf, mark_key, last_key)
+        })
+    }
+
+    /// Returns the graphs marked as dropped in the given snapshot
+    #[cfg(not(target_family = "wasm"))]
+    fn dropped_graphs(&self, reader: &Reader) -> Result<Vec<EncodedTerm>, StorageError> {
+        let mut graphs = Vec::new();
+        let mut iter = reader.scan_prefix(&self.default_cf, DROPPED_GRAPH_PREFIX)?;
+        while let Some(key) = iter.key() {
+            let graph_key = &key[DROPPED_GRAPH_PREFIX.len()..];
+            graphs.push(if graph_key.is_empty() {
+                EncodedTerm::DefaultGraph
+            } else {
+                decode_term(graph_key)?
+            });
+            iter.next();
+        }
+        iter.status()?;
+        Ok(graphs)
+    }
+
+    /// Loads an image written by [`StorageReader::save_to_write`].
+    ///
+    /// The images written by previous storage versions are migrated to the latest one.
+    /// The storage should be empty.
+    pub fn load_image(&self, read: impl Read) -> Result<(), StorageError> {
+        #[cfg_attr(target_family = "wasm", allow(unused_variables))]
+        let version = image::read_image(&self.db, &self.column_families_by_name(), read)?;
+        #[cfg(not(target_family = "wasm"))]
+        {
+            if version != LATEST_STORAGE_VERSION {
+                self.update_version(version)?;
+                self.migrate_to(LATEST_STORAGE_VERSION, &|_, _| ())?;
+            }
+            // The image might have been written during a graph drop
+            self.complete_graph_drops()?;
+        }
+        self.is_change_log_enabled
+            .store(self.has_change_counter()?, Ordering::Release);
+        self.is_spatial_index_enabled
+            .store(self.has_spatial_index()?, Ordering::Release);
+        self.load_text_index_config()
+    }
+
+    fn has_change_counter(&self) -> Result<bool, StorageError> {
+        let Some(changes_cf) = &self.changes_cf else {
+            return Ok(false);
+        };
+        self.db
+            .snapshot()
+            .contains_key(changes_cf, CHANGE_COUNTER_KEY)
+    }
+
+    fn changes_cf(&self) -> Result<&ColumnFamily, StorageError> {
+        self.changes_cf
+            .as_ref()
+            .ok_or_else(|| CorruptionError::from_missing_column_family_name(CHANGES_CF).into())
+    }
+
+    /// Starts to record in the `changes` column family all the quad insertions and removals.
+    ///
+    /// Does nothing if the change log is already enabled.
+    pub fn enable_change_log(&self) -> Result<(), StorageError> {
+        let changes_cf = self.changes_cf()?;
+        self.db.transaction(|mut transaction| {
+            if !transaction.contains_key_for_update(changes_cf, CHANGE_COUNTER_KEY)? {
+                transaction.insert(changes_cf, CHANGE_COUNTER_KEY, &0_u64.to_be_bytes())?;
+            }
+            Ok::<_, StorageError>(())
+        })?;
+        self.is_change_log_enabled.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    fn text_cf(&self) -> Result<&ColumnFamily, StorageError> {
+        self.text_cf
+            .as_ref()
+            .ok_or_else(|| CorruptionError::from_missing_column_family_name(TEXT_CF).into())
+    }
+
+    fn load_text_index_config(&self) -> Result<(), StorageError> {
+        let config = if let Some(text_cf) = &self.text_cf {
             self.db
-                .insert_stt_files(&[(&self.graphs_cf, stt_file.finish()?)])?;
-            version = 1;
-            self.update_version(version)?;
+                .snapshot()
+                .get(text_cf, TEXT_INDEX_CONFIG_KEY)?
+                .map(|config| TextIndexConfig::from_bytes(&config).map(Arc::new))
+                .transpose()?
+        } else {
+            None
+        };
+        *self.text_index_lock()? = config;
+        Ok(())
+    }
+
+    fn text_index_lock(
+        &self,
+    ) -> Result<RwLockWriteGuard<'_, Option<Arc<TextIndexConfig>>>, StorageError> {
+        self.text_index.write().map_err(|_| {
+            StorageError::Other("The text index configuration lock is poisoned".into())
+        })
+    }
+
+    fn text_index_config(&self) -> Result<Option<Arc<TextIndexConfig>>, StorageError> {
+        Ok(self
+            .text_index
+            .read()
+            .map_err(|_| {
+                StorageError::Other("The text index configuration lock is poisoned".into())
+            })?
+            .clone())
+    }
+
+    /// Builds the text index of the literals selected by `config` and keeps it up to date on writes.
+    ///
+    /// The existing text index is dropped first. Writes are blocked while the index is built.
+    pub fn enable_text_index(&self, config: TextIndexConfig) -> Result<(), StorageError> {
+        let text_cf = self.text_cf()?;
+        #[cfg(not(target_family = "wasm"))]
+        let _write_guard = self.str_gc.stop_writes()?;
+        let mut text_index = self.text_index_lock()?;
+
+        // We drop the configuration first so that an interrupted build leaves the index disabled
+        *text_index = None;
+        self.rebuild_index(
+            text_cf,
+            TEXT_INDEX_CONFIG_KEY,
+            &config.to_bytes(),
+            |snapshot, quad| {
+                Ok(if config.is_indexed(quad) {
+                    text_index_entries(&snapshot.decode_literal_value(&quad.object)?, quad)
+                } else {
+                    Vec::new()
+                })
+            },
+        )?;
+        *text_index = Some(Arc::new(config));
+        Ok(())
+    }
+
+    fn spatial_cf(&self) -> Result<&ColumnFamily, StorageError> {
+        self.spatial_cf
+            .as_ref()
+            .ok_or_else(|| CorruptionError::from_missing_column_family_name(SPATIAL_CF).into())
+    }
+
+    fn has_spatial_index(&self) -> Result<bool, StorageError> {
+        let Some(spatial_cf) = &self.spatial_cf else {
+            return Ok(false);
+        };
+        self.db
+            .snapshot()
+            .contains_key(spatial_cf, SPATIAL_INDEX_ENABLED_KEY)
+    }
+
+    /// Builds the spatial index of the `geo:wktLiteral` objects and keeps it up to date on writes.
+    ///
+    /// The existing spatial index is dropped first. Writes are blocked while the index is built.
+    pub fn enable_spatial_index(&self) -> Result<(), StorageError> {
+        let spatial_cf = self.spatial_cf()?;
+        #[cfg(not(target_family = "wasm"))]
+        let _write_guard = self.str_gc.stop_writes()?;
+
+        self.is_spatial_index_enabled
+            .store(false, Ordering::Release);
+        self.rebuild_index(
+            spatial_cf,
+            SPATIAL_INDEX_ENABLED_KEY,
+            &[],
+            |snapshot, quad| {
+                Ok(if is_wkt_literal(&quad.object) {
+                    spatial_index_entry(&snapshot.decode_literal_value(&quad.object)?, quad)
+                        .into_iter()
+                        .collect()
+                } else {
+                    Vec::new()
+                })
+            },
+        )?;
+        self.is_spatial_index_enabled.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Replaces the content of an index column family by the entries computed from all the quads.
+    ///
+    /// The `config_key` entry is removed first and written last so that an interrupted build is detected.
+    /// Writes must be blocked by the caller.
+    fn rebuild_index<V: AsRef<[u8]>>(
+        &self,
+        column_family: &ColumnFamily,
+        config_key: &[u8],
+        config: &[u8],
+        entries: impl Fn(&StorageReader, &EncodedQuad) -> Result<Vec<(Vec<u8>, V)>, StorageError>,
+    ) -> Result<(), StorageError> {
+        self.db.transaction(|mut transaction| {
+            transaction.remove(column_family, config_key)?;
+            Ok::<_, StorageError>(())
+        })?;
+        let snapshot = self.snapshot();
+        let mut keys = Vec::new();
+        let mut iter = snapshot.reader.scan_prefix(column_family, &[])?;
+        while let Some(key) = iter.key() {
+            keys.push(key.to_vec());
+            iter.next();
+        }
+        iter.status()?;
+        for batch in keys.chunks(INDEX_BUILD_BATCH_SIZE) {
+            self.db.transaction(|mut transaction| {
+                for key in batch {
+                    transaction.remove(column_family, key)?;
+                }
+                Ok::<_, StorageError>(())
+            })?;
         }
 
-        match version {
-            _ if version < LATEST_STORAGE_VERSION => Err(CorruptionError::msg(format!(
-                "The RocksDB database is using the outdated encoding version {version}. Automated migration is not supported, please dump the store dataset using a compatible Oxigraph version and load it again using the current version"
+        let mut new_entries = Vec::new();
+        for quad in snapshot.quads() {
+            new_entries.extend(entries(&snapshot, &quad?)?);
+        }
+        for batch in new_entries.chunks(INDEX_BUILD_BATCH_SIZE) {
+            self.db.transaction(|mut transaction| {
+                for (key, value) in batch {
+                    transaction.insert(column_family, key, value.as_ref())?;
+                }
+                Ok::<_, StorageError>(())
+            })?;
+        }
+        self.db.transaction(|mut transaction| {
+            transaction.insert(column_family, config_key, config)?;
+            Ok::<_, StorageError>(())
+        })
+    }
 
-            )).into()),
-            LATEST_STORAGE_VERSION => Ok(()),
-            _ => Err(CorruptionError::msg(format!(
-                "The RocksDB database is using the too recent version {version}. Upgrade to the latest Oxigraph version to load this database"
+    /// Removes from the change log all the changes with a sequence number lower than `before`.
+    pub fn truncate_change_log(&self, before: u64) -> Result<(), StorageError> {
+        let changes_cf = self.changes_cf()?;
+        let snapshot = self.snapshot();
+        let mut keys = Vec::new();
+        let mut iter = snapshot
+            .reader
+            .scan_from(changes_cf, &1_u64.to_be_bytes())?;
+        while let Some(key) = iter.key() {
+            if key >= before.to_be_bytes().as_slice() {
+                break;
+            }
+            keys.push(key.to_vec());
+            iter.next();
+        }
+        iter.status()?;
+        for batch in keys.chunks(CHANGE_LOG_BATCH_SIZE) {
+            self.db.transaction(|mut transaction| {
+                for key in batch {
+                    transaction.remove(changes_cf, key)?;
+                }
+                Ok::<_, StorageError>(())
+            })?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn flush(&self) -> Result<(), StorageError> {
+        self.db.flush()
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn compact(&self) -> Result<(), StorageError> {
+        self.collect_str_garbage()?;
+        self.update_statistics()?;
+        self.db.compact(&self.default_cf)?;
+        self.db.compact(&self.gspo_cf)?;
+        self.db.compact(&self.gpos_cf)?;
+        self.db.compact(&self.gosp_cf)?;
+        self.db.compact(&self.spog_cf)?;
+        self.db.compact(&self.posg_cf)?;
+        self.db.compact(&self.ospg_cf)?;
+        self.db.compact(&self.dspo_cf)?;
+        self.db.compact(&self.dpos_cf)?;
+        self.db.compact(&self.dosp_cf)?;
+        self.db.compact(self.changes_cf()?)?;
+        self.db.compact(self.text_cf()?)?;
+        self.db.compact(self.spatial_cf()?)?;
+        self.db.compact(&self.id2str_cf)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn backup(&self, target_directory: &Path) -> Result<(), StorageError> {
+        self.db.backup(target_directory)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn incremental_backup(&self, backup_directory: &Path) -> Result<BackupInfo, StorageError> {
+        let engine = BackupEngine::open(backup_directory)?;
+        let backup_id = self.db.create_new_backup(&engine)?;
+        engine
+            .backups()
+            .into_iter()
+            .find(|backup| backup.id == backup_id)
+            .ok_or_else(|| {
+                StorageError::Other(format!("The backup {backup_id} has not been found").into())
+            })
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn list_backups(backup_directory: &Path) -> Result<Vec<BackupInfo>, StorageError> {
+        Ok(BackupEngine::open(backup_directory)?.backups())
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn purge_old_backups(
+        backup_directory: &Path,
+        num_backups_to_keep: u32,
+    ) -> Result<(), StorageError> {
+        BackupEngine::open(backup_directory)?.purge_old_backups(num_backups_to_keep)
+    }
+
+    /// Restores a backup into `target_directory`.
+    ///
+    /// The backup is first restored in a sibling directory and validated.
+    /// The directories are only swapped if the validation succeeds.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn restore_backup(
+        backup_directory: &Path,
+        backup_id: Option<u32>,
+        target_directory: &Path,
+    ) -> Result<(), StorageError> {
+        let engine = BackupEngine::open(backup_directory)?;
+        let backup_id = if let Some(backup_id) = backup_id {
+            backup_id
+        } else {
+            engine
+                .backups()
+                .last()
+                .ok_or_else(|| {
+                    StorageError::Other(
+                        format!("There is no backup in {}", backup_directory.display()).into(),
+                    )
+                })?
+                .id
+        };
+        engine.verify_backup(backup_id)?;
+        let sibling_directory = |kind: &str| {
+            let mut name = target_directory
+                .file_name()
+                .unwrap_or(target_directory.as_os_str())
+                .to_owned();
+            name.push(format!(".{kind}-{}", random::<u64>()));
+            target_directory.with_file_name(name)
+        };
+        let restore_directory = sibling_directory("restore");
+        let result = engine
+            .restore_backup(backup_id, &restore_directory)
+            .and_then(|()| {
+                Self::open_read_only(&restore_directory)?
+                    .snapshot()
+                    .validate()
+            });
+        if let Err(e) = result {
+            drop(remove_dir_all(&restore_directory));
+            return Err(e);
+        }
+        if target_directory.exists() {
+            let old_directory = sibling_directory("old");
+            rename(target_directory, &old_directory)?;
+            if let Err(e) = rename(&restore_directory, target_directory) {
+                // We try to put back the previous content
+                drop(rename(&old_directory, target_directory));
+                return Err(e.into());
+            }
+            remove_dir_all(&old_directory)?;
+        } else {
+            rename(&restore_directory, target_directory)?;
+        }
+        Ok(())
+    }
+
+    /// Removes from `id2str` the strings that are not used by any quad or named graph anymore.
+    ///
+    /// Writes are allowed to run during the collection:
+    /// the strings written after the collection snapshot has been taken are never removed.
+    /// The writes are only blocked while a batch of strings is removed.
+    ///
+    /// The hashes of all the used strings are kept in memory during the collection.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn collect_str_garbage(&self) -> Result<(), StorageError> {
+        let _collection_guard = self
+            .str_gc
+            .collection
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Mutex poisoned"))?;
+        let (snapshot, older_snapshots) = {
+            // We wait for all the running writes to be done to get a snapshot containing all of them
+            let _writes_guard = self.str_gc.stop_writes()?;
+            // The snapshots taken before ours might still use strings that are not used anymore
+            let snapshots = self.str_gc.snapshots()?;
+            let snapshot = self.snapshot();
+            let older_snapshots = snapshots
+                .iter()
+                .filter_map(Weak::upgrade)
+                .map(|s| StorageReader {
+                    reader: s.reader(),
+                    storage: self.clone(),
+                    _snapshot: Some(s),
+                    dropped_graphs: OnceLock::new(),
+                })
+                .collect::<Vec<_>>();
+            self.str_gc.start_collection()?;
+            (snapshot, older_snapshots)
+        };
+        let result = self.remove_unused_strs(&snapshot, &older_snapshots);
+        self.str_gc.end_collection()?;
+        result
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn remove_unused_strs(
+        &self,
+        snapshot: &StorageReader,
+        older_snapshots: &[StorageReader],
+    ) -> Result<(), StorageError> {
+        let used = snapshot.used_strs(older_snapshots)?;
+        let mut unused = Vec::with_capacity(GARBAGE_COLLECTION_BATCH_SIZE);
+        let mut iter = snapshot.reader.iter(&self.id2str_cf)?;
+        while let Some(key) = iter.key() {
+            let key = StrHash::from_be_bytes(
+                key.try_into()
+                    .map_err(|e| CorruptionError::new(format!("Invalid key in id2str: {e}")))?,
+            );
+            if !used.contains(&key) {
+                unused.push(key);
+                if unused.len() >= GARBAGE_COLLECTION_BATCH_SIZE {
+                    self.remove_strs(&unused)?;
+                    unused.clear();
+                }
+            }
+            iter.next();
+        }
+        iter.status()?;
+        self.remove_strs(&unused)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn remove_strs(&self, keys: &[StrHash]) -> Result<(), StorageError> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+        // We stop all writes in order to remove the unused strings without concurrent reuse
+        // The lock is only held for one batch to not block the writers during the full collection
+        let _writes_guard = self.str_gc.stop_writes()?;
+        if self.str_gc.has_untracked_writes.load(Ordering::Acquire) {
+            // Any string might have been reused
+            return Ok(());
+        }
+        let written = self.str_gc.written_strs()?;
+        self.db.transaction(|mut transaction| {
+            for key in keys {
+                if !written.contains(key) {
+                    transaction.remove(&self.id2str_cf, &key.to_be_bytes())?;
+                }
+            }
+            Ok::<_, StorageError>(())
+        })
+    }
 
-            )).into())
+    /// Computes the cardinality statistics of the stored quads and saves them in the default column family.
+    ///
+    /// The statistics are only used by the query optimizer and are not updated on writes.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn update_statistics(&self) -> Result<(), StorageError> {
+        let snapshot = self.snapshot();
+        let statistics = snapshot.compute_statistics()?;
+        let mut entries =
+            Vec::with_capacity(statistics.predicates.len() + statistics.graphs.len() + 1);
+        entries.push((DATASET_STATISTICS_KEY.to_vec(), statistics.dataset));
+        for (predicate, predicate_statistics) in statistics.predicates {
+            entries.push((
+                statistics_key(PREDICATE_STATISTICS_PREFIX, &predicate),
+                predicate_statistics,
+            ));
+        }
+        for (graph_name, graph_statistics) in statistics.graphs {
+            entries.push((
+                statistics_key(GRAPH_STATISTICS_PREFIX, &graph_name),
+                graph_statistics,
+            ));
+        }
+        // We first write the new statistics and then remove the outdated ones
+        let mut outdated = HashSet::new();
+        let mut iter = snapshot
+            .reader
+            .scan_prefix(&self.default_cf, STATISTICS_PREFIX)?;
+        while let Some(key) = iter.key() {
+            outdated.insert(key.to_vec());
+            iter.next();
         }
+        iter.status()?;
+        for batch in entries.chunks(GARBAGE_COLLECTION_BATCH_SIZE) {
+            self.db.transaction(|mut transaction| {
+                for (key, value) in batch {
+                    transaction.insert(&self.default_cf, key, &encode_statistics(value))?;
+                }
+                Ok::<_, StorageError>(())
+            })?;
+        }
+        for (key, _) in &entries {
+            outdated.remove(key);
+        }
+        for batch in outdated
+            .into_iter()
+            .collect::<Vec<_>>()
+            .chunks(GARBAGE_COLLECTION_BATCH_SIZE)
+        {
+            self.db.transaction(|mut transaction| {
+                for key in batch {
+                    transaction.remove(&self.default_cf, key)?;
+                }
+                Ok::<_, StorageError>(())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Keeps track of the strings written while a `id2str` garbage collection is running.
+#[cfg(not(target_family = "wasm"))]
+#[derive(Default)]
+struct StrGarbageCollector {
+    /// Held in read mode during writes and in write mode by the collector when it needs all writes to be done
+    writes: RwLock<()>,
+    /// Held during a collection to avoid concurrent collections
+    collection: Mutex<()>,
+    is_collecting: AtomicBool,
+    written_strs: Mutex<HashSet<StrHash>>,
+    /// Set if strings have been written during the collection without being added to `written_strs`,
+    /// the collection must not remove any string
+    has_untracked_writes: AtomicBool,
+    /// The [`StorageSnapshot`]s that might still be alive, their strings must be kept by the collection
+    snapshots: Mutex<Vec<Weak<SharedSnapshot>>>,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl StrGarbageCollector {
+    fn start_write(&self) -> Result<RwLockReadGuard<'_, ()>, StorageError> {
+        Ok(self
+            .writes
+            .read()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "RwLock poisoned"))?)
+    }
+
+    fn stop_writes(&self) -> Result<RwLockWriteGuard<'_, ()>, StorageError> {
+        Ok(self
+            .writes
+            .write()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "RwLock poisoned"))?)
     }
 
-    #[cfg(not(target_family = "wasm"))]
-    fn ensure_version(&self) -> Result<u64, StorageError> {
-        Ok(
-            if let Some(version) = self.db.get(&self.default_cf, b"oxversion")? {
-                u64::from_be_bytes(version.as_ref().try_into().map_err(|e| {
-                    CorruptionError::new(format!("Error while parsing the version key: {e}"))
-                })?)
-            } else {
-                self.update_version(LATEST_STORAGE_VERSION)?;
-                LATEST_STORAGE_VERSION
-            },
-        )
+    fn snapshots(&self) -> Result<MutexGuard<'_, Vec<Weak<SharedSnapshot>>>, StorageError> {
+        Ok(self
+            .snapshots
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Mutex poisoned"))?)
     }
 
-    #[cfg(not(target_family = "wasm"))]
-    fn update_version(&self, version: u64) -> Result<(), StorageError> {
-        self.db
-            .insert(&self.default_cf, b"oxversion", &version.to_be_bytes())?;
-        self.db.flush()
+    fn on_str_write(&self, key: &StrHash) -> Result<(), StorageError> {
+        if self.is_collecting.load(Ordering::Acquire) {
+            self.written_strs
+                .lock()
+                .map_err(|_| io::Error::new(io::ErrorKind::Other, "Mutex poisoned"))?
+                .insert(*key);
+        }
+        Ok(())
     }
 
-    pub fn snapshot(&self) -> StorageReader {
-        StorageReader {
-            reader: self.db.snapshot(),
-            storage: self.clone(),
+    /// Notifies the running collection if any that some unknown strings have been written
+    fn on_untracked_strs_write(&self) {
+        if self.is_collecting.load(Ordering::Acquire) {
+            self.has_untracked_writes.store(true, Ordering::Release);
         }
     }
 
-    pub fn transaction<'a, 'b: 'a, T, E: Error + 'static + From<StorageError>>(
-        &'b self,
-        f: impl Fn(StorageWriter<'a>) -> Result<T, E>,
-    ) -> Result<T, E> {
-        self.db.transaction(|transaction| {
-            f(StorageWriter {
-                buffer: Vec::new(),
-                transaction,
-                storage: self,
-            })
-        })
+    fn start_collection(&self) -> Result<(), StorageError> {
+        self.written_strs
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Mutex poisoned"))?
+            .clear();
+        self.has_untracked_writes.store(false, Ordering::Release);
+        self.is_collecting.store(true, Ordering::Release);
+        Ok(())
     }
 
-    #[cfg(not(target_family = "wasm"))]
-    pub fn flush(&self) -> Result<(), StorageError> {
-        self.db.flush()
+    fn written_strs(&self) -> Result<MutexGuard<'_, HashSet<StrHash>>, StorageError> {
+        Ok(self
+            .written_strs
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Mutex poisoned"))?)
     }
 
-    #[cfg(not(target_family = "wasm"))]
-    pub fn compact(&self) -> Result<(), StorageError> {
-        self.db.compact(&self.default_cf)?;
-        self.db.compact(&self.gspo_cf)?;
-        self.db.compact(&self.gpos_cf)?;
-        self.db.compact(&self.gosp_cf)?;
-        self.db.compact(&self.spog_cf)?;
-        self.db.compact(&self.posg_cf)?;
-        self.db.compact(&self.ospg_cf)?;
-        self.db.compact(&self.dspo_cf)?;
-        self.db.compact(&self.dpos_cf)?;
-        self.db.compact(&self.dosp_cf)?;
-        self.db.compact(&self.id2str_cf)
+    fn end_collection(&self) -> Result<(), StorageError> {
+        self.is_collecting.store(false, Ordering::Release);
+        self.has_untracked_writes.store(false, Ordering::Release);
+        self.written_strs()?.clear();
+        Ok(())
     }
+}
 
-    #[cfg(not(target_family = "wasm"))]
-    pub fn backup(&self, target_directory: &Path) -> Result<(), StorageError> {
-        self.db.backup(target_directory)
+/// A snapshot of the storage that might be kept for a long time and shared between threads.
+///
+/// The `id2str` garbage collection keeps the strings used by such snapshots.
+#[derive(Clone)]
+pub struct StorageSnapshot {
+    snapshot: Arc<SharedSnapshot>,
+    storage: Storage,
+}
+
+impl StorageSnapshot {
+    pub fn reader(&self) -> StorageReader {
+        StorageReader {
+            reader: self.snapshot.reader(),
+            storage: self.storage.clone(),
+            _snapshot: Some(Arc::clone(&self.snapshot)),
+            dropped_graphs: OnceLock::new(),
+        }
     }
 }
 
 pub struct StorageReader {
     reader: Reader,
     storage: Storage,
+    /// Keeps the [`StorageSnapshot`] registered while the reader is alive
+    _snapshot: Option<Arc<SharedSnapshot>>,
+    /// The graphs being dropped whose quads are ignored, loaded on first use
+    dropped_graphs: OnceLock<Arc<[EncodedTerm]>>,
 }
 
 impl StorageReader {
@@ -323,6 +1660,9 @@ impl StorageReader {
     }
 
     pub fn contains(&self, quad: &EncodedQuad) -> Result<bool, StorageError> {
+        if self.dropped_graphs()?.contains(&quad.graph_name) {
+            return Ok(false);
+        }
         let mut buffer = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE);
         if quad.graph_name.is_default_graph() {
             write_spo_quad(&mut buffer, quad);
@@ -396,6 +1736,185 @@ impl StorageReader {
         }
     }
 
+    /// Returns the number of quads matching the pattern.
+    ///
+    /// The keys of the index whose prefix is the pattern are counted without being decoded.
+    pub fn count_for_pattern(
+        &self,
+        subject: Option<&EncodedTerm>,
+        predicate: Option<&EncodedTerm>,
+        object: Option<&EncodedTerm>,
+        graph_name: Option<&EncodedTerm>,
+    ) -> Result<usize, StorageError> {
+        let Some(graph_name) = graph_name else {
+            return Ok(self.count_for_pattern(
+                subject,
+                predicate,
+                object,
+                Some(&EncodedTerm::DefaultGraph),
+            )? + self.count_in_named_graphs(subject, predicate, object)?);
+        };
+        let (column_family, prefix) = if graph_name.is_default_graph() {
+            match (subject, predicate, object) {
+                (None, None, None) => return self.reader.len(&self.storage.dspo_cf),
+                (Some(subject), None, None) => (&self.storage.dspo_cf, encode_term(subject)),
+                (Some(subject), Some(predicate), None) => {
+                    (&self.storage.dspo_cf, encode_term_pair(subject, predicate))
+                }
+                (Some(subject), Some(predicate), Some(object)) => (
+                    &self.storage.dspo_cf,
+                    encode_term_triple(subject, predicate, object),
+                ),
+                (Some(subject), None, Some(object)) => {
+                    (&self.storage.dosp_cf, encode_term_pair(object, subject))
+                }
+                (None, Some(predicate), None) => (&self.storage.dpos_cf, encode_term(predicate)),
+                (None, Some(predicate), Some(object)) => {
+                    (&self.storage.dpos_cf, encode_term_pair(predicate, object))
+                }
+                (None, None, Some(object)) => (&self.storage.dosp_cf, encode_term(object)),
+            }
+        } else {
+            match (subject, predicate, object) {
+                (None, None, None) => (&self.storage.gspo_cf, encode_term(graph_name)),
+                (Some(subject), None, None) => {
+                    (&self.storage.gspo_cf, encode_term_pair(graph_name, subject))
+                }
+                (Some(subject), Some(predicate), None) => (
+                    &self.storage.gspo_cf,
+                    encode_term_triple(graph_name, subject, predicate),
+                ),
+                (Some(subject), Some(predicate), Some(object)) => (
+                    &self.storage.gspo_cf,
+                    encode_term_quad(graph_name, subject, predicate, object),
+                ),
+                (Some(subject), None, Some(object)) => (
+                    &self.storage.gosp_cf,
+                    encode_term_triple(graph_name, object, subject),
+                ),
+                (None, Some(predicate), None) => (
+                    &self.storage.gpos_cf,
+                    encode_term_pair(graph_name, predicate),
+                ),
+                (None, Some(predicate), Some(object)) => (
+                    &self.storage.gpos_cf,
+                    encode_term_triple(graph_name, predicate, object),
+                ),
+                (None, None, Some(object)) => {
+                    (&self.storage.gosp_cf, encode_term_pair(graph_name, object))
+                }
+            }
+        };
+        self.count_keys(column_family, &prefix)
+    }
+
+    fn count_in_named_graphs(
+        &self,
+        subject: Option<&EncodedTerm>,
+        predicate: Option<&EncodedTerm>,
+        object: Option<&EncodedTerm>,
+    ) -> Result<usize, StorageError> {
+        let (column_family, prefix) = match (subject, predicate, object) {
+            (None, None, None) => return self.reader.len(&self.storage.gspo_cf),
+            (Some(subject), None, None) => (&self.storage.spog_cf, encode_term(subject)),
+            (Some(subject), Some(predicate), None) => {
+                (&self.storage.spog_cf, encode_term_pair(subject, predicate))
+            }
+            (Some(subject), Some(predicate), Some(object)) => (
+                &self.storage.spog_cf,
+                encode_term_triple(subject, predicate, object),
+            ),
+            (Some(subject), None, Some(object)) => {
+                (&self.storage.ospg_cf, encode_term_pair(object, subject))
+            }
+            (None, Some(predicate), None) => (&self.storage.posg_cf, encode_term(predicate)),
+            (None, Some(predicate), Some(object)) => {
+                (&self.storage.posg_cf, encode_term_pair(predicate, object))
+            }
+            (None, None, Some(object)) => (&self.storage.ospg_cf, encode_term(object)),
+        };
+        self.count_keys(column_family, &prefix)
+    }
+
+    fn count_keys(
+        &self,
+        column_family: &ColumnFamily,
+        prefix: &[u8],
+    ) -> Result<usize, StorageError> {
+        let mut iter = self.reader.scan_prefix(column_family, prefix)?;
+        let mut count = 0;
+        while iter.key().is_some() {
+            count += 1;
+            iter.next();
+        }
+        iter.status()?;
+        Ok(count)
+    }
+
+    /// Returns the quads with the given predicate whose object is between `start` and `end` included.
+    ///
+    /// `start` and `end` must be literals of the same type, the quads with an object of an other type are not returned.
+    /// The timezone offsets of the date and time literals are ignored: only their timestamps are compared.
+    pub fn quads_for_predicate_object_range(
+        &self,
+        predicate: &EncodedTerm,
+        start: &EncodedTerm,
+        end: &EncodedTerm,
+        graph_name: Option<&EncodedTerm>,
+    ) -> ChainedDecodingQuadIterator {
+        let range = |prefix: Vec<u8>| {
+            let mut start_key = prefix.clone();
+            start_key.extend_from_slice(&encode_term_order_key(start));
+            let mut end_key = prefix;
+            end_key.extend_from_slice(&encode_term_order_key(end));
+            // All the keys starting with the end key are in the range
+            while let Some(last) = end_key.pop() {
+                if last < u8::MAX {
+                    end_key.push(last + 1);
+                    break;
+                }
+            }
+            (start_key, end_key)
+        };
+        match graph_name {
+            Some(graph_name) if graph_name.is_default_graph() => {
+                let (start, end) = range(encode_term(predicate));
+                ChainedDecodingQuadIterator::new(self.inner_quads_in_range(
+                    &self.storage.dpos_cf,
+                    &start,
+                    &end,
+                    QuadEncoding::Dpos,
+                ))
+            }
+            Some(graph_name) => {
+                let (start, end) = range(encode_term_pair(graph_name, predicate));
+                ChainedDecodingQuadIterator::new(self.inner_quads_in_range(
+                    &self.storage.gpos_cf,
+                    &start,
+                    &end,
+                    QuadEncoding::Gpos,
+                ))
+            }
+            None => {
+                let (start, end) = range(encode_term(predicate));
+                ChainedDecodingQuadIterator::pair(
+                    self.inner_quads_in_range(
+                        &self.storage.dpos_cf,
+                        &start,
+                        &end,
+                        QuadEncoding::Dpos,
+                    ),
+                    self.inner_quads_in_range(
+                        &self.storage.posg_cf,
+                        &start,
+                        &end,
+                        QuadEncoding::Posg,
+                    ),
+                )
+            }
+        }
+    }
+
     pub fn quads(&self) -> ChainedDecodingQuadIterator {
         ChainedDecodingQuadIterator::pair(self.dspo_quads(&[]), self.gspo_quads(&[]))
     }
@@ -578,6 +2097,133 @@ impl StorageReader {
             .contains_key(&self.storage.graphs_cf, &encode_term(graph_name))
     }
 
+    /// Returns the changes of the change log with a sequence number greater or equal to `from`
+    pub fn changes(&self, from: u64) -> DecodingChangeIterator {
+        let Some(changes_cf) = &self.storage.changes_cf else {
+            // The change log is disabled
+            return DecodingChangeIterator {
+                iter: None,
+                error: None,
+            };
+        };
+        match self.reader.scan_from(changes_cf, &from.to_be_bytes()) {
+            Ok(iter) => DecodingChangeIterator {
+                iter: Some(iter),
+                error: None,
+            },
+            Err(e) => DecodingChangeIterator {
+                iter: None,
+                error: Some(e),
+            },
+        }
+    }
+
+    /// Returns the sequence number of the last change written to the change log or `None` if the change log is disabled
+    pub fn last_change_sequence_number(&self) -> Result<Option<u64>, StorageError> {
+        let Some(changes_cf) = &self.storage.changes_cf else {
+            return Ok(None);
+        };
+        self.reader
+            .get(changes_cf, CHANGE_COUNTER_KEY)?
+            .map(|counter| decode_change_sequence_number(&counter))
+            .transpose()
+    }
+
+    /// Returns the quads whose object literal contains all the tokens of the query with their score.
+    ///
+    /// The score is the sum of the frequencies of the query tokens in the literal.
+    pub fn text_search(&self, query: &str) -> Result<Vec<(EncodedQuad, f32)>, StorageError> {
+        let Some(text_cf) = &self.storage.text_cf else {
+            return Ok(Vec::new());
+        };
+        let mut matches: Option<HashMap<Vec<u8>, f32>> = None;
+        for token in tokenize(query).keys() {
+            let prefix = token_prefix(token);
+            let mut token_matches = HashMap::new();
+            let mut iter = self.reader.scan_prefix(text_cf, &prefix)?;
+            while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                let quad = &key[prefix.len()..];
+                let score = if let Some(matches) = &matches {
+                    matches.get(quad).copied()
+                } else {
+                    Some(0.)
+                };
+                if let Some(score) = score {
+                    token_matches.insert(quad.to_vec(), score + decode_entry_score(value)?);
+                }
+                iter.next();
+            }
+            iter.status()?;
+            matches = Some(token_matches);
+        }
+        let dropped_graphs = self.dropped_graphs()?;
+        matches
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(quad, score)| Ok((decode_spog_quad(&quad)?, score)))
+            .filter(|result| {
+                result
+                    .as_ref()
+                    .map_or(true, |(quad, _)| !dropped_graphs.contains(&quad.graph_name))
+            })
+            .collect()
+    }
+
+    fn decode_literal_value(&self, literal: &EncodedTerm) -> Result<String, StorageError> {
+        if let Term::Literal(literal) = self.decode_term(literal)? {
+            Ok(literal.value().to_owned())
+        } else {
+            Err(CorruptionError::msg("A text indexed term is not a literal").into())
+        }
+    }
+
+    /// Returns if the spatial index is enabled and fully built in this snapshot
+    pub fn has_spatial_index(&self) -> Result<bool, StorageError> {
+        let Some(spatial_cf) = &self.storage.spatial_cf else {
+            return Ok(false);
+        };
+        self.reader
+            .contains_key(spatial_cf, SPATIAL_INDEX_ENABLED_KEY)
+    }
+
+    /// Returns the quads with a `geo:wktLiteral` object whose bounding box intersects the given one.
+    pub fn spatial_search(&self, bbox: &BoundingBox) -> Result<Vec<EncodedQuad>, StorageError> {
+        let Some(spatial_cf) = &self.storage.spatial_cf else {
+            return Ok(Vec::new());
+        };
+        let scan = SpatialScan::new(bbox);
+        let mut results = Vec::new();
+        let mut add_entry = |key: &[u8], value: &[u8]| {
+            let (quad, entry_bbox) = decode_spatial_entry(key, value)?;
+            if entry_bbox.intersects(bbox) {
+                results.push(quad);
+            }
+            Ok::<_, StorageError>(())
+        };
+        for prefix in &scan.ancestor_prefixes {
+            let mut iter = self.reader.scan_prefix(spatial_cf, prefix)?;
+            while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                add_entry(key, value)?;
+                iter.next();
+            }
+            iter.status()?;
+        }
+        let mut iter = self.reader.scan_from(spatial_cf, &scan.start)?;
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            if scan.is_after_range(key) {
+                break;
+            }
+            if scan.is_descendant(key) {
+                add_entry(key, value)?;
+            }
+            iter.next();
+        }
+        iter.status()?;
+        let dropped_graphs = self.dropped_graphs()?;
+        results.retain(|quad| !dropped_graphs.contains(&quad.graph_name));
+        Ok(results)
+    }
+
     fn spog_quads(&self, prefix: &[u8]) -> DecodingQuadIterator {
         self.inner_quads(&self.storage.spog_cf, prefix, QuadEncoding::Spog)
     }
@@ -620,19 +2266,65 @@ impl StorageReader {
         prefix: &[u8],
         encoding: QuadEncoding,
     ) -> DecodingQuadIterator {
-        DecodingQuadIterator {
-            iter: self.reader.scan_prefix(column_family, prefix).unwrap(), // TODO: propagate error?
+        DecodingQuadIterator::new(
+            self.dropped_graphs().and_then(|dropped_graphs| {
+                Ok((
+                    self.reader.scan_prefix(column_family, prefix)?,
+                    dropped_graphs,
+                ))
+            }),
+            encoding,
+        )
+    }
+
+    fn inner_quads_in_range(
+        &self,
+        column_family: &ColumnFamily,
+        start: &[u8],
+        end: &[u8],
+        encoding: QuadEncoding,
+    ) -> DecodingQuadIterator {
+        DecodingQuadIterator::new(
+            self.dropped_graphs().and_then(|dropped_graphs| {
+                Ok((
+                    self.reader.scan_range(column_family, start, end)?,
+                    dropped_graphs,
+                ))
+            }),
             encoding,
+        )
+    }
+
+    /// The graphs whose drop is not completed in this snapshot
+    fn dropped_graphs(&self) -> Result<Arc<[EncodedTerm]>, StorageError> {
+        if let Some(dropped_graphs) = self.dropped_graphs.get() {
+            return Ok(Arc::clone(dropped_graphs));
         }
+        #[cfg(not(target_family = "wasm"))]
+        let dropped_graphs = self.storage.dropped_graphs(&self.reader)?.into();
+        #[cfg(target_family = "wasm")]
+        let dropped_graphs = Arc::from(Vec::new());
+        Ok(Arc::clone(
+            self.dropped_graphs.get_or_init(|| dropped_graphs),
+        ))
     }
 
     #[cfg(not(target_family = "wasm"))]
     pub fn get_str(&self, key: &StrHash) -> Result<Option<String>, StorageError> {
-        Ok(self
+        let value = if let Some(value) = self
             .storage
             .db
             .get(&self.storage.id2str_cf, &key.to_be_bytes())?
-            .map(|v| String::from_utf8(v.into()))
+        {
+            Some(value.into())
+        } else {
+            // The string might have been garbage collected after the snapshot creation
+            self.reader
+                .get(&self.storage.id2str_cf, &key.to_be_bytes())?
+                .map(Vec::from)
+        };
+        Ok(value
+            .map(String::from_utf8)
             .transpose()
             .map_err(CorruptionError::new)?)
     }
@@ -649,9 +2341,198 @@ impl StorageReader {
 
     #[cfg(not(target_family = "wasm"))]
     pub fn contains_str(&self, key: &StrHash) -> Result<bool, StorageError> {
-        self.storage
+        // The string might have been garbage collected after the snapshot creation
+        Ok(self
+            .storage
             .db
-            .contains_key(&self.storage.id2str_cf, &key.to_be_bytes())
+            .contains_key(&self.storage.id2str_cf, &key.to_be_bytes())?
+            || self
+                .reader
+                .contains_key(&self.storage.id2str_cf, &key.to_be_bytes())?)
+    }
+
+    /// Returns the keys of `id2str` that are used by any quad, named graph or change in this snapshot or in the given ones
+    #[cfg(not(target_family = "wasm"))]
+    fn used_strs(&self, older_snapshots: &[Self]) -> Result<HashSet<StrHash>, StorageError> {
+        let mut used = HashSet::new();
+        self.mark_used_strs(&mut used)?;
+        for snapshot in older_snapshots {
+            snapshot.mark_used_strs(&mut used)?;
+        }
+        Ok(used)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn mark_used_strs(&self, used: &mut HashSet<StrHash>) -> Result<(), StorageError> {
+        let mut mark = |key: &StrHash| {
+            used.insert(*key);
+        };
+        for quad in self.quads() {
+            let quad = quad?;
+            for_each_str_hash(&quad.subject, &mut mark);
+            for_each_str_hash(&quad.predicate, &mut mark);
+            for_each_str_hash(&quad.object, &mut mark);
+            for_each_str_hash(&quad.graph_name, &mut mark);
+        }
+        for graph_name in self.named_graphs() {
+            for_each_str_hash(&graph_name?, &mut mark);
+        }
+        for change in self.changes(1) {
+            match change?.1 {
+                EncodedChange::Insert(quad) | EncodedChange::Remove(quad) => {
+                    for_each_str_hash(&quad.subject, &mut mark);
+                    for_each_str_hash(&quad.predicate, &mut mark);
+                    for_each_str_hash(&quad.object, &mut mark);
+                    for_each_str_hash(&quad.graph_name, &mut mark);
+                }
+                EncodedChange::InsertNamedGraph(graph_name)
+                | EncodedChange::RemoveNamedGraph(graph_name) => {
+                    for_each_str_hash(&graph_name, &mut mark);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the statistics about all the quads saved during the last [`Storage::update_statistics`] call
+    #[cfg(not(target_family = "wasm"))]
+    pub fn dataset_statistics(&self) -> Result<Option<TripleStatistics>, StorageError> {
+        self.reader
+            .get(&self.storage.default_cf, DATASET_STATISTICS_KEY)?
+            .map(|value| decode_statistics(&value))
+            .transpose()
+    }
+
+    /// Returns the statistics about the quads with the given predicate saved during the last [`Storage::update_statistics`] call
+    #[cfg(not(target_family = "wasm"))]
+    pub fn predicate_statistics(
+        &self,
+        predicate: &EncodedTerm,
+    ) -> Result<Option<TripleStatistics>, StorageError> {
+        self.reader
+            .get(
+                &self.storage.default_cf,
+                &statistics_key(PREDICATE_STATISTICS_PREFIX, predicate),
+            )?
+            .map(|value| decode_statistics(&value))
+            .transpose()
+    }
+
+    /// Returns the statistics about the quads in the given graph saved during the last [`Storage::update_statistics`] call
+    #[cfg(not(target_family = "wasm"))]
+    pub fn graph_statistics(
+        &self,
+        graph_name: &EncodedTerm,
+    ) -> Result<Option<TripleStatistics>, StorageError> {
+        self.reader
+            .get(
+                &self.storage.default_cf,
+                &statistics_key(GRAPH_STATISTICS_PREFIX, graph_name),
+            )?
+            .map(|value| decode_statistics(&value))
+            .transpose()
+    }
+
+    #[cfg(target_family = "wasm")]
+    #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
+    pub fn dataset_statistics(&self) -> Result<Option<TripleStatistics>, StorageError> {
+        Ok(None) // Statistics are not computed
+    }
+
+    #[cfg(target_family = "wasm")]
+    #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
+    pub fn predicate_statistics(
+        &self,
+        _predicate: &EncodedTerm,
+    ) -> Result<Option<TripleStatistics>, StorageError> {
+        Ok(None) // Statistics are not computed
+    }
+
+    #[cfg(target_family = "wasm")]
+    #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
+    pub fn graph_statistics(
+        &self,
+        _graph_name: &EncodedTerm,
+    ) -> Result<Option<TripleStatistics>, StorageError> {
+        Ok(None) // Statistics are not computed
+    }
+
+    /// Computes the statistics using the indexes sorted in the relevant order to count distinct values in streaming.
+    ///
+    /// The default graph and the named graphs are counted separately so the distinct counts might be overestimated.
+    #[cfg(not(target_family = "wasm"))]
+    fn compute_statistics(&self) -> Result<StorageStatistics, StorageError> {
+        let mut statistics = StorageStatistics::default();
+        for_each_quad_with_previous(
+            [self.dspo_quads(&[]), self.spog_quads(&[])],
+            |quad, previous| {
+                let same_subject = previous.map_or(false, |p| p.subject == quad.subject);
+                statistics.dataset.count += 1;
+                if !same_subject {
+                    statistics.dataset.distinct_subjects += 1;
+                }
+                let predicate = statistics
+                    .predicates
+                    .entry(quad.predicate.clone())
+                    .or_default();
+                predicate.count += 1;
+                if !same_subject || previous.map_or(true, |p| p.predicate != quad.predicate) {
+                    predicate.distinct_subjects += 1;
+                }
+            },
+        )?;
+        for_each_quad_with_previous(
+            [self.dpos_quads(&[]), self.posg_quads(&[])],
+            |quad, previous| {
+                if previous.map_or(true, |p| {
+                    p.predicate != quad.predicate || p.object != quad.object
+                }) {
+                    statistics
+                        .predicates
+                        .entry(quad.predicate.clone())
+                        .or_default()
+                        .distinct_objects += 1;
+                }
+            },
+        )?;
+        for_each_quad_with_previous(
+            [self.dosp_quads(&[]), self.ospg_quads(&[])],
+            |quad, previous| {
+                if previous.map_or(true, |p| p.object != quad.object) {
+                    statistics.dataset.distinct_objects += 1;
+                }
+            },
+        )?;
+        for_each_quad_with_previous(
+            [self.dspo_quads(&[]), self.gspo_quads(&[])],
+            |quad, previous| {
+                let graph = statistics
+                    .graphs
+                    .entry(quad.graph_name.clone())
+                    .or_default();
+                graph.count += 1;
+                if previous.map_or(true, |p| {
+                    p.graph_name != quad.graph_name || p.subject != quad.subject
+                }) {
+                    graph.distinct_subjects += 1;
+                }
+            },
+        )?;
+        for_each_quad_with_previous(
+            [self.dosp_quads(&[]), self.gosp_quads(&[])],
+            |quad, previous| {
+                if previous.map_or(true, |p| {
+                    p.graph_name != quad.graph_name || p.object != quad.object
+                }) {
+                    statistics
+                        .graphs
+                        .entry(quad.graph_name.clone())
+                        .or_default()
+                        .distinct_objects += 1;
+                }
+            },
+        )?;
+        Ok(statistics)
     }
 
     #[cfg(target_family = "wasm")]
@@ -660,6 +2541,17 @@ impl StorageReader {
             .contains_key(&self.storage.id2str_cf, &key.to_be_bytes())
     }
 
+    /// Writes the content of all the column families into `write`.
+    pub fn save_to_write<W: Write>(&self, write: W) -> Result<W, StorageError> {
+        image::write_image(&self.reader, &self.storage.column_families_by_name(), write)
+    }
+
+    /// Writes the content of all the column families into a single file image that might be opened with [`Storage::open_packed`].
+    #[cfg(not(target_family = "wasm"))]
+    pub fn save_packed_to_write<W: Write>(&self, write: W) -> Result<W, StorageError> {
+        packed::write_packed(&self.reader, &self.storage.column_families_by_name(), write)
+    }
+
     /// Validates that all the storage invariants held in the data
     #[cfg(not(target_family = "wasm"))]
     pub fn validate(&self) -> Result<(), StorageError> {
@@ -772,12 +2664,75 @@ impl StorageReader {
         Ok(())
     }
 
-    /// Validates that all the storage invariants held in the data
-    #[cfg(target_family = "wasm")]
-    #[allow(clippy::unused_self, clippy::unnecessary_wraps)]
-    pub fn validate(&self) -> Result<(), StorageError> {
-        Ok(()) // TODO
+    /// Validates that all the storage invariants held in the data
+    #[cfg(target_family = "wasm")]
+    #[allow(clippy::unused_self, clippy::unnecessary_wraps)]
+    pub fn validate(&self) -> Result<(), StorageError> {
+        Ok(()) // TODO
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(Default)]
+struct StorageStatistics {
+    dataset: TripleStatistics,
+    predicates: HashMap<EncodedTerm, TripleStatistics>,
+    graphs: HashMap<EncodedTerm, TripleStatistics>,
+}
+
+/// Calls `callback` on each quad with the previous quad returned by the same iterator
+#[cfg(not(target_family = "wasm"))]
+fn for_each_quad_with_previous(
+    iterators: impl IntoIterator<Item = DecodingQuadIterator>,
+    mut callback: impl FnMut(&EncodedQuad, Option<&EncodedQuad>),
+) -> Result<(), StorageError> {
+    for iterator in iterators {
+        let mut previous = None;
+        for quad in iterator {
+            let quad = quad?;
+            callback(&quad, previous.as_ref());
+            previous = Some(quad);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn statistics_key(prefix: &[u8], term: &EncodedTerm) -> Vec<u8> {
+    let mut key = Vec::with_capacity(prefix.len() + WRITTEN_TERM_MAX_SIZE);
+    key.extend_from_slice(prefix);
+    write_term(&mut key, term);
+    key
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn encode_statistics(statistics: &TripleStatistics) -> [u8; 24] {
+    let mut value = [0; 24];
+    value[..8].copy_from_slice(&statistics.count.to_be_bytes());
+    value[8..16].copy_from_slice(&statistics.distinct_subjects.to_be_bytes());
+    value[16..].copy_from_slice(&statistics.distinct_objects.to_be_bytes());
+    value
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn decode_statistics(value: &[u8]) -> Result<TripleStatistics, StorageError> {
+    if value.len() != 24 {
+        return Err(CorruptionError::new(format!(
+            "Invalid statistics value of length {}",
+            value.len()
+        ))
+        .into());
     }
+    let read = |i: usize| {
+        let mut buffer = [0; 8];
+        buffer.copy_from_slice(&value[i..i + 8]);
+        u64::from_be_bytes(buffer)
+    };
+    Ok(TripleStatistics {
+        count: read(0),
+        distinct_subjects: read(8),
+        distinct_objects: read(16),
+    })
 }
 
 pub struct ChainedDecodingQuadIterator {
@@ -816,20 +2771,54 @@ impl Iterator for ChainedDecodingQuadIterator {
 }
 
 pub struct DecodingQuadIterator {
-    iter: Iter,
+    iter: Option<Iter>,
     encoding: QuadEncoding,
+    /// The quads of these graphs are skipped
+    dropped_graphs: Arc<[EncodedTerm]>,
+    /// Error that happened while opening the iterator, returned by the first call to `next`
+    error: Option<StorageError>,
+}
+
+impl DecodingQuadIterator {
+    fn new(iter: Result<(Iter, Arc<[EncodedTerm]>), StorageError>, encoding: QuadEncoding) -> Self {
+        match iter {
+            Ok((iter, dropped_graphs)) => Self {
+                iter: Some(iter),
+                encoding,
+                dropped_graphs,
+                error: None,
+            },
+            Err(error) => Self {
+                iter: None,
+                encoding,
+                dropped_graphs: Arc::from(Vec::new()),
+                error: Some(error),
+            },
+        }
+    }
 }
 
 impl Iterator for DecodingQuadIterator {
     type Item = Result<EncodedQuad, StorageError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Err(e) = self.iter.status() {
+        if let Some(e) = self.error.take() {
             return Some(Err(e));
         }
-        let term = self.encoding.decode(self.iter.key()?);
-        self.iter.next();
-        Some(term)
+        let iter = self.iter.as_mut()?;
+        loop {
+            if let Err(e) = iter.status() {
+                return Some(Err(e));
+            }
+            let quad = self.encoding.decode(iter.key()?);
+            iter.next();
+            if !quad
+                .as_ref()
+                .map_or(false, |quad| self.dropped_graphs.contains(&quad.graph_name))
+            {
+                return Some(quad);
+            }
+        }
     }
 }
 
@@ -850,6 +2839,130 @@ impl Iterator for DecodingGraphIterator {
     }
 }
 
+pub struct DecodingChangeIterator {
+    iter: Option<Iter>,
+    /// Error that happened while opening the iterator, returned by the first call to `next`
+    error: Option<StorageError>,
+}
+
+impl Iterator for DecodingChangeIterator {
+    type Item = Result<(u64, EncodedChange), StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        let iter = self.iter.as_mut()?;
+        if let Err(e) = iter.status() {
+            return Some(Err(e));
+        }
+        let change = decode_change(iter.key()?, iter.value()?);
+        iter.next();
+        Some(change)
+    }
+}
+
+/// A change recorded in the change log
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum EncodedChange {
+    Insert(EncodedQuad),
+    Remove(EncodedQuad),
+    InsertNamedGraph(EncodedTerm),
+    RemoveNamedGraph(EncodedTerm),
+}
+
+fn decode_change(key: &[u8], value: &[u8]) -> Result<(u64, EncodedChange), StorageError> {
+    let sequence_number = decode_change_sequence_number(key)?;
+    let (operation, terms) = value
+        .split_first()
+        .ok_or_else(|| CorruptionError::msg("Empty value in the change log"))?;
+    let change = match *operation {
+        CHANGE_INSERT => EncodedChange::Insert(decode_spog_quad(terms)?),
+        CHANGE_REMOVE => EncodedChange::Remove(decode_spog_quad(terms)?),
+        CHANGE_INSERT_NAMED_GRAPH => EncodedChange::InsertNamedGraph(decode_term(terms)?),
+        CHANGE_REMOVE_NAMED_GRAPH => EncodedChange::RemoveNamedGraph(decode_term(terms)?),
+        _ => {
+            return Err(CorruptionError::msg(format!(
+                "Unexpected operation {operation} in the change log"
+            ))
+            .into())
+        }
+    };
+    Ok((sequence_number, change))
+}
+
+fn decode_change_sequence_number(value: &[u8]) -> Result<u64, StorageError> {
+    Ok(u64::from_be_bytes(value.try_into().map_err(|e| {
+        CorruptionError::new(format!("Invalid sequence number in the change log: {e}"))
+    })?))
+}
+
+/// Rewrites the terms of a sequence of encoded terms to migrate them to an other storage version
+#[cfg(not(target_family = "wasm"))]
+/// Position of a running migration in the column family it rewrites
+#[cfg(not(target_family = "wasm"))]
+enum MigrationPosition {
+    /// The column family has not been read yet
+    Start,
+    /// The column family has been read until this key, excluded
+    Next(Vec<u8>),
+    /// The column family has been fully read, the staged entries are moved back to it
+    MovingBack,
+}
+
+type TermsRewrite = fn(&[u8]) -> Result<Vec<u8>, StorageError>;
+
+/// Rewrites an entry of a column family to migrate it to an other storage version
+#[cfg(not(target_family = "wasm"))]
+type EntryRewrite = fn(&[u8], &[u8], TermsRewrite) -> Result<(Vec<u8>, Vec<u8>), StorageError>;
+
+#[cfg(not(target_family = "wasm"))]
+fn rewrite_quad_entry(
+    key: &[u8],
+    value: &[u8],
+    rewrite_terms: TermsRewrite,
+) -> Result<(Vec<u8>, Vec<u8>), StorageError> {
+    Ok((rewrite_terms(key)?, value.to_vec()))
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn rewrite_change_entry(
+    key: &[u8],
+    value: &[u8],
+    rewrite_terms: TermsRewrite,
+) -> Result<(Vec<u8>, Vec<u8>), StorageError> {
+    if key == CHANGE_COUNTER_KEY {
+        return Ok((key.to_vec(), value.to_vec()));
+    }
+    let (operation, quad) = value
+        .split_first()
+        .ok_or_else(|| CorruptionError::msg("Empty value in the change log"))?;
+    let mut new_value = vec![*operation];
+    new_value.extend_from_slice(&rewrite_terms(quad)?);
+    Ok((key.to_vec(), new_value))
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn rewrite_text_entry(
+    key: &[u8],
+    value: &[u8],
+    rewrite_terms: TermsRewrite,
+) -> Result<(Vec<u8>, Vec<u8>), StorageError> {
+    Ok((rewrite_text_key_terms(key, rewrite_terms)?, value.to_vec()))
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn rewrite_spatial_entry(
+    key: &[u8],
+    value: &[u8],
+    rewrite_terms: TermsRewrite,
+) -> Result<(Vec<u8>, Vec<u8>), StorageError> {
+    Ok((
+        rewrite_spatial_key_terms(key, rewrite_terms)?,
+        value.to_vec(),
+    ))
+}
+
 impl StrLookup for StorageReader {
     fn get_str(&self, key: &StrHash) -> Result<Option<String>, StorageError> {
         self.get_str(key)
@@ -860,6 +2973,8 @@ pub struct StorageWriter<'a> {
     buffer: Vec<u8>,
     transaction: Transaction<'a>,
     storage: &'a Storage,
+    next_change_sequence_number: Option<u64>,
+    text_index: Option<Arc<TextIndexConfig>>,
 }
 
 impl<'a> StorageWriter<'a> {
@@ -867,6 +2982,9 @@ impl<'a> StorageWriter<'a> {
         StorageReader {
             reader: self.transaction.reader(),
             storage: self.storage.clone(),
+            _snapshot: None,
+            // The graph drops are completed before allowing writes
+            dropped_graphs: OnceLock::from(Arc::from(Vec::new())),
         }
     }
 
@@ -952,6 +3070,13 @@ impl<'a> StorageWriter<'a> {
                 true
             }
         };
+        if result {
+            self.log_change(CHANGE_INSERT, &encoded)?;
+            if let TermRef::Literal(literal) = quad.object {
+                self.index_text(literal.value(), &encoded, true)?;
+                self.index_geometry(literal.value(), &encoded, true)?;
+            }
+        }
         Ok(result)
     }
 
@@ -972,6 +3097,7 @@ impl<'a> StorageWriter<'a> {
             self.transaction
                 .insert_empty(&self.storage.graphs_cf, &self.buffer)?;
             self.insert_term(graph_name.into(), &encoded_graph_name)?;
+            self.log_named_graph_change(CHANGE_INSERT_NAMED_GRAPH, &encoded_graph_name)?;
             true
         };
         Ok(result)
@@ -999,6 +3125,7 @@ impl<'a> StorageWriter<'a> {
 
     #[cfg(not(target_family = "wasm"))]
     fn insert_str(&mut self, key: &StrHash, value: &str) -> Result<(), StorageError> {
+        self.storage.str_gc.on_str_write(key)?;
         if self
             .storage
             .db
@@ -1006,7 +3133,8 @@ impl<'a> StorageWriter<'a> {
         {
             return Ok(());
         }
-        self.storage.db.insert(
+        self.transaction.insert_idempotent(
+            &self.storage.db,
             &self.storage.id2str_cf,
             &key.to_be_bytes(),
             value.as_bytes(),
@@ -1090,9 +3218,163 @@ impl<'a> StorageWriter<'a> {
                 false
             }
         };
+        if result {
+            self.on_remove(quad)?;
+        }
         Ok(result)
     }
 
+    /// Removes a quad of a graph whose graph-prefixed indexes are going to be cleared with range removals
+    ///
+    /// Only the other indexes, the change log and the literal indexes are updated.
+    /// The removal is blind: the quad must be in the store.
+    #[cfg(not(target_family = "wasm"))]
+    fn remove_encoded_before_range_removal(
+        &mut self,
+        quad: &EncodedQuad,
+    ) -> Result<(), StorageError> {
+        if !quad.graph_name.is_default_graph() {
+            self.buffer.clear();
+            write_spog_quad(&mut self.buffer, quad);
+            self.transaction
+                .remove(&self.storage.spog_cf, &self.buffer)?;
+
+            self.buffer.clear();
+            write_posg_quad(&mut self.buffer, quad);
+            self.transaction
+                .remove(&self.storage.posg_cf, &self.buffer)?;
+
+            self.buffer.clear();
+            write_ospg_quad(&mut self.buffer, quad);
+            self.transaction
+                .remove(&self.storage.ospg_cf, &self.buffer)?;
+        }
+        self.on_remove(quad)
+    }
+
+    fn on_remove(&mut self, quad: &EncodedQuad) -> Result<(), StorageError> {
+        self.log_change(CHANGE_REMOVE, quad)?;
+        if self
+            .text_index
+            .as_ref()
+            .map_or(false, |config| config.is_indexed(quad))
+        {
+            let value = self.reader().decode_literal_value(&quad.object)?;
+            self.index_text(&value, quad, false)?;
+        }
+        if self
+            .storage
+            .is_spatial_index_enabled
+            .load(Ordering::Acquire)
+            && is_wkt_literal(&quad.object)
+        {
+            let value = self.reader().decode_literal_value(&quad.object)?;
+            self.index_geometry(&value, quad, false)?;
+        }
+        Ok(())
+    }
+
+    /// Adds or removes the literal value to the text index if it is enabled and the quad is indexed
+    fn index_text(
+        &mut self,
+        value: &str,
+        quad: &EncodedQuad,
+        insert: bool,
+    ) -> Result<(), StorageError> {
+        if !self
+            .text_index
+            .as_ref()
+            .map_or(false, |config| config.is_indexed(quad))
+        {
+            return Ok(());
+        }
+        let text_cf = self.storage.text_cf()?;
+        for (key, value) in text_index_entries(value, quad) {
+            if insert {
+                self.transaction.insert(text_cf, &key, &value)?;
+            } else {
+                self.transaction.remove(text_cf, &key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds or removes the geometry to the spatial index if it is enabled and the literal is a `geo:wktLiteral`
+    fn index_geometry(
+        &mut self,
+        value: &str,
+        quad: &EncodedQuad,
+        insert: bool,
+    ) -> Result<(), StorageError> {
+        if !self
+            .storage
+            .is_spatial_index_enabled
+            .load(Ordering::Acquire)
+            || !is_wkt_literal(&quad.object)
+        {
+            return Ok(());
+        }
+        let Some((key, value)) = spatial_index_entry(value, quad) else {
+            return Ok(());
+        };
+        let spatial_cf = self.storage.spatial_cf()?;
+        if insert {
+            self.transaction.insert(spatial_cf, &key, &value)?;
+        } else {
+            self.transaction.remove(spatial_cf, &key)?;
+        }
+        Ok(())
+    }
+
+    /// Appends the quad change to the change log if it is enabled
+    fn log_change(&mut self, operation: u8, quad: &EncodedQuad) -> Result<(), StorageError> {
+        if !self.storage.is_change_log_enabled.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let mut value = Vec::with_capacity(1 + 4 * WRITTEN_TERM_MAX_SIZE);
+        value.push(operation);
+        write_spog_quad(&mut value, quad);
+        self.append_change(&value)
+    }
+
+    /// Appends the named graph change to the change log if it is enabled
+    fn log_named_graph_change(
+        &mut self,
+        operation: u8,
+        graph_name: &EncodedTerm,
+    ) -> Result<(), StorageError> {
+        if !self.storage.is_change_log_enabled.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let mut value = Vec::with_capacity(1 + WRITTEN_TERM_MAX_SIZE);
+        value.push(operation);
+        write_term(&mut value, graph_name);
+        self.append_change(&value)
+    }
+
+    fn append_change(&mut self, value: &[u8]) -> Result<(), StorageError> {
+        let sequence_number = if let Some(sequence_number) = self.next_change_sequence_number {
+            sequence_number
+        } else {
+            // The counter is locked until the commit so that the sequence numbers follow the commit order
+            let counter = self
+                .transaction
+                .get_for_update(self.storage.changes_cf()?, CHANGE_COUNTER_KEY)?
+                .ok_or_else(|| CorruptionError::msg("The change log counter is missing"))?;
+            decode_change_sequence_number(&counter)? + 1
+        };
+        let changes_cf = self.storage.changes_cf()?;
+        self.transaction
+            .insert(changes_cf, &sequence_number.to_be_bytes(), value)?;
+        self.transaction.insert(
+            changes_cf,
+            CHANGE_COUNTER_KEY,
+            &sequence_number.to_be_bytes(),
+        )?;
+        self.next_change_sequence_number = Some(sequence_number + 1);
+        Ok(())
+    }
+
     pub fn clear_graph(&mut self, graph_name: GraphNameRef<'_>) -> Result<(), StorageError> {
         if graph_name.is_default_graph() {
             for quad in self.reader().quads_for_graph(&EncodedTerm::DefaultGraph) {
@@ -1153,6 +3435,7 @@ impl<'a> StorageWriter<'a> {
             write_term(&mut self.buffer, graph_name);
             self.transaction
                 .remove(&self.storage.graphs_cf, &self.buffer)?;
+            self.log_named_graph_change(CHANGE_REMOVE_NAMED_GRAPH, graph_name)?;
             true
         } else {
             false
@@ -1185,6 +3468,7 @@ pub struct StorageBulkLoader {
     hooks: Vec<Box<dyn Fn(u64)>>,
     num_threads: Option<usize>,
     max_memory_size: Option<usize>,
+    is_atomic: bool,
 }
 
 #[cfg(not(target_family = "wasm"))]
@@ -1195,9 +3479,16 @@ impl StorageBulkLoader {
             hooks: Vec::new(),
             num_threads: None,
             max_memory_size: None,
+            is_atomic: false,
         }
     }
 
+    /// Stages the files built by all the loading threads and only inserts them if the full load succeeds
+    pub fn atomic(mut self) -> Self {
+        self.is_atomic = true;
+        self
+    }
+
     pub fn with_num_threads(mut self, num_threads: usize) -> Self {
         self.num_threads = Some(num_threads);
         self
@@ -1237,7 +3528,8 @@ impl StorageBulkLoader {
         }
         let done_counter = Mutex::new(0);
         let mut done_and_displayed_counter = 0;
-        thread::scope(|thread_scope| {
+        let staged = Mutex::new(BulkLoadBatch::default());
+        let result = thread::scope(|thread_scope| {
             let mut threads = VecDeque::with_capacity(num_threads - 1);
             let mut buffer = Vec::with_capacity(batch_size);
             for quad in quads {
@@ -1250,6 +3542,7 @@ impl StorageBulkLoader {
                         thread_scope,
                         &done_counter,
                         &mut done_and_displayed_counter,
+                        &staged,
                         num_threads,
                         batch_size,
                     )?;
@@ -1261,6 +3554,7 @@ impl StorageBulkLoader {
                 thread_scope,
                 &done_counter,
                 &mut done_and_displayed_counter,
+                &staged,
                 num_threads,
                 batch_size,
             )?;
@@ -1269,16 +3563,30 @@ impl StorageBulkLoader {
                 self.on_possible_progress(&done_counter, &mut done_and_displayed_counter)?;
             }
             Ok(())
-        })
+        });
+        if !self.is_atomic {
+            return result;
+        }
+        // All the threads are done, the staged files are only inserted if none of them failed
+        let staged = staged
+            .into_inner()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Mutex poisoned"))
+            .map_err(StorageError::from)?;
+        let result = result.and_then(|()| Ok(staged.ingest(&self.storage)?));
+        if result.is_err() {
+            staged.discard()?;
+        }
+        result
     }
 
-    fn spawn_load_thread<'scope>(
-        &'scope self,
+    fn spawn_load_thread<'scope, 'a: 'scope>(
+        &'a self,
         buffer: &mut Vec<Quad>,
         threads: &mut VecDeque<thread::ScopedJoinHandle<'scope, Result<(), StorageError>>>,
         thread_scope: &'scope thread::Scope<'scope, '_>,
         done_counter: &'scope Mutex<u64>,
         done_and_displayed_counter: &mut u64,
+        staged: &'scope Mutex<BulkLoadBatch<'a>>,
         num_threads: usize,
         batch_size: usize,
     ) -> Result<(), StorageError> {
@@ -1293,8 +3601,18 @@ impl StorageBulkLoader {
         let mut buffer_to_load = Vec::with_capacity(batch_size);
         swap(buffer, &mut buffer_to_load);
         let storage = &self.storage;
+        let is_atomic = self.is_atomic;
         threads.push_back(thread_scope.spawn(move || {
-            FileBulkLoader::new(storage, batch_size).load(buffer_to_load, done_counter)
+            let batch =
+                FileBulkLoader::new(storage, batch_size).load(buffer_to_load, done_counter)?;
+            if is_atomic {
+                staged
+                    .lock()
+                    .map_err(|_| io::Error::new(io::ErrorKind::Other, "Mutex poisoned"))?
+                    .stage(batch, storage)
+            } else {
+                batch.ingest(storage)
+            }
         }));
         Ok(())
     }
@@ -1325,6 +3643,10 @@ struct FileBulkLoader<'a> {
     quads: HashSet<EncodedQuad>,
     triples: HashSet<EncodedQuad>,
     graphs: HashSet<EncodedTerm>,
+    text_index: Option<Arc<TextIndexConfig>>,
+    text: Vec<(Vec<u8>, [u8; 8])>,
+    is_spatial_index_enabled: bool,
+    spatial: Vec<(Vec<u8>, [u8; 32])>,
 }
 
 #[cfg(not(target_family = "wasm"))]
@@ -1336,18 +3658,31 @@ impl<'a> FileBulkLoader<'a> {
             quads: HashSet::with_capacity(batch_size),
             triples: HashSet::with_capacity(batch_size),
             graphs: HashSet::default(),
+            text_index: None,
+            text: Vec::new(),
+            is_spatial_index_enabled: false,
+            spatial: Vec::new(),
         }
     }
 
-    fn load(&mut self, quads: Vec<Quad>, counter: &Mutex<u64>) -> Result<(), StorageError> {
+    fn load(
+        &mut self,
+        quads: Vec<Quad>,
+        counter: &Mutex<u64>,
+    ) -> Result<BulkLoadBatch<'a>, StorageError> {
+        self.text_index = self.storage.text_index_config()?;
+        self.is_spatial_index_enabled = self
+            .storage
+            .is_spatial_index_enabled
+            .load(Ordering::Acquire);
         self.encode(quads)?;
         let size = self.triples.len() + self.quads.len();
-        self.save()?;
+        let batch = self.build()?;
         *counter
             .lock()
             .map_err(|_| io::Error::new(io::ErrorKind::Other, "Mutex poisoned"))? +=
             size.try_into().unwrap_or(u64::MAX);
-        Ok(())
+        Ok(batch)
     }
 
     fn encode(&mut self, quads: Vec<Quad>) -> Result<(), StorageError> {
@@ -1358,11 +3693,13 @@ impl<'a> FileBulkLoader<'a> {
                     self.insert_term(quad.subject.as_ref().into(), &encoded.subject)?;
                     self.insert_term(quad.predicate.as_ref().into(), &encoded.predicate)?;
                     self.insert_term(quad.object.as_ref(), &encoded.object)?;
+                    self.index_literal(&quad, &encoded);
                 }
             } else if self.quads.insert(encoded.clone()) {
                 self.insert_term(quad.subject.as_ref().into(), &encoded.subject)?;
                 self.insert_term(quad.predicate.as_ref().into(), &encoded.predicate)?;
                 self.insert_term(quad.object.as_ref(), &encoded.object)?;
+                self.index_literal(&quad, &encoded);
 
                 if self.graphs.insert(encoded.graph_name.clone()) {
                     self.insert_term(
@@ -1384,8 +3721,36 @@ impl<'a> FileBulkLoader<'a> {
         Ok(())
     }
 
-    fn save(&mut self) -> Result<(), StorageError> {
+    fn index_literal(&mut self, quad: &Quad, encoded: &EncodedQuad) {
+        let Term::Literal(literal) = &quad.object else {
+            return;
+        };
+        if self
+            .text_index
+            .as_ref()
+            .map_or(false, |text_index| text_index.is_indexed(encoded))
+        {
+            self.text
+                .extend(text_index_entries(literal.value(), encoded));
+        }
+        if self.is_spatial_index_enabled && is_wkt_literal(&encoded.object) {
+            self.spatial
+                .extend(spatial_index_entry(literal.value(), encoded));
+        }
+    }
+
+    fn build(&mut self) -> Result<BulkLoadBatch<'a>, StorageError> {
         let mut to_load = Vec::new();
+        let changes = if self.storage.is_change_log_enabled.load(Ordering::Acquire) {
+            self.triples
+                .iter()
+                .chain(&self.quads)
+                .cloned()
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+        let strs = self.id2str.keys().copied().collect();
 
         // id2str
         if !self.id2str.is_empty() {
@@ -1505,7 +3870,32 @@ impl<'a> FileBulkLoader<'a> {
             self.quads.clear();
         }
 
-        self.storage.db.insert_stt_files(&to_load)
+        if !self.text.is_empty() {
+            let mut text = take(&mut self.text);
+            text.sort_unstable();
+            let mut text_sst = self.storage.db.new_sst_file()?;
+            for (k, v) in text {
+                text_sst.insert(&k, &v)?;
+            }
+            to_load.push((self.storage.text_cf()?, text_sst.finish()?));
+        }
+
+        if !self.spatial.is_empty() {
+            let mut spatial = take(&mut self.spatial);
+            spatial.sort_unstable();
+            let mut spatial_sst = self.storage.db.new_sst_file()?;
+            for (k, v) in spatial {
+                spatial_sst.insert(&k, &v)?;
+            }
+            to_load.push((self.storage.spatial_cf()?, spatial_sst.finish()?));
+        }
+
+        Ok(BulkLoadBatch {
+            ssts: to_load,
+            strs,
+            changes,
+            ..BulkLoadBatch::default()
+        })
     }
 
     fn insert_term(
@@ -1522,7 +3912,7 @@ impl<'a> FileBulkLoader<'a> {
     fn build_sst_for_keys(
         &self,
         values: impl Iterator<Item = Vec<u8>>,
-    ) -> Result<PathBuf, StorageError> {
+    ) -> Result<SstFile, StorageError> {
         let mut values = values.collect::<Vec<_>>();
         values.sort_unstable();
         let mut sst = self.storage.db.new_sst_file()?;
@@ -1533,6 +3923,143 @@ impl<'a> FileBulkLoader<'a> {
     }
 }
 
+/// The files built by a bulk load that are not inserted yet
+#[cfg(not(target_family = "wasm"))]
+#[derive(Default)]
+struct BulkLoadBatch<'a> {
+    ssts: Vec<(&'a ColumnFamily, SstFile)>,
+    /// The written strings, they must not be removed by a concurrent garbage collection
+    strs: Vec<StrHash>,
+    /// Set if the strings written by the staged batches are not in `strs`
+    has_untracked_strs: bool,
+    /// The inserted quads to add to the change log
+    changes: Vec<EncodedQuad>,
+    /// The inserted quads of the staged batches to add to the change log before `changes`
+    staged_changes: Option<StagedChanges>,
+}
+
+/// A temporary file containing change log entry values, each one prefixed by its big endian `u32` length
+#[cfg(not(target_family = "wasm"))]
+struct StagedChanges {
+    path: PathBuf,
+    len: u64,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl<'a> BulkLoadBatch<'a> {
+    /// Adds a batch to the staged ones.
+    ///
+    /// Only the files are kept: the changes are appended to a temporary file and the written strings are forgotten
+    /// so that the memory use does not grow with the size of the load.
+    fn stage(&mut self, other: Self, storage: &Storage) -> Result<(), StorageError> {
+        self.ssts.extend(other.ssts);
+        self.has_untracked_strs |= other.has_untracked_strs || !other.strs.is_empty();
+        if other.changes.is_empty() {
+            return Ok(());
+        }
+        let staged_changes = match &mut self.staged_changes {
+            Some(staged_changes) => staged_changes,
+            None => self.staged_changes.insert(StagedChanges {
+                path: storage.db.new_temporary_file_path()?,
+                len: 0,
+            }),
+        };
+        let mut file = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&staged_changes.path)?,
+        );
+        let mut value = Vec::with_capacity(1 + 4 * WRITTEN_TERM_MAX_SIZE);
+        for quad in &other.changes {
+            value.clear();
+            value.push(CHANGE_INSERT);
+            write_spog_quad(&mut value, quad);
+            let len = u32::try_from(value.len())
+                .map_err(|_| StorageError::Other("Too long quad to be staged".into()))?;
+            file.write_all(&len.to_be_bytes())?;
+            file.write_all(&value)?;
+        }
+        file.flush()?;
+        staged_changes.len += u64::try_from(other.changes.len()).unwrap_or(u64::MAX);
+        Ok(())
+    }
+
+    fn ingest(&self, storage: &Storage) -> Result<(), StorageError> {
+        let _write_guard = storage.str_gc.start_write()?;
+        if self.has_untracked_strs {
+            storage.str_gc.on_untracked_strs_write();
+        }
+        for key in &self.strs {
+            storage.str_gc.on_str_write(key)?;
+        }
+        let staged_len = self
+            .staged_changes
+            .as_ref()
+            .map_or(0, |staged_changes| staged_changes.len);
+        if self.changes.is_empty() && staged_len == 0 {
+            return storage.db.insert_stt_files(self.ssts.clone());
+        }
+        // The change log entries are ingested with the quads.
+        // The counter is locked during the ingestion so that the sequence numbers follow the commit order
+        let changes_cf = storage.changes_cf()?;
+        storage.db.transaction(|mut transaction| {
+            let counter = transaction
+                .get_for_update(changes_cf, CHANGE_COUNTER_KEY)?
+                .ok_or_else(|| CorruptionError::msg("The change log counter is missing"))?;
+            let first_sequence_number = decode_change_sequence_number(&counter)? + 1;
+            let last_sequence_number = first_sequence_number
+                + staged_len
+                + u64::try_from(self.changes.len()).unwrap_or(u64::MAX)
+                - 1;
+            let mut sst = storage.db.new_sst_file()?;
+            // The counter key is empty so it is the first one
+            sst.insert(CHANGE_COUNTER_KEY, &last_sequence_number.to_be_bytes())?;
+            let mut value = Vec::with_capacity(1 + 4 * WRITTEN_TERM_MAX_SIZE);
+            let mut sequence_number = first_sequence_number;
+            if let Some(staged_changes) = &self.staged_changes {
+                let mut file = BufReader::new(File::open(&staged_changes.path)?);
+                for _ in 0..staged_changes.len {
+                    let mut len = [0; 4];
+                    file.read_exact(&mut len)?;
+                    value.resize(
+                        usize::try_from(u32::from_be_bytes(len)).map_err(CorruptionError::new)?,
+                        0,
+                    );
+                    file.read_exact(&mut value)?;
+                    sst.insert(&sequence_number.to_be_bytes(), &value)?;
+                    sequence_number += 1;
+                }
+            }
+            for quad in &self.changes {
+                value.clear();
+                value.push(CHANGE_INSERT);
+                write_spog_quad(&mut value, quad);
+                sst.insert(&sequence_number.to_be_bytes(), &value)?;
+                sequence_number += 1;
+            }
+            let mut to_load = self.ssts.clone();
+            to_load.push((changes_cf, sst.finish()?));
+            transaction.insert_stt_files(&storage.db, to_load)
+        })?;
+        if let Some(staged_changes) = &self.staged_changes {
+            fs::remove_file(&staged_changes.path)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the files without inserting them
+    fn discard(self) -> Result<(), StorageError> {
+        for (_, sst) in self.ssts {
+            sst.remove()?;
+        }
+        if let Some(staged_changes) = self.staged_changes {
+            fs::remove_file(staged_changes.path)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(not(target_family = "wasm"))]
 fn map_thread_result<R>(result: thread::Result<R>) -> io::Result<R> {
     result.map_err(|e| {
@@ -1546,3 +4073,244 @@ fn map_thread_result<R>(result: thread::Result<R>) -> io::Result<R> {
         )
     })
 }
+
+#[cfg(not(target_family = "wasm"))]
+#[must_use]
+pub struct StorageBulkRemover {
+    storage: Storage,
+    hooks: Vec<Box<dyn Fn(u64)>>,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl StorageBulkRemover {
+    pub fn new(storage: Storage) -> Self {
+        Self {
+            storage,
+            hooks: Vec::new(),
+        }
+    }
+
+    pub fn on_progress(mut self, callback: impl Fn(u64) + 'static) -> Self {
+        self.hooks.push(Box::new(callback));
+        self
+    }
+
+    /// Removes the quads from all the indexes using one transaction per batch of quads
+    #[allow(clippy::trait_duplication_in_bounds)]
+    pub fn remove<EI, EO: From<StorageError> + From<EI>>(
+        &self,
+        quads: impl IntoIterator<Item = Result<Quad, EI>>,
+    ) -> Result<(), EO> {
+        let mut done_counter = 0;
+        let mut buffer = Vec::with_capacity(BULK_REMOVE_BATCH_SIZE);
+        for quad in quads {
+            buffer.push(EncodedQuad::from(quad?.as_ref()));
+            if buffer.len() >= BULK_REMOVE_BATCH_SIZE {
+                self.remove_batch(&mut buffer, &mut done_counter)?;
+            }
+        }
+        self.remove_batch(&mut buffer, &mut done_counter)?;
+        Ok(())
+    }
+
+    fn remove_batch(
+        &self,
+        buffer: &mut Vec<EncodedQuad>,
+        done_counter: &mut u64,
+    ) -> Result<(), StorageError> {
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        self.storage.transaction(|mut writer| {
+            for quad in buffer.iter() {
+                writer.remove_encoded(quad)?;
+            }
+            Ok::<_, StorageError>(())
+        })?;
+        let new_counter = *done_counter + u64::try_from(buffer.len()).unwrap_or(u64::MAX);
+        buffer.clear();
+        // Same progress steps as the bulk loader
+        let display_step = DEFAULT_BULK_LOAD_BATCH_SIZE as u64;
+        if new_counter / display_step > *done_counter / display_step {
+            for hook in &self.hooks {
+                hook(new_counter);
+            }
+        }
+        *done_counter = new_counter;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_family = "wasm"))]
+mod tests {
+    #![allow(clippy::panic_in_result_fn)]
+
+    use super::*;
+    use crate::model::{Literal, LiteralRef, NamedNodeRef};
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn collect_str_garbage_keeps_strings_written_during_collection() -> Result<(), StorageError> {
+        let ex = NamedNodeRef::new_unchecked("http://example.com");
+        let value = "a literal value too long to be inlined";
+        let quad = QuadRef::new(
+            ex,
+            ex,
+            LiteralRef::new_simple_literal(value),
+            GraphNameRef::DefaultGraph,
+        );
+        let storage = Storage::new()?;
+        storage.transaction(|mut writer| writer.insert(quad))?;
+        storage.transaction(|mut writer| writer.remove(quad))?;
+
+        // Same steps as Storage::collect_str_garbage with a write reusing the string in the middle
+        let snapshot = {
+            let _writes_guard = storage.str_gc.stop_writes()?;
+            let snapshot = storage.snapshot();
+            storage.str_gc.start_collection()?;
+            snapshot
+        };
+        assert!(!snapshot.used_strs(&[])?.contains(&StrHash::new(value)));
+        storage.transaction(|mut writer| writer.insert(QuadRef::new(ex, ex, quad.object, ex)))?;
+        storage.remove_unused_strs(&snapshot, &[])?;
+        storage.str_gc.end_collection()?;
+
+        let reader = storage.snapshot();
+        assert!(reader.contains_str(&StrHash::new(value))?);
+        reader.validate()?;
+
+        // The string is removed by the next collection once unused
+        storage.transaction(|mut writer| writer.remove(QuadRef::new(ex, ex, quad.object, ex)))?;
+        storage.collect_str_garbage()?;
+        assert!(!storage.snapshot().contains_str(&StrHash::new(value))?);
+        Ok(())
+    }
+
+    #[test]
+    fn interrupted_migration_resumes() -> Result<(), StorageError> {
+        static REWRITES: AtomicUsize = AtomicUsize::new(0);
+
+        fn failing_upgrade_v2_terms(value: &[u8]) -> Result<Vec<u8>, StorageError> {
+            if REWRITES.fetch_add(1, Ordering::Relaxed) == 20 {
+                return Err(StorageError::Other("Interrupted migration".into()));
+            }
+            upgrade_v2_terms(value)
+        }
+
+        let ex = NamedNodeRef::new_unchecked("http://example.com");
+        let storage = Storage::new()?;
+        storage.transaction(|mut writer| {
+            for i in 0..10 {
+                for value in [format!("urn:{i}"), format!("http://example.com/{i}")] {
+                    let value = Literal::new_typed_literal(value, xsd::ANY_URI);
+                    writer.insert(QuadRef::new(ex, ex, &value, GraphNameRef::DefaultGraph))?;
+                    writer.insert(QuadRef::new(ex, ex, &value, ex))?;
+                }
+            }
+            Ok::<_, StorageError>(())
+        })?;
+        let quads = storage.snapshot().quads().collect::<Result<Vec<_>, _>>()?;
+        storage.migrate_to(2, &|_, _| ())?;
+        assert!(storage
+            .rewrite_values(3, failing_upgrade_v2_terms, &|_, _| ())
+            .is_err());
+        assert!(storage.migration_progress()?.is_some());
+        storage.migrate_to(3, &|_, _| ())?;
+        assert!(storage.migration_progress()?.is_none());
+        let snapshot = storage.snapshot();
+        assert_eq!(snapshot.quads().collect::<Result<Vec<_>, _>>()?, quads);
+        assert!(snapshot
+            .reader
+            .scan_prefix(&storage.default_cf, MIGRATION_STAGING_PREFIX)?
+            .key()
+            .is_none());
+        snapshot.validate()?;
+        Ok(())
+    }
+
+    #[test]
+    fn image_of_previous_version_is_migrated() -> Result<(), StorageError> {
+        let ex = NamedNodeRef::new_unchecked("http://example.com");
+        let value = LiteralRef::new_typed_literal("http://example.com", xsd::ANY_URI);
+        let storage = Storage::new()?;
+        storage.transaction(|mut writer| {
+            writer.insert(QuadRef::new(ex, ex, value, GraphNameRef::DefaultGraph))
+        })?;
+        storage.migrate_to(2, &|_, _| ())?;
+        let mut image = storage.snapshot().save_to_write(Vec::new())?;
+        image[8..16].copy_from_slice(&2_u64.to_be_bytes()); // The image header version
+
+        let restored = Storage::new()?;
+        restored.load_image(image.as_slice())?;
+        assert_eq!(restored.storage_version()?, LATEST_STORAGE_VERSION);
+        let snapshot = restored.snapshot();
+        assert!(snapshot.contains(&QuadRef::new(ex, ex, value, GraphNameRef::DefaultGraph).into())?);
+        snapshot.validate()?;
+        Ok(())
+    }
+
+    #[test]
+    fn interrupted_graph_drop_is_hidden_and_completed() -> Result<(), StorageError> {
+        let ex = NamedNodeRef::new_unchecked("http://example.com");
+        let storage = Storage::new()?;
+        storage.enable_change_log()?;
+        storage.transaction(|mut writer| {
+            for i in 0..5 {
+                let value = Literal::from(i);
+                writer.insert(QuadRef::new(ex, ex, &value, GraphNameRef::DefaultGraph))?;
+                writer.insert(QuadRef::new(ex, ex, &value, ex))?;
+            }
+            Ok::<_, StorageError>(())
+        })?;
+        let default_graph_quads = storage
+            .snapshot()
+            .quads_for_graph(&EncodedTerm::DefaultGraph)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // We simulate a drop interrupted after the removal of a first quad
+        let graph_name = EncodedTerm::from(ex);
+        let mark_key = [DROPPED_GRAPH_PREFIX, &encode_term(&graph_name)].concat();
+        storage.db.transaction(|mut transaction| {
+            transaction.insert(&storage.default_cf, &mark_key, &[])
+        })?;
+        let snapshot = storage.db.snapshot();
+        let iter = snapshot.scan_prefix(&storage.gspo_cf, &encode_term(&graph_name))?;
+        let first_key = iter.key().unwrap_or_default().to_vec();
+        storage.remove_before_range_removal(
+            &[QuadEncoding::Gspo.decode(&first_key)?],
+            &None,
+            &mark_key,
+            &first_key,
+        )?;
+        let snapshot = storage.snapshot();
+        assert_eq!(
+            snapshot.quads().collect::<Result<Vec<_>, _>>()?,
+            default_graph_quads
+        );
+        assert!(!snapshot.contains(&QuadRef::new(ex, ex, &Literal::from(1), ex).into())?);
+
+        storage.complete_graph_drops()?;
+        let snapshot = storage.snapshot();
+        assert!(snapshot.dropped_graphs()?.is_empty());
+        assert_eq!(
+            snapshot.quads().collect::<Result<Vec<_>, _>>()?,
+            default_graph_quads
+        );
+        // Each quad removal is logged once
+        assert_eq!(
+            snapshot
+                .changes(0)
+                .filter(
+                    |change| change.as_ref().map_or(true, |(_, change)| matches!(
+                        change,
+                        EncodedChange::Remove(_)
+                    ))
+                )
+                .count(),
+            5
+        );
+        snapshot.validate()?;
+        Ok(())
+    }
+}
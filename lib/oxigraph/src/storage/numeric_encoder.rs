@@ -1,5 +1,6 @@
 #![allow(clippy::unreadable_literal)]
 
+use crate::model::vocab::xsd;
 use crate::model::*;
 use crate::storage::error::{CorruptionError, StorageError};
 use crate::storage::small_string::SmallString;
@@ -38,6 +39,60 @@ impl StrHash {
     }
 }
 
+/// The datatypes derived from `xsd:integer` whose values are encoded inline
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum DerivedIntegerDatatype {
+    Byte,
+    Short,
+    Int,
+    Long,
+    UnsignedByte,
+    UnsignedShort,
+    UnsignedInt,
+    UnsignedLong,
+    PositiveInteger,
+    NegativeInteger,
+    NonPositiveInteger,
+    NonNegativeInteger,
+}
+
+impl DerivedIntegerDatatype {
+    pub fn iri(self) -> NamedNodeRef<'static> {
+        match self {
+            Self::Byte => xsd::BYTE,
+            Self::Short => xsd::SHORT,
+            Self::Int => xsd::INT,
+            Self::Long => xsd::LONG,
+            Self::UnsignedByte => xsd::UNSIGNED_BYTE,
+            Self::UnsignedShort => xsd::UNSIGNED_SHORT,
+            Self::UnsignedInt => xsd::UNSIGNED_INT,
+            Self::UnsignedLong => xsd::UNSIGNED_LONG,
+            Self::PositiveInteger => xsd::POSITIVE_INTEGER,
+            Self::NegativeInteger => xsd::NEGATIVE_INTEGER,
+            Self::NonPositiveInteger => xsd::NON_POSITIVE_INTEGER,
+            Self::NonNegativeInteger => xsd::NON_NEGATIVE_INTEGER,
+        }
+    }
+
+    /// Checks if the value is in the datatype value space
+    pub fn contains(self, value: Integer) -> bool {
+        let value = i64::from(value);
+        match self {
+            Self::Byte => i8::try_from(value).is_ok(),
+            Self::Short => i16::try_from(value).is_ok(),
+            Self::Int => i32::try_from(value).is_ok(),
+            Self::Long => true,
+            Self::UnsignedByte => u8::try_from(value).is_ok(),
+            Self::UnsignedShort => u16::try_from(value).is_ok(),
+            Self::UnsignedInt => u32::try_from(value).is_ok(),
+            Self::UnsignedLong | Self::NonNegativeInteger => value >= 0,
+            Self::PositiveInteger => value > 0,
+            Self::NegativeInteger => value < 0,
+            Self::NonPositiveInteger => value <= 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum EncodedTerm {
     DefaultGraph,
@@ -55,6 +110,10 @@ pub enum EncodedTerm {
     BigStringLiteral {
         value_id: StrHash,
     },
+    SmallAnyUriLiteral(SmallString),
+    BigAnyUriLiteral {
+        value_id: StrHash,
+    },
     SmallSmallLangStringLiteral {
         value: SmallString,
         language: SmallString,
@@ -83,6 +142,10 @@ pub enum EncodedTerm {
     FloatLiteral(Float),
     DoubleLiteral(Double),
     IntegerLiteral(Integer),
+    DerivedIntegerLiteral {
+        value: Integer,
+        datatype: DerivedIntegerDatatype,
+    },
     DecimalLiteral(Decimal),
     DateTimeLiteral(DateTime),
     TimeLiteral(Time),
@@ -121,6 +184,15 @@ impl PartialEq for EncodedTerm {
                     value_id: value_id_b,
                 },
             ) => value_id_a == value_id_b,
+            (Self::SmallAnyUriLiteral(a), Self::SmallAnyUriLiteral(b)) => a == b,
+            (
+                Self::BigAnyUriLiteral {
+                    value_id: value_id_a,
+                },
+                Self::BigAnyUriLiteral {
+                    value_id: value_id_b,
+                },
+            ) => value_id_a == value_id_b,
             (
                 Self::SmallSmallLangStringLiteral {
                     value: value_a,
@@ -185,6 +257,16 @@ impl PartialEq for EncodedTerm {
             (Self::FloatLiteral(a), Self::FloatLiteral(b)) => a.is_identical_with(*b),
             (Self::DoubleLiteral(a), Self::DoubleLiteral(b)) => a.is_identical_with(*b),
             (Self::IntegerLiteral(a), Self::IntegerLiteral(b)) => a.is_identical_with(*b),
+            (
+                Self::DerivedIntegerLiteral {
+                    value: value_a,
+                    datatype: datatype_a,
+                },
+                Self::DerivedIntegerLiteral {
+                    value: value_b,
+                    datatype: datatype_b,
+                },
+            ) => value_a.is_identical_with(*value_b) && datatype_a == datatype_b,
             (Self::DecimalLiteral(a), Self::DecimalLiteral(b)) => a.is_identical_with(*b),
             (Self::DateTimeLiteral(a), Self::DateTimeLiteral(b)) => a.is_identical_with(*b),
             (Self::TimeLiteral(a), Self::TimeLiteral(b)) => a.is_identical_with(*b),
@@ -219,6 +301,8 @@ impl Hash for EncodedTerm {
             Self::DefaultGraph => (),
             Self::SmallStringLiteral(value) => value.hash(state),
             Self::BigStringLiteral { value_id } => value_id.hash(state),
+            Self::SmallAnyUriLiteral(value) => value.hash(state),
+            Self::BigAnyUriLiteral { value_id } => value_id.hash(state),
             Self::SmallSmallLangStringLiteral { value, language } => {
                 value.hash(state);
                 language.hash(state);
@@ -253,6 +337,10 @@ impl Hash for EncodedTerm {
             Self::FloatLiteral(value) => value.to_be_bytes().hash(state),
             Self::DoubleLiteral(value) => value.to_be_bytes().hash(state),
             Self::IntegerLiteral(value) => value.hash(state),
+            Self::DerivedIntegerLiteral { value, datatype } => {
+                value.hash(state);
+                datatype.hash(state);
+            }
             Self::DecimalLiteral(value) => value.hash(state),
             Self::DateTimeLiteral(value) => value.hash(state),
             Self::TimeLiteral(value) => value.hash(state),
@@ -289,6 +377,8 @@ impl EncodedTerm {
             self,
             Self::SmallStringLiteral { .. }
                 | Self::BigStringLiteral { .. }
+                | Self::SmallAnyUriLiteral(_)
+                | Self::BigAnyUriLiteral { .. }
                 | Self::SmallSmallLangStringLiteral { .. }
                 | Self::SmallBigLangStringLiteral { .. }
                 | Self::BigSmallLangStringLiteral { .. }
@@ -299,6 +389,7 @@ impl EncodedTerm {
                 | Self::FloatLiteral(_)
                 | Self::DoubleLiteral(_)
                 | Self::IntegerLiteral(_)
+                | Self::DerivedIntegerLiteral { .. }
                 | Self::DecimalLiteral(_)
                 | Self::DateTimeLiteral(_)
                 | Self::TimeLiteral(_)
@@ -540,19 +631,43 @@ impl From<LiteralRef<'_>> for EncodedTerm {
             }
             "http://www.w3.org/2001/XMLSchema#float" => parse_float_str(value),
             "http://www.w3.org/2001/XMLSchema#double" => parse_double_str(value),
-            "http://www.w3.org/2001/XMLSchema#integer"
-            | "http://www.w3.org/2001/XMLSchema#byte"
-            | "http://www.w3.org/2001/XMLSchema#short"
-            | "http://www.w3.org/2001/XMLSchema#int"
-            | "http://www.w3.org/2001/XMLSchema#long"
-            | "http://www.w3.org/2001/XMLSchema#unsignedByte"
-            | "http://www.w3.org/2001/XMLSchema#unsignedShort"
-            | "http://www.w3.org/2001/XMLSchema#unsignedInt"
-            | "http://www.w3.org/2001/XMLSchema#unsignedLong"
-            | "http://www.w3.org/2001/XMLSchema#positiveInteger"
-            | "http://www.w3.org/2001/XMLSchema#negativeInteger"
-            | "http://www.w3.org/2001/XMLSchema#nonPositiveInteger"
-            | "http://www.w3.org/2001/XMLSchema#nonNegativeInteger" => parse_integer_str(value),
+            "http://www.w3.org/2001/XMLSchema#integer" => parse_integer_str(value),
+            "http://www.w3.org/2001/XMLSchema#byte" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::Byte)
+            }
+            "http://www.w3.org/2001/XMLSchema#short" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::Short)
+            }
+            "http://www.w3.org/2001/XMLSchema#int" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::Int)
+            }
+            "http://www.w3.org/2001/XMLSchema#long" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::Long)
+            }
+            "http://www.w3.org/2001/XMLSchema#unsignedByte" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::UnsignedByte)
+            }
+            "http://www.w3.org/2001/XMLSchema#unsignedShort" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::UnsignedShort)
+            }
+            "http://www.w3.org/2001/XMLSchema#unsignedInt" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::UnsignedInt)
+            }
+            "http://www.w3.org/2001/XMLSchema#unsignedLong" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::UnsignedLong)
+            }
+            "http://www.w3.org/2001/XMLSchema#positiveInteger" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::PositiveInteger)
+            }
+            "http://www.w3.org/2001/XMLSchema#negativeInteger" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::NegativeInteger)
+            }
+            "http://www.w3.org/2001/XMLSchema#nonPositiveInteger" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::NonPositiveInteger)
+            }
+            "http://www.w3.org/2001/XMLSchema#nonNegativeInteger" => {
+                parse_derived_integer_str(value, DerivedIntegerDatatype::NonNegativeInteger)
+            }
             "http://www.w3.org/2001/XMLSchema#decimal" => parse_decimal_str(value),
             "http://www.w3.org/2001/XMLSchema#dateTime"
             | "http://www.w3.org/2001/XMLSchema#dateTimeStamp" => parse_date_time_str(value),
@@ -570,6 +685,15 @@ impl From<LiteralRef<'_>> for EncodedTerm {
             "http://www.w3.org/2001/XMLSchema#dayTimeDuration" => {
                 parse_day_time_duration_str(value)
             }
+            "http://www.w3.org/2001/XMLSchema#anyURI" => {
+                Some(if let Ok(value) = SmallString::try_from(value) {
+                    Self::SmallAnyUriLiteral(value)
+                } else {
+                    Self::BigAnyUriLiteral {
+                        value_id: StrHash::new(value),
+                    }
+                })
+            }
             _ => None,
         };
         match native_encoding {
@@ -723,6 +847,7 @@ pub fn insert_term<F: FnMut(&StrHash, &str) -> Result<(), StorageError>>(
         },
         TermRef::Literal(literal) => match encoded {
             EncodedTerm::BigStringLiteral { value_id }
+            | EncodedTerm::BigAnyUriLiteral { value_id }
             | EncodedTerm::BigSmallLangStringLiteral { value_id, .. } => {
                 insert_str(value_id, literal.value())
             }
@@ -755,11 +880,13 @@ pub fn insert_term<F: FnMut(&StrHash, &str) -> Result<(), StorageError>>(
                 insert_str(datatype_id, literal.datatype().as_str())
             }
             EncodedTerm::SmallStringLiteral(..)
+            | EncodedTerm::SmallAnyUriLiteral(..)
             | EncodedTerm::SmallSmallLangStringLiteral { .. }
             | EncodedTerm::BooleanLiteral(..)
             | EncodedTerm::FloatLiteral(..)
             | EncodedTerm::DoubleLiteral(..)
             | EncodedTerm::IntegerLiteral(..)
+            | EncodedTerm::DerivedIntegerLiteral { .. }
             | EncodedTerm::DecimalLiteral(..)
             | EncodedTerm::DateTimeLiteral(..)
             | EncodedTerm::TimeLiteral(..)
@@ -790,6 +917,62 @@ pub fn insert_term<F: FnMut(&StrHash, &str) -> Result<(), StorageError>>(
     }
 }
 
+/// Calls `callback` on all the [`StrHash`] the encoded term relies on.
+#[cfg(not(target_family = "wasm"))]
+pub fn for_each_str_hash(encoded: &EncodedTerm, callback: &mut impl FnMut(&StrHash)) {
+    match encoded {
+        EncodedTerm::NamedNode { iri_id } => callback(iri_id),
+        EncodedTerm::BigBlankNode { id_id } => callback(id_id),
+        EncodedTerm::BigStringLiteral { value_id }
+        | EncodedTerm::BigAnyUriLiteral { value_id }
+        | EncodedTerm::BigSmallLangStringLiteral { value_id, .. } => callback(value_id),
+        EncodedTerm::SmallBigLangStringLiteral { language_id, .. } => callback(language_id),
+        EncodedTerm::BigBigLangStringLiteral {
+            value_id,
+            language_id,
+        } => {
+            callback(value_id);
+            callback(language_id);
+        }
+        EncodedTerm::SmallTypedLiteral { datatype_id, .. } => callback(datatype_id),
+        EncodedTerm::BigTypedLiteral {
+            value_id,
+            datatype_id,
+        } => {
+            callback(value_id);
+            callback(datatype_id);
+        }
+        EncodedTerm::Triple(triple) => {
+            for_each_str_hash(&triple.subject, callback);
+            for_each_str_hash(&triple.predicate, callback);
+            for_each_str_hash(&triple.object, callback);
+        }
+        EncodedTerm::DefaultGraph
+        | EncodedTerm::NumericalBlankNode { .. }
+        | EncodedTerm::SmallBlankNode(..)
+        | EncodedTerm::SmallStringLiteral(..)
+        | EncodedTerm::SmallAnyUriLiteral(..)
+        | EncodedTerm::SmallSmallLangStringLiteral { .. }
+        | EncodedTerm::BooleanLiteral(..)
+        | EncodedTerm::FloatLiteral(..)
+        | EncodedTerm::DoubleLiteral(..)
+        | EncodedTerm::IntegerLiteral(..)
+        | EncodedTerm::DerivedIntegerLiteral { .. }
+        | EncodedTerm::DecimalLiteral(..)
+        | EncodedTerm::DateTimeLiteral(..)
+        | EncodedTerm::TimeLiteral(..)
+        | EncodedTerm::DateLiteral(..)
+        | EncodedTerm::GYearMonthLiteral(..)
+        | EncodedTerm::GYearLiteral(..)
+        | EncodedTerm::GMonthDayLiteral(..)
+        | EncodedTerm::GDayLiteral(..)
+        | EncodedTerm::GMonthLiteral(..)
+        | EncodedTerm::DurationLiteral(..)
+        | EncodedTerm::YearMonthDurationLiteral(..)
+        | EncodedTerm::DayTimeDurationLiteral(..) => (),
+    }
+}
+
 pub fn parse_boolean_str(value: &str) -> Option<EncodedTerm> {
     value.parse().map(EncodedTerm::BooleanLiteral).ok()
 }
@@ -806,6 +989,16 @@ pub fn parse_integer_str(value: &str) -> Option<EncodedTerm> {
     value.parse().map(EncodedTerm::IntegerLiteral).ok()
 }
 
+pub fn parse_derived_integer_str(
+    value: &str,
+    datatype: DerivedIntegerDatatype,
+) -> Option<EncodedTerm> {
+    let value = value.parse().ok()?;
+    datatype
+        .contains(value)
+        .then_some(EncodedTerm::DerivedIntegerLiteral { value, datatype })
+}
+
 pub fn parse_decimal_str(value: &str) -> Option<EncodedTerm> {
     value.parse().map(EncodedTerm::DecimalLiteral).ok()
 }
@@ -961,6 +1154,14 @@ impl<S: StrLookup> Decoder for S {
             EncodedTerm::BigStringLiteral { value_id } => {
                 Ok(Literal::new_simple_literal(get_required_str(self, value_id)?).into())
             }
+            EncodedTerm::SmallAnyUriLiteral(value) => {
+                Ok(Literal::new_typed_literal(*value, xsd::ANY_URI).into())
+            }
+            EncodedTerm::BigAnyUriLiteral { value_id } => Ok(Literal::new_typed_literal(
+                get_required_str(self, value_id)?,
+                xsd::ANY_URI,
+            )
+            .into()),
             EncodedTerm::SmallSmallLangStringLiteral { value, language } => {
                 Ok(Literal::new_language_tagged_literal_unchecked(*value, *language).into())
             }
@@ -1005,6 +1206,9 @@ impl<S: StrLookup> Decoder for S {
             EncodedTerm::FloatLiteral(value) => Ok(Literal::from(*value).into()),
             EncodedTerm::DoubleLiteral(value) => Ok(Literal::from(*value).into()),
             EncodedTerm::IntegerLiteral(value) => Ok(Literal::from(*value).into()),
+            EncodedTerm::DerivedIntegerLiteral { value, datatype } => {
+                Ok(Literal::new_typed_literal(value.to_string(), datatype.iri()).into())
+            }
             EncodedTerm::DecimalLiteral(value) => Ok(Literal::from(*value).into()),
             EncodedTerm::DateTimeLiteral(value) => Ok(Literal::from(*value).into()),
             EncodedTerm::DateLiteral(value) => Ok(Literal::from(*value).into()),
@@ -0,0 +1,417 @@
+//! Read-only single file image of the storage content that is queried without being loaded.
+//!
+//! The file starts with a magic number and the storage encoding version.
+//! The entries of each column family are then written in the key order.
+//! An entry is a big endian `u32` key length, the key, a big endian `u32` value length and the value.
+//! The entries of a column family are followed by the big endian `u64` offsets of one entry every [`BLOCK_SIZE`] entries, used to binary search the keys.
+//! The file ends with the column families directory, the big endian `u64` offset of the directory and the magic number.
+//! A directory entry is the column family name length as a byte, the name and the big endian `u64` offsets of its entries, of its block offsets and of its end.
+
+use crate::storage::backend::{
+    ColumnFamily, Reader, StorageBackend, StorageBackendIter, StorageBackendSnapshot,
+    StorageBackendTransaction,
+};
+use crate::storage::binary_encoder::LATEST_STORAGE_VERSION;
+use crate::storage::{CorruptionError, StorageError};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+
+const MAGIC_NUMBER: &[u8; 8] = b"OXIGPACK";
+/// Number of entries between two block offsets
+const BLOCK_SIZE: u64 = 16;
+
+/// Writes the content of the given column families into `write`.
+pub fn write_packed<W: Write>(
+    reader: &Reader,
+    column_families: &[(&'static str, &ColumnFamily)],
+    write: W,
+) -> Result<W, StorageError> {
+    let mut write = CountingWrite {
+        inner: write,
+        position: 0,
+    };
+    write.write_all(MAGIC_NUMBER)?;
+    write.write_all(&LATEST_STORAGE_VERSION.to_be_bytes())?;
+    let mut directory = Vec::with_capacity(column_families.len());
+    for (name, column_family) in column_families {
+        let entries_start = write.position;
+        let mut block_offsets = Vec::new();
+        let mut count = 0;
+        let mut iter = reader.iter(column_family)?;
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            if count % BLOCK_SIZE == 0 {
+                block_offsets.push(write.position);
+            }
+            write_slice(&mut write, key)?;
+            write_slice(&mut write, value)?;
+            count += 1;
+            iter.next();
+        }
+        iter.status()?;
+        let blocks_start = write.position;
+        for offset in block_offsets {
+            write.write_all(&offset.to_be_bytes())?;
+        }
+        directory.push((*name, entries_start, blocks_start, write.position));
+    }
+    let directory_start = write.position;
+    for (name, entries_start, blocks_start, end) in directory {
+        write.write_all(&[name.len().try_into().map_err(|_| {
+            StorageError::Other(format!("Too long column family name {name}").into())
+        })?])?;
+        write.write_all(name.as_bytes())?;
+        write.write_all(&entries_start.to_be_bytes())?;
+        write.write_all(&blocks_start.to_be_bytes())?;
+        write.write_all(&end.to_be_bytes())?;
+    }
+    write.write_all(&directory_start.to_be_bytes())?;
+    write.write_all(MAGIC_NUMBER)?;
+    Ok(write.inner)
+}
+
+fn write_slice(write: &mut impl Write, slice: &[u8]) -> Result<(), StorageError> {
+    let len = u32::try_from(slice.len())
+        .map_err(|_| StorageError::Other("Too long key or value to be packed".into()))?;
+    write.write_all(&len.to_be_bytes())?;
+    write.write_all(slice)?;
+    Ok(())
+}
+
+/// Keeps track of the number of written bytes to compute the offsets
+struct CountingWrite<W: Write> {
+    inner: W,
+    position: u64,
+}
+
+impl<W: Write> Write for CountingWrite<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.position +=
+            u64::try_from(written).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A read-only [`StorageBackend`] on top of a file written by [`write_packed`].
+pub struct PackedBackend(Arc<PackedFile>);
+
+struct PackedFile {
+    data: Mapping,
+    column_families: HashMap<String, PackedColumnFamily>,
+}
+
+#[derive(Clone, Copy)]
+struct PackedColumnFamily {
+    entries_start: usize,
+    blocks_start: usize,
+    end: usize,
+}
+
+impl PackedBackend {
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        let data = Mapping::open(path)?;
+        let version = read_u64(&data, MAGIC_NUMBER.len())?;
+        if data.get(..MAGIC_NUMBER.len()) != Some(MAGIC_NUMBER)
+            || data.get(data.len().saturating_sub(MAGIC_NUMBER.len())..) != Some(MAGIC_NUMBER)
+        {
+            return Err(CorruptionError::msg("The file is not a packed Oxigraph store").into());
+        }
+        if version != LATEST_STORAGE_VERSION {
+            return Err(CorruptionError::msg(format!(
+                "The packed store is using the encoding version {version} but only version {LATEST_STORAGE_VERSION} is supported. Please pack the original database again with the `oxigraph pack` command of this Oxigraph version"
+            ))
+            .into());
+        }
+        let directory_end = data.len() - MAGIC_NUMBER.len() - 8;
+        let mut position = read_offset(&data, directory_end)?;
+        let mut column_families = HashMap::new();
+        while position < directory_end {
+            let name_len = usize::from(*data.get(position).ok_or_else(truncated_error)?);
+            let name = data
+                .get(position + 1..position + 1 + name_len)
+                .ok_or_else(truncated_error)?;
+            let name = String::from_utf8(name.to_vec())
+                .map_err(|_| CorruptionError::msg("Invalid column family name"))?;
+            position += 1 + name_len;
+            let column_family = PackedColumnFamily {
+                entries_start: read_offset(&data, position)?,
+                blocks_start: read_offset(&data, position + 8)?,
+                end: read_offset(&data, position + 16)?,
+            };
+            if column_family.entries_start > column_family.blocks_start
+                || column_family.blocks_start > column_family.end
+                || column_family.end > directory_end
+                || (column_family.end - column_family.blocks_start) % 8 != 0
+            {
+                return Err(CorruptionError::msg(format!(
+                    "Invalid offsets for the column family {name}"
+                ))
+                .into());
+            }
+            position += 24;
+            column_families.insert(name, column_family);
+        }
+        Ok(Self(Arc::new(PackedFile {
+            data,
+            column_families,
+        })))
+    }
+}
+
+impl StorageBackend for PackedBackend {
+    fn create_column_family(&self, _name: &str) -> Result<(), StorageError> {
+        // The missing column families are considered empty
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Box<dyn StorageBackendSnapshot> {
+        Box::new(PackedSnapshot(Arc::clone(&self.0)))
+    }
+
+    fn transaction(&self) -> Result<Box<dyn StorageBackendTransaction + '_>, StorageError> {
+        Err(StorageError::Other("Packed stores are read-only".into()))
+    }
+}
+
+struct PackedSnapshot(Arc<PackedFile>);
+
+impl StorageBackendSnapshot for PackedSnapshot {
+    fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        let Some(column_family) = self.0.column_families.get(column_family) else {
+            return Ok(None);
+        };
+        let position = self.0.seek(column_family, key)?;
+        if position < column_family.blocks_start {
+            let (found_key, value, _) = self.0.entry(position)?;
+            if found_key == key {
+                return Ok(Some(value.to_vec()));
+            }
+        }
+        Ok(None)
+    }
+
+    fn scan(
+        &self,
+        column_family: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<StorageBackendIter, StorageError> {
+        let Some(column_family) = self.0.column_families.get(column_family).copied() else {
+            return Ok(Box::new(std::iter::empty()));
+        };
+        Ok(Box::new(PackedIter {
+            position: self.0.seek(&column_family, start)?,
+            file: Arc::clone(&self.0),
+            end_key: end.map(<[u8]>::to_vec),
+            end: column_family.blocks_start,
+        }))
+    }
+}
+
+impl PackedFile {
+    /// Returns the position of the first entry of the column family with a key greater or equal to `key`
+    fn seek(&self, column_family: &PackedColumnFamily, key: &[u8]) -> Result<usize, StorageError> {
+        // We look for the last block starting with a key lower or equal to the given one
+        let block_count = (column_family.end - column_family.blocks_start) / 8;
+        let (mut low, mut high) = (0, block_count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let (block_key, _, _) = self.entry(self.block_offset(column_family, middle)?)?;
+            if block_key <= key {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        let mut position = if low == 0 {
+            column_family.entries_start
+        } else {
+            self.block_offset(column_family, low - 1)?
+        };
+        while position < column_family.blocks_start {
+            let (entry_key, _, next) = self.entry(position)?;
+            if entry_key >= key {
+                break;
+            }
+            position = next;
+        }
+        Ok(position)
+    }
+
+    fn block_offset(
+        &self,
+        column_family: &PackedColumnFamily,
+        block: usize,
+    ) -> Result<usize, StorageError> {
+        read_offset(&self.data, column_family.blocks_start + 8 * block)
+    }
+
+    /// Returns the key and the value of the entry at the given position and the position of the next entry
+    fn entry(&self, position: usize) -> Result<(&[u8], &[u8], usize), StorageError> {
+        let (key, position) = self.slice(position)?;
+        let (value, position) = self.slice(position)?;
+        Ok((key, value, position))
+    }
+
+    fn slice(&self, position: usize) -> Result<(&[u8], usize), StorageError> {
+        let len = usize::try_from(u32::from_be_bytes(
+            self.data
+                .get(position..position + 4)
+                .ok_or_else(truncated_error)?
+                .try_into()
+                .map_err(|_| truncated_error())?,
+        ))
+        .map_err(|_| truncated_error())?;
+        let slice = self
+            .data
+            .get(position + 4..position + 4 + len)
+            .ok_or_else(truncated_error)?;
+        Ok((slice, position + 4 + len))
+    }
+}
+
+struct PackedIter {
+    file: Arc<PackedFile>,
+    position: usize,
+    end: usize,
+    end_key: Option<Vec<u8>>,
+}
+
+impl Iterator for PackedIter {
+    type Item = Result<(Vec<u8>, Vec<u8>), StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.end {
+            return None;
+        }
+        let (key, value, next) = match self.file.entry(self.position) {
+            Ok(entry) => entry,
+            Err(e) => {
+                self.position = self.end;
+                return Some(Err(e));
+            }
+        };
+        if self
+            .end_key
+            .as_ref()
+            .map_or(false, |end_key| key >= end_key.as_slice())
+        {
+            self.position = self.end;
+            return None;
+        }
+        self.position = next;
+        Some(Ok((key.to_vec(), value.to_vec())))
+    }
+}
+
+fn read_u64(data: &[u8], position: usize) -> Result<u64, StorageError> {
+    Ok(u64::from_be_bytes(
+        data.get(position..position + 8)
+            .ok_or_else(truncated_error)?
+            .try_into()
+            .map_err(|_| truncated_error())?,
+    ))
+}
+
+fn read_offset(data: &[u8], position: usize) -> Result<usize, StorageError> {
+    usize::try_from(read_u64(data, position)?)
+        .map_err(|_| CorruptionError::msg("Too large offset in the packed store").into())
+}
+
+fn truncated_error() -> StorageError {
+    CorruptionError::msg("The packed store file is truncated").into()
+}
+
+/// The file content, memory-mapped if possible
+enum Mapping {
+    #[cfg(unix)]
+    Mapped {
+        pointer: *mut libc::c_void,
+        len: usize,
+    },
+    #[cfg(not(unix))]
+    Loaded(Vec<u8>),
+}
+
+// SAFETY: the mapping is read-only and never changed after its creation
+#[allow(unsafe_code)]
+unsafe impl Send for Mapping {}
+// SAFETY: the mapping is read-only and never changed after its creation
+#[allow(unsafe_code)]
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    #[cfg(unix)]
+    #[allow(unsafe_code)]
+    fn open(path: &Path) -> Result<Self, StorageError> {
+        use std::os::unix::io::AsRawFd;
+
+        let file = File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| CorruptionError::msg("The packed store file is too large"))?;
+        if len < 2 * MAGIC_NUMBER.len() + 16 {
+            return Err(truncated_error());
+        }
+        // SAFETY: we map a file we opened in read-only mode, the mapping is kept valid after the file is closed
+        let pointer = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if pointer == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(Self::Mapped { pointer, len })
+    }
+
+    #[cfg(not(unix))]
+    fn open(path: &Path) -> Result<Self, StorageError> {
+        let data = std::fs::read(path)?;
+        if data.len() < 2 * MAGIC_NUMBER.len() + 16 {
+            return Err(truncated_error());
+        }
+        Ok(Self::Loaded(data))
+    }
+}
+
+impl Deref for Mapping {
+    type Target = [u8];
+
+    #[allow(unsafe_code)]
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(unix)]
+            // SAFETY: the mapping is valid and readable for len bytes until it is dropped
+            Self::Mapped { pointer, len } => unsafe {
+                std::slice::from_raw_parts(pointer.cast::<u8>(), *len)
+            },
+            #[cfg(not(unix))]
+            Self::Loaded(data) => data,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mapping {
+    #[allow(unsafe_code)]
+    fn drop(&mut self) {
+        let Self::Mapped { pointer, len } = self;
+        // SAFETY: the mapping has been created by mmap and is not used anymore
+        unsafe {
+            libc::munmap(*pointer, *len);
+        }
+    }
+}
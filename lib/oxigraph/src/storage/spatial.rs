@@ -0,0 +1,182 @@
+//! Spatial index over `geo:wktLiteral` objects.
+//!
+//! The plane is split in a quadtree of at most [`MAX_LEVEL`] levels over the CRS84 longitudes and latitudes.
+//! Each geometry is stored in the deepest cell containing its bounding box.
+//! The cells are identified by their Z-order code, left-aligned on 32 bits, so that the entries of a cell
+//! and of all its descendants are a contiguous range of the `spatial` column family.
+//!
+//! The key is the cell code as a big endian `u32`, the cell level as a `u8`
+//! and the quad in the SPOG encoding (without graph name for the default graph).
+//! The value is the bounding box of the geometry as four big endian `f64`: min x, min y, max x and max y.
+//! The empty key is present if the index is enabled.
+
+use crate::geo::{BoundingBox, Geometry, WKT_LITERAL};
+use crate::storage::binary_encoder::{decode_spog_quad, write_spog_quad};
+use crate::storage::error::{CorruptionError, StorageError};
+use crate::storage::numeric_encoder::{EncodedQuad, EncodedTerm, StrHash};
+
+/// Key of the `spatial` column family marking the index as enabled.
+pub const SPATIAL_INDEX_ENABLED_KEY: &[u8] = b"";
+const MAX_LEVEL: u8 = 16;
+const CELL_PREFIX_LEN: usize = 5;
+
+/// Returns if the term is a `geo:wktLiteral`
+pub fn is_wkt_literal(term: &EncodedTerm) -> bool {
+    match term {
+        EncodedTerm::SmallTypedLiteral { datatype_id, .. }
+        | EncodedTerm::BigTypedLiteral { datatype_id, .. } => {
+            *datatype_id == StrHash::new(WKT_LITERAL)
+        }
+        _ => false,
+    }
+}
+
+/// Returns the entry to write in the `spatial` column family for a WKT literal value.
+///
+/// Invalid and empty geometries are not indexed.
+pub fn spatial_index_entry(value: &str, quad: &EncodedQuad) -> Option<(Vec<u8>, [u8; 32])> {
+    let bbox = value.parse::<Geometry>().ok()?.bounding_box()?;
+    let (code, level) = cell(&bbox);
+    let mut key = cell_prefix(code, level).to_vec();
+    write_spog_quad(&mut key, quad);
+    let mut value = [0; 32];
+    for (chunk, coordinate) in value
+        .chunks_exact_mut(8)
+        .zip([bbox.min_x, bbox.min_y, bbox.max_x, bbox.max_y])
+    {
+        chunk.copy_from_slice(&coordinate.to_be_bytes());
+    }
+    Some((key, value))
+}
+
+/// Decodes an entry of the `spatial` column family
+pub fn decode_entry(key: &[u8], value: &[u8]) -> Result<(EncodedQuad, BoundingBox), StorageError> {
+    let quad = decode_spog_quad(
+        key.get(CELL_PREFIX_LEN..)
+            .ok_or_else(|| CorruptionError::msg("Invalid spatial index key"))?,
+    )?;
+    if value.len() != 32 {
+        return Err(CorruptionError::msg("Invalid spatial index value").into());
+    }
+    let mut coordinates = value.chunks_exact(8).map(|chunk| {
+        let mut buffer = [0; 8];
+        buffer.copy_from_slice(chunk);
+        f64::from_be_bytes(buffer)
+    });
+    let mut next = || {
+        coordinates
+            .next()
+            .ok_or_else(|| CorruptionError::msg("Invalid spatial index value"))
+    };
+    let bbox = BoundingBox {
+        min_x: next()?,
+        min_y: next()?,
+        max_x: next()?,
+        max_y: next()?,
+    };
+    Ok((quad, bbox))
+}
+
+/// Rewrites the quad terms of a key, used to migrate the key between storage versions
+#[cfg(not(target_family = "wasm"))]
+pub fn rewrite_key_terms(
+    key: &[u8],
+    rewrite_terms: fn(&[u8]) -> Result<Vec<u8>, StorageError>,
+) -> Result<Vec<u8>, StorageError> {
+    if key.len() < CELL_PREFIX_LEN {
+        // The enabled marker key
+        return Ok(key.to_vec());
+    }
+    let (prefix, quad) = key.split_at(CELL_PREFIX_LEN);
+    let mut new_key = prefix.to_vec();
+    new_key.extend_from_slice(&rewrite_terms(quad)?);
+    Ok(new_key)
+}
+
+/// The ranges of the `spatial` column family to scan to find the geometries intersecting a bounding box
+pub struct SpatialScan {
+    /// The prefixes of the ancestor cells of the query cell
+    pub ancestor_prefixes: Vec<[u8; CELL_PREFIX_LEN]>,
+    /// The first key of the query cell and of its descendants
+    pub start: [u8; 4],
+    /// The first cell code after the query cell descendants, `None` if it overflows
+    pub end: Option<u32>,
+    /// The level of the query cell, the keys in the range with a lower level are ancestors
+    pub level: u8,
+}
+
+impl SpatialScan {
+    pub fn new(bbox: &BoundingBox) -> Self {
+        let (code, level) = cell(bbox);
+        Self {
+            ancestor_prefixes: (0..level)
+                .map(|l| cell_prefix(code & level_mask(l), l))
+                .collect(),
+            start: code.to_be_bytes(),
+            end: 1_u32
+                .checked_shl((2 * (MAX_LEVEL - level)).into())
+                .and_then(|size| code.checked_add(size)),
+            level,
+        }
+    }
+
+    /// Returns if the key of the range is in the query cell or one of its descendants and not in an ancestor
+    pub fn is_descendant(&self, key: &[u8]) -> bool {
+        key.get(4).map_or(false, |level| *level >= self.level)
+    }
+
+    /// Returns if the key is after the query cell and its descendants
+    pub fn is_after_range(&self, key: &[u8]) -> bool {
+        self.end.map_or(false, |end| {
+            key.get(..4)
+                .map_or(true, |code| code >= end.to_be_bytes().as_slice())
+        })
+    }
+}
+
+/// Returns the code and the level of the deepest cell containing the bounding box
+fn cell(bbox: &BoundingBox) -> (u32, u8) {
+    let (min_x, max_x) = (quantize(bbox.min_x, 180.), quantize(bbox.max_x, 180.));
+    let (min_y, max_y) = (quantize(bbox.min_y, 90.), quantize(bbox.max_y, 90.));
+    let level = (0..=MAX_LEVEL)
+        .rev()
+        .find(|level| {
+            let shift = MAX_LEVEL - level;
+            min_x >> shift == max_x >> shift && min_y >> shift == max_y >> shift
+        })
+        .unwrap_or(0);
+    (interleave(min_x, min_y) & level_mask(level), level)
+}
+
+/// Maps a coordinate in `[-bound, bound]` to a `MAX_LEVEL` bits integer
+fn quantize(coordinate: f64, bound: f64) -> u32 {
+    let scaled = ((coordinate + bound) / (2. * bound) * f64::from(1_u32 << MAX_LEVEL)).floor();
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    {
+        scaled.clamp(0., f64::from((1_u32 << MAX_LEVEL) - 1)) as u32
+    }
+}
+
+/// Z-order code of the cell: the bits of x and y are interleaved, x first
+fn interleave(x: u32, y: u32) -> u32 {
+    let mut code = 0;
+    for i in 0..MAX_LEVEL {
+        code |= ((x >> i) & 1) << (2 * i + 1);
+        code |= ((y >> i) & 1) << (2 * i);
+    }
+    code
+}
+
+/// The bits of a code identifying a cell of the given level
+fn level_mask(level: u8) -> u32 {
+    u32::MAX
+        .checked_shl((2 * (MAX_LEVEL - level)).into())
+        .unwrap_or(0)
+}
+
+fn cell_prefix(code: u32, level: u8) -> [u8; CELL_PREFIX_LEN] {
+    let mut prefix = [0; CELL_PREFIX_LEN];
+    prefix[..4].copy_from_slice(&code.to_be_bytes());
+    prefix[4] = level;
+    prefix
+}
@@ -0,0 +1,167 @@
+//! Full-text index over string literals.
+//!
+//! The `text` column family contains one entry per token and indexed quad.
+//! The key is the token, a `0` byte and the quad in the SPOG encoding (without graph name for the default graph).
+//! The value is the number of occurrences of the token in the literal followed by the number of tokens of the literal,
+//! both as big endian `u32`.
+//! The index configuration is stored under the empty key.
+
+use crate::storage::binary_encoder::write_spog_quad;
+use crate::storage::error::{CorruptionError, StorageError};
+use crate::storage::numeric_encoder::{EncodedQuad, EncodedTerm, StrHash};
+use std::collections::{BTreeMap, HashSet};
+
+/// Key of the `text` column family storing the index configuration.
+///
+/// It is only present if the text index is enabled.
+pub const TEXT_INDEX_CONFIG_KEY: &[u8] = b"";
+const TOKEN_SEPARATOR: u8 = 0;
+
+/// Which literals are indexed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TextIndexConfig {
+    /// The predicates whose objects are indexed, all string literals are indexed if `None`
+    predicates: Option<HashSet<StrHash>>,
+}
+
+impl TextIndexConfig {
+    pub fn for_predicates(predicates: impl IntoIterator<Item = EncodedTerm>) -> Self {
+        let predicates = predicates
+            .into_iter()
+            .filter_map(|predicate| {
+                if let EncodedTerm::NamedNode { iri_id } = predicate {
+                    Some(iri_id)
+                } else {
+                    None
+                }
+            })
+            .collect::<HashSet<_>>();
+        Self {
+            predicates: (!predicates.is_empty()).then_some(predicates),
+        }
+    }
+
+    /// Returns if the object of the quad should be indexed
+    pub fn is_indexed(&self, quad: &EncodedQuad) -> bool {
+        is_string_literal(&quad.object)
+            && match (&self.predicates, &quad.predicate) {
+                (None, _) => true,
+                (Some(predicates), EncodedTerm::NamedNode { iri_id }) => {
+                    predicates.contains(iri_id)
+                }
+                (Some(_), _) => false,
+            }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if let Some(predicates) = &self.predicates {
+            let mut predicates = predicates
+                .iter()
+                .map(|p| p.to_be_bytes())
+                .collect::<Vec<_>>();
+            predicates.sort_unstable();
+            for predicate in predicates {
+                bytes.extend_from_slice(&predicate);
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StorageError> {
+        if bytes.is_empty() {
+            return Ok(Self::default());
+        }
+        if bytes.len() % 16 != 0 {
+            return Err(CorruptionError::msg("Invalid text index configuration").into());
+        }
+        Ok(Self {
+            predicates: Some(
+                bytes
+                    .chunks_exact(16)
+                    .map(|chunk| {
+                        let mut hash = [0; 16];
+                        hash.copy_from_slice(chunk);
+                        StrHash::from_be_bytes(hash)
+                    })
+                    .collect(),
+            ),
+        })
+    }
+}
+
+fn is_string_literal(term: &EncodedTerm) -> bool {
+    matches!(
+        term,
+        EncodedTerm::SmallStringLiteral(_)
+            | EncodedTerm::BigStringLiteral { .. }
+            | EncodedTerm::SmallSmallLangStringLiteral { .. }
+            | EncodedTerm::SmallBigLangStringLiteral { .. }
+            | EncodedTerm::BigSmallLangStringLiteral { .. }
+            | EncodedTerm::BigBigLangStringLiteral { .. }
+    )
+}
+
+/// Splits the text into lowercase alphanumeric tokens and counts their occurrences
+pub fn tokenize(text: &str) -> BTreeMap<String, u32> {
+    let mut tokens = BTreeMap::new();
+    for token in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+    {
+        *tokens.entry(token.to_lowercase()).or_insert(0) += 1;
+    }
+    tokens
+}
+
+/// Returns the entries to write in the `text` column family for a literal value
+pub fn text_index_entries(value: &str, quad: &EncodedQuad) -> Vec<(Vec<u8>, [u8; 8])> {
+    let tokens = tokenize(value);
+    let token_count = tokens.values().sum::<u32>();
+    tokens
+        .into_iter()
+        .map(|(token, count)| {
+            let mut key = token_prefix(&token);
+            write_spog_quad(&mut key, quad);
+            let mut value = [0; 8];
+            value[..4].copy_from_slice(&count.to_be_bytes());
+            value[4..].copy_from_slice(&token_count.to_be_bytes());
+            (key, value)
+        })
+        .collect()
+}
+
+/// The prefix of all the keys of a token
+pub fn token_prefix(token: &str) -> Vec<u8> {
+    let mut prefix = Vec::with_capacity(token.len() + 1);
+    prefix.extend_from_slice(token.as_bytes());
+    prefix.push(TOKEN_SEPARATOR);
+    prefix
+}
+
+/// Returns the score contribution of a token from the value of its entry i.e. its frequency in the literal
+pub fn decode_entry_score(value: &[u8]) -> Result<f32, StorageError> {
+    let (Some(count), Some(token_count)) = (value.get(..4), value.get(4..8)) else {
+        return Err(CorruptionError::msg("Invalid text index entry").into());
+    };
+    let count = u32::from_be_bytes(count.try_into().map_err(CorruptionError::new)?);
+    let token_count = u32::from_be_bytes(token_count.try_into().map_err(CorruptionError::new)?);
+    #[allow(clippy::cast_precision_loss)]
+    Ok(count as f32 / token_count.max(1) as f32)
+}
+
+/// Rewrites the quad terms of a key, used to migrate the key between storage versions
+#[cfg(not(target_family = "wasm"))]
+pub fn rewrite_key_terms(
+    key: &[u8],
+    rewrite_terms: fn(&[u8]) -> Result<Vec<u8>, StorageError>,
+) -> Result<Vec<u8>, StorageError> {
+    let Some(separator) = key.iter().position(|b| *b == TOKEN_SEPARATOR) else {
+        // The configuration key
+        return Ok(key.to_vec());
+    };
+    let (token, quad) = key.split_at(separator + 1);
+    let mut new_key = token.to_vec();
+    new_key.extend_from_slice(&rewrite_terms(quad)?);
+    Ok(new_key)
+}
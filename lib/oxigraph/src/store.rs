@@ -34,16 +34,27 @@ use crate::sparql::{
     QueryResults, Update, UpdateOptions,
 };
 use crate::storage::numeric_encoder::{Decoder, EncodedQuad, EncodedTerm};
+pub use crate::storage::{
+    check_storage_backend, CorruptionError, LoaderError, SerializerError, StorageBackend,
+    StorageBackendIter, StorageBackendSnapshot, StorageBackendTransaction, StorageError,
+};
 #[cfg(not(target_family = "wasm"))]
-use crate::storage::StorageBulkLoader;
+pub use crate::storage::{BackupInfo, LATEST_STORAGE_VERSION};
 use crate::storage::{
-    ChainedDecodingQuadIterator, DecodingGraphIterator, Storage, StorageReader, StorageWriter,
+    ChainedDecodingQuadIterator, DecodingChangeIterator, DecodingGraphIterator, EncodedChange,
+    Storage, StorageReader, StorageSnapshot, StorageWriter, TextIndexConfig,
 };
-pub use crate::storage::{CorruptionError, LoaderError, SerializerError, StorageError};
+#[cfg(not(target_family = "wasm"))]
+use crate::storage::{DbOptions, StorageBulkLoader, StorageBulkRemover};
 use std::error::Error;
+#[cfg(not(target_family = "wasm"))]
+use std::fs::File;
+#[cfg(not(target_family = "wasm"))]
+use std::io::{BufReader, BufWriter};
 use std::io::{Read, Write};
 #[cfg(not(target_family = "wasm"))]
 use std::path::Path;
+use std::sync::Arc;
 use std::{fmt, str};
 
 /// An on-disk [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset).
@@ -102,8 +113,49 @@ impl Store {
     /// use [`Store::open_secondary`] or [`Store::open_read_only`].
     #[cfg(not(target_family = "wasm"))]
     pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Self::open_with_options(path, &StoreOptions::default())
+    }
+
+    /// Opens a read-write [`Store`] with the given tuning [`StoreOptions`] and creates it if it does not exist yet.
+    ///
+    /// The options are only used by the current process and are not persisted with the data.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::store::{Store, StoreOptions};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = Store::open_with_options(
+    ///     "example_small.db",
+    ///     &StoreOptions::default()
+    ///         .with_block_cache_size_in_megabytes(8)
+    ///         .with_max_open_files(128),
+    /// )?;
+    /// assert!(store.is_empty()?);
+    /// # };
+    /// # remove_dir_all("example_small.db")?;
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[cfg(not(target_family = "wasm"))]
+    pub fn open_with_options(
+        path: impl AsRef<Path>,
+        options: &StoreOptions,
+    ) -> Result<Self, StorageError> {
+        Ok(Self {
+            storage: Storage::open(path.as_ref(), &options.inner)?,
+        })
+    }
+
+    /// Opens a [`Store`] on top of a custom key-value [`StorageBackend`].
+    ///
+    /// The missing column families are created and the store content is migrated to the latest storage version if needed.
+    /// Backups are only supported by the built-in storage.
+    ///
+    /// See [`StorageBackend`] for an implementation example.
+    pub fn open_with_backend(backend: impl StorageBackend) -> Result<Self, StorageError> {
         Ok(Self {
-            storage: Storage::open(path.as_ref())?,
+            storage: Storage::open_with_backend(Arc::new(backend))?,
         })
     }
 
@@ -145,6 +197,19 @@ impl Store {
         })
     }
 
+    /// Opens a read-only [`Store`] from a file written by [`Store::pack_to_file`].
+    ///
+    /// The file is memory-mapped and queried in place, without RocksDB.
+    /// All the write operations fail.
+    ///
+    /// <div class="warning">The file must not be modified while the store is open.</div>
+    #[cfg(not(target_family = "wasm"))]
+    pub fn open_packed(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Ok(Self {
+            storage: Storage::open_packed(path.as_ref())?,
+        })
+    }
+
     /// Opens a read-only [`Store`] from disk.
     ///
     /// Opening as read-only while having an other process writing the database is undefined behavior.
@@ -355,6 +420,41 @@ impl Store {
         self.storage.snapshot().len()
     }
 
+    /// Returns the number of quads matching a pattern, for example the number of quads in a graph.
+    ///
+    /// The matching entries of the storage indexes are counted without being decoded,
+    /// which is much faster than counting the results of [`Store::quads_for_pattern`].
+    ///
+    /// <div class="warning">This function scans all the matching index entries.</div>
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::store::Store;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let store = Store::new()?;
+    /// store.insert(QuadRef::new(ex, ex, ex, ex))?;
+    /// store.insert(QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph))?;
+    /// assert_eq!(2, store.count_for_pattern(Some(ex.into()), None, None, None)?);
+    /// assert_eq!(1, store.count_for_pattern(None, None, None, Some(ex.into()))?);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn count_for_pattern(
+        &self,
+        subject: Option<SubjectRef<'_>>,
+        predicate: Option<NamedNodeRef<'_>>,
+        object: Option<TermRef<'_>>,
+        graph_name: Option<GraphNameRef<'_>>,
+    ) -> Result<usize, StorageError> {
+        self.storage.snapshot().count_for_pattern(
+            subject.map(EncodedTerm::from).as_ref(),
+            predicate.map(EncodedTerm::from).as_ref(),
+            object.map(EncodedTerm::from).as_ref(),
+            graph_name.map(EncodedTerm::from).as_ref(),
+        )
+    }
+
     /// Returns if the store is empty.
     ///
     /// Usage example:
@@ -374,6 +474,37 @@ impl Store {
         self.storage.snapshot().is_empty()
     }
 
+    /// Returns a read-only view of the store frozen at the current point in time.
+    ///
+    /// All the operations done on the returned [`StoreSnapshot`] see the same data,
+    /// even if the store is modified in the meantime.
+    /// The snapshot can be kept as long as needed and shared between threads.
+    ///
+    /// The strings used by alive snapshots are kept by [`Store::optimize`].
+    ///
+    /// Snapshots are not supported on secondary stores (see [`Store::open_secondary`]) because their content changes on each catch up with the primary.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::store::Store;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let store = Store::new()?;
+    /// store.insert(QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph))?;
+    ///
+    /// let snapshot = store.snapshot()?;
+    /// store.insert(QuadRef::new(ex, ex, ex, ex))?;
+    /// assert_eq!(store.len()?, 2);
+    /// assert_eq!(snapshot.len()?, 1);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn snapshot(&self) -> Result<StoreSnapshot, StorageError> {
+        Ok(StoreSnapshot {
+            snapshot: self.storage.shared_snapshot()?,
+        })
+    }
+
     /// Executes a transaction.
     ///
     /// Transactions ensure the "repeatable read" isolation level: the store only exposes changes that have
@@ -854,6 +985,14 @@ impl Store {
 
     /// Clears a graph from this store.
     ///
+    /// The graph quads are hidden from the readers as soon as the operation starts.
+    /// The graph-prefixed indexes are then cleared with range deletions so that clearing a large graph is cheap.
+    /// An interrupted operation is completed when the store is opened again.
+    ///
+    /// <div class="warning">This operation blocks the other writes while running.
+    /// The change log records the quad removals progressively.
+    /// Use [`Transaction::clear_graph`] for a single transaction.</div>
+    ///
     /// Usage example:
     /// ```
     /// use oxigraph::model::{NamedNodeRef, QuadRef};
@@ -875,6 +1014,12 @@ impl Store {
         graph_name: impl Into<GraphNameRef<'a>>,
     ) -> Result<(), StorageError> {
         let graph_name = graph_name.into();
+        #[cfg(not(target_family = "wasm"))]
+        {
+            self.storage.drop_graph(&graph_name.into(), false)?;
+            Ok(())
+        }
+        #[cfg(target_family = "wasm")]
         self.transaction(|mut t| t.clear_graph(graph_name))
     }
 
@@ -882,6 +1027,14 @@ impl Store {
     ///
     /// Returns `true` if the graph was in the store and has been removed.
     ///
+    /// The graph quads are hidden from the readers as soon as the operation starts.
+    /// The graph-prefixed indexes are then cleared with range deletions so that removing a large graph is cheap.
+    /// An interrupted operation is completed when the store is opened again.
+    ///
+    /// <div class="warning">This operation blocks the other writes while running.
+    /// The change log records the quad removals progressively.
+    /// Use [`Transaction::remove_named_graph`] for a single transaction.</div>
+    ///
     /// Usage example:
     /// ```
     /// use oxigraph::model::{NamedNodeRef, QuadRef};
@@ -903,6 +1056,11 @@ impl Store {
         graph_name: impl Into<NamedOrBlankNodeRef<'a>>,
     ) -> Result<bool, StorageError> {
         let graph_name = graph_name.into();
+        #[cfg(not(target_family = "wasm"))]
+        {
+            self.storage.drop_graph(&graph_name.into(), true)
+        }
+        #[cfg(target_family = "wasm")]
         self.transaction(|mut t| t.remove_named_graph(graph_name))
     }
 
@@ -939,12 +1097,37 @@ impl Store {
     ///
     /// Useful to call after a batch upload or another similar operation.
     ///
+    /// It also removes from the storage the IRIs and literals that are not used by any quad or named graph anymore
+    /// and refreshes the cardinality statistics used by the SPARQL query planner.
+    /// Writes can be done while this operation is running.
+    ///
     /// <div class="warning">Can take hours on huge databases.</div>
+    ///
+    /// <div class="warning">The set of the used IRIs and literals hashes is kept in memory during the operation:
+    /// expect 16 bytes of memory use per distinct IRI or literal stored (plus hash set overhead).</div>
     #[cfg(not(target_family = "wasm"))]
     pub fn optimize(&self) -> Result<(), StorageError> {
         self.storage.compact()
     }
 
+    /// Returns the version of the on-disk storage format.
+    ///
+    /// Stores are migrated to [`LATEST_STORAGE_VERSION`] when opened in read-write mode.
+    /// Use [`StoreMigrator`] to migrate a store to an other version.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::store::{Store, LATEST_STORAGE_VERSION};
+    ///
+    /// let store = Store::new()?;
+    /// assert_eq!(store.storage_version()?, LATEST_STORAGE_VERSION);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[cfg(not(target_family = "wasm"))]
+    pub fn storage_version(&self) -> Result<u64, StorageError> {
+        self.storage.storage_version()
+    }
+
     /// Creates database backup into the `target_directory`.
     ///
     /// After its creation, the backup is usable using [`Store::open`]
@@ -968,6 +1151,355 @@ impl Store {
         self.storage.backup(target_directory.as_ref())
     }
 
+    /// Creates a new incremental backup in the `backup_directory` and returns its description.
+    ///
+    /// The `backup_directory` is created if it does not exist yet.
+    /// It stores multiple backups: the files already saved by a previous backup are not copied again.
+    /// Use [`Store::list_backups`] to list the stored backups, [`Store::purge_old_backups`] to remove the oldest ones
+    /// and [`Store::restore_backup`] to restore one of them.
+    ///
+    /// <div class="warning">
+    ///
+    /// Backups are only possible for on-disk databases.</div>
+    /// Temporary in-memory databases created using [`Store::new`] are not compatible with RocksDB backup system.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::store::Store;
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let store = Store::open("example_incremental.db")?;
+    /// store.insert(QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph))?;
+    /// let first = store.incremental_backup("example_incremental_backups")?;
+    /// store.insert(QuadRef::new(ex, ex, ex, ex))?;
+    /// let second = store.incremental_backup("example_incremental_backups")?;
+    /// assert_eq!(
+    ///     Store::list_backups("example_incremental_backups")?,
+    ///     [first.clone(), second]
+    /// );
+    /// drop(store);
+    ///
+    /// // We restore the first backup
+    /// Store::restore_backup("example_incremental_backups", Some(first.id), "example_incremental.db")?;
+    /// assert_eq!(Store::open("example_incremental.db")?.len()?, 1);
+    /// # };
+    /// # remove_dir_all("example_incremental.db")?;
+    /// # remove_dir_all("example_incremental_backups")?;
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[cfg(not(target_family = "wasm"))]
+    pub fn incremental_backup(
+        &self,
+        backup_directory: impl AsRef<Path>,
+    ) -> Result<BackupInfo, StorageError> {
+        self.storage.incremental_backup(backup_directory.as_ref())
+    }
+
+    /// Lists the backups created with [`Store::incremental_backup`] in the `backup_directory` from the oldest to the newest.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn list_backups(
+        backup_directory: impl AsRef<Path>,
+    ) -> Result<Vec<BackupInfo>, StorageError> {
+        Storage::list_backups(backup_directory.as_ref())
+    }
+
+    /// Removes the oldest backups created with [`Store::incremental_backup`] in the `backup_directory`
+    /// to only keep the `num_backups_to_keep` newest ones.
+    ///
+    /// The files that are only used by the removed backups are deleted.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn purge_old_backups(
+        backup_directory: impl AsRef<Path>,
+        num_backups_to_keep: u32,
+    ) -> Result<(), StorageError> {
+        Storage::purge_old_backups(backup_directory.as_ref(), num_backups_to_keep)
+    }
+
+    /// Restores a backup created with [`Store::incremental_backup`] into the `target_directory`.
+    ///
+    /// If `backup_id` is [`None`] the newest backup is restored.
+    ///
+    /// The backup files checksums are first verified.
+    /// Then the backup is restored in a directory next to `target_directory` and checked using [`Store::validate`].
+    /// The content of `target_directory` is only replaced if these checks succeed.
+    ///
+    /// <div class="warning">No store should be opened on the `target_directory` during the restoration.</div>
+    #[cfg(not(target_family = "wasm"))]
+    pub fn restore_backup(
+        backup_directory: impl AsRef<Path>,
+        backup_id: Option<u32>,
+        target_directory: impl AsRef<Path>,
+    ) -> Result<(), StorageError> {
+        Storage::restore_backup(
+            backup_directory.as_ref(),
+            backup_id,
+            target_directory.as_ref(),
+        )
+    }
+
+    /// Saves the store content into a file that can be loaded back with [`Store::load_snapshot`].
+    ///
+    /// The file contains the store internal encoding and not an RDF serialization.
+    /// It can only be loaded by Oxigraph versions using the same or a more recent storage encoding.
+    /// If you want to move your data to another RDF storage system, you should have a look at the [`Store::dump_to_write`] function instead.
+    ///
+    /// Writes can be done while this operation is running, they are not included in the saved file.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<(), StorageError> {
+        self.save_to_write(BufWriter::new(File::create(path)?))?
+            .flush()?;
+        Ok(())
+    }
+
+    /// Saves the store content into a [`Write`] implementation.
+    ///
+    /// The output can be loaded back with [`Store::load_snapshot_from_read`].
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::store::Store;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let store = Store::new()?;
+    /// store.insert(QuadRef::new(ex, ex, ex, ex))?;
+    ///
+    /// let image = store.save_to_write(Vec::new())?;
+    /// let restored = Store::load_snapshot_from_read(image.as_slice())?;
+    /// assert!(restored.contains(QuadRef::new(ex, ex, ex, ex))?);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn save_to_write<W: Write>(&self, write: W) -> Result<W, StorageError> {
+        self.storage.snapshot().save_to_write(write)
+    }
+
+    /// Writes the store content into a single read-only file that can be opened with [`Store::open_packed`].
+    ///
+    /// The file contains all the store indexes sorted by key so that it can be queried without being loaded.
+    /// It is a convenient format to distribute published datasets.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::store::Store;
+    /// # use std::fs::remove_file;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let store = Store::new()?;
+    /// store.insert(QuadRef::new(ex, ex, ex, ex))?;
+    /// store.pack_to_file("example.oxpack")?;
+    ///
+    /// let packed = Store::open_packed("example.oxpack")?;
+    /// assert!(packed.contains(QuadRef::new(ex, ex, ex, ex))?);
+    /// assert!(packed.insert(QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph)).is_err());
+    /// # drop(packed);
+    /// # remove_file("example.oxpack")?;
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[cfg(not(target_family = "wasm"))]
+    pub fn pack_to_file(&self, path: impl AsRef<Path>) -> Result<(), StorageError> {
+        let file = self
+            .pack_to_write(BufWriter::new(File::create(path)?))?
+            .into_inner()
+            .map_err(std::io::IntoInnerError::into_error)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Writes the store content into a [`Write`] implementation using the format of [`Store::pack_to_file`].
+    #[cfg(not(target_family = "wasm"))]
+    pub fn pack_to_write<W: Write>(&self, write: W) -> Result<W, StorageError> {
+        self.storage.snapshot().save_packed_to_write(write)
+    }
+
+    /// Creates a temporary [`Store`] from a file written by [`Store::save_to_file`].
+    ///
+    /// The content is restored as it is without parsing any RDF.
+    /// The files written by previous Oxigraph versions are migrated to the latest storage encoding.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn load_snapshot(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Self::load_snapshot_from_read(BufReader::new(File::open(path)?))
+    }
+
+    /// Creates a temporary [`Store`] from the output of [`Store::save_to_write`].
+    ///
+    /// The content is restored as it is without parsing any RDF.
+    pub fn load_snapshot_from_read(read: impl Read) -> Result<Self, StorageError> {
+        let storage = Storage::new()?;
+        storage.load_image(read)?;
+        Ok(Self { storage })
+    }
+
+    /// Starts to record all the quad and named graph insertions and removals into the store change log.
+    ///
+    /// The change log is persisted with the store and stays enabled when the store is opened again.
+    /// Each inserted or removed quad or named graph gets a sequence number.
+    /// A named graph insertion is only recorded if it is done with [`Store::insert_named_graph`] or its equivalents:
+    /// the named graphs implicitly created by quad insertions are not recorded.
+    /// The sequence numbers start at 1 and follow the commit order.
+    /// The changes of a transaction or of a SPARQL update always get consecutive sequence numbers.
+    ///
+    /// Only the writes started after this method returns are guaranteed to be recorded.
+    /// The quads added by the [bulk loader](Store::bulk_loader) are all recorded as insertions even if they were already in the store.
+    ///
+    /// Use [`Store::changes`] to read the change log and [`Store::truncate_changes`] to remove the changes that are not needed anymore.
+    pub fn enable_change_log(&self) -> Result<(), StorageError> {
+        self.storage.enable_change_log()
+    }
+
+    /// Returns the changes recorded in the change log with a sequence number greater or equal to `from`, in the commit order.
+    ///
+    /// The change log must first be enabled using [`Store::enable_change_log`].
+    /// To resume reading the change log, call this method again with the sequence number following the last returned one.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::store::{ChangeOperation, Store};
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let quad = QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph);
+    /// let store = Store::new()?;
+    /// store.enable_change_log()?;
+    /// store.insert(quad)?;
+    /// store.remove(quad)?;
+    ///
+    /// let changes = store.changes(1).collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(changes.len(), 2);
+    /// assert_eq!(changes[0].operation, ChangeOperation::Insert(quad.into_owned()));
+    /// assert_eq!(changes[1].operation, ChangeOperation::Remove(quad.into_owned()));
+    ///
+    /// // We resume after the last seen change
+    /// assert_eq!(store.changes(changes[1].sequence_number + 1).count(), 0);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn changes(&self, from: u64) -> ChangeIter {
+        let reader = self.storage.snapshot();
+        ChangeIter {
+            iter: reader.changes(from),
+            reader,
+        }
+    }
+
+    /// Returns the sequence number of the last change recorded in the change log.
+    ///
+    /// Returns `Some(0)` if no change has been recorded yet and `None` if the change log is not enabled.
+    /// The sequence number of the last change is kept when the change log is truncated.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::store::Store;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let store = Store::new()?;
+    /// assert_eq!(store.last_change_sequence_number()?, None);
+    /// store.enable_change_log()?;
+    /// assert_eq!(store.last_change_sequence_number()?, Some(0));
+    /// store.insert(QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph))?;
+    /// assert_eq!(store.last_change_sequence_number()?, Some(1));
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn last_change_sequence_number(&self) -> Result<Option<u64>, StorageError> {
+        self.storage.snapshot().last_change_sequence_number()
+    }
+
+    /// Removes from the change log all the changes with a sequence number lower than `before`.
+    ///
+    /// The sequence numbers of the following changes are not modified.
+    pub fn truncate_changes(&self, before: u64) -> Result<(), StorageError> {
+        self.storage.truncate_change_log(before)
+    }
+
+    /// Builds a full-text index of the string literals and keeps it up to date on writes.
+    ///
+    /// Only the objects of the given `predicates` are indexed, or all the string literals if `predicates` is empty.
+    /// The index configuration is persisted with the store and stays enabled when the store is opened again.
+    /// Calling this method again rebuilds the index with the new configuration.
+    /// Writes are blocked while the index is built.
+    ///
+    /// Literals are split into lowercase alphanumeric tokens.
+    /// The index is queried in SPARQL with the `<http://oxigraph.org/text#search>` property function:
+    /// `?s text:search "query"` binds `?s` to the subjects of the indexed literals containing all the query tokens,
+    /// by decreasing score.
+    /// The `<http://oxigraph.org/text#score>(?s, "query")` function returns the score of a subject as a `xsd:float`:
+    /// the best sum of the frequencies of the query tokens among its matching literals.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::sparql::QueryResults;
+    /// use oxigraph::store::Store;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let label = NamedNodeRef::new("http://www.w3.org/2000/01/rdf-schema#label")?;
+    /// let store = Store::new()?;
+    /// store.insert(QuadRef::new(ex, label, LiteralRef::new_simple_literal("The quick brown fox"), GraphNameRef::DefaultGraph))?;
+    /// store.enable_text_index([label])?;
+    ///
+    /// if let QueryResults::Solutions(mut solutions) = store.query(
+    ///     "PREFIX text: <http://oxigraph.org/text#>
+    ///      SELECT ?s ?score WHERE { ?s text:search \"Brown fox\" BIND(text:score(?s, \"Brown fox\") AS ?score) }",
+    /// )? {
+    ///     let solution = solutions.next().unwrap()?;
+    ///     assert_eq!(solution.get("s"), Some(&ex.into_owned().into()));
+    ///     assert_eq!(solution.get("score"), Some(&Literal::from(0.5_f32).into()));
+    /// }
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn enable_text_index<'a>(
+        &self,
+        predicates: impl IntoIterator<Item = NamedNodeRef<'a>>,
+    ) -> Result<(), StorageError> {
+        self.storage
+            .enable_text_index(TextIndexConfig::for_predicates(
+                predicates.into_iter().map(EncodedTerm::from),
+            ))
+    }
+
+    /// Builds a spatial index of the `geo:wktLiteral` objects and keeps it up to date on writes.
+    ///
+    /// The index is persisted with the store and stays enabled when the store is opened again.
+    /// Calling this method again rebuilds the index.
+    /// Writes are blocked while the index is built.
+    ///
+    /// The GeoSPARQL functions (`geof:sfIntersects`, `geof:sfWithin`, `geof:distance`, `geof:buffer`...)
+    /// are always available in SPARQL.
+    /// With the index, a `FILTER(geof:sfIntersects(?wkt, "..."^^geo:wktLiteral))` on a triple pattern binding `?wkt`
+    /// only reads the geometries whose bounding box intersects the constant geometry
+    /// instead of all the triples matching the pattern.
+    /// It is also the case for `geof:sfWithin`, `geof:sfContains` and `geof:sfEquals`.
+    /// Geometries are read in the default CRS84 coordinate reference system: longitudes first, then latitudes.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::sparql::QueryResults;
+    /// use oxigraph::store::Store;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com/paris")?;
+    /// let as_wkt = NamedNodeRef::new("http://www.opengis.net/ont/geosparql#asWKT")?;
+    /// let wkt_literal = NamedNodeRef::new("http://www.opengis.net/ont/geosparql#wktLiteral")?;
+    /// let store = Store::new()?;
+    /// store.insert(QuadRef::new(ex, as_wkt, LiteralRef::new_typed_literal("POINT(2.35 48.85)", wkt_literal), GraphNameRef::DefaultGraph))?;
+    /// store.enable_spatial_index()?;
+    ///
+    /// if let QueryResults::Solutions(mut solutions) = store.query(
+    ///     "PREFIX geo: <http://www.opengis.net/ont/geosparql#>
+    ///      PREFIX geof: <http://www.opengis.net/def/function/geosparql/>
+    ///      SELECT ?s WHERE { ?s geo:asWKT ?wkt FILTER(geof:sfWithin(?wkt, \"POLYGON((2 48, 3 48, 3 49, 2 49, 2 48))\"^^geo:wktLiteral)) }",
+    /// )? {
+    ///     assert_eq!(solutions.next().unwrap()?.get("s"), Some(&ex.into_owned().into()));
+    /// }
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn enable_spatial_index(&self) -> Result<(), StorageError> {
+        self.storage.enable_spatial_index()
+    }
+
     /// Creates a bulk loader allowing to load at lot of data quickly into the store.
     ///
     /// Usage example:
@@ -998,19 +1530,336 @@ impl Store {
         }
     }
 
-    /// Validates that all the store invariants held in the data
-    #[doc(hidden)]
-    pub fn validate(&self) -> Result<(), StorageError> {
-        self.storage.snapshot().validate()
-    }
-}
-
-impl fmt::Display for Store {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for t in self.iter() {
-            writeln!(f, "{} .", t.map_err(|_| fmt::Error)?)?;
+    /// Creates a bulk remover allowing to remove a lot of quads quickly from the store.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::io::RdfFormat;
+    /// use oxigraph::store::Store;
+    ///
+    /// let store = Store::new()?;
+    /// let file =
+    ///     b"<http://example.com> <http://example.com> <http://example.com> <http://example.com> .";
+    /// store
+    ///     .bulk_loader()
+    ///     .load_from_read(RdfFormat::NQuads, file.as_ref())?;
+    ///
+    /// // quads file removal
+    /// store
+    ///     .bulk_remover()
+    ///     .remove_from_read(RdfFormat::NQuads, file.as_ref())?;
+    /// assert!(store.is_empty()?);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    #[cfg(not(target_family = "wasm"))]
+    pub fn bulk_remover(&self) -> BulkRemover {
+        BulkRemover {
+            storage: StorageBulkRemover::new(self.storage.clone()),
+            on_parse_error: None,
+        }
+    }
+
+    /// Validates that all the store invariants held in the data
+    #[doc(hidden)]
+    pub fn validate(&self) -> Result<(), StorageError> {
+        self.storage.snapshot().validate()
+    }
+}
+
+impl fmt::Display for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for t in self.iter() {
+            writeln!(f, "{} .", t.map_err(|_| fmt::Error)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tuning options of a read-write [`Store`] given to [`Store::open_with_options`].
+///
+/// The default values are tailored for a server with a few gigabytes of memory.
+/// Smaller values allow to run Oxigraph on constrained devices.
+///
+/// ```
+/// use oxigraph::store::{Store, StoreOptions};
+/// # use std::fs::remove_dir_all;
+///
+/// let options = StoreOptions::default()
+///     .with_block_cache_size_in_megabytes(16)
+///     .with_write_buffer_size_in_megabytes(8)
+///     .with_max_open_files(256)
+///     .with_compression(false)
+///     .with_bloom_filter_bits_per_key(10.);
+/// # {
+/// let store = Store::open_with_options("example_options.db", &options)?;
+/// # assert!(store.is_empty()?);
+/// # };
+/// # remove_dir_all("example_options.db")?;
+/// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+/// ```
+#[cfg(not(target_family = "wasm"))]
+#[derive(Clone, Debug, Default)]
+#[must_use]
+pub struct StoreOptions {
+    inner: DbOptions,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl StoreOptions {
+    /// Sets the size of the RocksDB block cache used to keep uncompressed data blocks in memory.
+    ///
+    /// The IRIs and literals dictionary uses an other cache of the same size.
+    ///
+    /// By default, a 32MB cache is used for the quads indexes and a 128MB cache for the dictionary.
+    pub fn with_block_cache_size_in_megabytes(mut self, block_cache_size: usize) -> Self {
+        self.inner.block_cache_size = Some(block_cache_size.saturating_mul(1024 * 1024));
+        self
+    }
+
+    /// Enables or disables the LZ4 compression of the data written on disk.
+    ///
+    /// Compression is enabled by default.
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.inner.compression = Some(compression);
+        self
+    }
+
+    /// Sets the maximal number of files RocksDB keeps open at the same time.
+    ///
+    /// It must be lower than the operating system limit.
+    ///
+    /// By default, all the file descriptors allowed by the operating system minus 48 are used.
+    pub fn with_max_open_files(mut self, max_open_files: u32) -> Self {
+        self.inner.max_open_files = Some(max_open_files);
+        self
+    }
+
+    /// Sets the size of the in-memory buffer in which writes are accumulated before being written to disk.
+    ///
+    /// There is one buffer per index and a few buffers might be alive at the same time.
+    ///
+    /// The default value is 128MB.
+    pub fn with_write_buffer_size_in_megabytes(mut self, write_buffer_size: usize) -> Self {
+        self.inner.write_buffer_size = Some(write_buffer_size.saturating_mul(1024 * 1024));
+        self
+    }
+
+    /// Enables bloom filters on the quads indexes with the given number of bits per key.
+    ///
+    /// Bloom filters speed up the lookups of absent quads at the cost of more memory usage.
+    /// 10 bits per key give a false positive rate of around 1%.
+    ///
+    /// They are disabled by default except for the IRIs and literals dictionary.
+    pub fn with_bloom_filter_bits_per_key(mut self, bits_per_key: f64) -> Self {
+        self.inner.bloom_filter_bits_per_key = Some(bits_per_key);
+        self
+    }
+}
+
+/// A read-only view of a [`Store`] at a given point in time.
+///
+/// It is built using [`Store::snapshot`].
+#[derive(Clone)]
+pub struct StoreSnapshot {
+    snapshot: StorageSnapshot,
+}
+
+impl StoreSnapshot {
+    /// Executes a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) on the snapshot.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::sparql::QueryResults;
+    /// use oxigraph::store::Store;
+    ///
+    /// let store = Store::new()?;
+    /// let snapshot = store.snapshot()?;
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// store.insert(QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph))?;
+    ///
+    /// if let QueryResults::Boolean(result) = snapshot.query("ASK { ?s ?p ?o }")? {
+    ///     assert!(!result);
+    /// }
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn query(
+        &self,
+        query: impl TryInto<Query, Error = impl Into<EvaluationError>>,
+    ) -> Result<QueryResults, EvaluationError> {
+        self.query_opt(query, QueryOptions::default())
+    }
+
+    /// Executes a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) on the snapshot with some options.
+    pub fn query_opt(
+        &self,
+        query: impl TryInto<Query, Error = impl Into<EvaluationError>>,
+        options: QueryOptions,
+    ) -> Result<QueryResults, EvaluationError> {
+        let (results, _) = self.explain_query_opt(query, options, false)?;
+        results
+    }
+
+    /// Executes a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) on the snapshot with some options and
+    /// returns a query explanation with some statistics (if enabled with the `with_stats` parameter).
+    ///
+    /// See [`Store::explain_query_opt`] for more details.
+    pub fn explain_query_opt(
+        &self,
+        query: impl TryInto<Query, Error = impl Into<EvaluationError>>,
+        options: QueryOptions,
+        with_stats: bool,
+    ) -> Result<(Result<QueryResults, EvaluationError>, QueryExplanation), EvaluationError> {
+        evaluate_query(self.snapshot.reader(), query, options, with_stats)
+    }
+
+    /// Retrieves quads with a filter on each quad component.
+    ///
+    /// See [`Store::quads_for_pattern`] for more details.
+    pub fn quads_for_pattern(
+        &self,
+        subject: Option<SubjectRef<'_>>,
+        predicate: Option<NamedNodeRef<'_>>,
+        object: Option<TermRef<'_>>,
+        graph_name: Option<GraphNameRef<'_>>,
+    ) -> QuadIter {
+        let reader = self.snapshot.reader();
+        QuadIter {
+            iter: reader.quads_for_pattern(
+                subject.map(EncodedTerm::from).as_ref(),
+                predicate.map(EncodedTerm::from).as_ref(),
+                object.map(EncodedTerm::from).as_ref(),
+                graph_name.map(EncodedTerm::from).as_ref(),
+            ),
+            reader,
+        }
+    }
+
+    /// Returns all the quads contained in the snapshot.
+    pub fn iter(&self) -> QuadIter {
+        self.quads_for_pattern(None, None, None, None)
+    }
+
+    /// Checks if the snapshot contains a given quad.
+    pub fn contains<'a>(&self, quad: impl Into<QuadRef<'a>>) -> Result<bool, StorageError> {
+        let quad = EncodedQuad::from(quad.into());
+        self.snapshot.reader().contains(&quad)
+    }
+
+    /// Returns the number of quads in the snapshot.
+    ///
+    /// <div class="warning">This function executes a full scan.</div>
+    pub fn len(&self) -> Result<usize, StorageError> {
+        self.snapshot.reader().len()
+    }
+
+    /// Returns the number of quads of the snapshot matching a pattern.
+    ///
+    /// See [`Store::count_for_pattern`] for more details.
+    pub fn count_for_pattern(
+        &self,
+        subject: Option<SubjectRef<'_>>,
+        predicate: Option<NamedNodeRef<'_>>,
+        object: Option<TermRef<'_>>,
+        graph_name: Option<GraphNameRef<'_>>,
+    ) -> Result<usize, StorageError> {
+        self.snapshot.reader().count_for_pattern(
+            subject.map(EncodedTerm::from).as_ref(),
+            predicate.map(EncodedTerm::from).as_ref(),
+            object.map(EncodedTerm::from).as_ref(),
+            graph_name.map(EncodedTerm::from).as_ref(),
+        )
+    }
+
+    /// Returns the changes of the change log included in the snapshot with a sequence number greater or equal to `from`.
+    ///
+    /// See [`Store::changes`] for more details.
+    pub fn changes(&self, from: u64) -> ChangeIter {
+        let reader = self.snapshot.reader();
+        ChangeIter {
+            iter: reader.changes(from),
+            reader,
+        }
+    }
+
+    /// Returns the sequence number of the last change included in the snapshot.
+    ///
+    /// The snapshot content is the result of all the changes up to this sequence number.
+    /// See [`Store::last_change_sequence_number`] for more details.
+    pub fn last_change_sequence_number(&self) -> Result<Option<u64>, StorageError> {
+        self.snapshot.reader().last_change_sequence_number()
+    }
+
+    /// Returns if the snapshot is empty.
+    pub fn is_empty(&self) -> Result<bool, StorageError> {
+        self.snapshot.reader().is_empty()
+    }
+
+    /// Returns all the snapshot named graphs.
+    pub fn named_graphs(&self) -> GraphNameIter {
+        let reader = self.snapshot.reader();
+        GraphNameIter {
+            iter: reader.named_graphs(),
+            reader,
+        }
+    }
+
+    /// Checks if the snapshot contains a given graph.
+    pub fn contains_named_graph<'a>(
+        &self,
+        graph_name: impl Into<NamedOrBlankNodeRef<'a>>,
+    ) -> Result<bool, StorageError> {
+        let graph_name = EncodedTerm::from(graph_name.into());
+        self.snapshot.reader().contains_named_graph(&graph_name)
+    }
+
+    /// Dumps the snapshot into a file.
+    ///
+    /// ```
+    /// use oxigraph::io::RdfFormat;
+    /// use oxigraph::store::Store;
+    ///
+    /// let file =
+    ///     "<http://example.com> <http://example.com> <http://example.com> <http://example.com> .\n"
+    ///         .as_bytes();
+    ///
+    /// let store = Store::new()?;
+    /// store.load_from_read(RdfFormat::NQuads, file)?;
+    /// let snapshot = store.snapshot()?;
+    /// store.clear()?;
+    ///
+    /// let buffer = snapshot.dump_to_write(RdfFormat::NQuads, Vec::new())?;
+    /// assert_eq!(file, buffer.as_slice());
+    /// # std::io::Result::Ok(())
+    /// ```
+    pub fn dump_to_write<W: Write>(
+        &self,
+        serializer: impl Into<RdfSerializer>,
+        write: W,
+    ) -> Result<W, SerializerError> {
+        let serializer = serializer.into();
+        if !serializer.format().supports_datasets() {
+            return Err(SerializerError::DatasetFormatExpected(serializer.format()));
+        }
+        let mut writer = serializer.serialize_to_write(write);
+        for quad in self.iter() {
+            writer.write_quad(&quad?)?;
+        }
+        Ok(writer.finish()?)
+    }
+
+    /// Dumps a graph of the snapshot into a file.
+    pub fn dump_graph_to_write<'a, W: Write>(
+        &self,
+        from_graph_name: impl Into<GraphNameRef<'a>>,
+        serializer: impl Into<RdfSerializer>,
+        write: W,
+    ) -> Result<W, SerializerError> {
+        let mut writer = serializer.into().serialize_to_write(write);
+        for quad in self.quads_for_pattern(None, None, None, Some(from_graph_name.into())) {
+            writer.write_triple(quad?.as_ref())?;
         }
-        Ok(())
+        Ok(writer.finish()?)
     }
 }
 
@@ -1159,6 +2008,24 @@ impl<'a> Transaction<'a> {
         self.writer.reader().len()
     }
 
+    /// Returns the number of quads matching a pattern, including the changes done by the transaction.
+    ///
+    /// See [`Store::count_for_pattern`] for more details.
+    pub fn count_for_pattern(
+        &self,
+        subject: Option<SubjectRef<'_>>,
+        predicate: Option<NamedNodeRef<'_>>,
+        object: Option<TermRef<'_>>,
+        graph_name: Option<GraphNameRef<'_>>,
+    ) -> Result<usize, StorageError> {
+        self.writer.reader().count_for_pattern(
+            subject.map(EncodedTerm::from).as_ref(),
+            predicate.map(EncodedTerm::from).as_ref(),
+            object.map(EncodedTerm::from).as_ref(),
+            graph_name.map(EncodedTerm::from).as_ref(),
+        )
+    }
+
     /// Returns if the store is empty.
     pub fn is_empty(&self) -> Result<bool, StorageError> {
         self.writer.reader().is_empty()
@@ -1531,6 +2398,69 @@ impl Iterator for QuadIter {
     }
 }
 
+/// A change recorded in the [`Store`] change log.
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct Change {
+    /// The position of the change in the change log.
+    pub sequence_number: u64,
+    /// The recorded operation.
+    pub operation: ChangeOperation,
+}
+
+/// An operation recorded in the [`Store`] change log.
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum ChangeOperation {
+    /// The quad has been inserted.
+    Insert(Quad),
+    /// The quad has been removed.
+    Remove(Quad),
+    /// The named graph has been explicitly inserted.
+    InsertNamedGraph(NamedOrBlankNode),
+    /// The named graph has been removed.
+    ///
+    /// The removals of its quads are recorded before.
+    RemoveNamedGraph(NamedOrBlankNode),
+}
+
+/// An iterator returning the changes recorded in a [`Store`] change log.
+pub struct ChangeIter {
+    iter: DecodingChangeIterator,
+    reader: StorageReader,
+}
+
+impl Iterator for ChangeIter {
+    type Item = Result<Change, StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (sequence_number, change) = match self.iter.next()? {
+            Ok(change) => change,
+            Err(error) => return Some(Err(error)),
+        };
+        Some(
+            match change {
+                EncodedChange::Insert(quad) => {
+                    self.reader.decode_quad(&quad).map(ChangeOperation::Insert)
+                }
+                EncodedChange::Remove(quad) => {
+                    self.reader.decode_quad(&quad).map(ChangeOperation::Remove)
+                }
+                EncodedChange::InsertNamedGraph(graph_name) => self
+                    .reader
+                    .decode_named_or_blank_node(&graph_name)
+                    .map(ChangeOperation::InsertNamedGraph),
+                EncodedChange::RemoveNamedGraph(graph_name) => self
+                    .reader
+                    .decode_named_or_blank_node(&graph_name)
+                    .map(ChangeOperation::RemoveNamedGraph),
+            }
+            .map(|operation| Change {
+                sequence_number,
+                operation,
+            }),
+        )
+    }
+}
+
 /// An iterator returning the graph names contained in a [`Store`].
 pub struct GraphNameIter {
     iter: DecodingGraphIterator,
@@ -1555,7 +2485,7 @@ impl Iterator for GraphNameIter {
 
 /// A bulk loader allowing to load at lot of data quickly into the store.
 ///
-/// <div class="warning">The operations provided here are not atomic.
+/// <div class="warning">The operations provided here are not atomic unless [`atomic`](BulkLoader::atomic) is used.
 /// If the operation fails in the middle, only a part of the data may be written to the store.
 /// Results might get weird if you delete data during the loading process.</div>
 ///
@@ -1653,11 +2583,46 @@ impl BulkLoader {
         self
     }
 
+    /// Makes the loading operations atomic.
+    ///
+    /// The files built by the loading threads are kept aside and only inserted into the store
+    /// when the full input has been successfully loaded.
+    /// If an error happens, they are deleted and the store is left unchanged.
+    ///
+    /// The data only becomes visible at the end of the loading operation
+    /// and the files built during the whole operation are kept on disk until then.
+    /// The change log entries of the loaded quads are also staged in a temporary file
+    /// so that the memory usage stays bounded by [`with_max_memory_size_in_megabytes`](Self::with_max_memory_size_in_megabytes).
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::io::RdfFormat;
+    /// use oxigraph::store::Store;
+    ///
+    /// let store = Store::new()?;
+    ///
+    /// // the second line is invalid
+    /// let file = b"<http://example.com> <http://example.com> <http://example.com> .\n<http://example.com> .";
+    /// assert!(store
+    ///     .bulk_loader()
+    ///     .atomic()
+    ///     .load_from_read(RdfFormat::NTriples, file.as_ref())
+    ///     .is_err());
+    ///
+    /// // nothing has been written
+    /// assert!(store.is_empty()?);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn atomic(mut self) -> Self {
+        self.storage = self.storage.atomic();
+        self
+    }
+
     /// Loads a file using the bulk loader.
     ///
     /// This function is optimized for large dataset loading speed. For small files, [`Store::load_from_read`] might be more convenient.
     ///
-    /// <div class="warning">This method is not atomic.
+    /// <div class="warning">This method is not atomic unless [`atomic`](Self::atomic) is used.
     /// If the parsing fails in the middle of the file, only a part of it may be written to the store.
     /// Results might get weird if you delete data during the loading process.</div>
     ///
@@ -1729,7 +2694,7 @@ impl BulkLoader {
     ///
     /// This function is optimized for large dataset loading speed. For small files, [`Store::load_dataset`] might be more convenient.
     ///
-    /// <div class="warning">This method is not atomic.
+    /// <div class="warning">This method is not atomic unless [`atomic`](Self::atomic) is used.
     /// If the parsing fails in the middle of the file, only a part of it may be written to the store.
     /// Results might get weird if you delete data during the loading process.</div>
     ///
@@ -1793,7 +2758,7 @@ impl BulkLoader {
     ///
     /// This function is optimized for large graph loading speed. For small files, [`Store::load_graph`] might be more convenient.   
     ///
-    /// <div class="warning">This method is not atomic.
+    /// <div class="warning">This method is not atomic unless [`atomic`](Self::atomic) is used.
     /// If the parsing fails in the middle of the file, only a part of it may be written to the store.
     /// Results might get weird if you delete data during the loading process.</div>
     ///
@@ -1861,7 +2826,7 @@ impl BulkLoader {
 
     /// Adds a set of quads using the bulk loader.
     ///
-    /// <div class="warning">This method is not atomic.
+    /// <div class="warning">This method is not atomic unless [`atomic`](Self::atomic) is used.
     /// If the process fails in the middle of the file, only a part of the data may be written to the store.
     /// Results might get weird if you delete data during the loading process.</div>
     ///
@@ -1877,7 +2842,7 @@ impl BulkLoader {
 
     /// Adds a set of quads using the bulk loader while breaking in the middle of the process in case of error.
     ///
-    /// <div class="warning">This method is not atomic.
+    /// <div class="warning">This method is not atomic unless [`atomic`](Self::atomic) is used.
     /// If the process fails in the middle of the file, only a part of the data may be written to the store.
     /// Results might get weird if you delete data during the loading process.</div>
     ///
@@ -1893,6 +2858,204 @@ impl BulkLoader {
     }
 }
 
+/// A bulk remover allowing to remove a lot of quads quickly from the store.
+///
+/// It is the counterpart of [`BulkLoader`] for removals.
+/// The quads are removed from all the indexes by batches, each batch being removed in its own transaction.
+/// This avoids building a single huge transaction like [`Store::remove`] in a loop inside of [`Store::transaction`] or a SPARQL `DELETE` would do.
+///
+/// <div class="warning">The operations provided here are not atomic.
+/// If the operation fails in the middle, only a part of the quads may be removed from the store.</div>
+///
+/// The named graphs are kept in the store even if all their quads are removed.
+///
+/// Usage example:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::store::Store;
+///
+/// let ex = NamedNodeRef::new("http://example.com")?;
+/// let store = Store::new()?;
+/// store.insert(QuadRef::new(ex, ex, ex, ex))?;
+///
+/// store.bulk_remover().remove_quads([Quad::new(ex, ex, ex, ex)])?;
+/// assert!(store.is_empty()?);
+/// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+/// ```
+#[cfg(not(target_family = "wasm"))]
+#[must_use]
+pub struct BulkRemover {
+    storage: StorageBulkRemover,
+    on_parse_error: Option<Box<dyn Fn(RdfParseError) -> Result<(), RdfParseError>>>,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl BulkRemover {
+    /// Adds a `callback` evaluated from time to time with the number of processed quads.
+    pub fn on_progress(mut self, callback: impl Fn(u64) + 'static) -> Self {
+        self.storage = self.storage.on_progress(callback);
+        self
+    }
+
+    /// Adds a `callback` catching all parse errors and choosing if the parsing should continue
+    /// by returning `Ok` or fail by returning `Err`.
+    ///
+    /// By default the parsing fails.
+    pub fn on_parse_error(
+        mut self,
+        callback: impl Fn(RdfParseError) -> Result<(), RdfParseError> + 'static,
+    ) -> Self {
+        self.on_parse_error = Some(Box::new(callback));
+        self
+    }
+
+    /// Removes the quads contained in a file using the bulk remover.
+    ///
+    /// The blank nodes are not renamed: a blank node in the file matches the blank node with the same identifier in the store.
+    ///
+    /// <div class="warning">This method is not atomic.
+    /// If the parsing fails in the middle of the file, only a part of its quads may be removed from the store.</div>
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::io::RdfFormat;
+    /// use oxigraph::model::*;
+    /// use oxigraph::store::Store;
+    ///
+    /// let ex = NamedNodeRef::new("http://example.com")?;
+    /// let store = Store::new()?;
+    /// store.insert(QuadRef::new(ex, ex, ex, GraphNameRef::DefaultGraph))?;
+    ///
+    /// let file = b"<http://example.com> <http://example.com> <http://example.com> .";
+    /// store
+    ///     .bulk_remover()
+    ///     .remove_from_read(RdfFormat::NTriples, file.as_ref())?;
+    /// assert!(store.is_empty()?);
+    /// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+    /// ```
+    pub fn remove_from_read(
+        &self,
+        parser: impl Into<RdfParser>,
+        read: impl Read,
+    ) -> Result<(), LoaderError> {
+        self.remove_ok_quads(parser.into().parse_read(read).filter_map(|r| match r {
+            Ok(q) => Some(Ok(q)),
+            Err(e) => {
+                if let Some(callback) = &self.on_parse_error {
+                    if let Err(e) = callback(e) {
+                        Some(Err(e))
+                    } else {
+                        None
+                    }
+                } else {
+                    Some(Err(e))
+                }
+            }
+        }))
+    }
+
+    /// Removes a set of quads using the bulk remover.
+    ///
+    /// <div class="warning">This method is not atomic.
+    /// If the process fails in the middle, only a part of the quads may be removed from the store.</div>
+    pub fn remove_quads(
+        &self,
+        quads: impl IntoIterator<Item = impl Into<Quad>>,
+    ) -> Result<(), StorageError> {
+        self.remove_ok_quads(quads.into_iter().map(Ok::<_, StorageError>))
+    }
+
+    /// Removes a set of quads using the bulk remover while breaking in the middle of the process in case of error.
+    ///
+    /// <div class="warning">This method is not atomic.
+    /// If the process fails in the middle, only a part of the quads may be removed from the store.</div>
+    pub fn remove_ok_quads<EI, EO: From<StorageError> + From<EI>>(
+        &self,
+        quads: impl IntoIterator<Item = Result<impl Into<Quad>, EI>>,
+    ) -> Result<(), EO> {
+        self.storage
+            .remove(quads.into_iter().map(|q| q.map(Into::into)))
+    }
+}
+
+/// Migrates the on-disk storage format of a [`Store`] to a given version.
+///
+/// Contrary to [`Store::open`] that always migrates to [`LATEST_STORAGE_VERSION`],
+/// it allows to downgrade a store so that it can be opened again by an older Oxigraph version.
+/// The store must not be opened by any other process during the migration.
+///
+/// <div class="warning">The migration rewrites the store in place: migrate a copy or a backup of huge stores.</div>
+///
+/// Indexes are rewritten in bounded batches, each one saving the migration progress.
+/// An interrupted migration is resumed from its last batch by the next migration or by the next [`Store::open`].
+///
+/// Usage example:
+/// ```
+/// use oxigraph::store::{Store, StoreMigrator, LATEST_STORAGE_VERSION};
+/// # use std::fs::remove_dir_all;
+///
+/// # {
+/// Store::open("example_migration.db")?;
+/// let migrator = StoreMigrator::open("example_migration.db")?;
+/// assert_eq!(migrator.storage_version()?, LATEST_STORAGE_VERSION);
+/// migrator
+///     .on_progress(|version, entries| {
+///         println!("{entries} entries migrated to the version {version}")
+///     })
+///     .migrate(1)?;
+///
+/// // Opening the store migrates it back to the latest version
+/// assert_eq!(
+///     Store::open("example_migration.db")?.storage_version()?,
+///     LATEST_STORAGE_VERSION
+/// );
+/// # };
+/// # remove_dir_all("example_migration.db")?;
+/// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+/// ```
+#[cfg(not(target_family = "wasm"))]
+#[must_use]
+pub struct StoreMigrator {
+    storage: Storage,
+    hooks: Vec<Box<dyn Fn(u64, u64)>>,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl StoreMigrator {
+    /// Opens an existing store without migrating it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Ok(Self {
+            storage: Storage::open_without_migration(path.as_ref())?,
+            hooks: Vec::new(),
+        })
+    }
+
+    /// Returns the current version of the store storage format.
+    pub fn storage_version(&self) -> Result<u64, StorageError> {
+        self.storage.storage_version()
+    }
+
+    /// Adds a `callback` evaluated from time to time with the version the store is being migrated to
+    /// and the number of entries processed by the migration to this version.
+    pub fn on_progress(mut self, callback: impl Fn(u64, u64) + 'static) -> Self {
+        self.hooks.push(Box::new(callback));
+        self
+    }
+
+    /// Migrates the store to `target_version`, one version at a time, and closes it.
+    ///
+    /// The supported versions are 1 to [`LATEST_STORAGE_VERSION`].
+    pub fn migrate(self, target_version: u64) -> Result<(), StorageError> {
+        self.storage
+            .migrate_to(target_version, &|version, entries| {
+                for hook in &self.hooks {
+                    hook(version, entries);
+                }
+            })?;
+        self.storage.flush()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::panic_in_result_fn)]
@@ -2111,4 +3274,138 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    #[cfg(not(target_family = "wasm"))]
+    fn optimize_removes_unused_strings() -> Result<(), StorageError> {
+        use crate::model::*;
+        use crate::storage::numeric_encoder::StrHash;
+
+        let ex = NamedNodeRef::new_unchecked("http://example.com");
+        let value = "a literal value too long to be inlined";
+        let quad = QuadRef::new(
+            ex,
+            ex,
+            LiteralRef::new_simple_literal(value),
+            GraphNameRef::DefaultGraph,
+        );
+
+        let store = Store::new()?;
+        store.insert(quad)?;
+        store.remove(quad)?;
+        store.insert_named_graph(ex)?;
+        store.optimize()?;
+        let reader = store.storage.snapshot();
+        assert!(!reader.contains_str(&StrHash::new(value))?);
+        assert!(reader.contains_str(&StrHash::new(ex.as_str()))?);
+
+        store.insert(quad)?;
+        assert_eq!(
+            store.iter().collect::<Result<Vec<_>, _>>()?,
+            vec![quad.into_owned()]
+        );
+        store.validate()?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(target_family = "wasm"))]
+    fn optimize_keeps_strings_of_snapshots() -> Result<(), StorageError> {
+        use crate::model::*;
+        use crate::storage::numeric_encoder::StrHash;
+
+        let ex = NamedNodeRef::new_unchecked("http://example.com");
+        let value = "a literal value too long to be inlined";
+        let quad = QuadRef::new(
+            ex,
+            ex,
+            LiteralRef::new_simple_literal(value),
+            GraphNameRef::DefaultGraph,
+        );
+
+        let store = Store::new()?;
+        store.insert(quad)?;
+        let snapshot = store.snapshot()?;
+        store.remove(quad)?;
+        store.optimize()?;
+        assert!(store
+            .storage
+            .snapshot()
+            .contains_str(&StrHash::new(value))?);
+        assert_eq!(
+            snapshot.iter().collect::<Result<Vec<_>, _>>()?,
+            vec![quad.into_owned()]
+        );
+
+        drop(snapshot);
+        store.optimize()?;
+        assert!(!store
+            .storage
+            .snapshot()
+            .contains_str(&StrHash::new(value))?);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(target_family = "wasm"))]
+    fn optimize_computes_statistics() -> Result<(), StorageError> {
+        use crate::model::*;
+        use sparopt::TripleStatistics;
+
+        let s1 = NamedNodeRef::new_unchecked("http://example.com/s1");
+        let s2 = NamedNodeRef::new_unchecked("http://example.com/s2");
+        let p = NamedNodeRef::new_unchecked("http://example.com/p");
+        let g = NamedNodeRef::new_unchecked("http://example.com/g");
+        let o = LiteralRef::new_simple_literal("o");
+
+        let store = Store::new()?;
+        store.insert(QuadRef::new(s1, p, o, GraphNameRef::DefaultGraph))?;
+        store.insert(QuadRef::new(s2, p, o, GraphNameRef::DefaultGraph))?;
+        store.insert(QuadRef::new(s1, p, s2, g))?;
+        store.insert(QuadRef::new(s1, g, s2, g))?;
+        assert_eq!(store.storage.snapshot().dataset_statistics()?, None);
+
+        store.optimize()?;
+        let reader = store.storage.snapshot();
+        assert_eq!(
+            reader.dataset_statistics()?,
+            Some(TripleStatistics {
+                count: 4,
+                distinct_subjects: 3,
+                distinct_objects: 2,
+            })
+        );
+        assert_eq!(
+            reader.predicate_statistics(&p.into())?,
+            Some(TripleStatistics {
+                count: 3,
+                distinct_subjects: 3,
+                distinct_objects: 2,
+            })
+        );
+        assert_eq!(
+            reader.graph_statistics(&g.into())?,
+            Some(TripleStatistics {
+                count: 2,
+                distinct_subjects: 1,
+                distinct_objects: 1,
+            })
+        );
+        assert_eq!(
+            reader.graph_statistics(&GraphNameRef::DefaultGraph.into())?,
+            Some(TripleStatistics {
+                count: 2,
+                distinct_subjects: 2,
+                distinct_objects: 1,
+            })
+        );
+
+        store.remove(QuadRef::new(s1, g, s2, g))?;
+        store.optimize()?;
+        assert_eq!(
+            store.storage.snapshot().predicate_statistics(&g.into())?,
+            None
+        );
+        Ok(())
+    }
 }
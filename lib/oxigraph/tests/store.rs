@@ -4,14 +4,26 @@
 use oxigraph::io::RdfFormat;
 use oxigraph::model::vocab::{rdf, xsd};
 use oxigraph::model::*;
-use oxigraph::store::Store;
+use oxigraph::sparql::{
+    AggregateFunctionAccumulator, CancellationToken, EvaluationError, Query, QueryOptions,
+    QueryResults, ServiceHandler, SparqlParser,
+};
+use oxigraph::store::{
+    check_storage_backend, Change, ChangeOperation, StorageBackend, StorageBackendIter,
+    StorageBackendSnapshot, StorageBackendTransaction, StorageError, Store,
+};
+#[cfg(not(target_family = "wasm"))]
+use oxigraph::store::{StoreMigrator, StoreOptions, LATEST_STORAGE_VERSION};
 #[cfg(not(target_family = "wasm"))]
 use rand::random;
 #[cfg(not(target_family = "wasm"))]
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+#[cfg(not(target_family = "wasm"))]
 use std::env::temp_dir;
 use std::error::Error;
 #[cfg(not(target_family = "wasm"))]
-use std::fs::{create_dir_all, remove_dir_all, File};
+use std::fs::{copy, create_dir_all, read_dir, remove_dir_all, remove_file, File};
 #[cfg(not(target_family = "wasm"))]
 use std::io::Write;
 #[cfg(not(target_family = "wasm"))]
@@ -22,6 +34,12 @@ use std::iter::once;
 use std::path::{Path, PathBuf};
 #[cfg(target_os = "linux")]
 use std::process::Command;
+#[cfg(not(target_family = "wasm"))]
+use std::rc::Rc;
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::thread::spawn;
+use std::time::Duration;
 
 #[allow(clippy::non_ascii_literal)]
 const DATA: &str = r#"
@@ -56,6 +74,10 @@ GRAPH <http://www.wikidata.org/wiki/Special:EntityData/Q90> {
 "#;
 const NUMBER_OF_TRIPLES: usize = 8;
 
+const EX: NamedNodeRef<'static> = NamedNodeRef::new_unchecked("http://example.com");
+const BIG: LiteralRef<'static> =
+    LiteralRef::new_simple_literal("a string that is too long to be inlined");
+
 fn quads(graph_name: impl Into<GraphNameRef<'static>>) -> Vec<QuadRef<'static>> {
     let graph_name = graph_name.into();
     let paris = NamedNodeRef::new_unchecked("http://www.wikidata.org/entity/Q90");
@@ -163,6 +185,119 @@ fn test_bulk_load_empty() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+#[cfg(not(target_family = "wasm"))]
+fn test_bulk_load_atomic() -> Result<(), Box<dyn Error>> {
+    let dir = TempDir::default();
+    let store = Store::open(&dir)?;
+    store.insert(QuadRef::new(EX, EX, EX, GraphNameRef::DefaultGraph))?;
+    store.enable_change_log()?;
+    let to_load = (0..25_000)
+        .map(|i| {
+            Quad::new(
+                NamedNode::new_unchecked(format!("http://example.com/{i}")),
+                EX,
+                Literal::from(i),
+                if i % 2 == 0 {
+                    GraphName::DefaultGraph
+                } else {
+                    EX.into()
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+    let is_staged_file = |name: &str| name.bytes().all(|b| b.is_ascii_digit());
+
+    // The failure happens after two batches have been built
+    store
+        .bulk_loader()
+        .atomic()
+        .with_max_memory_size_in_megabytes(20)
+        .load_ok_quads::<_, Box<dyn Error>>(to_load.iter().cloned().map(Ok).chain([Err(Box::<
+            dyn Error,
+        >::from(
+            "Input error",
+        ))]))
+        .unwrap_err();
+    assert_eq!(store.len()?, 1);
+    assert!(!store.contains_named_graph(EX)?);
+    assert_eq!(store.changes(0).count(), 0);
+    for entry in read_dir(&dir)? {
+        assert!(!is_staged_file(&entry?.file_name().to_string_lossy()));
+    }
+    store.validate()?;
+
+    store
+        .bulk_loader()
+        .atomic()
+        .with_max_memory_size_in_megabytes(20)
+        .load_quads(to_load.clone())?;
+    assert_eq!(store.len()?, 25_001);
+    for quad in &to_load {
+        assert!(store.contains(quad)?);
+    }
+    let mut changed = store
+        .changes(0)
+        .map(|change| match change?.operation {
+            ChangeOperation::Insert(quad) => Ok(quad),
+            operation => Err(format!("Unexpected change {operation:?}").into()),
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+    changed.sort_unstable_by_key(ToString::to_string);
+    let mut loaded = to_load.clone();
+    loaded.sort_unstable_by_key(ToString::to_string);
+    assert_eq!(changed, loaded);
+    store.validate()?;
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_family = "wasm"))]
+fn test_bulk_remove() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    let to_load = (0..120_000)
+        .map(|i| {
+            Quad::new(
+                NamedNode::new_unchecked(format!("http://example.com/{i}")),
+                EX,
+                Literal::from(i),
+                if i % 2 == 0 {
+                    GraphName::DefaultGraph
+                } else {
+                    EX.into()
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+    store.bulk_loader().load_quads(to_load.clone())?;
+    store.enable_change_log()?;
+
+    // Every third quad is removed, the batches span over the default and the named graph
+    store
+        .bulk_remover()
+        .remove_quads(to_load.iter().step_by(3).cloned().chain([Quad::new(
+            EX,
+            EX,
+            EX,
+            GraphName::DefaultGraph,
+        )]))?;
+    assert_eq!(store.len()?, 80_000);
+    for (i, quad) in to_load.iter().enumerate().step_by(7) {
+        assert_eq!(store.contains(quad)?, i % 3 != 0);
+    }
+    assert!(store.contains_named_graph(EX)?);
+    assert_eq!(store.changes(40_000).count(), 1);
+
+    // Removal from a file
+    store.bulk_remover().remove_from_read(
+        RdfFormat::NQuads,
+        b"<http://example.com/1> <http://example.com> \"1\"^^<http://www.w3.org/2001/XMLSchema#integer> <http://example.com> .\n<http://example.com/2> <http://example.com> \"2\"^^<http://www.w3.org/2001/XMLSchema#integer> .".as_slice(),
+    )?;
+    assert_eq!(store.len()?, 79_998);
+    store.validate()?;
+    Ok(())
+}
+
 #[test]
 fn test_load_dataset() -> Result<(), Box<dyn Error>> {
     let store = Store::new()?;
@@ -237,6 +372,900 @@ fn test_dump_dataset() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+fn test_save_and_load_snapshot() -> Result<(), Box<dyn Error>> {
+    let quads = [
+        QuadRef::new(EX, EX, BIG, GraphNameRef::DefaultGraph),
+        QuadRef::new(EX, EX, EX, EX),
+    ];
+    let store = Store::new()?;
+    store.extend(quads.iter().map(|q| q.into_owned()))?;
+    store.insert_named_graph(NamedNodeRef::new_unchecked("http://example.com/g"))?;
+
+    let image = store.save_to_write(Vec::new())?;
+    let restored = Store::load_snapshot_from_read(image.as_slice())?;
+    restored.validate()?;
+    assert_eq!(restored.len()?, 2);
+    for quad in quads {
+        assert!(restored.contains(quad)?);
+    }
+    assert_eq!(restored.named_graphs().count(), 2);
+
+    assert!(Store::load_snapshot_from_read(b"<http://example.com>".as_slice()).is_err());
+    // A truncated image is not loaded
+    assert!(Store::load_snapshot_from_read(&image[..image.len() - 10]).is_err());
+    // An entry length bigger than the image is not trusted
+    let mut corrupted = image[..16].to_vec(); // Magic number and version
+    corrupted.push(6);
+    corrupted.extend_from_slice(b"id2str");
+    corrupted.extend_from_slice(&(u32::MAX - 1).to_be_bytes());
+    corrupted.extend_from_slice(b"foo");
+    assert!(Store::load_snapshot_from_read(corrupted.as_slice()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_long_lived_snapshot() -> Result<(), Box<dyn Error>> {
+    let quad = QuadRef::new(EX, EX, BIG, EX);
+    let store = Store::new()?;
+    store.insert(quad)?;
+    let snapshot = store.snapshot()?;
+    store.update("CLEAR ALL")?;
+
+    // The unused strings are kept while the snapshot is alive
+    #[cfg(not(target_family = "wasm"))]
+    store.optimize()?;
+    assert!(store.is_empty()?);
+    let snapshot = spawn(move || snapshot)
+        .join()
+        .map_err(|_| "the snapshot moving thread panicked")?;
+    assert_eq!(
+        snapshot.iter().collect::<Result<Vec<_>, _>>()?,
+        [quad.into_owned()]
+    );
+    assert!(snapshot.contains_named_graph(EX)?);
+    let QueryResults::Solutions(solutions) =
+        snapshot.query("SELECT ?o WHERE { GRAPH ?g { ?s ?p ?o } }")?
+    else {
+        return Err("Solutions expected".into());
+    };
+    assert_eq!(
+        solutions
+            .map(|s| s.map(|s| s.get("o").cloned()))
+            .collect::<Result<Vec<_>, _>>()?,
+        [Some(BIG.into_owned().into())]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_change_log() -> Result<(), Box<dyn Error>> {
+    let quad = QuadRef::new(EX, EX, BIG, EX);
+    let store = Store::new()?;
+    store.insert(QuadRef::new(EX, EX, EX, GraphNameRef::DefaultGraph))?;
+    store.enable_change_log()?;
+    store.enable_change_log()?;
+    assert_eq!(store.changes(0).count(), 0);
+
+    store.transaction(|mut t| {
+        t.insert(quad)?;
+        t.insert(quad)?;
+        t.remove(QuadRef::new(EX, EX, EX, GraphNameRef::DefaultGraph))
+    })?;
+    store.update("CLEAR GRAPH <http://example.com>")?;
+    let changes = store.changes(0).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        changes,
+        [
+            Change {
+                sequence_number: 1,
+                operation: ChangeOperation::Insert(quad.into_owned()),
+            },
+            Change {
+                sequence_number: 2,
+                operation: ChangeOperation::Remove(
+                    QuadRef::new(EX, EX, EX, GraphNameRef::DefaultGraph).into_owned()
+                ),
+            },
+            Change {
+                sequence_number: 3,
+                operation: ChangeOperation::Remove(quad.into_owned()),
+            }
+        ]
+    );
+
+    // The strings used by the change log are kept
+    #[cfg(not(target_family = "wasm"))]
+    store.optimize()?;
+    assert_eq!(
+        store.changes(3).next().transpose()?,
+        changes.last().cloned()
+    );
+
+    store.truncate_changes(3)?;
+    assert_eq!(
+        store.changes(0).next().transpose()?,
+        changes.last().cloned()
+    );
+    assert_eq!(store.last_change_sequence_number()?, Some(3));
+    let snapshot = store.snapshot()?;
+    store.insert(quad)?;
+    assert_eq!(store.last_change_sequence_number()?, Some(4));
+    assert_eq!(snapshot.last_change_sequence_number()?, Some(3));
+    assert_eq!(snapshot.changes(4).count(), 0);
+    assert_eq!(
+        store
+            .changes(4)
+            .map(|c| c.map(|c| c.sequence_number))
+            .collect::<Result<Vec<_>, _>>()?,
+        [4]
+    );
+
+    #[cfg(not(target_family = "wasm"))]
+    {
+        store
+            .bulk_loader()
+            .load_quads([QuadRef::new(EX, EX, EX, EX).into_owned()])?;
+        assert_eq!(
+            store.changes(5).collect::<Result<Vec<_>, _>>()?,
+            [Change {
+                sequence_number: 5,
+                operation: ChangeOperation::Insert(QuadRef::new(EX, EX, EX, EX).into_owned()),
+            }]
+        );
+        // The counter has been updated by the bulk load
+        store.remove(QuadRef::new(EX, EX, EX, EX))?;
+        assert_eq!(
+            store
+                .changes(6)
+                .map(|c| c.map(|c| c.sequence_number))
+                .collect::<Result<Vec<_>, _>>()?,
+            [6]
+        );
+    }
+
+    // The explicit named graph insertions and the named graph removals are logged
+    let graph_name = NamedNodeRef::new_unchecked("http://example.com/g");
+    let quad = QuadRef::new(EX, EX, EX, graph_name);
+    let from = store.last_change_sequence_number()?.unwrap_or_default() + 1;
+    store.insert_named_graph(graph_name)?;
+    store.insert_named_graph(graph_name)?;
+    store.insert(quad)?;
+    store.remove_named_graph(graph_name)?;
+    store.transaction(|mut t| {
+        t.insert(quad)?;
+        t.remove_named_graph(graph_name)
+    })?;
+    assert_eq!(
+        store
+            .changes(from)
+            .map(|c| c.map(|c| c.operation))
+            .collect::<Result<Vec<_>, _>>()?,
+        [
+            ChangeOperation::InsertNamedGraph(graph_name.into_owned().into()),
+            ChangeOperation::Insert(quad.into_owned()),
+            ChangeOperation::Remove(quad.into_owned()),
+            ChangeOperation::RemoveNamedGraph(graph_name.into_owned().into()),
+            ChangeOperation::Insert(quad.into_owned()),
+            ChangeOperation::Remove(quad.into_owned()),
+            ChangeOperation::RemoveNamedGraph(graph_name.into_owned().into()),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_drop_graph() -> Result<(), Box<dyn Error>> {
+    const FOX_SEARCH: &str =
+        "{ ?s text:search \"fox\" } UNION { GRAPH ?g { ?s text:search \"fox\" } }";
+    let label = NamedNodeRef::new_unchecked("http://www.w3.org/2000/01/rdf-schema#label");
+    let g = NamedNodeRef::new_unchecked("http://example.com/g");
+    let h = NamedNodeRef::new_unchecked("http://example.com/h");
+    let new_store = || -> Result<Store, Box<dyn Error>> {
+        let store = Store::new()?;
+        for graph_name in [GraphNameRef::DefaultGraph, g.into(), h.into()] {
+            for q in quads(graph_name) {
+                store.insert(q)?;
+            }
+            store.insert(QuadRef::new(
+                EX,
+                label,
+                LiteralRef::new_simple_literal("The quick brown fox"),
+                graph_name,
+            ))?;
+        }
+        store.insert_named_graph(EX)?;
+        store.enable_change_log()?;
+        store.enable_text_index([label])?;
+        Ok(store)
+    };
+    let assert_same = |store: &Store, expected: &Store| -> Result<(), Box<dyn Error>> {
+        store.validate()?;
+        assert_eq!(
+            store.iter().collect::<Result<Vec<_>, _>>()?,
+            expected.iter().collect::<Result<Vec<_>, _>>()?
+        );
+        assert_eq!(
+            store.named_graphs().collect::<Result<Vec<_>, _>>()?,
+            expected.named_graphs().collect::<Result<Vec<_>, _>>()?
+        );
+        assert_eq!(
+            store.changes(0).collect::<Result<Vec<_>, _>>()?,
+            expected.changes(0).collect::<Result<Vec<_>, _>>()?
+        );
+        assert_eq!(
+            text_search(store, FOX_SEARCH)?,
+            text_search(expected, FOX_SEARCH)?
+        );
+        Ok(())
+    };
+
+    // The results must be the same as the transactional versions
+    let store = new_store()?;
+    let expected = new_store()?;
+    store.clear_graph(g)?;
+    expected.transaction(|mut t| t.clear_graph(g))?;
+    assert_same(&store, &expected)?;
+    assert!(store.contains_named_graph(g)?);
+    assert!(!store
+        .quads_for_pattern(None, None, None, Some(g.into()))
+        .any(|_| true));
+
+    assert!(store.remove_named_graph(h)?);
+    assert!(expected.transaction(|mut t| t.remove_named_graph(h))?);
+    assert_same(&store, &expected)?;
+    assert!(!store.remove_named_graph(h)?);
+    assert!(store.remove_named_graph(EX)?);
+    assert!(expected.transaction(|mut t| t.remove_named_graph(EX))?);
+    assert_same(&store, &expected)?;
+
+    store.clear_graph(GraphNameRef::DefaultGraph)?;
+    expected.transaction(|mut t| t.clear_graph(GraphNameRef::DefaultGraph))?;
+    assert_same(&store, &expected)?;
+    assert!(store.is_empty()?);
+
+    // Without change log and literal indexes the default graph is only cleared with range removals
+    let store = Store::new()?;
+    store.load_from_read(RdfFormat::TriG, GRAPH_DATA.as_bytes())?;
+    store.load_from_read(RdfFormat::Turtle, DATA.as_bytes())?;
+    let len = store.len()?;
+    store.clear_graph(GraphNameRef::DefaultGraph)?;
+    assert_eq!(store.len()?, len - quads(GraphNameRef::DefaultGraph).len());
+    assert_eq!(
+        store
+            .quads_for_pattern(None, None, None, Some(GraphNameRef::DefaultGraph))
+            .count(),
+        0
+    );
+    store.validate()?;
+    Ok(())
+}
+
+fn text_search(store: &Store, pattern: &str) -> Result<Vec<Term>, Box<dyn Error>> {
+    let QueryResults::Solutions(solutions) = store.query(
+        format!("PREFIX text: <http://oxigraph.org/text#> SELECT ?s WHERE {{ {pattern} }}")
+            .as_str(),
+    )?
+    else {
+        return Err("Solutions expected".into());
+    };
+    Ok(solutions
+        .map(|s| s.map(|s| s.get("s").cloned()))
+        .collect::<Result<Option<Vec<_>>, _>>()?
+        .ok_or("?s should be bound")?)
+}
+
+#[test]
+fn test_text_index() -> Result<(), Box<dyn Error>> {
+    let label = NamedNodeRef::new_unchecked("http://www.w3.org/2000/01/rdf-schema#label");
+    let comment = NamedNodeRef::new_unchecked("http://www.w3.org/2000/01/rdf-schema#comment");
+    let a = NamedNodeRef::new_unchecked("http://example.com/a");
+    let b = NamedNodeRef::new_unchecked("http://example.com/b");
+    let c = NamedNodeRef::new_unchecked("http://example.com/c");
+    let fox = QuadRef::new(
+        a,
+        label,
+        LiteralRef::new_simple_literal("The quick brown fox"),
+        GraphNameRef::DefaultGraph,
+    );
+    let store = Store::new()?;
+    store.insert(fox)?;
+    store.insert(QuadRef::new(
+        b,
+        comment,
+        LiteralRef::new_simple_literal("A brown dog"),
+        GraphNameRef::DefaultGraph,
+    ))?;
+    store.insert(QuadRef::new(
+        c,
+        label,
+        LiteralRef::new_language_tagged_literal_unchecked("Brown bear", "en"),
+        EX,
+    ))?;
+    store.enable_text_index([label])?;
+    assert_eq!(
+        text_search(&store, "?s text:search \"BROWN\"")?,
+        [a.into_owned().into()]
+    );
+    assert_eq!(
+        text_search(&store, "GRAPH ?g { ?s text:search \"brown\" }")?,
+        [c.into_owned().into()]
+    );
+    assert_eq!(
+        text_search(&store, "?s text:search \"brown dog\"")?,
+        Vec::<Term>::new()
+    );
+
+    // The query can be bound by another pattern
+    store.insert(QuadRef::new(
+        NamedNodeRef::new_unchecked("http://example.com/q"),
+        NamedNodeRef::new_unchecked("http://example.com/query"),
+        LiteralRef::new_simple_literal("fox"),
+        GraphNameRef::DefaultGraph,
+    ))?;
+    for _ in 0..2 {
+        assert_eq!(
+            text_search(
+                &store,
+                "?s text:search ?q . ?x <http://example.com/query> ?q"
+            )?,
+            [a.into_owned().into()]
+        );
+        // The statistics must not make the optimizer evaluate the search first
+        #[cfg(not(target_family = "wasm"))]
+        store.optimize()?;
+    }
+
+    // Writes update the index
+    store.insert(QuadRef::new(
+        b,
+        label,
+        LiteralRef::new_simple_literal("Brown, brown!"),
+        GraphNameRef::DefaultGraph,
+    ))?;
+    store.remove(fox)?;
+    assert_eq!(
+        text_search(&store, "?s text:search \"brown\"")?,
+        [b.into_owned().into()]
+    );
+    #[cfg(not(target_family = "wasm"))]
+    {
+        store.bulk_loader().load_quads([fox.into_owned()])?;
+        assert_eq!(
+            text_search(
+                &store,
+                "?s text:search \"brown\" BIND(text:score(?s, \"brown\") AS ?score) FILTER(?score < 1)"
+            )?,
+            [a.into_owned().into()]
+        );
+    }
+    store.update("CLEAR ALL")?;
+    assert_eq!(
+        text_search(&store, "GRAPH ?g { ?s text:search \"brown\" }")?,
+        Vec::<Term>::new()
+    );
+
+    // Indexing all string literals
+    store.insert(QuadRef::new(
+        b,
+        comment,
+        LiteralRef::new_simple_literal("A brown dog"),
+        GraphNameRef::DefaultGraph,
+    ))?;
+    store.enable_text_index([])?;
+    assert_eq!(
+        text_search(&store, "?s text:search \"dog\"")?,
+        [b.into_owned().into()]
+    );
+    text_search(&store, "?s text:search ?q").unwrap_err();
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_family = "wasm"))]
+fn test_text_index_is_persisted() -> Result<(), Box<dyn Error>> {
+    let dir = TempDir::default();
+    let quad = QuadRef::new(
+        EX,
+        EX,
+        LiteralRef::new_simple_literal("foo bar"),
+        GraphNameRef::DefaultGraph,
+    );
+    Store::open(&dir)?.enable_text_index([EX])?;
+    let store = Store::open(&dir)?;
+    store.insert(quad)?;
+    assert_eq!(
+        text_search(&store, "?s text:search \"bar\"")?,
+        [EX.into_owned().into()]
+    );
+    Ok(())
+}
+
+fn wkt_literal(wkt: &str) -> Literal {
+    Literal::new_typed_literal(
+        wkt,
+        NamedNodeRef::new_unchecked("http://www.opengis.net/ont/geosparql#wktLiteral"),
+    )
+}
+
+fn geo_query(store: &Store, query: &str) -> Result<Vec<Option<Term>>, Box<dyn Error>> {
+    let QueryResults::Solutions(solutions) = store.query(
+        format!(
+            "PREFIX geo: <http://www.opengis.net/ont/geosparql#>
+            PREFIX geof: <http://www.opengis.net/def/function/geosparql/>
+            PREFIX uom: <http://www.opengis.net/def/uom/OGC/1.0/>
+            {query}"
+        )
+        .as_str(),
+    )?
+    else {
+        return Err("Solutions expected".into());
+    };
+    let mut results = solutions
+        .map(|s| s.map(|s| s.get("r").cloned()))
+        .collect::<Result<Vec<_>, _>>()?;
+    results.sort_unstable_by_key(|r| r.as_ref().map(ToString::to_string));
+    Ok(results)
+}
+
+#[test]
+fn test_geosparql_functions() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    let eval = |expression: &str| -> Result<Option<Term>, Box<dyn Error>> {
+        Ok(geo_query(
+            &store,
+            &format!("SELECT ?r WHERE {{ BIND({expression} AS ?r) }}"),
+        )?
+        .pop()
+        .flatten())
+    };
+    let square = "\"POLYGON((0 0, 2 0, 2 2, 0 2, 0 0))\"^^geo:wktLiteral";
+    assert_eq!(
+        eval(&format!(
+            "geof:sfWithin(\"POINT(1 1)\"^^geo:wktLiteral, {square})"
+        ))?,
+        Some(Literal::from(true).into())
+    );
+    assert_eq!(
+        eval(&format!(
+            "geof:sfContains({square}, \"LINESTRING(1 1, 3 3)\"^^geo:wktLiteral)"
+        ))?,
+        Some(Literal::from(false).into())
+    );
+    assert_eq!(
+        eval(&format!(
+            "geof:sfIntersects({square}, \"LINESTRING(1 1, 3 3)\"^^geo:wktLiteral)"
+        ))?,
+        Some(Literal::from(true).into())
+    );
+    assert_eq!(
+        eval(&format!(
+            "geof:sfDisjoint({square}, \"POINT(3 3)\"^^geo:wktLiteral)"
+        ))?,
+        Some(Literal::from(true).into())
+    );
+    assert_eq!(
+        eval(&format!(
+            "geof:sfEquals({square}, \"POLYGON((2 2, 0 2, 0 0, 2 0, 2 2))\"^^geo:wktLiteral)"
+        ))?,
+        Some(Literal::from(true).into())
+    );
+    assert_eq!(
+        eval(&format!(
+            "geof:distance({square}, \"POINT(5 2)\"^^geo:wktLiteral, uom:degree)"
+        ))?,
+        Some(Literal::from(3.).into())
+    );
+    assert_eq!(
+        eval("geof:envelope(\"LINESTRING(0 0, 1 2)\"^^geo:wktLiteral)")?,
+        Some(wkt_literal("POLYGON ((0 0, 1 0, 1 2, 0 2, 0 0))").into())
+    );
+    assert_eq!(
+        eval("geof:convexHull(\"MULTIPOINT((0 0), (2 0), (1 0.5), (0 2))\"^^geo:wktLiteral)")?,
+        Some(wkt_literal("POLYGON ((0 0, 2 0, 0 2, 0 0))").into())
+    );
+    assert_eq!(
+        eval("geof:sfWithin(\"POINT(1.5 0)\"^^geo:wktLiteral, geof:buffer(\"POINT(0 0)\"^^geo:wktLiteral, 2, uom:degree))")?,
+        Some(Literal::from(true).into())
+    );
+    let Some(Term::Literal(distance)) = eval(
+        "geof:distance(\"POINT(0 0)\"^^geo:wktLiteral, \"POINT(0 1)\"^^geo:wktLiteral, uom:metre)",
+    )?
+    else {
+        return Err("A literal distance is expected".into());
+    };
+    assert!((distance.value().parse::<f64>()? - 111_195.).abs() < 1.);
+
+    // Invalid geometries and units are errors
+    assert_eq!(
+        eval("geof:sfWithin(\"POINT(1)\"^^geo:wktLiteral, \"POINT(1 1)\"^^geo:wktLiteral)")?,
+        None
+    );
+    assert_eq!(
+        eval("geof:sfWithin(\"POINT(1 1)\", \"POINT(1 1)\"^^geo:wktLiteral)")?,
+        None
+    );
+    assert_eq!(eval("geof:distance(\"POINT(0 0)\"^^geo:wktLiteral, \"POINT(0 1)\"^^geo:wktLiteral, uom:foot)")?, None);
+    Ok(())
+}
+
+#[test]
+fn test_spatial_index() -> Result<(), Box<dyn Error>> {
+    let as_wkt = NamedNodeRef::new_unchecked("http://www.opengis.net/ont/geosparql#asWKT");
+    let mut quads = Vec::new();
+    for i in -18_i32..18 {
+        for j in -9..9 {
+            let (x, y) = (f64::from(i) * 10. + 0.5, f64::from(j) * 10. + 0.5);
+            let wkt = match (i + j).rem_euclid(3) {
+                0 => format!("POINT({x} {y})"),
+                1 => format!("LINESTRING({x} {y}, {} {})", x + 12., y + 3.),
+                _ => format!(
+                    "POLYGON(({x} {y}, {} {y}, {} {}, {x} {}, {x} {y}))",
+                    x + 0.01,
+                    x + 0.01,
+                    y + 0.01,
+                    y + 0.01
+                ),
+            };
+            quads.push(Quad::new(
+                NamedNode::new(format!("http://example.com/{i}/{j}"))?,
+                as_wkt,
+                wkt_literal(&wkt),
+                if i % 2 == 0 {
+                    GraphName::DefaultGraph
+                } else {
+                    EX.into_owned().into()
+                },
+            ));
+        }
+    }
+    quads.push(Quad::new(
+        EX,
+        as_wkt,
+        wkt_literal("POINT(1 2 3"),
+        GraphName::DefaultGraph,
+    ));
+    quads.push(Quad::new(
+        EX,
+        EX,
+        wkt_literal("POINT(1.5 1.5)"),
+        GraphName::DefaultGraph,
+    ));
+
+    let reference = Store::new()?;
+    reference.extend(quads.clone())?;
+    let store = Store::new()?;
+    store.extend(quads[..100].iter().cloned())?;
+    store.enable_spatial_index()?;
+    store.extend(quads[100..].iter().cloned())?;
+
+    let filters = [
+        "geof:sfIntersects(?w, \"POLYGON((0 0, 40 0, 40 40, 0 40, 0 0))\"^^geo:wktLiteral)",
+        "geof:sfWithin(?w, \"POLYGON((-180 -90, 0 -90, 0 0, -180 0, -180 -90))\"^^geo:wktLiteral)",
+        "geof:sfContains(?w, \"POINT(-39.5 -29.5)\"^^geo:wktLiteral)",
+        "geof:sfIntersects(\"POINT(40.5 30.5)\"^^geo:wktLiteral, ?w) && geof:sfEquals(?w, ?w)",
+        "geof:sfIntersects(?w, \"LINESTRING(-175 20.5, 175 20.5)\"^^geo:wktLiteral)",
+        "geof:sfDisjoint(?w, \"POINT(0.5 0.5)\"^^geo:wktLiteral)",
+    ];
+    for filter in filters {
+        for pattern in [
+            "?r geo:asWKT ?w",
+            "GRAPH ?g { ?r geo:asWKT ?w }",
+            "?r ?p ?w",
+            "GRAPH <http://example.com> { ?r geo:asWKT ?w }",
+        ] {
+            let query = format!("SELECT ?r WHERE {{ {pattern} FILTER({filter}) }}");
+            let expected = geo_query(&reference, &query)?;
+            assert!(!expected.is_empty() || pattern != "?r ?p ?w", "{query}");
+            assert_eq!(geo_query(&store, &query)?, expected, "{query}");
+        }
+    }
+    let point = |r: &str| -> Result<Vec<Option<Term>>, Box<dyn Error>> {
+        geo_query(
+            &store,
+            &format!("SELECT ?r WHERE {{ ?r ?p ?w FILTER(geof:sfWithin(?w, \"POLYGON((1 1, 2 1, 2 2, 1 2, 1 1))\"^^geo:wktLiteral) && ?r = {r}) }}"),
+        )
+    };
+    assert_eq!(
+        point("<http://example.com>")?,
+        [Some(EX.into_owned().into())]
+    );
+
+    // Writes update the index
+    store.remove(QuadRef::new(
+        EX,
+        EX,
+        &wkt_literal("POINT(1.5 1.5)"),
+        GraphNameRef::DefaultGraph,
+    ))?;
+    assert_eq!(point("<http://example.com>")?, []);
+    #[cfg(not(target_family = "wasm"))]
+    {
+        store.bulk_loader().load_quads([Quad::new(
+            EX,
+            EX,
+            wkt_literal("POINT(1.5 1.5)"),
+            GraphName::DefaultGraph,
+        )])?;
+        assert_eq!(
+            point("<http://example.com>")?,
+            [Some(EX.into_owned().into())]
+        );
+    }
+    store.update("CLEAR ALL")?;
+    assert_eq!(
+        geo_query(
+            &store,
+            "SELECT ?r WHERE { GRAPH ?g { ?r ?p ?w } FILTER(geof:sfIntersects(?w, \"POLYGON((-180 -90, 180 -90, 180 90, -180 90, -180 -90))\"^^geo:wktLiteral)) }"
+        )?,
+        []
+    );
+    Ok(())
+}
+
+#[test]
+fn test_count_for_pattern() -> Result<(), Box<dyn Error>> {
+    let ex = |i: usize| NamedNode::new_unchecked(format!("http://example.com/{i}"));
+    let g = NamedNodeRef::new_unchecked("http://example.com/g");
+    let h = NamedNodeRef::new_unchecked("http://example.com/h");
+    let store = Store::new()?;
+    for i in 0..30 {
+        let graph_name = match i % 3 {
+            0 => GraphNameRef::DefaultGraph,
+            1 => g.into(),
+            _ => h.into(),
+        };
+        store.insert(QuadRef::new(&ex(i % 4), &ex(i % 2), &ex(i % 5), graph_name))?;
+        store.insert(QuadRef::new(
+            &ex(i % 4),
+            &ex(i % 2),
+            &Literal::from(i64::try_from(i)?),
+            graph_name,
+        ))?;
+    }
+    store.insert_named_graph(EX)?;
+
+    let subjects: [Option<Subject>; 3] = [None, Some(ex(1).into()), Some(ex(7).into())];
+    let predicates = [None, Some(ex(1))];
+    let objects: [Option<Term>; 3] = [None, Some(ex(3).into()), Some(Literal::from(3).into())];
+    let graph_names = [
+        None,
+        Some(GraphName::DefaultGraph),
+        Some(g.into()),
+        Some(EX.into()),
+    ];
+    for subject in &subjects {
+        for predicate in &predicates {
+            for object in &objects {
+                for graph_name in &graph_names {
+                    let pattern = (
+                        subject.as_ref().map(Subject::as_ref),
+                        predicate.as_ref().map(NamedNode::as_ref),
+                        object.as_ref().map(Term::as_ref),
+                        graph_name.as_ref().map(GraphName::as_ref),
+                    );
+                    let expected = store
+                        .quads_for_pattern(pattern.0, pattern.1, pattern.2, pattern.3)
+                        .count();
+                    assert_eq!(
+                        store.count_for_pattern(pattern.0, pattern.1, pattern.2, pattern.3)?,
+                        expected,
+                        "{pattern:?}"
+                    );
+                }
+            }
+        }
+    }
+    let graph_len = store.count_for_pattern(None, None, None, Some(g.into()))?;
+    store.transaction(|mut transaction| {
+        transaction.insert(QuadRef::new(&ex(9), &ex(9), &ex(9), g))?;
+        assert_eq!(
+            transaction.count_for_pattern(None, None, None, Some(g.into()))?,
+            graph_len + 1
+        );
+        Result::<_, StorageError>::Ok(())
+    })?;
+
+    // SPARQL COUNT(*) are computed from the same counts, the always true filter prevents it
+    let count = |query: &str, union_default_graph: bool| -> Result<(i64, bool), Box<dyn Error>> {
+        let mut query = Query::parse(query, None)?;
+        if union_default_graph {
+            query.dataset_mut().set_default_graph_as_union();
+        }
+        let (results, explanation) =
+            store.explain_query_opt(query, QueryOptions::default(), false)?;
+        let QueryResults::Solutions(mut solutions) = results? else {
+            return Err("Solutions expected".into());
+        };
+        let Some(Term::Literal(count)) = solutions
+            .next()
+            .ok_or("A solution is expected")??
+            .get("c")
+            .cloned()
+        else {
+            return Err("A literal count is expected".into());
+        };
+        Ok((
+            count.value().parse()?,
+            format!("{explanation:?}").contains("CountQuadPattern"),
+        ))
+    };
+    for pattern in [
+        "?s ?p ?o",
+        "?s <http://example.com/1> ?o",
+        "<http://example.com/2> ?p ?o",
+        "?s ?p 3",
+        "GRAPH ?g { ?s ?p ?o }",
+        "GRAPH ?g { ?s ?p <http://example.com/3> }",
+        "GRAPH <http://example.com/g> { ?s ?p ?o }",
+    ] {
+        for dataset in [
+            "",
+            "FROM <http://example.com/g> FROM <http://example.com/h>",
+            "FROM NAMED <http://example.com/h>",
+        ] {
+            for union_default_graph in [false, true] {
+                if union_default_graph && !dataset.is_empty() {
+                    continue;
+                }
+                let (actual, is_pushed_down) = count(
+                    &format!("SELECT (COUNT(*) AS ?c) {dataset} WHERE {{ {pattern} }}"),
+                    union_default_graph,
+                )?;
+                let (expected, _) = count(
+                    &format!(
+                        "SELECT (COUNT(*) AS ?c) {dataset} WHERE {{ {pattern} FILTER(true) }}"
+                    ),
+                    union_default_graph,
+                )?;
+                assert_eq!(
+                    actual, expected,
+                    "{pattern} {dataset} {union_default_graph}"
+                );
+                assert!(is_pushed_down, "{pattern} {dataset}");
+            }
+        }
+    }
+    assert_eq!(
+        count("SELECT (COUNT(*) AS ?c) WHERE { ?s ?p ?o }", false)?,
+        (20, true)
+    );
+    // Repeated variables require to compare the terms
+    assert_eq!(
+        count("SELECT (COUNT(*) AS ?c) WHERE { ?s ?p ?s }", false)?,
+        (3, false)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_range_filters() -> Result<(), Box<dyn Error>> {
+    let values = [
+        "-5",
+        "-1",
+        "0",
+        "2",
+        "3",
+        "4",
+        "9223372036854775807",
+        "-2.5",
+        "0.5",
+        "2.5",
+        "\"2.5\"^^xsd:float",
+        "\"1e10\"^^xsd:float",
+        "-1e10",
+        "-0.0e0",
+        "0.0e0",
+        "2.5e0",
+        "\"NaN\"^^xsd:double",
+        "\"INF\"^^xsd:double",
+        "\"-INF\"^^xsd:double",
+        "\"3\"",
+        "true",
+        "\"2019-12-31\"^^xsd:date",
+        "\"2020-01-01\"^^xsd:date",
+        "\"2020-01-02+05:00\"^^xsd:date",
+        "\"2020-01-01T11:00:00Z\"^^xsd:dateTime",
+        "\"2020-01-01T12:00:00\"^^xsd:dateTime",
+        "\"2020-01-01T13:00:00+14:00\"^^xsd:dateTime",
+        "\"2020-01-02T00:00:00-14:00\"^^xsd:dateTime",
+    ];
+    let store = Store::new()?;
+    let data = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            if i % 2 == 0 {
+                format!("<http://example.com/{i}> <http://example.com/p> {value} . ")
+            } else {
+                format!("GRAPH <http://example.com> {{ <http://example.com/{i}> <http://example.com/p> {value} }} ")
+            }
+        })
+        .collect::<String>();
+    store.update(
+        format!("PREFIX xsd: <http://www.w3.org/2001/XMLSchema#> INSERT DATA {{ {data} }}")
+            .as_str(),
+    )?;
+    let select = |pattern: &str, filter: &str| -> Result<Vec<String>, Box<dyn Error>> {
+        let QueryResults::Solutions(solutions) = store.query(
+            format!("PREFIX xsd: <http://www.w3.org/2001/XMLSchema#> SELECT ?s WHERE {{ {pattern} FILTER({filter}) }}")
+                .as_str(),
+        )?
+        else {
+            return Err("Solutions expected".into());
+        };
+        let mut results = solutions
+            .map(|s| Ok(s?.get("s").map_or_else(String::new, ToString::to_string)))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        results.sort_unstable();
+        Ok(results)
+    };
+
+    let filters = [
+        "?o > 2",
+        "?o >= 2.5",
+        "?o < -1",
+        "2 < ?o && ?o <= 4.0e0",
+        "?o = 2.5",
+        "-1 >= ?o",
+        "?o > 9223372036854775806",
+        "?o < \"1e9\"^^xsd:float && ?o > -1e300",
+        "?o > \"2019-12-31\"^^xsd:date",
+        "?o <= \"2020-01-01\"^^xsd:date",
+        "?o < \"2020-01-01T12:00:00Z\"^^xsd:dateTime",
+        "?o >= \"2020-01-01T12:00:00\"^^xsd:dateTime",
+        "?o = \"2020-01-01T12:00:00\"^^xsd:dateTime",
+    ];
+    for filter in filters {
+        for pattern in [
+            "?s <http://example.com/p> ?o",
+            "GRAPH ?g { ?s <http://example.com/p> ?o }",
+            "GRAPH <http://example.com> { ?s <http://example.com/p> ?o }",
+        ] {
+            // The comparison with true hides the range from the query evaluator
+            let expected = select(pattern, &format!("({filter}) = true"))?;
+            assert_eq!(select(pattern, filter)?, expected, "{pattern} {filter}");
+        }
+    }
+    assert_eq!(
+        select("?s <http://example.com/p> ?o", "?o > 2")?,
+        [
+            "<http://example.com/10>",
+            "<http://example.com/4>",
+            "<http://example.com/6>"
+        ]
+    );
+    assert_eq!(
+        select(
+            "?s <http://example.com/p> ?o",
+            "?o < \"2020-01-01T12:00:00Z\"^^xsd:dateTime"
+        )?,
+        ["<http://example.com/24>", "<http://example.com/26>"]
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_family = "wasm"))]
+fn test_spatial_index_is_persisted() -> Result<(), Box<dyn Error>> {
+    let dir = TempDir::default();
+    Store::open(&dir)?.enable_spatial_index()?;
+    Store::open(&dir)?.insert(QuadRef::new(
+        EX,
+        EX,
+        &wkt_literal("POINT(1 2)"),
+        GraphNameRef::DefaultGraph,
+    ))?;
+    assert_eq!(
+        geo_query(
+            &Store::open_read_only(&dir)?,
+            "SELECT ?r WHERE { ?r ?p ?w FILTER(geof:sfIntersects(?w, \"POINT(1 2)\"^^geo:wktLiteral)) }"
+        )?,
+        [Some(EX.into_owned().into())]
+    );
+    Ok(())
+}
+
 #[test]
 fn test_snapshot_isolation_iterator() -> Result<(), Box<dyn Error>> {
     let quad = QuadRef::new(
@@ -273,6 +1302,32 @@ fn test_bulk_load_on_existing_delete_overrides_the_delete() -> Result<(), Box<dy
     Ok(())
 }
 
+#[test]
+#[cfg(not(target_family = "wasm"))]
+fn test_open_with_options() -> Result<(), Box<dyn Error>> {
+    let dir = TempDir::default();
+    let quad = QuadRef::new(EX, EX, BIG, EX);
+    {
+        let store = Store::open_with_options(
+            &dir,
+            &StoreOptions::default()
+                .with_block_cache_size_in_megabytes(1)
+                .with_write_buffer_size_in_megabytes(1)
+                .with_max_open_files(128)
+                .with_compression(false)
+                .with_bloom_filter_bits_per_key(10.),
+        )?;
+        store.insert(quad)?;
+        store.flush()?;
+        store.validate()?;
+    }
+    // The options are not persisted
+    let store = Store::open(&dir)?;
+    assert!(store.contains(quad)?);
+    store.validate()?;
+    Ok(())
+}
+
 #[test]
 #[cfg(not(target_family = "wasm"))]
 fn test_open_bad_dir() -> Result<(), Box<dyn Error>> {
@@ -343,6 +1398,138 @@ fn test_backup() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+#[cfg(not(target_family = "wasm"))]
+fn test_packed() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    store.load_from_read(RdfFormat::TriG, GRAPH_DATA.as_bytes())?;
+    store.extend((0..100).map(|i| {
+        Quad::new(
+            NamedNode::new_unchecked(format!("http://example.com/s{i}")),
+            rdf::VALUE,
+            Literal::from(i),
+            NamedNode::new_unchecked("http://example.com/g"),
+        )
+    }))?;
+    store.insert_named_graph(NamedNodeRef::new_unchecked("http://example.com/empty"))?;
+    let packed_file = TempDir::default();
+    store.pack_to_file(&packed_file)?;
+
+    let packed = Store::open_packed(&packed_file)?;
+    packed.validate()?;
+    assert_eq!(
+        packed.iter().collect::<Result<Vec<_>, _>>()?,
+        store.iter().collect::<Result<Vec<_>, _>>()?
+    );
+    assert_eq!(
+        packed.named_graphs().collect::<Result<Vec<_>, _>>()?,
+        store.named_graphs().collect::<Result<Vec<_>, _>>()?
+    );
+    for i in [0, 15, 16, 17, 99] {
+        let subject = NamedNode::new_unchecked(format!("http://example.com/s{i}"));
+        assert_eq!(
+            packed
+                .quads_for_pattern(Some(subject.as_ref().into()), None, None, None)
+                .collect::<Result<Vec<_>, _>>()?,
+            vec![Quad::new(
+                subject,
+                rdf::VALUE,
+                Literal::from(i),
+                NamedNode::new_unchecked("http://example.com/g"),
+            )]
+        );
+    }
+    assert!(packed
+        .quads_for_pattern(
+            Some(NamedNodeRef::new_unchecked("http://example.com/s100").into()),
+            None,
+            None,
+            None
+        )
+        .next()
+        .is_none());
+    let query = "SELECT (SUM(?o) AS ?s) WHERE { GRAPH ?g { ?s <http://www.w3.org/1999/02/22-rdf-syntax-ns#value> ?o } }";
+    let QueryResults::Solutions(mut solutions) = packed.query(query)? else {
+        return Err("Solutions expected".into());
+    };
+    assert_eq!(
+        solutions.next().ok_or("Solution expected")??.get("s"),
+        Some(&Literal::from(4950).into())
+    );
+
+    packed
+        .insert(QuadRef::new(
+            NamedNodeRef::new_unchecked("http://example.com/s"),
+            rdf::VALUE,
+            NamedNodeRef::new_unchecked("http://example.com/o"),
+            GraphNameRef::DefaultGraph,
+        ))
+        .unwrap_err();
+    assert_eq!(packed.len()?, store.len()?);
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_family = "wasm"))]
+fn test_incremental_backup() -> Result<(), Box<dyn Error>> {
+    let first_quad = QuadRef::new(EX, EX, BIG, GraphNameRef::DefaultGraph);
+    let second_quad = QuadRef::new(EX, EX, EX, EX);
+    let store_dir = TempDir::default();
+    let backups_dir = TempDir::default();
+    let restore_dir = TempDir::default();
+
+    let store = Store::open(&store_dir)?;
+    store.insert(first_quad)?;
+    let first = store.incremental_backup(&backups_dir)?;
+    store.insert(second_quad)?;
+    let second = store.incremental_backup(&backups_dir)?;
+    assert!(first.id < second.id);
+    assert_eq!(
+        Store::list_backups(&backups_dir)?,
+        [first.clone(), second.clone()]
+    );
+
+    // Backups from read-only and secondary instances
+    let secondary_store = Store::open_secondary(&store_dir)?;
+    let third = secondary_store.incremental_backup(&backups_dir)?;
+    drop(secondary_store);
+    drop(store);
+    let fourth = Store::open_read_only(&store_dir)?.incremental_backup(&backups_dir)?;
+
+    // Restoration into a new directory and on top of an existing store
+    Store::restore_backup(&backups_dir, Some(first.id), &restore_dir)?;
+    let restored = Store::open(&restore_dir)?;
+    restored.validate()?;
+    assert_eq!(
+        restored.iter().collect::<Result<Vec<_>, _>>()?,
+        [first_quad.into_owned()]
+    );
+    drop(restored);
+    Store::restore_backup(&backups_dir, None, &restore_dir)?;
+    let restored = Store::open_read_only(&restore_dir)?;
+    restored.validate()?;
+    assert_eq!(restored.len()?, 2);
+    assert!(restored.contains(second_quad)?);
+    drop(restored);
+
+    // Retention
+    Store::purge_old_backups(&backups_dir, 2)?;
+    assert_eq!(Store::list_backups(&backups_dir)?, [third, fourth]);
+    assert!(Store::restore_backup(&backups_dir, Some(first.id), &restore_dir).is_err());
+    // The failed restoration does not change the target directory
+    assert_eq!(Store::open_read_only(&restore_dir)?.len()?, 2);
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_family = "wasm"))]
+fn test_incremental_backup_on_in_memory() -> Result<(), Box<dyn Error>> {
+    let backups_dir = TempDir::default();
+    Store::new()?.incremental_backup(&backups_dir).unwrap_err();
+    assert!(Store::restore_backup(&backups_dir, None, TempDir::default()).is_err());
+    Ok(())
+}
+
 #[test]
 #[cfg(not(target_family = "wasm"))]
 fn test_bad_backup() -> Result<(), Box<dyn Error>> {
@@ -386,6 +1573,490 @@ fn test_backward_compatibility() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+#[cfg(not(target_family = "wasm"))]
+fn test_read_only_backward_compatibility() -> Result<(), Box<dyn Error>> {
+    // The store has been created before the addition of the change log
+    let dir = TempDir::default();
+    create_dir_all(&dir)?;
+    for entry in read_dir("tests/rocksdb_v1_data")? {
+        let entry = entry?;
+        copy(entry.path(), dir.0.join(entry.file_name()))?;
+    }
+    let ex = NamedNodeRef::new_unchecked("http://example.com/s");
+    let p = NamedNodeRef::new_unchecked("http://example.com/p");
+    let g = NamedNodeRef::new_unchecked("http://example.com/g");
+    let quads = [
+        QuadRef::new(
+            ex,
+            p,
+            LiteralRef::new_simple_literal("a string that is too long to be inlined"),
+            GraphNameRef::DefaultGraph,
+        ),
+        QuadRef::new(ex, p, ex, g),
+    ];
+    for store in [Store::open_read_only(&dir)?, Store::open_secondary(&dir)?] {
+        for quad in quads {
+            assert!(store.contains(quad)?);
+        }
+        assert_eq!(store.changes(0).count(), 0);
+        assert!(store.enable_change_log().is_err());
+        store.validate()?;
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_family = "wasm"))]
+fn test_migration() -> Result<(), Box<dyn Error>> {
+    let label = NamedNodeRef::new_unchecked("http://www.w3.org/2000/01/rdf-schema#label");
+    let values = [
+        Literal::from(-2.5),
+        Literal::from(-1),
+        Literal::from(12),
+        Literal::new_typed_literal("2020-01-01T01:01:01Z", xsd::DATE_TIME),
+        Literal::new_simple_literal("The quick brown fox"),
+        Literal::new_typed_literal("http://example.com/a/long/uri/not/inlined", xsd::ANY_URI),
+    ];
+    let dir = TempDir::default();
+    let (quads, changes) = {
+        let store = Store::open(&dir)?;
+        store.enable_change_log()?;
+        store.enable_text_index([label])?;
+        for (i, value) in values.iter().enumerate() {
+            store.insert(QuadRef::new(EX, label, value, GraphNameRef::DefaultGraph))?;
+            store.insert(QuadRef::new(EX, label, value, EX))?;
+            if i % 2 == 0 {
+                store.remove(QuadRef::new(EX, label, value, EX))?;
+            }
+        }
+        assert_eq!(store.storage_version()?, LATEST_STORAGE_VERSION);
+        (
+            store.iter().collect::<Result<Vec<_>, _>>()?,
+            store.changes(0).collect::<Result<Vec<_>, _>>()?,
+        )
+    };
+
+    // Downgrade
+    let migrator = StoreMigrator::open(&dir)?;
+    assert_eq!(migrator.storage_version()?, LATEST_STORAGE_VERSION);
+    let progress = Rc::new(RefCell::new(Vec::new()));
+    let progress_hook = Rc::clone(&progress);
+    migrator
+        .on_progress(move |version, entries| {
+            progress_hook.borrow_mut().push((version, entries));
+        })
+        .migrate(1)?;
+    assert!(matches!(
+        progress.borrow().as_slice(),
+        [(2, _), (1, entries)] if *entries > 40
+    ));
+    assert_eq!(StoreMigrator::open(&dir)?.storage_version()?, 1);
+    assert!(Store::open_read_only(&dir).is_err());
+
+    // Unsupported versions
+    for version in [0, LATEST_STORAGE_VERSION + 1] {
+        assert!(StoreMigrator::open(&dir)?.migrate(version).is_err());
+    }
+    assert!(StoreMigrator::open(dir.0.join("missing")).is_err());
+
+    // Upgrade on open
+    let store = Store::open(&dir)?;
+    assert_eq!(store.storage_version()?, LATEST_STORAGE_VERSION);
+    store.validate()?;
+    assert_eq!(store.iter().collect::<Result<Vec<_>, _>>()?, quads);
+    assert_eq!(store.changes(0).collect::<Result<Vec<_>, _>>()?, changes);
+    assert_eq!(
+        text_search(&store, "?s text:search \"fox\"")?,
+        [Term::from(EX)]
+    );
+    let QueryResults::Solutions(solutions) =
+        store.query("SELECT ?o WHERE { ?s ?p ?o FILTER(?o > -2) }")?
+    else {
+        return Err("Solutions expected".into());
+    };
+    assert_eq!(solutions.count(), 2);
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_family = "wasm"))]
+fn test_read_only_open_of_previous_version() -> Result<(), Box<dyn Error>> {
+    let p = NamedNodeRef::new_unchecked("http://example.com/p");
+    let custom = NamedNodeRef::new_unchecked("http://example.com/datatype");
+    let dir = TempDir::default();
+    {
+        let store = Store::open(&dir)?;
+        store.enable_change_log()?;
+        store.insert(QuadRef::new(
+            EX,
+            p,
+            LiteralRef::new_typed_literal("foo", custom),
+            GraphNameRef::DefaultGraph,
+        ))?;
+        store.insert(QuadRef::new(EX, p, EX, EX))?;
+    }
+    StoreMigrator::open(&dir)?.migrate(2)?;
+    // Neither the typed literals nor the change log have to be rewritten
+    assert_eq!(Store::open_read_only(&dir)?.len()?, 2);
+
+    Store::open(&dir)?.insert(QuadRef::new(
+        EX,
+        p,
+        LiteralRef::new_typed_literal("http://example.com", xsd::ANY_URI),
+        GraphNameRef::DefaultGraph,
+    ))?;
+    StoreMigrator::open(&dir)?.migrate(2)?;
+    assert!(Store::open_read_only(&dir).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_derived_integer_and_any_uri_literals() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    store.load_from_read(
+        RdfFormat::Turtle,
+        r#"
+        @prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+        <http://example.com/a> <http://example.com/v> "12"^^xsd:int ;
+            <http://example.com/u> "http://example.com/a"^^xsd:anyURI .
+        <http://example.com/b> <http://example.com/v> "3"^^xsd:long ;
+            <http://example.com/u> "http://example.com/b"^^xsd:anyURI .
+        <http://example.com/c> <http://example.com/v> "100"^^xsd:unsignedByte .
+        <http://example.com/d> <http://example.com/v> "300"^^xsd:unsignedByte .
+        <http://example.com/e> <http://example.com/v> "12"^^xsd:integer .
+        "#
+        .as_bytes(),
+    )?;
+    assert!(store.contains(QuadRef::new(
+        NamedNodeRef::new_unchecked("http://example.com/a"),
+        NamedNodeRef::new_unchecked("http://example.com/v"),
+        LiteralRef::new_typed_literal("12", xsd::INT),
+        GraphNameRef::DefaultGraph,
+    ))?);
+    assert_eq!(store.len()?, 7);
+
+    let QueryResults::Solutions(solutions) = store.query(
+        "SELECT ?v (DATATYPE(?v) AS ?t) WHERE { ?s <http://example.com/v> ?v FILTER(?v > 5) } ORDER BY ?v ?t",
+    )?
+    else {
+        return Err("Solutions expected".into());
+    };
+    let solutions = solutions
+        .map(|s| {
+            let s = s?;
+            Ok((s.get("v").cloned(), s.get("t").cloned()))
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+    assert_eq!(
+        solutions,
+        [
+            (
+                Some(Literal::new_typed_literal("12", xsd::INT).into()),
+                Some(xsd::INT.into())
+            ),
+            (Some(Literal::from(12).into()), Some(xsd::INTEGER.into())),
+            (
+                Some(Literal::new_typed_literal("100", xsd::UNSIGNED_BYTE).into()),
+                Some(xsd::UNSIGNED_BYTE.into())
+            ),
+        ]
+    );
+
+    let QueryResults::Solutions(mut solutions) = store.query(
+        "SELECT (SUM(?v) AS ?sum) WHERE { ?s <http://example.com/v> ?v FILTER(isNumeric(?v)) }",
+    )?
+    else {
+        return Err("Solutions expected".into());
+    };
+    assert_eq!(
+        solutions.next().ok_or("Solution expected")??.get("sum"),
+        Some(&Literal::from(127).into())
+    );
+
+    let QueryResults::Solutions(solutions) = store.query(
+        "SELECT ?u WHERE { ?s <http://example.com/u> ?u FILTER(DATATYPE(?u) = <http://www.w3.org/2001/XMLSchema#anyURI>) } ORDER BY DESC(?u)",
+    )?
+    else {
+        return Err("Solutions expected".into());
+    };
+    assert_eq!(
+        solutions
+            .map(|s| Ok(s?.get("u").cloned()))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?,
+        [
+            Some(Literal::new_typed_literal("http://example.com/b", xsd::ANY_URI).into()),
+            Some(Literal::new_typed_literal("http://example.com/a", xsd::ANY_URI).into())
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_query_timeout_and_cancellation() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    for i in 0..100 {
+        store.insert(QuadRef::new(
+            NamedNodeRef::new(&format!("http://example.com/{i}"))?,
+            rdf::TYPE,
+            LiteralRef::new_simple_literal(&i.to_string()),
+            GraphNameRef::DefaultGraph,
+        ))?;
+    }
+
+    let QueryResults::Solutions(mut solutions) = store.query_opt(
+        "SELECT * WHERE { ?s1 ?p1 ?o1 . ?s2 ?p2 ?o2 . ?s3 ?p3 ?o3 } ORDER BY ?o1 ?o2 ?o3",
+        QueryOptions::default().with_timeout(Duration::from_millis(1)),
+    )?
+    else {
+        return Err("Solutions expected".into());
+    };
+    assert!(matches!(
+        solutions.next(),
+        Some(Err(EvaluationError::Timeout))
+    ));
+    assert!(solutions.next().is_none());
+
+    assert!(matches!(
+        store.update_opt(
+            "DELETE WHERE { ?s ?p ?o }",
+            QueryOptions::default().with_timeout(Duration::ZERO),
+        ),
+        Err(EvaluationError::Timeout)
+    ));
+    assert_eq!(store.len()?, 100);
+
+    let token = CancellationToken::new();
+    let QueryResults::Solutions(mut solutions) = store.query_opt(
+        "SELECT * WHERE { ?s ?p ?o }",
+        QueryOptions::default().with_cancellation_token(token.clone()),
+    )?
+    else {
+        return Err("Solutions expected".into());
+    };
+    solutions.next().ok_or("Solution expected")??;
+    assert!(!token.is_cancelled());
+    spawn(move || token.cancel())
+        .join()
+        .map_err(|_| "Cancellation failed")?;
+    assert!(matches!(
+        solutions.next(),
+        Some(Err(EvaluationError::Cancelled))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_memory_budget() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    for i in 0..300 {
+        let subject = NamedNode::new(format!("http://example.com/{i}"))?;
+        store.insert(QuadRef::new(
+            &subject,
+            rdf::VALUE,
+            &Literal::from(i % 7),
+            GraphNameRef::DefaultGraph,
+        ))?;
+        if i % 3 == 0 {
+            store.insert(QuadRef::new(
+                &subject,
+                rdf::TYPE,
+                &Literal::from(i % 5),
+                GraphNameRef::DefaultGraph,
+            ))?;
+        }
+    }
+    for query in [
+        "SELECT ?s ?v WHERE { ?s <http://www.w3.org/1999/02/22-rdf-syntax-ns#value> ?v } ORDER BY DESC(?v) ?s",
+        "SELECT ?v (COUNT(?s) AS ?c) (SAMPLE(?s) AS ?x) WHERE { ?s <http://www.w3.org/1999/02/22-rdf-syntax-ns#value> ?v } GROUP BY ?v ORDER BY ?v",
+        "SELECT ?s (SUM(?v) AS ?sum) WHERE { ?s ?p ?v } GROUP BY ?s ORDER BY ?s",
+        "SELECT * WHERE { { ?s <http://www.w3.org/1999/02/22-rdf-syntax-ns#value> ?v } UNION { ?s a ?v } { SELECT ?s (COUNT(*) AS ?c) WHERE { ?s ?p ?o } GROUP BY ?s } } ORDER BY ?s ?v",
+        "SELECT * WHERE { { ?s <http://www.w3.org/1999/02/22-rdf-syntax-ns#value> ?v } UNION { ?s a ?v } OPTIONAL { SELECT ?s (COUNT(*) AS ?c) WHERE { ?s a ?o } GROUP BY ?s } } ORDER BY ?s ?v",
+    ] {
+        let expected = store.query(query)?;
+        let actual = store.query_opt(query, QueryOptions::default().with_memory_budget(0))?;
+        let (QueryResults::Solutions(expected), QueryResults::Solutions(actual)) =
+            (expected, actual)
+        else {
+            return Err("Solutions expected".into());
+        };
+        let expected = expected.collect::<Result<Vec<_>, _>>()?;
+        let actual = actual.collect::<Result<Vec<_>, _>>()?;
+        assert!(!expected.is_empty());
+        assert_eq!(
+            actual.iter().map(|s| s.values().to_vec()).collect::<Vec<_>>(),
+            expected.iter().map(|s| s.values().to_vec()).collect::<Vec<_>>(),
+            "{query}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_batched_service_join() -> Result<(), Box<dyn Error>> {
+    struct CountingServiceHandler {
+        store: Store,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl ServiceHandler for CountingServiceHandler {
+        type Error = EvaluationError;
+
+        fn handle(&self, _: NamedNode, query: Query) -> Result<QueryResults, EvaluationError> {
+            assert!(query.to_string().contains("VALUES"), "{query}");
+            self.calls.fetch_add(1, atomic::Ordering::Relaxed);
+            self.store.query(query)
+        }
+    }
+
+    let remote = Store::new()?;
+    let local = Store::new()?;
+    for i in 0..250 {
+        let subject = NamedNode::new(format!("http://example.com/{i}"))?;
+        remote.insert(QuadRef::new(
+            &subject,
+            rdf::VALUE,
+            &Literal::from(i),
+            GraphNameRef::DefaultGraph,
+        ))?;
+        local.insert(QuadRef::new(
+            &subject,
+            rdf::TYPE,
+            &NamedNode::new("http://example.com/C")?,
+            GraphNameRef::DefaultGraph,
+        ))?;
+    }
+    let calls = Arc::new(AtomicUsize::new(0));
+    let (results, explanation) = local.explain_query_opt(
+        "SELECT ?s ?v WHERE { ?s a <http://example.com/C> SERVICE <http://example.com/service> { ?s <http://www.w3.org/1999/02/22-rdf-syntax-ns#value> ?v } }",
+        QueryOptions::default()
+            .with_service_handler(CountingServiceHandler {
+                store: remote,
+                calls: Arc::clone(&calls),
+            })
+            .with_service_batch_size(100),
+        false,
+    )?;
+    let QueryResults::Solutions(solutions) = results? else {
+        return Err("Solutions expected".into());
+    };
+    assert_eq!(solutions.collect::<Result<Vec<_>, _>>()?.len(), 250);
+    assert_eq!(calls.load(atomic::Ordering::Relaxed), 3);
+    assert!(
+        format!("{explanation:?}").contains("BatchedServiceJoin"),
+        "{explanation:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_top_k() -> Result<(), Box<dyn Error>> {
+    let store = Store::new()?;
+    for i in 0..300 {
+        store.insert(QuadRef::new(
+            &NamedNode::new(format!("http://example.com/{i}"))?,
+            rdf::VALUE,
+            &Literal::from(i % 7),
+            GraphNameRef::DefaultGraph,
+        ))?;
+    }
+    let solutions = |query: &str| -> Result<(Vec<Vec<Option<Term>>>, String), Box<dyn Error>> {
+        let (results, explanation) =
+            store.explain_query_opt(query, QueryOptions::default(), false)?;
+        let QueryResults::Solutions(solutions) = results? else {
+            return Err("Solutions expected".into());
+        };
+        Ok((
+            solutions
+                .map(|s| Ok(s?.values().to_vec()))
+                .collect::<Result<_, EvaluationError>>()?,
+            format!("{explanation:?}"),
+        ))
+    };
+    let (all, _) = solutions(
+        "SELECT ?s ?v WHERE { ?s <http://www.w3.org/1999/02/22-rdf-syntax-ns#value> ?v } ORDER BY DESC(?v) ?s",
+    )?;
+    for (start, length) in [(0, 1), (0, 10), (5, 10), (295, 10), (400, 10)] {
+        let (actual, explanation) = solutions(&format!("SELECT ?s ?v WHERE {{ ?s <http://www.w3.org/1999/02/22-rdf-syntax-ns#value> ?v }} ORDER BY DESC(?v) ?s OFFSET {start} LIMIT {length}"))?;
+        assert_eq!(
+            actual,
+            all.iter()
+                .skip(start)
+                .take(length)
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+        assert!(explanation.contains("TopK"), "{explanation}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_custom_aggregate_function() -> Result<(), Box<dyn Error>> {
+    #[derive(Default)]
+    struct LongestAccumulator {
+        longest: Option<Term>,
+    }
+
+    impl AggregateFunctionAccumulator for LongestAccumulator {
+        fn add(&mut self, element: Term) {
+            if self
+                .longest
+                .as_ref()
+                .map_or(true, |l| element.to_string().len() > l.to_string().len())
+            {
+                self.longest = Some(element);
+            }
+        }
+
+        fn finish(&mut self) -> Option<Term> {
+            self.longest.take()
+        }
+    }
+
+    let store = Store::new()?;
+    for i in [1, 100, 10] {
+        store.insert(QuadRef::new(
+            NamedNodeRef::new("http://example.com/s")?,
+            rdf::VALUE,
+            &Literal::from(i),
+            GraphNameRef::DefaultGraph,
+        ))?;
+    }
+    let longest = NamedNode::new("http://example.com/longest")?;
+    let options = QueryOptions::default()
+        .with_custom_aggregate_function(longest.clone(), || Box::<LongestAccumulator>::default());
+    for query in [
+        SparqlParser::new()
+            .with_custom_aggregate_function(longest.clone())
+            .parse_query("SELECT (<http://example.com/longest>(?o) AS ?l) WHERE { ?s ?p ?o }")?,
+        SparqlParser::new().parse_query(
+            "SELECT (<http://example.com/longest>(DISTINCT ?o) AS ?l) WHERE { ?s ?p ?o } GROUP BY ?s",
+        )?,
+    ] {
+        let QueryResults::Solutions(mut solutions) = store.query_opt(query, options.clone())? else {
+            return Err("Solutions expected".into());
+        };
+        assert_eq!(
+            solutions.next().ok_or("A solution is expected")??.get("l"),
+            Some(&Literal::from(100).into())
+        );
+        assert!(solutions.next().is_none());
+    }
+
+    // Unknown aggregate functions are unbound
+    let QueryResults::Solutions(mut solutions) = store
+        .query("SELECT (<http://example.com/unknown>(DISTINCT ?o) AS ?l) WHERE { ?s ?p ?o }")?
+    else {
+        return Err("Solutions expected".into());
+    };
+    assert_eq!(
+        solutions.next().ok_or("A solution is expected")??.get("l"),
+        None
+    );
+    Ok(())
+}
+
 #[test]
 #[cfg(not(target_family = "wasm"))]
 fn test_secondary() -> Result<(), Box<dyn Error>> {
@@ -418,6 +2089,9 @@ fn test_secondary() -> Result<(), Box<dyn Error>> {
     primary.validate()?;
     secondary.validate()?;
 
+    // Snapshots are not supported by secondaries
+    assert!(secondary.snapshot().is_err());
+
     // We close the primary store and remove its content
     drop(primary);
     remove_dir_all(&primary_dir)?;
@@ -502,6 +2176,194 @@ fn test_open_read_only_bad_dir() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+type MemoryTables = BTreeMap<String, BTreeMap<Vec<u8>, Vec<u8>>>;
+
+/// A [`StorageBackend`] copying its whole content on each transaction
+#[derive(Clone, Default)]
+struct MemoryBackend {
+    tables: Arc<RwLock<Arc<MemoryTables>>>,
+    writer: Arc<Mutex<()>>,
+    /// Simulates a bug: the scans ignore their upper bound
+    ignore_scan_end: bool,
+}
+
+struct MemorySnapshot {
+    tables: Arc<MemoryTables>,
+    ignore_scan_end: bool,
+}
+
+struct MemoryTransaction<'a> {
+    backend: &'a MemoryBackend,
+    tables: MemoryTables,
+    _writer: MutexGuard<'a, ()>,
+}
+
+fn memory_scan(
+    tables: &MemoryTables,
+    column_family: &str,
+    start: &[u8],
+    end: Option<&[u8]>,
+) -> StorageBackendIter {
+    let entries = tables
+        .get(column_family)
+        .into_iter()
+        .flat_map(|table| table.range(start.to_vec()..))
+        .take_while(|(k, _)| end.map_or(true, |end| k.as_slice() < end))
+        .map(|(k, v)| Ok((k.clone(), v.clone())))
+        .collect::<Vec<_>>();
+    Box::new(entries.into_iter())
+}
+
+#[allow(clippy::unwrap_in_result)]
+impl StorageBackend for MemoryBackend {
+    fn create_column_family(&self, name: &str) -> Result<(), StorageError> {
+        let mut tables = self.tables.write().unwrap();
+        Arc::make_mut(&mut tables).entry(name.into()).or_default();
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Box<dyn StorageBackendSnapshot> {
+        Box::new(MemorySnapshot {
+            tables: Arc::clone(&self.tables.read().unwrap()),
+            ignore_scan_end: self.ignore_scan_end,
+        })
+    }
+
+    fn transaction(&self) -> Result<Box<dyn StorageBackendTransaction + '_>, StorageError> {
+        let writer = self.writer.lock().unwrap();
+        Ok(Box::new(MemoryTransaction {
+            backend: self,
+            tables: MemoryTables::clone(&self.tables.read().unwrap()),
+            _writer: writer,
+        }))
+    }
+}
+
+impl StorageBackendSnapshot for MemorySnapshot {
+    fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self
+            .tables
+            .get(column_family)
+            .and_then(|table| table.get(key))
+            .cloned())
+    }
+
+    fn scan(
+        &self,
+        column_family: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<StorageBackendIter, StorageError> {
+        Ok(memory_scan(
+            &self.tables,
+            column_family,
+            start,
+            end.filter(|_| !self.ignore_scan_end),
+        ))
+    }
+}
+
+#[allow(clippy::unwrap_in_result)]
+impl StorageBackendTransaction for MemoryTransaction<'_> {
+    fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self
+            .tables
+            .get(column_family)
+            .and_then(|table| table.get(key))
+            .cloned())
+    }
+
+    fn scan(
+        &self,
+        column_family: &str,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<StorageBackendIter, StorageError> {
+        Ok(memory_scan(&self.tables, column_family, start, end))
+    }
+
+    fn insert(
+        &mut self,
+        column_family: &str,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        self.tables
+            .entry(column_family.into())
+            .or_default()
+            .insert(key.into(), value.into());
+        Ok(())
+    }
+
+    fn remove(&mut self, column_family: &str, key: &[u8]) -> Result<(), StorageError> {
+        if let Some(table) = self.tables.get_mut(column_family) {
+            table.remove(key);
+        }
+        Ok(())
+    }
+
+    fn commit(self: Box<Self>) -> Result<(), StorageError> {
+        let this = *self;
+        *this.backend.tables.write().unwrap() = Arc::new(this.tables);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_storage_backend_conformance() -> Result<(), Box<dyn Error>> {
+    check_storage_backend(|| Ok(MemoryBackend::default()))?;
+    assert!(check_storage_backend(|| Ok(MemoryBackend {
+        ignore_scan_end: true,
+        ..MemoryBackend::default()
+    }))
+    .is_err());
+    Ok(())
+}
+
+/// Runs some operations and returns the sizes of a snapshot, of the store and of the change log
+fn exercise_store(store: &Store) -> Result<(usize, usize, usize), Box<dyn Error>> {
+    store.enable_change_log()?;
+    store.load_from_read(RdfFormat::Turtle, DATA.as_bytes())?;
+    store.transaction(|mut t| {
+        t.insert(QuadRef::new(EX, EX, EX, EX))?;
+        t.remove(QuadRef::new(EX, EX, EX, EX))?;
+        t.insert(QuadRef::new(EX, EX, BIG, EX))
+    })?;
+    let snapshot = store.snapshot()?;
+    store.update("DELETE WHERE { ?s <http://schema.org/name> ?o }")?;
+    #[cfg(not(target_family = "wasm"))]
+    {
+        store
+            .bulk_loader()
+            .load_quads(quads(NamedNodeRef::new("http://example.com/g")?))?;
+        store.optimize()?;
+    }
+    let other_graph = NamedNodeRef::new("http://example.com/h")?;
+    store.insert(QuadRef::new(EX, EX, EX, other_graph))?;
+    assert!(store.remove_named_graph(other_graph)?);
+    store.clear_graph(NamedNodeRef::new("http://example.com/g")?)?;
+    store.validate()?;
+    Ok((snapshot.len()?, store.len()?, store.changes(0).count()))
+}
+
+#[test]
+fn test_custom_backend() -> Result<(), Box<dyn Error>> {
+    let backend = MemoryBackend::default();
+    let store = Store::open_with_backend(backend.clone())?;
+    assert_eq!(exercise_store(&store)?, exercise_store(&Store::new()?)?);
+    #[cfg(not(target_family = "wasm"))]
+    assert!(store.backup(TempDir::default()).is_err());
+    let content = store.iter().collect::<Result<Vec<_>, _>>()?;
+    drop(store);
+
+    // The content is kept by the backend
+    let store = Store::open_with_backend(backend)?;
+    store.validate()?;
+    assert_eq!(store.iter().collect::<Result<Vec<_>, _>>()?, content);
+    assert!(store.contains(QuadRef::new(EX, EX, BIG, EX))?);
+    Ok(())
+}
+
 #[cfg(target_os = "linux")]
 fn reset_dir(dir: &str) -> Result<(), Box<dyn Error>> {
     assert!(Command::new("git")
@@ -537,6 +2399,8 @@ impl Drop for TempDir {
     fn drop(&mut self) {
         if self.0.is_dir() {
             remove_dir_all(&self.0).unwrap();
+        } else if self.0.is_file() {
+            remove_file(&self.0).unwrap();
         }
     }
 }
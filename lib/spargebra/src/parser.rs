@@ -31,6 +31,80 @@ pub fn parse_update(update: &str, base_iri: Option<&str>) -> Result<Update, Spar
     })
 }
 
+/// A SPARQL parser.
+///
+/// It allows to declare [custom aggregate functions](https://www.w3.org/TR/sparql11-query/#aggregateExample2):
+/// a function call to one of them is parsed as an aggregation even without the `DISTINCT` keyword.
+///
+/// ```
+/// use oxrdf::NamedNode;
+/// use spargebra::SparqlParser;
+///
+/// let query = SparqlParser::new()
+///     .with_custom_aggregate_function(NamedNode::new("http://example.com/median")?)
+///     .parse_query("SELECT (<http://example.com/median>(?v) AS ?m) WHERE { ?s ?p ?v }")?;
+/// assert!(query
+///     .to_sse()
+///     .contains("(group () (((<http://example.com/median> ?v)"));
+/// # Result::<_, Box<dyn std::error::Error>>::Ok(())
+/// ```
+#[derive(Clone, Default)]
+#[must_use]
+pub struct SparqlParser {
+    base_iri: Option<Iri<String>>,
+    custom_aggregate_functions: HashSet<NamedNode>,
+}
+
+impl SparqlParser {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Provides an IRI that could be used to resolve the query relative IRIs.
+    #[inline]
+    pub fn with_base_iri(mut self, base_iri: impl Into<String>) -> Result<Self, IriParseError> {
+        self.base_iri = Some(Iri::parse(base_iri.into())?);
+        Ok(self)
+    }
+
+    /// Declares a custom aggregate function.
+    #[inline]
+    pub fn with_custom_aggregate_function(mut self, name: impl Into<NamedNode>) -> Self {
+        self.custom_aggregate_functions.insert(name.into());
+        self
+    }
+
+    /// Parses a SPARQL query.
+    pub fn parse_query(&self, query: &str) -> Result<Query, SparqlSyntaxError> {
+        let mut state = self.state();
+        parser::QueryUnit(query, &mut state)
+            .map_err(|e| SparqlSyntaxError(ParseErrorKind::Syntax(e)))
+    }
+
+    /// Parses a SPARQL update.
+    pub fn parse_update(&self, update: &str) -> Result<Update, SparqlSyntaxError> {
+        let mut state = self.state();
+        let operations = parser::UpdateInit(update, &mut state)
+            .map_err(|e| SparqlSyntaxError(ParseErrorKind::Syntax(e)))?;
+        Ok(Update {
+            operations,
+            base_iri: state.base_iri,
+        })
+    }
+
+    fn state(&self) -> ParserState {
+        ParserState {
+            base_iri: self.base_iri.clone(),
+            namespaces: HashMap::default(),
+            used_bnodes: HashSet::default(),
+            currently_used_bnodes: HashSet::default(),
+            aggregates: Vec::new(),
+            custom_aggregate_functions: self.custom_aggregate_functions.clone(),
+        }
+    }
+}
+
 /// Error returned during SPARQL parsing.
 #[derive(Debug, thiserror::Error)]
 #[error(transparent)]
@@ -666,6 +740,7 @@ pub struct ParserState {
     used_bnodes: HashSet<BlankNode>,
     currently_used_bnodes: HashSet<BlankNode>,
     aggregates: Vec<Vec<(Variable, AggregateExpression)>>,
+    custom_aggregate_functions: HashSet<NamedNode>,
 }
 
 impl ParserState {
@@ -683,6 +758,7 @@ impl ParserState {
             used_bnodes: HashSet::default(),
             currently_used_bnodes: HashSet::default(),
             aggregates: Vec::new(),
+            custom_aggregate_functions: HashSet::default(),
         })
     }
 
@@ -1906,10 +1982,16 @@ parser! {
             name:iri() _ "(" _ i("DISTINCT") _ expr:Expression() _ ")" { AggregateExpression::FunctionCall { name: AggregateFunction::Custom(name), expr, distinct: true } } /
             name:iri() _ "(" _ expr:Expression() _ ")" { AggregateExpression::FunctionCall { name: AggregateFunction::Custom(name), expr, distinct: false } }
 
-        rule iriOrFunction() -> Expression = i: iri() _ a: ArgList()? {
+        rule iriOrFunction() -> Expression = name:iri() _ "(" _ i("DISTINCT") _ expr:Expression() _ ")" {?
+            state.new_aggregation(AggregateExpression::FunctionCall { name: AggregateFunction::Custom(name), expr, distinct: true }).map(Into::into)
+        } / i: iri() _ a: ArgList()? {?
             match a {
-                Some(a) => Expression::FunctionCall(Function::Custom(i), a),
-                None => i.into()
+                Some(a) if state.custom_aggregate_functions.contains(&i) => {
+                    let [expr] = <[Expression; 1]>::try_from(a).map_err(|_| "Custom aggregate functions take exactly one argument")?;
+                    state.new_aggregation(AggregateExpression::FunctionCall { name: AggregateFunction::Custom(i), expr, distinct: false }).map(Into::into)
+                }
+                Some(a) => Ok(Expression::FunctionCall(Function::Custom(i), a)),
+                None => Ok(i.into())
             }
         }
 
@@ -709,6 +709,15 @@ pub enum GraphPattern {
         start: usize,
         length: Option<usize>,
     },
+    /// [OrderBy](https://www.w3.org/TR/sparql11-query/#defn_algOrdered) followed by a [Slice](https://www.w3.org/TR/sparql11-query/#defn_algSlice) with a length.
+    ///
+    /// It allows to only keep the `start + length` first solutions in memory while sorting.
+    TopK {
+        inner: Box<Self>,
+        expression: Vec<OrderExpression>,
+        start: usize,
+        length: usize,
+    },
     /// [Group](https://www.w3.org/TR/sparql11-query/#aggregateAlgebra).
     Group {
         inner: Box<Self>,
@@ -989,6 +998,26 @@ impl GraphPattern {
         }
     }
 
+    pub fn top_k(
+        inner: Self,
+        expression: Vec<OrderExpression>,
+        start: usize,
+        length: usize,
+    ) -> Self {
+        if inner.is_empty() || length == 0 {
+            return Self::empty();
+        }
+        if expression.is_empty() {
+            return Self::slice(inner, start, Some(length));
+        }
+        Self::TopK {
+            inner: Box::new(inner),
+            expression,
+            start,
+            length,
+        }
+    }
+
     pub fn group(
         inner: Self,
         variables: Vec<Variable>,
@@ -1089,7 +1118,8 @@ impl GraphPattern {
             Self::OrderBy { inner, .. }
             | Self::Distinct { inner }
             | Self::Reduced { inner }
-            | Self::Slice { inner, .. } => inner.lookup_used_variables(callback),
+            | Self::Slice { inner, .. }
+            | Self::TopK { inner, .. } => inner.lookup_used_variables(callback),
             Self::Service { inner, name, .. } => {
                 if let NamedNodePattern::Variable(v) = name {
                     callback(v);
@@ -1468,6 +1498,19 @@ impl From<&GraphPattern> for AlGraphPattern {
                 start: *start,
                 length: *length,
             },
+            GraphPattern::TopK {
+                inner,
+                expression,
+                start,
+                length,
+            } => Self::Slice {
+                inner: Box::new(Self::OrderBy {
+                    inner: Box::new(inner.as_ref().into()),
+                    expression: expression.iter().map(Into::into).collect(),
+                }),
+                start: *start,
+                length: Some(*length),
+            },
             GraphPattern::Group {
                 inner,
                 variables,
@@ -1,6 +1,7 @@
 use crate::algebra::{
     Expression, GraphPattern, JoinAlgorithm, LeftJoinAlgorithm, MinusAlgorithm, OrderExpression,
 };
+use crate::statistics::{NoStatistics, StatisticsProvider, TripleStatistics};
 use crate::type_inference::{
     infer_expression_type, infer_graph_pattern_types, VariableType, VariableTypes,
 };
@@ -13,8 +14,16 @@ pub struct Optimizer;
 
 impl Optimizer {
     pub fn optimize_graph_pattern(pattern: GraphPattern) -> GraphPattern {
+        Self::optimize_graph_pattern_with_statistics(pattern, &NoStatistics)
+    }
+
+    /// Optimizes the graph pattern using the given [`StatisticsProvider`] to estimate the size of the triple patterns.
+    pub fn optimize_graph_pattern_with_statistics(
+        pattern: GraphPattern,
+        statistics: &dyn StatisticsProvider,
+    ) -> GraphPattern {
         let pattern = Self::normalize_pattern(pattern, &VariableTypes::default());
-        let pattern = Self::reorder_joins(pattern, &VariableTypes::default());
+        let pattern = Self::reorder_joins(pattern, &VariableTypes::default(), statistics);
         Self::push_filters(pattern, Vec::new(), &VariableTypes::default())
     }
 
@@ -153,6 +162,31 @@ impl Optimizer {
                 start,
                 length,
             } => GraphPattern::slice(Self::normalize_pattern(*inner, input_types), start, length),
+            GraphPattern::TopK {
+                inner,
+                expression,
+                start,
+                length,
+            } => {
+                let inner = Self::normalize_pattern(*inner, input_types);
+                let inner_types = infer_graph_pattern_types(&inner, input_types.clone());
+                GraphPattern::top_k(
+                    inner,
+                    expression
+                        .into_iter()
+                        .map(|e| match e {
+                            OrderExpression::Asc(e) => {
+                                OrderExpression::Asc(Self::normalize_expression(e, &inner_types))
+                            }
+                            OrderExpression::Desc(e) => {
+                                OrderExpression::Desc(Self::normalize_expression(e, &inner_types))
+                            }
+                        })
+                        .collect(),
+                    start,
+                    length,
+                )
+            }
             GraphPattern::Group {
                 inner,
                 variables,
@@ -443,6 +477,20 @@ impl Optimizer {
                 ),
                 Expression::and_all(filters),
             ),
+            GraphPattern::TopK {
+                inner,
+                expression,
+                start,
+                length,
+            } => GraphPattern::filter(
+                GraphPattern::top_k(
+                    Self::push_filters(*inner, Vec::new(), input_types),
+                    expression,
+                    start,
+                    length,
+                ),
+                Expression::and_all(filters),
+            ),
             GraphPattern::Distinct { inner } => {
                 GraphPattern::distinct(Self::push_filters(*inner, filters, input_types))
             }
@@ -479,7 +527,11 @@ impl Optimizer {
         }
     }
 
-    fn reorder_joins(pattern: GraphPattern, input_types: &VariableTypes) -> GraphPattern {
+    fn reorder_joins(
+        pattern: GraphPattern,
+        input_types: &VariableTypes,
+        statistics: &dyn StatisticsProvider,
+    ) -> GraphPattern {
         match pattern {
             GraphPattern::QuadPattern { .. }
             | GraphPattern::Path { .. }
@@ -512,7 +564,12 @@ impl Optimizer {
                     .enumerate()
                     .filter(|(_, v)| **v)
                     .map(|(i, _)| i)
-                    .min_by_key(|i| estimate_graph_pattern_size(&to_reorder[*i], input_types))
+                    .min_by_key(|i| {
+                        (
+                            is_waiting_for_bound_object(&to_reorder[*i], input_types, statistics),
+                            estimate_graph_pattern_size(&to_reorder[*i], input_types, statistics),
+                        )
+                    })
                 {
                     not_yet_reordered_ids[next_entry_id] = false; // It's now done
                     let mut output = to_reorder[next_entry_id].clone();
@@ -525,6 +582,11 @@ impl Optimizer {
                         .map(|(i, _)| i)
                         .filter(|i| {
                             has_common_variables(&output_types, &to_reorder_types[*i], input_types)
+                                && !is_waiting_for_bound_object(
+                                    &to_reorder[*i],
+                                    &output_types,
+                                    statistics,
+                                )
                         })
                         .min_by_key(|i| {
                             // Estimation of the join cost
@@ -540,6 +602,7 @@ impl Optimizer {
                                     &output_types,
                                     &to_reorder[*i],
                                     input_types,
+                                    statistics,
                                 )
                             } else {
                                 estimate_join_cost(
@@ -553,6 +616,7 @@ impl Optimizer {
                                         ),
                                     },
                                     input_types,
+                                    statistics,
                                 )
                             }
                         })
@@ -604,8 +668,8 @@ impl Optimizer {
                             &infer_graph_pattern_types(&right, input_types.clone()),
                             input_types,
                         );
-                        if estimate_graph_pattern_size(&left, input_types)
-                            <= estimate_graph_pattern_size(&right, input_types)
+                        if estimate_graph_pattern_size(&left, input_types, statistics)
+                            <= estimate_graph_pattern_size(&right, input_types, statistics)
                         {
                             GraphPattern::join(
                                 left,
@@ -626,8 +690,8 @@ impl Optimizer {
             GraphPattern::Lateral { left, right } => {
                 let left_types = infer_graph_pattern_types(&left, input_types.clone());
                 GraphPattern::lateral(
-                    Self::reorder_joins(*left, input_types),
-                    Self::reorder_joins(*right, &left_types),
+                    Self::reorder_joins(*left, input_types, statistics),
+                    Self::reorder_joins(*right, &left_types, statistics),
                 )
             }
             GraphPattern::LeftJoin {
@@ -636,9 +700,9 @@ impl Optimizer {
                 expression,
                 ..
             } => {
-                let left = Self::reorder_joins(*left, input_types);
+                let left = Self::reorder_joins(*left, input_types, statistics);
                 let left_types = infer_graph_pattern_types(&left, input_types.clone());
-                let right = Self::reorder_joins(*right, input_types);
+                let right = Self::reorder_joins(*right, input_types, statistics);
                 let right_types = infer_graph_pattern_types(&right, input_types.clone());
                 #[cfg(feature = "sep-0006")]
                 {
@@ -666,9 +730,9 @@ impl Optimizer {
                 )
             }
             GraphPattern::Minus { left, right, .. } => {
-                let left = Self::reorder_joins(*left, input_types);
+                let left = Self::reorder_joins(*left, input_types, statistics);
                 let left_types = infer_graph_pattern_types(&left, input_types.clone());
-                let right = Self::reorder_joins(*right, input_types);
+                let right = Self::reorder_joins(*right, input_types, statistics);
                 let right_types = infer_graph_pattern_types(&right, input_types.clone());
                 GraphPattern::minus(
                     left,
@@ -683,46 +747,97 @@ impl Optimizer {
                 expression,
                 variable,
             } => GraphPattern::extend(
-                Self::reorder_joins(*inner, input_types),
+                Self::reorder_joins(*inner, input_types, statistics),
                 variable,
                 expression,
             ),
-            GraphPattern::Filter { inner, expression } => {
-                GraphPattern::filter(Self::reorder_joins(*inner, input_types), expression)
-            }
+            GraphPattern::Filter { inner, expression } => GraphPattern::filter(
+                Self::reorder_joins(*inner, input_types, statistics),
+                expression,
+            ),
             GraphPattern::Union { inner } => GraphPattern::union_all(
                 inner
                     .into_iter()
-                    .map(|c| Self::reorder_joins(c, input_types)),
+                    .map(|c| Self::reorder_joins(c, input_types, statistics)),
             ),
             GraphPattern::Slice {
                 inner,
                 start,
                 length,
-            } => GraphPattern::slice(Self::reorder_joins(*inner, input_types), start, length),
+            } => {
+                let child = Self::reorder_joins(*inner, input_types, statistics);
+                let Some(length) = length else {
+                    return GraphPattern::slice(child, start, None);
+                };
+                // We fuse the sort and the slice to only keep the first solutions in memory
+                match child {
+                    GraphPattern::OrderBy {
+                        inner: sorted,
+                        expression,
+                    } => GraphPattern::top_k(*sorted, expression, start, length),
+                    GraphPattern::Project {
+                        inner: projected,
+                        variables,
+                    } => match *projected {
+                        GraphPattern::OrderBy {
+                            inner: sorted,
+                            expression,
+                        } => GraphPattern::project(
+                            GraphPattern::top_k(*sorted, expression, start, length),
+                            variables,
+                        ),
+                        other => GraphPattern::slice(
+                            GraphPattern::Project {
+                                inner: Box::new(other),
+                                variables,
+                            },
+                            start,
+                            Some(length),
+                        ),
+                    },
+                    other => GraphPattern::slice(other, start, Some(length)),
+                }
+            }
+            GraphPattern::TopK {
+                inner,
+                expression,
+                start,
+                length,
+            } => GraphPattern::top_k(
+                Self::reorder_joins(*inner, input_types, statistics),
+                expression,
+                start,
+                length,
+            ),
             GraphPattern::Distinct { inner } => {
-                GraphPattern::distinct(Self::reorder_joins(*inner, input_types))
+                GraphPattern::distinct(Self::reorder_joins(*inner, input_types, statistics))
             }
             GraphPattern::Reduced { inner } => {
-                GraphPattern::reduced(Self::reorder_joins(*inner, input_types))
-            }
-            GraphPattern::Project { inner, variables } => {
-                GraphPattern::project(Self::reorder_joins(*inner, input_types), variables)
-            }
-            GraphPattern::OrderBy { inner, expression } => {
-                GraphPattern::order_by(Self::reorder_joins(*inner, input_types), expression)
+                GraphPattern::reduced(Self::reorder_joins(*inner, input_types, statistics))
             }
+            GraphPattern::Project { inner, variables } => GraphPattern::project(
+                Self::reorder_joins(*inner, input_types, statistics),
+                variables,
+            ),
+            GraphPattern::OrderBy { inner, expression } => GraphPattern::order_by(
+                Self::reorder_joins(*inner, input_types, statistics),
+                expression,
+            ),
             GraphPattern::Service {
                 inner,
                 name,
                 silent,
-            } => GraphPattern::service(Self::reorder_joins(*inner, input_types), name, silent),
+            } => GraphPattern::service(
+                Self::reorder_joins(*inner, input_types, statistics),
+                name,
+                silent,
+            ),
             GraphPattern::Group {
                 inner,
                 variables,
                 aggregates,
             } => GraphPattern::group(
-                Self::reorder_joins(*inner, input_types),
+                Self::reorder_joins(*inner, input_types, statistics),
                 variables,
                 aggregates,
             ),
@@ -798,6 +913,7 @@ fn is_fit_for_for_loop_join(
         | GraphPattern::Minus { .. }
         | GraphPattern::Service { .. }
         | GraphPattern::OrderBy { .. }
+        | GraphPattern::TopK { .. }
         | GraphPattern::Distinct { .. }
         | GraphPattern::Reduced { .. }
         | GraphPattern::Slice { .. }
@@ -891,18 +1007,25 @@ fn join_key_variables(
         .collect()
 }
 
-fn estimate_graph_pattern_size(pattern: &GraphPattern, input_types: &VariableTypes) -> usize {
+fn estimate_graph_pattern_size(
+    pattern: &GraphPattern,
+    input_types: &VariableTypes,
+    statistics: &dyn StatisticsProvider,
+) -> usize {
     match pattern {
         GraphPattern::Values { bindings, .. } => bindings.len(),
         GraphPattern::QuadPattern {
             subject,
             predicate,
             object,
-            ..
-        } => estimate_triple_pattern_size(
+            graph_name,
+        } => estimate_quad_pattern_size(
             is_term_pattern_bound(subject, input_types),
+            predicate,
             is_named_node_pattern_bound(predicate, input_types),
             is_term_pattern_bound(object, input_types),
+            graph_name.as_ref(),
+            statistics,
         ),
         GraphPattern::Path {
             subject,
@@ -913,12 +1036,13 @@ fn estimate_graph_pattern_size(pattern: &GraphPattern, input_types: &VariableTyp
             is_term_pattern_bound(subject, input_types),
             path,
             is_term_pattern_bound(object, input_types),
+            statistics,
         ),
         GraphPattern::Join {
             left,
             right,
             algorithm,
-        } => estimate_join_cost(left, right, algorithm, input_types),
+        } => estimate_join_cost(left, right, algorithm, input_types, statistics),
         GraphPattern::LeftJoin {
             left,
             right,
@@ -926,13 +1050,14 @@ fn estimate_graph_pattern_size(pattern: &GraphPattern, input_types: &VariableTyp
             ..
         } => match algorithm {
             LeftJoinAlgorithm::HashBuildRightProbeLeft { keys } => {
-                let left_size = estimate_graph_pattern_size(left, input_types);
+                let left_size = estimate_graph_pattern_size(left, input_types, statistics);
                 max(
                     left_size,
                     left_size
                         .saturating_mul(estimate_graph_pattern_size(
                             right,
                             &infer_graph_pattern_types(right, input_types.clone()),
+                            statistics,
                         ))
                         .saturating_div(1_000_usize.saturating_pow(keys.len().try_into().unwrap())),
                 )
@@ -944,12 +1069,15 @@ fn estimate_graph_pattern_size(pattern: &GraphPattern, input_types: &VariableTyp
             &infer_graph_pattern_types(left, input_types.clone()),
             right,
             input_types,
+            statistics,
         ),
         GraphPattern::Union { inner } => inner
             .iter()
-            .map(|inner| estimate_graph_pattern_size(inner, input_types))
+            .map(|inner| estimate_graph_pattern_size(inner, input_types, statistics))
             .fold(0, usize::saturating_add),
-        GraphPattern::Minus { left, .. } => estimate_graph_pattern_size(left, input_types),
+        GraphPattern::Minus { left, .. } => {
+            estimate_graph_pattern_size(left, input_types, statistics)
+        }
         GraphPattern::Filter { inner, .. }
         | GraphPattern::Extend { inner, .. }
         | GraphPattern::OrderBy { inner, .. }
@@ -957,19 +1085,25 @@ fn estimate_graph_pattern_size(pattern: &GraphPattern, input_types: &VariableTyp
         | GraphPattern::Distinct { inner, .. }
         | GraphPattern::Reduced { inner, .. }
         | GraphPattern::Group { inner, .. }
-        | GraphPattern::Service { inner, .. } => estimate_graph_pattern_size(inner, input_types),
+        | GraphPattern::Service { inner, .. } => {
+            estimate_graph_pattern_size(inner, input_types, statistics)
+        }
         GraphPattern::Slice {
             inner,
             start,
             length,
         } => {
-            let inner = estimate_graph_pattern_size(inner, input_types);
+            let inner = estimate_graph_pattern_size(inner, input_types, statistics);
             if let Some(length) = length {
                 min(inner, *length - *start)
             } else {
                 inner
             }
         }
+        GraphPattern::TopK { inner, length, .. } => min(
+            estimate_graph_pattern_size(inner, input_types, statistics),
+            *length,
+        ),
     }
 }
 
@@ -978,11 +1112,12 @@ fn estimate_join_cost(
     right: &GraphPattern,
     algorithm: &JoinAlgorithm,
     input_types: &VariableTypes,
+    statistics: &dyn StatisticsProvider,
 ) -> usize {
     match algorithm {
         JoinAlgorithm::HashBuildLeftProbeRight { keys } => {
-            estimate_graph_pattern_size(left, input_types)
-                .saturating_mul(estimate_graph_pattern_size(right, input_types))
+            estimate_graph_pattern_size(left, input_types, statistics)
+                .saturating_mul(estimate_graph_pattern_size(right, input_types, statistics))
                 .saturating_div(1_000_usize.saturating_pow(keys.len().try_into().unwrap()))
         }
     }
@@ -992,9 +1127,50 @@ fn estimate_lateral_cost(
     left_types: &VariableTypes,
     right: &GraphPattern,
     input_types: &VariableTypes,
+    statistics: &dyn StatisticsProvider,
+) -> usize {
+    estimate_graph_pattern_size(left, input_types, statistics)
+        .saturating_mul(estimate_graph_pattern_size(right, left_types, statistics))
+}
+
+fn estimate_quad_pattern_size(
+    subject_bound: bool,
+    predicate: &NamedNodePattern,
+    predicate_bound: bool,
+    object_bound: bool,
+    graph_name: Option<&NamedNodePattern>,
+    statistics: &dyn StatisticsProvider,
+) -> usize {
+    let triple_statistics = match (predicate, graph_name) {
+        (NamedNodePattern::NamedNode(predicate), _) => statistics.predicate_statistics(predicate),
+        (NamedNodePattern::Variable(_), _) if predicate_bound => None,
+        (NamedNodePattern::Variable(_), None) => statistics.graph_statistics(None),
+        (NamedNodePattern::Variable(_), Some(NamedNodePattern::NamedNode(graph_name))) => {
+            statistics.graph_statistics(Some(graph_name))
+        }
+        (NamedNodePattern::Variable(_), Some(NamedNodePattern::Variable(_))) => None,
+    };
+    if let Some(triple_statistics) = triple_statistics {
+        estimate_triple_pattern_size_from_statistics(subject_bound, object_bound, triple_statistics)
+    } else {
+        estimate_triple_pattern_size(subject_bound, predicate_bound, object_bound)
+    }
+}
+
+fn estimate_triple_pattern_size_from_statistics(
+    subject_bound: bool,
+    object_bound: bool,
+    statistics: TripleStatistics,
 ) -> usize {
-    estimate_graph_pattern_size(left, input_types)
-        .saturating_mul(estimate_graph_pattern_size(right, left_types))
+    // We assume that the values are uniformly distributed
+    let mut size = statistics.count;
+    if subject_bound {
+        size /= max(statistics.distinct_subjects, 1);
+    }
+    if object_bound {
+        size /= max(statistics.distinct_objects, 1);
+    }
+    max(size, 1).try_into().unwrap_or(usize::MAX)
 }
 
 fn estimate_triple_pattern_size(
@@ -1014,28 +1190,45 @@ fn estimate_triple_pattern_size(
     }
 }
 
-fn estimate_path_size(start_bound: bool, path: &PropertyPathExpression, end_bound: bool) -> usize {
+fn estimate_path_size(
+    start_bound: bool,
+    path: &PropertyPathExpression,
+    end_bound: bool,
+    statistics: &dyn StatisticsProvider,
+) -> usize {
     match path {
-        PropertyPathExpression::NamedNode(_) => {
-            estimate_triple_pattern_size(start_bound, true, end_bound)
+        PropertyPathExpression::NamedNode(predicate) => {
+            if let Some(triple_statistics) = statistics.predicate_statistics(predicate) {
+                estimate_triple_pattern_size_from_statistics(
+                    start_bound,
+                    end_bound,
+                    triple_statistics,
+                )
+            } else {
+                estimate_triple_pattern_size(start_bound, true, end_bound)
+            }
+        }
+        PropertyPathExpression::Reverse(p) => {
+            estimate_path_size(end_bound, p, start_bound, statistics)
         }
-        PropertyPathExpression::Reverse(p) => estimate_path_size(end_bound, p, start_bound),
         PropertyPathExpression::Sequence(a, b) => {
             // We do a for loop join in the best direction
             min(
-                estimate_path_size(start_bound, a, false)
-                    .saturating_mul(estimate_path_size(true, b, end_bound)),
-                estimate_path_size(start_bound, a, true)
-                    .saturating_mul(estimate_path_size(false, b, end_bound)),
+                estimate_path_size(start_bound, a, false, statistics)
+                    .saturating_mul(estimate_path_size(true, b, end_bound, statistics)),
+                estimate_path_size(start_bound, a, true, statistics)
+                    .saturating_mul(estimate_path_size(false, b, end_bound, statistics)),
             )
         }
-        PropertyPathExpression::Alternative(a, b) => estimate_path_size(start_bound, a, end_bound)
-            .saturating_add(estimate_path_size(start_bound, b, end_bound)),
+        PropertyPathExpression::Alternative(a, b) => {
+            estimate_path_size(start_bound, a, end_bound, statistics)
+                .saturating_add(estimate_path_size(start_bound, b, end_bound, statistics))
+        }
         PropertyPathExpression::ZeroOrMore(p) => {
             if start_bound && end_bound {
                 1
             } else if start_bound || end_bound {
-                estimate_path_size(start_bound, p, end_bound).saturating_mul(1000)
+                estimate_path_size(start_bound, p, end_bound, statistics).saturating_mul(1000)
             } else {
                 1_000_000_000
             }
@@ -1044,14 +1237,14 @@ fn estimate_path_size(start_bound: bool, path: &PropertyPathExpression, end_boun
             if start_bound && end_bound {
                 1
             } else {
-                estimate_path_size(start_bound, p, end_bound).saturating_mul(1000)
+                estimate_path_size(start_bound, p, end_bound, statistics).saturating_mul(1000)
             }
         }
         PropertyPathExpression::ZeroOrOne(p) => {
             if start_bound && end_bound {
                 1
             } else if start_bound || end_bound {
-                estimate_path_size(start_bound, p, end_bound)
+                estimate_path_size(start_bound, p, end_bound, statistics)
             } else {
                 1_000_000_000
             }
@@ -1062,6 +1255,24 @@ fn estimate_path_size(start_bound: bool, path: &PropertyPathExpression, end_boun
     }
 }
 
+/// Returns if the pattern is a property function call whose object is not bound yet
+fn is_waiting_for_bound_object(
+    pattern: &GraphPattern,
+    input_types: &VariableTypes,
+    statistics: &dyn StatisticsProvider,
+) -> bool {
+    if let GraphPattern::QuadPattern {
+        predicate: NamedNodePattern::NamedNode(predicate),
+        object,
+        ..
+    } = pattern
+    {
+        statistics.requires_bound_object(predicate) && !is_term_pattern_bound(object, input_types)
+    } else {
+        false
+    }
+}
+
 fn is_term_pattern_bound(pattern: &GroundTermPattern, input_types: &VariableTypes) -> bool {
     match pattern {
         GroundTermPattern::NamedNode(_) | GroundTermPattern::Literal(_) => true,
@@ -1081,3 +1292,86 @@ fn is_named_node_pattern_bound(pattern: &NamedNodePattern, input_types: &Variabl
         NamedNodePattern::Variable(v) => !input_types.get(v).undef,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxrdf::NamedNode;
+
+    struct PredicateStatistics(Vec<(NamedNode, u64)>);
+
+    impl StatisticsProvider for PredicateStatistics {
+        fn predicate_statistics(&self, predicate: &NamedNode) -> Option<TripleStatistics> {
+            Some(TripleStatistics {
+                count: self
+                    .0
+                    .iter()
+                    .find(|(p, _)| p == predicate)
+                    .map_or(0, |(_, count)| *count),
+                distinct_subjects: 1,
+                distinct_objects: 1,
+            })
+        }
+
+        fn graph_statistics(&self, _: Option<&NamedNode>) -> Option<TripleStatistics> {
+            None
+        }
+    }
+
+    fn pattern(predicate: &NamedNode, object: &str) -> GraphPattern {
+        GraphPattern::QuadPattern {
+            subject: Variable::new_unchecked("s").into(),
+            predicate: predicate.clone().into(),
+            object: Variable::new_unchecked(object).into(),
+            graph_name: None,
+        }
+    }
+
+    fn first_joined_pattern(pattern: GraphPattern) -> GraphPattern {
+        if let GraphPattern::Join { left, .. } = pattern {
+            *left
+        } else {
+            pattern
+        }
+    }
+
+    #[test]
+    fn join_order_follows_statistics() {
+        let p1 = NamedNode::new_unchecked("http://example.com/p1");
+        let p2 = NamedNode::new_unchecked("http://example.com/p2");
+        let p3 = NamedNode::new_unchecked("http://example.com/p3");
+        let join = GraphPattern::join(
+            pattern(&p1, "o1"),
+            pattern(&p2, "o2"),
+            JoinAlgorithm::default(),
+        );
+
+        assert_eq!(
+            first_joined_pattern(Optimizer::optimize_graph_pattern_with_statistics(
+                join.clone(),
+                &PredicateStatistics(vec![(p1.clone(), 10), (p2.clone(), 1000)])
+            )),
+            pattern(&p1, "o1")
+        );
+        assert_eq!(
+            first_joined_pattern(Optimizer::optimize_graph_pattern_with_statistics(
+                join,
+                &PredicateStatistics(vec![(p1.clone(), 1000), (p2.clone(), 10)])
+            )),
+            pattern(&p2, "o2")
+        );
+
+        // Predicates without statistics are not used
+        assert_eq!(
+            first_joined_pattern(Optimizer::optimize_graph_pattern_with_statistics(
+                GraphPattern::join(
+                    pattern(&p1, "o1"),
+                    pattern(&p3, "o3"),
+                    JoinAlgorithm::default(),
+                ),
+                &PredicateStatistics(vec![(p1, 10)])
+            )),
+            pattern(&p3, "o3")
+        );
+    }
+}
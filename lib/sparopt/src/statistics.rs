@@ -0,0 +1,49 @@
+use oxrdf::NamedNode;
+
+/// Cardinality statistics about a set of triples.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, Hash)]
+pub struct TripleStatistics {
+    /// The number of triples.
+    pub count: u64,
+    /// The number of distinct subjects of the triples.
+    pub distinct_subjects: u64,
+    /// The number of distinct objects of the triples.
+    pub distinct_objects: u64,
+}
+
+/// Provides statistics about the queried data to the [`Optimizer`](crate::Optimizer).
+///
+/// The statistics are only used to estimate the cost of the possible query plans.
+/// They are allowed to be approximate or outdated.
+/// If no statistics are available `None` should be returned and the optimizer falls back to its built-in heuristics.
+/// If statistics are available but no triple matches, `Some(TripleStatistics::default())` should be returned.
+pub trait StatisticsProvider {
+    /// Returns statistics about the triples using the given predicate in all the graphs.
+    fn predicate_statistics(&self, predicate: &NamedNode) -> Option<TripleStatistics>;
+
+    /// Returns statistics about the triples of the given graph.
+    ///
+    /// `None` is the default graph.
+    fn graph_statistics(&self, graph_name: Option<&NamedNode>) -> Option<TripleStatistics>;
+
+    /// Returns if the triple patterns using the given predicate can only be evaluated once their object is bound.
+    ///
+    /// It is the case of property functions like full-text search.
+    /// The optimizer then only joins these patterns after the patterns binding their object.
+    fn requires_bound_object(&self, _predicate: &NamedNode) -> bool {
+        false
+    }
+}
+
+/// A [`StatisticsProvider`] that does not provide any statistics.
+pub(crate) struct NoStatistics;
+
+impl StatisticsProvider for NoStatistics {
+    fn predicate_statistics(&self, _: &NamedNode) -> Option<TripleStatistics> {
+        None
+    }
+
+    fn graph_statistics(&self, _: Option<&NamedNode>) -> Option<TripleStatistics> {
+        None
+    }
+}
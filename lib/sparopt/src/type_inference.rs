@@ -89,7 +89,8 @@ pub fn infer_graph_pattern_types(
         GraphPattern::Distinct { inner }
         | GraphPattern::Reduced { inner }
         | GraphPattern::OrderBy { inner, .. }
-        | GraphPattern::Slice { inner, .. } => infer_graph_pattern_types(inner, types),
+        | GraphPattern::Slice { inner, .. }
+        | GraphPattern::TopK { inner, .. } => infer_graph_pattern_types(inner, types),
         GraphPattern::Group {
             inner,
             variables,
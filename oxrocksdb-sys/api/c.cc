@@ -1,16 +1,21 @@
 #include "c.h"
 
 #include <rocksdb/db.h>
+#include <rocksdb/utilities/backup_engine.h>
 #include <rocksdb/utilities/checkpoint.h>
 #include <rocksdb/utilities/transaction_db.h>
 
 #include <vector>
 
+using ROCKSDB_NAMESPACE::BackupEngine;
+using ROCKSDB_NAMESPACE::BackupEngineOptions;
+using ROCKSDB_NAMESPACE::BackupID;
 using ROCKSDB_NAMESPACE::Checkpoint;
 using ROCKSDB_NAMESPACE::ColumnFamilyDescriptor;
 using ROCKSDB_NAMESPACE::ColumnFamilyHandle;
 using ROCKSDB_NAMESPACE::ColumnFamilyOptions;
 using ROCKSDB_NAMESPACE::CompactRangeOptions;
+using ROCKSDB_NAMESPACE::CreateBackupOptions;
 using ROCKSDB_NAMESPACE::DB;
 using ROCKSDB_NAMESPACE::DBOptions;
 using ROCKSDB_NAMESPACE::FlushOptions;
@@ -19,17 +24,24 @@ using ROCKSDB_NAMESPACE::Iterator;
 using ROCKSDB_NAMESPACE::Options;
 using ROCKSDB_NAMESPACE::PinnableSlice;
 using ROCKSDB_NAMESPACE::ReadOptions;
+using ROCKSDB_NAMESPACE::RestoreOptions;
 using ROCKSDB_NAMESPACE::Slice;
 using ROCKSDB_NAMESPACE::SstFileWriter;
 using ROCKSDB_NAMESPACE::Status;
 using ROCKSDB_NAMESPACE::Transaction;
 using ROCKSDB_NAMESPACE::TransactionDB;
 using ROCKSDB_NAMESPACE::TransactionDBOptions;
+using ROCKSDB_NAMESPACE::TransactionDBWriteOptimizations;
+using ROCKSDB_NAMESPACE::WriteBatch;
 using ROCKSDB_NAMESPACE::WriteOptions;
 using std::vector;
 
 // From RocksDB
 extern "C" {
+struct rocksdb_backup_engine_t {
+  BackupEngine* rep;
+};
+
 struct rocksdb_t {
   DB* rep;
 };
@@ -160,6 +172,24 @@ rocksdb_t* rocksdb_open_for_read_only_column_families_with_status(
   return result;
 }
 
+char** rocksdb_list_column_families_with_status(
+    const rocksdb_options_t* options, const char* name, size_t* lencfs,
+    rocksdb_status_t* statusptr) {
+  vector<std::string> fams;
+  if (SaveStatus(statusptr, DB::ListColumnFamilies(DBOptions(options->rep),
+                                                   std::string(name), &fams))) {
+    *lencfs = 0;
+    return nullptr;
+  }
+  *lencfs = fams.size();
+  char** column_families =
+      static_cast<char**>(malloc(sizeof(char*) * fams.size()));
+  for (size_t i = 0; i < fams.size(); i++) {
+    column_families[i] = strdup(fams[i].c_str());
+  }
+  return column_families;
+}
+
 void rocksdb_try_catch_up_with_primary_with_status(
     rocksdb_t* db, rocksdb_status_t* statusptr) {
   SaveStatus(statusptr, db->rep->TryCatchUpWithPrimary());
@@ -313,6 +343,28 @@ void rocksdb_transactiondb_ingest_external_files_with_status(
   SaveStatus(statusptr, db->rep->IngestExternalFiles(args));
 }
 
+void rocksdb_transactiondb_delete_ranges_cf_with_status(
+    rocksdb_transactiondb_t* txn_db, const rocksdb_writeoptions_t* options,
+    rocksdb_column_family_handle_t* const* column_families,
+    const char* const* start_keys, const size_t* start_keys_len,
+    const char* const* end_keys, const size_t* end_keys_len, size_t num_ranges,
+    rocksdb_status_t* statusptr) {
+  WriteBatch batch;
+  for (size_t i = 0; i < num_ranges; ++i) {
+    Status s = batch.DeleteRange(column_families[i]->rep,
+                                 Slice(start_keys[i], start_keys_len[i]),
+                                 Slice(end_keys[i], end_keys_len[i]));
+    if (!s.ok()) {
+      SaveStatus(statusptr, s);
+      return;
+    }
+  }
+  // Range deletions are not supported by the transaction concurrency control
+  TransactionDBWriteOptimizations optimizations;
+  optimizations.skip_concurrency_control = true;
+  SaveStatus(statusptr, txn_db->rep->Write(options->rep, optimizations, &batch));
+}
+
 void rocksdb_transactiondb_create_checkpoint_with_status(
     rocksdb_transactiondb_t* db, const char* checkpoint_dir,
     rocksdb_status_t* statusptr) {
@@ -327,6 +379,60 @@ void rocksdb_transactiondb_create_checkpoint_with_status(
   delete checkpoint;
 }
 
+rocksdb_backup_engine_t* rocksdb_backup_engine_open_with_status(
+    const rocksdb_options_t* options, const char* path,
+    rocksdb_status_t* statusptr) {
+  BackupEngine* be;
+  if (SaveStatus(statusptr, BackupEngine::Open(options->rep.env,
+                                               BackupEngineOptions(path), &be))) {
+    return nullptr;
+  }
+  rocksdb_backup_engine_t* result = new rocksdb_backup_engine_t;
+  result->rep = be;
+  return result;
+}
+
+uint32_t rocksdb_backup_engine_create_new_backup_flush_with_status(
+    rocksdb_backup_engine_t* be, rocksdb_t* db,
+    unsigned char flush_before_backup, rocksdb_status_t* statusptr) {
+  CreateBackupOptions options;
+  options.flush_before_backup = flush_before_backup;
+  BackupID backup_id = 0;
+  SaveStatus(statusptr, be->rep->CreateNewBackup(options, db->rep, &backup_id));
+  return backup_id;
+}
+
+uint32_t rocksdb_transactiondb_create_new_backup_with_status(
+    rocksdb_backup_engine_t* be, rocksdb_transactiondb_t* db,
+    rocksdb_status_t* statusptr) {
+  CreateBackupOptions options;
+  options.flush_before_backup = true;
+  BackupID backup_id = 0;
+  SaveStatus(statusptr, be->rep->CreateNewBackup(options, db->rep, &backup_id));
+  return backup_id;
+}
+
+void rocksdb_backup_engine_purge_old_backups_with_status(
+    rocksdb_backup_engine_t* be, uint32_t num_backups_to_keep,
+    rocksdb_status_t* statusptr) {
+  SaveStatus(statusptr, be->rep->PurgeOldBackups(num_backups_to_keep));
+}
+
+void rocksdb_backup_engine_verify_backup_with_status(
+    rocksdb_backup_engine_t* be, uint32_t backup_id,
+    rocksdb_status_t* statusptr) {
+  SaveStatus(statusptr, be->rep->VerifyBackup(backup_id, true));
+}
+
+void rocksdb_backup_engine_restore_db_from_backup_with_status(
+    rocksdb_backup_engine_t* be, const char* db_dir, uint32_t backup_id,
+    rocksdb_status_t* statusptr) {
+  SaveStatus(statusptr,
+             be->rep->RestoreDBFromBackup(RestoreOptions(), backup_id,
+                                          std::string(db_dir),
+                                          std::string(db_dir)));
+}
+
 void rocksdb_transaction_commit_with_status(rocksdb_transaction_t* txn,
                                             rocksdb_status_t* statusptr) {
   SaveStatus(statusptr, txn->rep->Commit());
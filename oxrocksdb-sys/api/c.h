@@ -79,6 +79,10 @@ rocksdb_open_for_read_only_column_families_with_status(
     rocksdb_column_family_handle_t** column_family_handles,
     unsigned char error_if_wal_file_exists, rocksdb_status_t* statusptr);
 
+extern ROCKSDB_LIBRARY_API char** rocksdb_list_column_families_with_status(
+    const rocksdb_options_t* options, const char* name, size_t* lencfs,
+    rocksdb_status_t* statusptr);
+
 extern ROCKSDB_LIBRARY_API void rocksdb_try_catch_up_with_primary_with_status(
     rocksdb_t* db, rocksdb_status_t* statusptr);
 
@@ -130,11 +134,47 @@ rocksdb_transactiondb_ingest_external_files_with_status(
     rocksdb_transactiondb_t* db, const rocksdb_ingestexternalfilearg_t* list,
     const size_t list_len, rocksdb_status_t* statusptr);
 
+extern ROCKSDB_LIBRARY_API void rocksdb_transactiondb_delete_ranges_cf_with_status(
+    rocksdb_transactiondb_t* txn_db, const rocksdb_writeoptions_t* options,
+    rocksdb_column_family_handle_t* const* column_families,
+    const char* const* start_keys, const size_t* start_keys_len,
+    const char* const* end_keys, const size_t* end_keys_len, size_t num_ranges,
+    rocksdb_status_t* statusptr);
+
 extern ROCKSDB_LIBRARY_API void
 rocksdb_transactiondb_create_checkpoint_with_status(
     rocksdb_transactiondb_t* db, const char* checkpoint_dir,
     rocksdb_status_t* statusptr);
 
+extern ROCKSDB_LIBRARY_API rocksdb_backup_engine_t*
+rocksdb_backup_engine_open_with_status(const rocksdb_options_t* options,
+                                       const char* path,
+                                       rocksdb_status_t* statusptr);
+
+extern ROCKSDB_LIBRARY_API uint32_t
+rocksdb_backup_engine_create_new_backup_flush_with_status(
+    rocksdb_backup_engine_t* be, rocksdb_t* db,
+    unsigned char flush_before_backup, rocksdb_status_t* statusptr);
+
+extern ROCKSDB_LIBRARY_API uint32_t
+rocksdb_transactiondb_create_new_backup_with_status(
+    rocksdb_backup_engine_t* be, rocksdb_transactiondb_t* db,
+    rocksdb_status_t* statusptr);
+
+extern ROCKSDB_LIBRARY_API void
+rocksdb_backup_engine_purge_old_backups_with_status(
+    rocksdb_backup_engine_t* be, uint32_t num_backups_to_keep,
+    rocksdb_status_t* statusptr);
+
+extern ROCKSDB_LIBRARY_API void rocksdb_backup_engine_verify_backup_with_status(
+    rocksdb_backup_engine_t* be, uint32_t backup_id,
+    rocksdb_status_t* statusptr);
+
+extern ROCKSDB_LIBRARY_API void
+rocksdb_backup_engine_restore_db_from_backup_with_status(
+    rocksdb_backup_engine_t* be, const char* db_dir, uint32_t backup_id,
+    rocksdb_status_t* statusptr);
+
 extern ROCKSDB_LIBRARY_API void rocksdb_transaction_commit_with_status(
     rocksdb_transaction_t* txn, rocksdb_status_t* statusptr);
 
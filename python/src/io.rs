@@ -617,6 +617,31 @@ pub fn allow_threads_unsafe<T>(_py: Python<'_>, f: impl FnOnce() -> T) -> T {
     f()
 }
 
+/// Acquires the GIL, even if it has been released by [`allow_threads_unsafe`]
+///
+/// Contrary to [`Python::with_gil`], it does not rely on the pyo3 GIL count that is not updated by [`allow_threads_unsafe`].
+#[allow(unsafe_code)]
+pub fn with_gil_unsafe<T>(f: impl FnOnce(Python<'_>) -> T) -> T {
+    struct ReleaseGuard {
+        state: pyo3::ffi::PyGILState_STATE,
+    }
+
+    impl Drop for ReleaseGuard {
+        fn drop(&mut self) {
+            // SAFETY: not cloned so called once
+            unsafe {
+                pyo3::ffi::PyGILState_Release(self.state);
+            }
+        }
+    }
+
+    // SAFETY: we have the release part in Drop to make sure it's properly executed
+    let state = unsafe { pyo3::ffi::PyGILState_Ensure() };
+    let _guard = ReleaseGuard { state };
+    // SAFETY: the GIL is held until the guard is dropped
+    f(unsafe { Python::assume_gil_acquired() })
+}
+
 pub fn python_version() -> (u8, u8) {
     static VERSION: OnceLock<(u8, u8)> = OnceLock::new();
     *VERSION.get_or_init(|| {
@@ -2,19 +2,19 @@ use crate::io::*;
 use crate::model::*;
 use crate::store::map_storage_error;
 use oxigraph::io::RdfSerializer;
-use oxigraph::model::Term;
+use oxigraph::model::{NamedNode, Term};
 use oxigraph::sparql::results::{
     FromReadQueryResultsReader, FromReadSolutionsReader, QueryResultsFormat,
     QueryResultsParseError, QueryResultsParser, QueryResultsSerializer,
 };
 use oxigraph::sparql::{
-    EvaluationError, Query, QueryResults, QuerySolution, QuerySolutionIter, QueryTripleIter,
-    Variable,
+    AggregateFunctionAccumulator, EvaluationError, Query, QueryOptions, QueryResults,
+    QuerySolution, QuerySolutionIter, QueryTripleIter, SparqlParser, Variable,
 };
 use pyo3::basic::CompareOp;
 use pyo3::exceptions::{PyRuntimeError, PySyntaxError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::PyBytes;
+use pyo3::types::{PyBytes, PyDict};
 use std::ffi::OsStr;
 use std::io;
 use std::path::{Path, PathBuf};
@@ -26,10 +26,25 @@ pub fn parse_query(
     use_default_graph_as_union: bool,
     default_graph: Option<&PyAny>,
     named_graphs: Option<&PyAny>,
+    custom_aggregate_functions: Option<&PyDict>,
     py: Python<'_>,
 ) -> PyResult<Query> {
-    let mut query = allow_threads_unsafe(py, || Query::parse(query, base_iri))
-        .map_err(|e| map_evaluation_error(e.into()))?;
+    let mut parser = SparqlParser::new();
+    if let Some(base_iri) = base_iri {
+        parser = parser.with_base_iri(base_iri).map_err(|e| {
+            PySyntaxError::new_err(format!("Invalid SPARQL base IRI provided: {e}"))
+        })?;
+    }
+    if let Some(custom_aggregate_functions) = custom_aggregate_functions {
+        for name in custom_aggregate_functions.keys() {
+            parser = parser
+                .with_custom_aggregate_function(NamedNode::from(name.extract::<PyNamedNode>()?));
+        }
+    }
+    let mut query = Query::from(
+        allow_threads_unsafe(py, || parser.parse_query(query))
+            .map_err(|e| map_evaluation_error(e.into()))?,
+    );
 
     if use_default_graph_as_union && default_graph.is_some() {
         return Err(PyValueError::new_err(
@@ -71,6 +86,91 @@ pub fn parse_query(
     Ok(query)
 }
 
+pub fn query_options(custom_aggregate_functions: Option<&PyDict>) -> PyResult<QueryOptions> {
+    let mut options = QueryOptions::default();
+    if let Some(custom_aggregate_functions) = custom_aggregate_functions {
+        for (name, factory) in custom_aggregate_functions {
+            let name = NamedNode::from(name.extract::<PyNamedNode>()?);
+            if !factory.is_callable() {
+                return Err(PyValueError::new_err(format!(
+                    "The custom aggregate function {name} factory should be callable"
+                )));
+            }
+            let factory = UnsafeGilObject(Some(factory.into()));
+            options = options.with_custom_aggregate_function(name, move || {
+                Box::new(PyAggregateFunctionAccumulator {
+                    inner: UnsafeGilObject(
+                        factory.call(|py, factory| factory.call0(py).ok()).flatten(),
+                    ),
+                })
+            });
+        }
+    }
+    Ok(options)
+}
+
+/// Wraps a Python object with ``add(term)`` and ``finish()`` methods.
+///
+/// If one of the Python calls fails, the aggregate value is unbound.
+struct PyAggregateFunctionAccumulator {
+    inner: UnsafeGilObject,
+}
+
+impl AggregateFunctionAccumulator for PyAggregateFunctionAccumulator {
+    fn add(&mut self, element: Term) {
+        let failed = self
+            .inner
+            .call(|py, inner| {
+                inner
+                    .call_method1(py, "add", (PyTerm::from(element),))
+                    .is_err()
+            })
+            .unwrap_or(false);
+        if failed {
+            self.inner.clear();
+        }
+    }
+
+    fn finish(&mut self) -> Option<Term> {
+        let result = self.inner.call(|py, inner| {
+            Some(
+                inner
+                    .call_method0(py, "finish")
+                    .ok()?
+                    .extract::<Option<PyTerm>>(py)
+                    .ok()??
+                    .into(),
+            )
+        })?;
+        self.inner.clear();
+        result
+    }
+}
+
+/// A Python object used during query evaluation, when the GIL is released by [`allow_threads_unsafe`].
+///
+/// The GIL is acquired with [`with_gil_unsafe`] to use and to drop the object.
+struct UnsafeGilObject(Option<PyObject>);
+
+impl UnsafeGilObject {
+    fn call<T>(&self, f: impl FnOnce(Python<'_>, &PyObject) -> T) -> Option<T> {
+        let object = self.0.as_ref()?;
+        Some(with_gil_unsafe(|py| f(py, object)))
+    }
+
+    fn clear(&mut self) {
+        if let Some(object) = self.0.take() {
+            with_gil_unsafe(|_| drop(object))
+        }
+    }
+}
+
+impl Drop for UnsafeGilObject {
+    fn drop(&mut self) {
+        self.clear()
+    }
+}
+
 pub fn query_results_to_python(py: Python<'_>, results: QueryResults) -> PyObject {
     match results {
         QueryResults::Solutions(inner) => PyQuerySolutions {
@@ -9,10 +9,12 @@ use crate::sparql::*;
 use oxigraph::io::RdfParser;
 use oxigraph::model::{GraphName, GraphNameRef};
 use oxigraph::sparql::Update;
+#[cfg(not(target_family = "wasm"))]
+use oxigraph::store::StoreOptions;
 use oxigraph::store::{self, LoaderError, SerializerError, StorageError, Store};
 use pyo3::exceptions::{PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::PyBytes;
+use pyo3::types::{PyBytes, PyDict};
 use std::path::PathBuf;
 
 /// RDF store.
@@ -32,7 +34,18 @@ use std::path::PathBuf;
 ///              If no directory is provided a temporary one is created and removed when the Python garbage collector removes the store.
 ///              In this case, the store data are kept in memory and never written on disk.
 /// :type path: str or os.PathLike[str] or None, optional
+/// :param block_cache_size: the size in megabytes of the cache of uncompressed data blocks. By default, 32MB are used for the quads and 128MB for the IRIs and literals.
+/// :type block_cache_size: int or None, optional
+/// :param write_buffer_size: the size in megabytes of the in-memory buffers in which writes are accumulated before being written to disk. By default, 128MB are used.
+/// :type write_buffer_size: int or None, optional
+/// :param max_open_files: the maximal number of files to keep open at the same time. By default, all the file descriptors allowed by the operating system minus 48 are used.
+/// :type max_open_files: int or None, optional
+/// :param compression: if the data written on disk should be compressed. Enabled by default.
+/// :type compression: bool or None, optional
+/// :param bloom_filter_bits_per_key: enables bloom filters on the quad indexes with the given number of bits per key.
+/// :type bloom_filter_bits_per_key: float or None, optional
 /// :raises OSError: if the target directory contains invalid data or could not be accessed.
+/// :raises ValueError: if tuning options are given without a path.
 ///
 /// The :py:class:`str` function provides a serialization of the store in NQuads:
 ///
@@ -50,16 +63,52 @@ pub struct PyStore {
 impl PyStore {
     #[cfg(not(target_family = "wasm"))]
     #[new]
-    #[pyo3(signature = (path = None))]
-    fn new(path: Option<PathBuf>, py: Python<'_>) -> PyResult<Self> {
+    #[pyo3(signature = (path = None, *, block_cache_size = None, write_buffer_size = None, max_open_files = None, compression = None, bloom_filter_bits_per_key = None))]
+    fn new(
+        path: Option<PathBuf>,
+        block_cache_size: Option<usize>,
+        write_buffer_size: Option<usize>,
+        max_open_files: Option<u32>,
+        compression: Option<bool>,
+        bloom_filter_bits_per_key: Option<f64>,
+        py: Python<'_>,
+    ) -> PyResult<Self> {
+        let Some(path) = path else {
+            if block_cache_size.is_some()
+                || write_buffer_size.is_some()
+                || max_open_files.is_some()
+                || compression.is_some()
+                || bloom_filter_bits_per_key.is_some()
+            {
+                return Err(PyValueError::new_err(
+                    "The store tuning options are only supported with a path",
+                ));
+            }
+            return py.allow_threads(|| {
+                Ok(Self {
+                    inner: Store::new().map_err(map_storage_error)?,
+                })
+            });
+        };
+        let mut options = StoreOptions::default();
+        if let Some(block_cache_size) = block_cache_size {
+            options = options.with_block_cache_size_in_megabytes(block_cache_size);
+        }
+        if let Some(write_buffer_size) = write_buffer_size {
+            options = options.with_write_buffer_size_in_megabytes(write_buffer_size);
+        }
+        if let Some(max_open_files) = max_open_files {
+            options = options.with_max_open_files(max_open_files);
+        }
+        if let Some(compression) = compression {
+            options = options.with_compression(compression);
+        }
+        if let Some(bits_per_key) = bloom_filter_bits_per_key {
+            options = options.with_bloom_filter_bits_per_key(bits_per_key);
+        }
         py.allow_threads(|| {
             Ok(Self {
-                inner: if let Some(path) = path {
-                    Store::open(path)
-                } else {
-                    Store::new()
-                }
-                .map_err(map_storage_error)?,
+                inner: Store::open_with_options(path, &options).map_err(map_storage_error)?,
             })
         })
     }
@@ -266,6 +315,8 @@ impl PyStore {
     /// :type default_graph: NamedNode or BlankNode or DefaultGraph or list[NamedNode or BlankNode or DefaultGraph] or None, optional
     /// :param named_graphs: list of the named graphs that could be used in SPARQL `GRAPH` clause. By default, all the store named graphs are available.
     /// :type named_graphs: list[NamedNode or BlankNode] or None, optional
+    /// :param custom_aggregate_functions: custom aggregate functions usable in the query. Each function name is mapped to a callable building a new accumulator for each group of solutions. The accumulator must have an ``add(term)`` method called with each aggregated value and a ``finish()`` method returning the aggregate value or :py:const:`None`. If one of these calls raises an exception, the aggregate value is unbound.
+    /// :type custom_aggregate_functions: dict[NamedNode, typing.Callable] or None, optional
     /// :return: a :py:class:`bool` for ``ASK`` queries, an iterator of :py:class:`Triple` for ``CONSTRUCT`` and ``DESCRIBE`` queries and an iterator of :py:class:`QuerySolution` for ``SELECT`` queries.
     /// :rtype: QuerySolutions or QueryBoolean or QueryTriples
     /// :raises SyntaxError: if the provided query is invalid.
@@ -291,7 +342,23 @@ impl PyStore {
     /// >>> store.add(Quad(NamedNode('http://example.com'), NamedNode('http://example.com/p'), Literal('1')))
     /// >>> bool(store.query('ASK { ?s ?p ?o }'))
     /// True
-    #[pyo3(signature = (query, *, base_iri = None, use_default_graph_as_union = false, default_graph = None, named_graphs = None))]
+    ///
+    /// Custom aggregate function:
+    ///
+    /// >>> class Concat:
+    /// ...     def __init__(self):
+    /// ...         self.values = []
+    /// ...     def add(self, term):
+    /// ...         self.values.append(term.value)
+    /// ...     def finish(self):
+    /// ...         return Literal(''.join(sorted(self.values)))
+    /// >>> store = Store()
+    /// >>> store.add(Quad(NamedNode('http://example.com'), NamedNode('http://example.com/p'), Literal('b')))
+    /// >>> store.add(Quad(NamedNode('http://example.com'), NamedNode('http://example.com/p'), Literal('a')))
+    /// >>> [solution['c'] for solution in store.query('SELECT (<http://example.com/concat>(?o) AS ?c) WHERE { ?s ?p ?o }', custom_aggregate_functions={NamedNode('http://example.com/concat'): Concat})]
+    /// [<Literal value=ab datatype=<NamedNode value=http://www.w3.org/2001/XMLSchema#string>>]
+    #[pyo3(signature = (query, *, base_iri = None, use_default_graph_as_union = false, default_graph = None, named_graphs = None, custom_aggregate_functions = None))]
+    #[allow(clippy::too_many_arguments)]
     fn query(
         &self,
         query: &str,
@@ -299,6 +366,7 @@ impl PyStore {
         use_default_graph_as_union: bool,
         default_graph: Option<&PyAny>,
         named_graphs: Option<&PyAny>,
+        custom_aggregate_functions: Option<&PyDict>,
         py: Python<'_>,
     ) -> PyResult<PyObject> {
         let query = parse_query(
@@ -307,10 +375,12 @@ impl PyStore {
             use_default_graph_as_union,
             default_graph,
             named_graphs,
+            custom_aggregate_functions,
             py,
         )?;
-        let results =
-            allow_threads_unsafe(py, || self.inner.query(query)).map_err(map_evaluation_error)?;
+        let options = query_options(custom_aggregate_functions)?;
+        let results = allow_threads_unsafe(py, || self.inner.query_opt(query, options))
+            .map_err(map_evaluation_error)?;
         Ok(query_results_to_python(py, results))
     }
 
@@ -755,6 +825,39 @@ impl PyStore {
         })
     }
 
+    /// Saves the store content into a file that can be loaded back with :py:func:`Store.load_snapshot`.
+    ///
+    /// The file contains the store internal encoding and not an RDF serialization.
+    /// If you want to move your data to another RDF storage system, you should have a look at the :py:func:`dump` function instead.
+    ///
+    /// :param path: the file to write the store content to.
+    /// :type path: str or os.PathLike[str]
+    /// :rtype: None
+    /// :raises OSError: if an error happens during the save.
+    #[cfg(not(target_family = "wasm"))]
+    fn save_to_file(&self, path: PathBuf, py: Python<'_>) -> PyResult<()> {
+        py.allow_threads(|| self.inner.save_to_file(path).map_err(map_storage_error))
+    }
+
+    /// Creates a new in-memory store from a file written by :py:func:`Store.save_to_file`.
+    ///
+    /// The content is restored as it is without parsing any RDF.
+    ///
+    /// :param path: the file to read the store content from.
+    /// :type path: str or os.PathLike[str]
+    /// :return: the loaded store.
+    /// :rtype: Store
+    /// :raises OSError: if the file contains invalid data or could not be accessed.
+    #[cfg(not(target_family = "wasm"))]
+    #[staticmethod]
+    fn load_snapshot(path: PathBuf, py: Python<'_>) -> PyResult<Self> {
+        py.allow_threads(|| {
+            Ok(Self {
+                inner: Store::load_snapshot(path).map_err(map_storage_error)?,
+            })
+        })
+    }
+
     fn __str__(&self, py: Python<'_>) -> String {
         py.allow_threads(|| self.inner.to_string())
     }
@@ -151,6 +151,36 @@ class TestStore(unittest.TestCase):
         self.assertEqual(s, foo)
         self.assertEqual(o, baz)
 
+    def test_select_query_custom_aggregate_function(self) -> None:
+        class Longest:
+            def __init__(self) -> None:
+                self.longest: Any = None
+
+            def add(self, term: Any) -> None:
+                if self.longest is None or len(term.value) > len(self.longest.value):
+                    self.longest = term
+
+            def finish(self) -> Any:
+                return self.longest
+
+        class Failing:
+            def add(self, term: Any) -> None:
+                raise ValueError(term)
+
+            def finish(self) -> Any:
+                return foo
+
+        store = Store()
+        store.add(Quad(foo, bar, baz))
+        store.add(Quad(foo, bar, graph))
+        solutions: Any = store.query(
+            "SELECT (<http://longest>(?o) AS ?l) (<http://failing>(?o) AS ?f) WHERE { ?s ?p ?o }",
+            custom_aggregate_functions={NamedNode("http://longest"): Longest, NamedNode("http://failing"): Failing},
+        )
+        solution = next(solutions)
+        self.assertEqual(solution["l"], graph)
+        self.assertIsNone(solution["f"])
+
     def test_select_query_union_default_graph(self) -> None:
         store = Store()
         store.add(Quad(foo, bar, baz, graph))
@@ -396,6 +426,26 @@ class TestStore(unittest.TestCase):
             store = Store.read_only(dir)
             self.assertEqual(list(store), [quad])
 
+    @unittest.skipIf(is_wasm, "Not supported with WASM")
+    def test_open_with_options(self) -> None:
+        quad = Quad(foo, bar, baz, graph)
+        with TemporaryDirectory() as dir:
+            store = Store(
+                dir,
+                block_cache_size=1,
+                write_buffer_size=1,
+                max_open_files=128,
+                compression=False,
+                bloom_filter_bits_per_key=10,
+            )
+            store.add(quad)
+            del store
+            gc.collect()
+            store = Store(dir)
+            self.assertEqual(list(store), [quad])
+        with self.assertRaises(ValueError):
+            Store(block_cache_size=1)
+
     @unittest.skipIf(is_wasm, "Not supported with WASM")
     def test_secondary(self) -> None:
         quad = Quad(foo, bar, baz, graph)
@@ -413,6 +463,16 @@ class TestStore(unittest.TestCase):
             del secondary_store
             del store
 
+    @unittest.skipIf(is_wasm, "Not supported with WASM")
+    def test_save_and_load_snapshot(self) -> None:
+        quad = Quad(foo, bar, baz, graph)
+        with TemporaryDirectory() as dir:
+            store = Store()
+            store.add(quad)
+            file_name = Path(dir) / "store.bin"
+            store.save_to_file(file_name)
+            self.assertEqual(list(Store.load_snapshot(file_name)), [quad])
+
 
 if __name__ == "__main__":
     unittest.main()